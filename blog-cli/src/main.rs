@@ -1,17 +1,28 @@
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tonic::Code;
 
-use blog_client::{BlogClient, Transport};
+use blog_client::{BlogClient, BlogClientError, Transport, UnverifiedClaims};
 
 const DEFAULT_HTTP_SERVER: &str = "http://localhost:3000";
 const DEFAULT_GRPC_SERVER: &str = "http://localhost:50051";
 const TOKEN_FILE: &str = ".blog_token";
+const TOKEN_ENV: &str = "BLOG_TOKEN";
 
 #[derive(Parser)]
 #[command(name = "blog-cli")]
 #[command(about = "CLI client for the blog API", long_about = None)]
+#[command(after_help = "Exit codes: 0 success, 1 other failure, \
+    2 authentication, 3 not found, 4 rejected request (e.g. validation), \
+    5 server unreachable")]
 struct Cli {
     /// Use gRPC transport instead of HTTP
     #[arg(long, global = true)]
@@ -21,42 +32,80 @@ struct Cli {
     #[arg(long, global = true)]
     server: Option<String>,
 
+    /// Print the token after register/login instead of writing the token
+    /// file
+    #[arg(long, global = true)]
+    no_save_token: bool,
+
+    /// Print results as pretty JSON on stdout, and errors as
+    /// `{"error": "..."}` on stderr
+    #[arg(long, global = true)]
+    json: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Where register and login take the password from
+#[derive(Args)]
+struct PasswordArgs {
+    /// Prompted for without echo when omitted, which keeps it out of
+    /// shell history
+    #[arg(long)]
+    password: Option<String>,
+    /// Read the password from stdin: a hidden prompt on a terminal,
+    /// otherwise the next line of input. Cannot be combined with
+    /// --password.
+    #[arg(long, conflicts_with = "password")]
+    password_stdin: bool,
+}
+
+impl PasswordArgs {
+    fn read(self) -> Result<String> {
+        or_prompt_password(self.password, self.password_stdin)
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Register a new user
     Register {
         #[arg(long)]
         username: String,
+        /// Prompted for when omitted
         #[arg(long)]
-        email: String,
-        #[arg(long)]
-        password: String,
+        email: Option<String>,
+        #[command(flatten)]
+        password: PasswordArgs,
     },
 
     /// Login with existing credentials
     Login {
+        /// Prompted for when omitted
         #[arg(long)]
-        username: String,
-        #[arg(long)]
-        password: String,
+        username: Option<String>,
+        #[command(flatten)]
+        password: PasswordArgs,
     },
 
-    /// Create a new post
+    /// Create a new post, saved as a draft unless --publish is given
     Create {
         #[arg(long)]
         title: String,
         #[arg(long)]
         content: String,
+        /// Publish the post right away
+        #[arg(long)]
+        publish: bool,
     },
 
     /// Get a post by ID
     Get {
         #[arg(long)]
         id: i64,
+        /// Print only the post content, without the header
+        #[arg(long)]
+        raw: bool,
     },
 
     /// Update a post
@@ -75,13 +124,78 @@ enum Commands {
         id: i64,
     },
 
+    /// Make a draft visible to everyone
+    Publish {
+        #[arg(long)]
+        id: i64,
+    },
+
+    /// Turn a published post back into a draft
+    Unpublish {
+        #[arg(long)]
+        id: i64,
+    },
+
     /// List posts with pagination
     List {
         #[arg(long, default_value = "10")]
         limit: i64,
         #[arg(long, default_value = "0")]
         offset: i64,
+        /// Every post, printed as it arrives (one JSON object per line
+        /// with --json); ignores --limit and --offset
+        #[arg(long)]
+        all: bool,
     },
+
+    /// Comment on a post
+    Comment {
+        /// Post to comment on
+        #[arg(long)]
+        post_id: i64,
+        #[arg(long)]
+        content: String,
+    },
+
+    /// List a post's comments, oldest first
+    Comments {
+        #[arg(long)]
+        post_id: i64,
+        #[arg(long, default_value = "10")]
+        limit: i64,
+        #[arg(long, default_value = "0")]
+        offset: i64,
+    },
+
+    /// Export all posts to a JSON file
+    Export {
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Import posts from a JSON file (creates them as the logged-in user)
+    Import {
+        #[arg(long)]
+        input: PathBuf,
+        /// Publish the imported posts instead of keeping them as drafts
+        #[arg(long)]
+        publish: bool,
+    },
+
+    /// Check that the server is up and ready, and how long it takes to
+    /// answer. Fails unless it reports "ok".
+    Ping,
+
+    /// Show who the saved token belongs to and when it expires. Decoded
+    /// locally, so the signature is not verified.
+    Whoami,
+}
+
+/// Fields needed to recreate a post; other exported fields are ignored
+#[derive(Deserialize)]
+struct ImportedPost {
+    title: String,
+    content: String,
 }
 
 fn token_path() -> PathBuf {
@@ -90,17 +204,210 @@ fn token_path() -> PathBuf {
         .join(TOKEN_FILE)
 }
 
+/// `BLOG_TOKEN` takes precedence over the token file
 fn load_token() -> Option<String> {
-    std::fs::read_to_string(token_path()).ok()
+    std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+        .or_else(|| std::fs::read_to_string(token_path()).ok())
+        .map(|token| token.trim().to_string())
+}
+
+/// Writes the token file, or else prints the token so it is not lost.
+/// JSON output already carries it.
+fn save_token(token: &str, enabled: bool, output: Output) -> Result<()> {
+    if !enabled {
+        if output == Output::Text {
+            println!("Token: {token}");
+        }
+        return Ok(());
+    }
+    std::fs::write(token_path(), token).context("Failed to save token")?;
+    output.note(&format!("Token saved to {}", token_path().display()));
+    Ok(())
 }
 
-fn save_token(token: &str) -> Result<()> {
-    std::fs::write(token_path(), token).context("Failed to save token")
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Text,
+    /// Results as JSON on stdout for scripts; everything else goes to
+    /// stderr so stdout stays parseable
+    Json,
+}
+
+impl Output {
+    /// `value` as pretty JSON, or whatever `text` prints
+    fn result<T: Serialize>(
+        self,
+        value: &T,
+        text: impl FnOnce(),
+    ) -> Result<()> {
+        match self {
+            Self::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            Self::Text => text(),
+        }
+        Ok(())
+    }
+
+    /// A progress message that is not part of the result
+    fn note(self, message: &str) {
+        match self {
+            Self::Json => eprintln!("{message}"),
+            Self::Text => println!("{message}"),
+        }
+    }
+
+    /// The message names the request id when the server sent one, so
+    /// it can be pasted into a bug report; JSON adds the server's error
+    /// code
+    fn error(self, error: &anyhow::Error) {
+        match self {
+            Self::Json => {
+                let client_error = error
+                    .chain()
+                    .find_map(|cause| cause.downcast_ref::<BlogClientError>());
+                eprintln!(
+                    "{}",
+                    json!({
+                        "error": format!("{error:#}"),
+                        "code": client_error.and_then(BlogClientError::code),
+                        "request_id": client_error
+                            .and_then(BlogClientError::request_id),
+                    })
+                );
+            }
+            Self::Text => eprintln!("Error: {error:?}"),
+        }
+    }
+}
+
+/// Reads one line from stdin, without the line ending
+fn read_line() -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .context("Failed to read from stdin")?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// `value`, or a line typed at the prompt. Piped input is read a line at
+/// a time without prompting.
+fn or_prompt(value: Option<String>, prompt: &str) -> Result<String> {
+    if let Some(value) = value {
+        return Ok(value);
+    }
+    if std::io::stdin().is_terminal() {
+        eprint!("{prompt}");
+        std::io::stderr().flush()?;
+    }
+    read_line()
+}
+
+/// Like [`or_prompt`], but the typed password is not echoed. With
+/// `from_stdin` the password always comes from stdin.
+fn or_prompt_password(
+    value: Option<String>,
+    from_stdin: bool,
+) -> Result<String> {
+    if let Some(value) = value.filter(|_| !from_stdin) {
+        return Ok(value);
+    }
+    if std::io::stdin().is_terminal() {
+        rpassword::prompt_password("Password: ")
+            .context("Failed to read password")
+    } else {
+        read_line()
+    }
+}
+
+/// Process exit codes, one per failure category, so scripts can branch
+/// on why a command failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitStatus {
+    /// Anything not covered below
+    Failure = 1,
+    /// Missing, invalid or expired token, or wrong credentials
+    Auth = 2,
+    NotFound = 3,
+    /// The server rejected the request, e.g. failed validation
+    Invalid = 4,
+    /// The server could not be reached
+    Connection = 5,
+}
+
+impl From<&BlogClientError> for ExitStatus {
+    fn from(error: &BlogClientError) -> Self {
+        match error {
+            BlogClientError::Unauthorized { .. }
+            | BlogClientError::NoToken
+            | BlogClientError::MalformedToken(_) => Self::Auth,
+            BlogClientError::NotFound { .. } => Self::NotFound,
+            BlogClientError::InvalidRequest(_) => Self::Invalid,
+            BlogClientError::Api { status, .. } => Self::of_http(*status),
+            BlogClientError::Transport(_)
+            | BlogClientError::ConnectionRejected(_)
+            | BlogClientError::Timeout => Self::Connection,
+            BlogClientError::Http(e) if e.is_connect() => Self::Connection,
+            BlogClientError::Grpc(status) => match status.code() {
+                Code::Unauthenticated | Code::PermissionDenied => Self::Auth,
+                Code::NotFound => Self::NotFound,
+                Code::InvalidArgument | Code::AlreadyExists => Self::Invalid,
+                Code::Unavailable | Code::DeadlineExceeded => Self::Connection,
+                _ => Self::Failure,
+            },
+            BlogClientError::Http(_)
+            | BlogClientError::Serialization(_)
+            | BlogClientError::CaCertificate { .. } => Self::Failure,
+            BlogClientError::WithRequestId { error, .. } => {
+                Self::from(error.as_ref())
+            }
+        }
+    }
+}
+
+impl ExitStatus {
+    /// Category of an HTTP error status, in line with the gRPC codes the
+    /// server answers the same failures with
+    const fn of_http(status: u16) -> Self {
+        match status {
+            401 | 403 => Self::Auth,
+            404 => Self::NotFound,
+            502..=504 => Self::Connection,
+            400..=428 | 430..=499 => Self::Invalid,
+            _ => Self::Failure,
+        }
+    }
+
+    /// Category of the first client error in the chain; the context added
+    /// by `run_command` wraps it but keeps it as a source
+    fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<BlogClientError>())
+            .map_or(Self::Failure, Self::from)
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    let output = if cli.json { Output::Json } else { Output::Text };
+
+    match run(cli, output).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            output.error(&e);
+            ExitCode::from(ExitStatus::of(&e) as u8)
+        }
+    }
+}
+
+async fn run(cli: Cli, output: Output) -> Result<()> {
+    // Needs no server, and creating a gRPC client already connects
+    if matches!(cli.command, Commands::Whoami) {
+        return whoami(output);
+    }
 
     let server = cli.server.unwrap_or_else(|| {
         if cli.grpc {
@@ -116,78 +423,65 @@ async fn main() -> Result<()> {
         Transport::Http(server)
     };
 
-    let mut client = BlogClient::new(transport)
-        .await
-        .context("Failed to create client")?;
+    let mut client =
+        BlogClient::new(transport).context("Failed to create client")?;
 
     if let Some(token) = load_token() {
         client.set_token(token);
     }
 
-    run_command(&mut client, cli.command).await
+    run_command(&mut client, cli.command, !cli.no_save_token, output).await
 }
 
-async fn run_command(client: &mut BlogClient, command: Commands) -> Result<()> {
+async fn run_command(
+    client: &mut BlogClient,
+    command: Commands,
+    persist_token: bool,
+    output: Output,
+) -> Result<()> {
     match command {
         Commands::Register {
             username,
             email,
             password,
         } => {
-            let response = client
-                .register(&username, &email, &password)
-                .await
-                .context("Registration failed")?;
-
-            save_token(&response.token)?;
-
-            println!("Registration successful!");
-            println!("User ID: {}", response.user.id);
-            println!("Username: {}", response.user.username);
-            println!("Email: {}", response.user.email);
-            println!("Token saved to {}", token_path().display());
+            register(client, &username, email, password, persist_token, output)
+                .await?;
         }
 
         Commands::Login { username, password } => {
-            let response = client
-                .login(&username, &password)
-                .await
-                .context("Login failed")?;
-
-            save_token(&response.token)?;
-
-            println!("Login successful!");
-            println!("User ID: {}", response.user.id);
-            println!("Username: {}", response.user.username);
-            println!("Token saved to {}", token_path().display());
+            login(client, username, password, persist_token, output).await?;
         }
 
-        Commands::Create { title, content } => {
-            let post = client
-                .create_post(&title, &content)
-                .await
-                .context("Failed to create post")?;
-
-            println!("Post created successfully!");
-            print_post(&post);
+        Commands::Create {
+            title,
+            content,
+            publish,
+        } => {
+            create_post(client, &title, &content, publish, output).await?;
         }
 
-        Commands::Get { id } => {
+        Commands::Get { id, raw } => {
             let post =
                 client.get_post(id).await.context("Failed to get post")?;
 
-            print_post(&post);
+            output.result(&post, || {
+                if raw {
+                    print!("{}", post.content);
+                } else {
+                    print_post(&post);
+                }
+            })?;
         }
 
         Commands::Update { id, title, content } => {
-            let content = if let Some(c) = content {
-                c
-            } else {
-                let existing = client
+            let content = match content {
+                Some(content) => content,
+                None => client
                     .get_post(id)
                     .await
-                    .context("Failed to fetch existing post")?;
-                existing.content
+                    .map(|post| post.content)
+                    .context("Failed to fetch existing post")?,
             };
 
             let post = client
@@ -195,8 +489,10 @@ async fn run_command(client: &mut BlogClient, command: Commands) -> Result<()> {
                 .await
                 .context("Failed to update post")?;
 
-            println!("Post updated successfully!");
-            print_post(&post);
+            output.result(&post, || {
+                println!("Post updated successfully!");
+                print_post(&post);
+            })?;
         }
 
         Commands::Delete { id } => {
@@ -205,41 +501,419 @@ async fn run_command(client: &mut BlogClient, command: Commands) -> Result<()> {
                 .await
                 .context("Failed to delete post")?;
 
-            println!("Post {id} deleted successfully!");
+            output.result(&json!({ "id": id, "deleted": true }), || {
+                println!("Post {id} deleted successfully!");
+            })?;
         }
 
-        Commands::List { limit, offset } => {
-            let list = client
-                .list_posts(limit, offset)
-                .await
-                .context("Failed to list posts")?;
-
-            let end =
-                (offset + i64::try_from(list.posts.len())?).min(list.total);
-            println!("Posts ({}-{end} of {}):", offset + 1, list.total);
-            println!("{}", "-".repeat(60));
-
-            for post in &list.posts {
-                println!(
-                    "[{}] {} (by {})",
-                    post.id,
-                    post.title,
-                    post.author_username.as_deref().unwrap_or("unknown")
-                );
-            }
+        Commands::Publish { id } => {
+            set_published(client, id, true, output).await?;
+        }
+
+        Commands::Unpublish { id } => {
+            set_published(client, id, false, output).await?;
+        }
+
+        Commands::List { limit, offset, all } => {
+            list_posts(client, limit, offset, all, output).await?;
+        }
+
+        Commands::Comment { post_id, content } => {
+            add_comment(client, post_id, &content, output).await?;
+        }
+
+        Commands::Comments {
+            post_id,
+            limit,
+            offset,
+        } => {
+            list_comments(client, post_id, limit, offset, output).await?;
+        }
 
-            if list.posts.is_empty() {
-                println!("No posts found.");
+        Commands::Export { output: path } => {
+            export_posts(client, &path, output).await?;
+        }
+
+        Commands::Import { input, publish } => {
+            import_posts(client, &input, publish, output).await?;
+        }
+
+        Commands::Ping => ping(client, output).await?,
+
+        Commands::Whoami => whoami(output)?,
+    }
+
+    Ok(())
+}
+
+async fn register(
+    client: &mut BlogClient,
+    username: &str,
+    email: Option<String>,
+    password: PasswordArgs,
+    persist_token: bool,
+    output: Output,
+) -> Result<()> {
+    let email = or_prompt(email, "Email: ")?;
+    let password = password.read()?;
+    let response = client
+        .register(username, &email, &password)
+        .await
+        .context("Registration failed")?;
+
+    output.result(&response, || {
+        println!("Registration successful!");
+        println!("User ID: {}", response.user.id);
+        println!("Username: {}", response.user.username);
+        println!("Email: {}", response.user.email);
+    })?;
+
+    save_token(&response.token, persist_token, output)
+}
+
+async fn login(
+    client: &mut BlogClient,
+    username: Option<String>,
+    password: PasswordArgs,
+    persist_token: bool,
+    output: Output,
+) -> Result<()> {
+    let username = or_prompt(username, "Username: ")?;
+    let password = password.read()?;
+    let response = client
+        .login(&username, &password)
+        .await
+        .context("Login failed")?;
+
+    output.result(&response, || {
+        println!("Login successful!");
+        println!("User ID: {}", response.user.id);
+        println!("Username: {}", response.user.username);
+    })?;
+
+    save_token(&response.token, persist_token, output)
+}
+
+async fn ping(client: &mut BlogClient, output: Output) -> Result<()> {
+    let started = Instant::now();
+    let health = client
+        .get_health()
+        .await
+        .context("Failed to reach server")?;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    output.result(
+        &json!({
+            "status": health.status,
+            "timestamp": health.timestamp,
+            "latency_ms": latency_ms,
+        }),
+        || {
+            println!("Status: {}", health.status);
+            println!("Server time: {}", health.timestamp);
+            println!("Round trip: {latency_ms:.1} ms");
+        },
+    )?;
+
+    anyhow::ensure!(health.is_ok(), "Server is {}", health.status);
+    Ok(())
+}
+
+/// Creates a post, publishing it right after with `publish`
+async fn create_post(
+    client: &mut BlogClient,
+    title: &str,
+    content: &str,
+    publish: bool,
+    output: Output,
+) -> Result<()> {
+    let mut post = client
+        .create_post(title, content)
+        .await
+        .context("Failed to create post")?;
+    if publish {
+        post = client
+            .publish_post(post.id)
+            .await
+            .context("Post created but failed to publish it")?;
+    }
+
+    output.result(&post, || {
+        println!("Post created successfully!");
+        print_post(&post);
+        if !publish {
+            println!(
+                "Saved as a draft; run `publish --id {}` to show it",
+                post.id
+            );
+        }
+    })
+}
+
+/// Publishes a post, or turns it back into a draft
+async fn set_published(
+    client: &mut BlogClient,
+    id: i64,
+    publish: bool,
+    output: Output,
+) -> Result<()> {
+    let post = if publish {
+        client
+            .publish_post(id)
+            .await
+            .context("Failed to publish post")?
+    } else {
+        client
+            .unpublish_post(id)
+            .await
+            .context("Failed to unpublish post")?
+    };
+
+    output.result(&post, || {
+        if publish {
+            println!("Post {id} published");
+        } else {
+            println!("Post {id} is a draft again");
+        }
+    })
+}
+
+async fn add_comment(
+    client: &mut BlogClient,
+    post_id: i64,
+    content: &str,
+    output: Output,
+) -> Result<()> {
+    let comment = client
+        .create_comment(post_id, content)
+        .await
+        .context("Failed to add comment")?;
+
+    output.result(&comment, || {
+        println!("Comment added to post {post_id}!");
+        print_comment(&comment);
+    })
+}
+
+async fn list_comments(
+    client: &mut BlogClient,
+    post_id: i64,
+    limit: i64,
+    offset: i64,
+    output: Output,
+) -> Result<()> {
+    let list = client
+        .list_comments(post_id, limit, offset)
+        .await
+        .context("Failed to list comments")?;
+
+    output.result(&list, || {
+        let shown = i64::try_from(list.comments.len()).unwrap_or(i64::MAX);
+        let end = offset.saturating_add(shown).min(list.total);
+        println!(
+            "Comments on post {post_id} ({}-{end} of {}):",
+            offset + 1,
+            list.total
+        );
+        println!("{}", "-".repeat(60));
+
+        for comment in &list.comments {
+            print_comment(comment);
+        }
+
+        if list.comments.is_empty() {
+            println!("No comments yet.");
+        }
+    })
+}
+
+async fn export_posts(
+    client: &mut BlogClient,
+    path: &Path,
+    output: Output,
+) -> Result<()> {
+    let posts = fetch_all_posts(client).await?;
+
+    let json = serde_json::to_string_pretty(&posts)?;
+    std::fs::write(path, json)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let summary = json!({ "exported": posts.len(), "output": path });
+    output.result(&summary, || {
+        println!("Exported {} posts to {}", posts.len(), path.display());
+    })
+}
+
+/// Creates every post from the file, publishing it with `publish`,
+/// reporting each one and continuing past failures
+async fn import_posts(
+    client: &mut BlogClient,
+    input: &Path,
+    publish: bool,
+    output: Output,
+) -> Result<()> {
+    let json = std::fs::read_to_string(input)
+        .with_context(|| format!("Failed to read {}", input.display()))?;
+    let posts: Vec<ImportedPost> =
+        serde_json::from_str(&json).context("Invalid posts file")?;
+
+    let mut failed = 0;
+    for post in &posts {
+        let created = match client.create_post(&post.title, &post.content).await
+        {
+            Ok(created) if publish => client.publish_post(created.id).await,
+            result => result,
+        };
+        match created {
+            Ok(created) => {
+                output.note(&format!(
+                    "[ok] {} -> post {}",
+                    post.title, created.id
+                ));
+            }
+            Err(e) => {
+                failed += 1;
+                output.note(&format!("[failed] {}: {e}", post.title));
             }
         }
     }
 
+    let imported = posts.len() - failed;
+    output.result(
+        &json!({ "imported": imported, "failed": failed }),
+        || {
+            println!("Imported {imported} of {} posts", posts.len());
+        },
+    )?;
+    if failed > 0 {
+        anyhow::bail!("{failed} posts failed to import");
+    }
+    Ok(())
+}
+
+/// Prints the claims of the saved token without asking the server
+fn whoami(output: Output) -> Result<()> {
+    let token = load_token()
+        .ok_or(BlogClientError::NoToken)
+        .context("Not logged in")?;
+    let claims =
+        UnverifiedClaims::decode(&token).context("Unreadable saved token")?;
+    let expired = claims.is_expired_at(chrono::Utc::now());
+
+    let mut summary = serde_json::to_value(&claims)?;
+    summary["expires_at"] = json!(claims.expires_at());
+    summary["expired"] = json!(expired);
+    summary["verified"] = json!(false);
+    output.result(&summary, || {
+        println!("Username: {}", claims.username);
+        println!("User ID: {}", claims.user_id);
+        if let Some(role) = &claims.role {
+            println!("Role: {role}");
+        }
+        if let Some(admin_id) = claims.impersonator_id {
+            println!("Impersonated by: user {admin_id}");
+        }
+        if let Some(expires_at) = claims.expires_at() {
+            let status = if expired { " (EXPIRED)" } else { "" };
+            println!(
+                "Expires: {}{status}",
+                expires_at.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+        }
+        println!("(decoded locally; the signature is not verified)");
+    })
+}
+
+/// Pages through the whole listing
+async fn fetch_all_posts(
+    client: &mut BlogClient,
+) -> Result<Vec<blog_client::Post>> {
+    client
+        .stream_posts()
+        .await
+        .context("Failed to list posts")?
+        .try_collect()
+        .await
+        .context("Failed to list posts")
+}
+
+async fn list_posts(
+    client: &mut BlogClient,
+    limit: i64,
+    offset: i64,
+    all: bool,
+    output: Output,
+) -> Result<()> {
+    if all {
+        return list_all_posts(client, output).await;
+    }
+    let list = client
+        .list_posts(limit, offset)
+        .await
+        .context("Failed to list posts")?;
+
+    output.result(&list, || print_list(&list, offset))
+}
+
+/// Prints posts while they stream in, so nothing is buffered
+async fn list_all_posts(client: &mut BlogClient, output: Output) -> Result<()> {
+    let mut posts = client
+        .stream_posts()
+        .await
+        .context("Failed to list posts")?;
+    let mut count = 0;
+
+    while let Some(post) = posts.try_next().await? {
+        match output {
+            Output::Json => println!("{}", serde_json::to_string(&post)?),
+            Output::Text => println!(
+                "[{}] {} (by {})",
+                post.id,
+                post.title,
+                post.author_username.as_deref().unwrap_or("unknown")
+            ),
+        }
+        count += 1;
+    }
+
+    output.note(&format!("{count} posts"));
     Ok(())
 }
 
+fn print_list(list: &blog_client::PostsList, offset: i64) {
+    let shown = i64::try_from(list.posts.len()).unwrap_or(i64::MAX);
+    let end = offset.saturating_add(shown).min(list.total);
+    println!("Posts ({}-{end} of {}):", offset + 1, list.total);
+    println!("{}", "-".repeat(60));
+
+    for post in &list.posts {
+        println!(
+            "[{}] {} (by {})",
+            post.id,
+            post.title,
+            post.author_username.as_deref().unwrap_or("unknown")
+        );
+    }
+
+    if list.posts.is_empty() {
+        println!("No posts found.");
+    }
+}
+
+fn print_comment(comment: &blog_client::Comment) {
+    println!(
+        "[{}] {} at {}: {}",
+        comment.id,
+        comment.author_username.as_deref().unwrap_or("unknown"),
+        comment.created_at.format("%Y-%m-%d %H:%M:%S"),
+        comment.content
+    );
+}
+
 fn print_post(post: &blog_client::Post) {
     println!("ID: {}", post.id);
     println!("Title: {}", post.title);
+    println!("Status: {}", post.status);
     println!("Content: {}", post.content);
     println!(
         "Author: {} (ID: {})",
@@ -249,3 +923,26 @@ fn print_post(post: &blog_client::Post) {
     println!("Created: {}", post.created_at.format("%Y-%m-%d %H:%M:%S"));
     println!("Updated: {}", post.updated_at.format("%Y-%m-%d %H:%M:%S"));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_errors_exit_by_status() {
+        let exit = |status| {
+            ExitStatus::from(&BlogClientError::Api {
+                status,
+                code: None,
+                message: String::new(),
+            })
+        };
+
+        assert_eq!(exit(400), ExitStatus::Invalid);
+        assert_eq!(exit(403), ExitStatus::Auth);
+        assert_eq!(exit(409), ExitStatus::Invalid);
+        assert_eq!(exit(429), ExitStatus::Failure);
+        assert_eq!(exit(500), ExitStatus::Failure);
+        assert_eq!(exit(503), ExitStatus::Connection);
+    }
+}
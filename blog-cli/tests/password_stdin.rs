@@ -0,0 +1,101 @@
+//! Runs the built CLI against a stub login endpoint
+
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+use axum::{Json, Router, extract::State, routing::post};
+use serde_json::{Value, json};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Body of the last login request
+type Seen = Arc<Mutex<Option<Value>>>;
+
+async fn login(
+    State(seen): State<Seen>,
+    Json(body): Json<Value>,
+) -> Json<Value> {
+    *seen.lock().unwrap() = Some(body);
+    Json(json!({
+        "token": "token",
+        "user": {
+            "id": 1,
+            "username": "ivan",
+            "email": "ivan@example.com",
+            "created_at": "2025-01-01T00:00:00Z",
+        },
+    }))
+}
+
+async fn serve() -> (String, Seen) {
+    let seen = Seen::default();
+    let app = Router::new()
+        .route("/api/v1/auth/login", post(login))
+        .with_state(seen.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("http://{addr}"), seen)
+}
+
+fn cli(server: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_blog-cli"));
+    command
+        .args(["--server", server, "--no-save-token", "--json"])
+        .args(args)
+        .env_remove("BLOG_TOKEN")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+#[tokio::test]
+async fn test_login_reads_piped_password() {
+    let (server, seen) = serve().await;
+
+    let mut child =
+        cli(&server, &["login", "--username", "ivan", "--password-stdin"])
+            .spawn()
+            .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"secret123\n").await.unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().await.unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        seen.lock().unwrap().take(),
+        Some(json!({ "username": "ivan", "password": "secret123" }))
+    );
+}
+
+#[tokio::test]
+async fn test_password_stdin_cannot_be_combined_with_password() {
+    let (server, seen) = serve().await;
+
+    let output = cli(
+        &server,
+        &[
+            "login",
+            "--username",
+            "ivan",
+            "--password",
+            "secret123",
+            "--password-stdin",
+        ],
+    )
+    .output()
+    .await
+    .unwrap();
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("cannot be used with")
+    );
+    assert_eq!(seen.lock().unwrap().take(), None);
+}
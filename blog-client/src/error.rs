@@ -1,28 +1,201 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum BlogClientError {
     #[error("HTTP error: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(reqwest::Error),
 
     #[error("gRPC error: {0}")]
-    Grpc(#[from] tonic::Status),
+    Grpc(tonic::Status),
 
     #[error("gRPC transport error: {0}")]
     Transport(#[from] tonic::transport::Error),
 
+    /// The server dropped the HTTP/2 connection without answering, which
+    /// is how a TLS server treats a plaintext client
+    #[error(
+        "gRPC connection rejected: {}; if the server uses TLS, connect \
+         with an https:// address",
+        .0.message()
+    )]
+    ConnectionRejected(tonic::Status),
+
+    #[error("Failed to read CA certificate {}: {source}", path.display())]
+    CaCertificate {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[error("Resource not found")]
-    NotFound,
+    NotFound {
+        /// Such as `post_not_found`; None when the body has none
+        code: Option<String>,
+    },
 
-    #[error("Unauthorized: {0}")]
-    Unauthorized(String),
+    #[error("Unauthorized: {message}")]
+    Unauthorized {
+        /// Such as `invalid_credentials`; None when the body has none
+        code: Option<String>,
+        message: String,
+    },
 
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
+    /// An HTTP error response other than 401 and 404
+    #[error("HTTP {status}: {message}")]
+    Api {
+        status: u16,
+        /// Stable machine-readable code from the body, such as
+        /// `user_already_exists`; None when the body has none
+        code: Option<String>,
+        message: String,
+    },
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
     #[error("No token set")]
     NoToken,
+
+    #[error("Malformed token: {0}")]
+    MalformedToken(String),
+
+    #[error("Request timed out")]
+    Timeout,
+
+    /// A failure the server answered, tagged with the id it logged the
+    /// request under; worth quoting in a bug report
+    #[error("{error} (request id: {request_id})")]
+    WithRequestId {
+        request_id: String,
+        error: Box<Self>,
+    },
+}
+
+/// Header (gRPC: metadata key) the server echoes the request id in
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+impl BlogClientError {
+    /// Id of the failed request, when the server sent one
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Self::WithRequestId { request_id, .. } => Some(request_id),
+            _ => None,
+        }
+    }
+
+    /// The error itself, without its request id
+    pub fn inner(&self) -> &Self {
+        match self {
+            Self::WithRequestId { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// Code the server gave with an HTTP error response; branch on it
+    /// rather than on the message
+    pub fn code(&self) -> Option<&str> {
+        match self.inner() {
+            Self::Api { code, .. }
+            | Self::NotFound { code }
+            | Self::Unauthorized { code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of an [`Api`](Self::Api) error
+    pub fn http_status(&self) -> Option<u16> {
+        match self.inner() {
+            Self::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn with_request_id(self, request_id: Option<&str>) -> Self {
+        match request_id {
+            Some(request_id) => Self::WithRequestId {
+                request_id: request_id.to_string(),
+                error: Box::new(self),
+            },
+            None => self,
+        }
+    }
+}
+
+/// Whether `status` reports a connection attempt that ran out of
+/// `connect_timeout`, rather than an answer from the server
+fn is_connect_timeout(status: &tonic::Status) -> bool {
+    let mut source = std::error::Error::source(status);
+    while let Some(error) = source {
+        if error
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::TimedOut)
+        {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// Whether `status` is the connection failing at the HTTP/2 level, before
+/// the server sent a gRPC answer
+fn is_rejected_connection(status: &tonic::Status) -> bool {
+    status.code() == tonic::Code::Unknown
+        && status.message().starts_with("h2 protocol error")
+}
+
+impl From<tonic::Status> for BlogClientError {
+    fn from(status: tonic::Status) -> Self {
+        if is_connect_timeout(&status) {
+            return Self::Timeout;
+        }
+        if is_rejected_connection(&status) {
+            return Self::ConnectionRejected(status);
+        }
+        let request_id = status
+            .metadata()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .map(str::to_string);
+        Self::Grpc(status).with_request_id(request_id.as_deref())
+    }
+}
+
+impl From<reqwest::Error> for BlogClientError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::Http(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connect_timeout_status_is_a_timeout() {
+        let timed_out = tonic::Status::from_error(Box::new(
+            std::io::Error::new(std::io::ErrorKind::TimedOut, "connect"),
+        ));
+        let refused = tonic::Status::from_error(Box::new(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "no",
+        )));
+
+        assert!(matches!(
+            BlogClientError::from(timed_out),
+            BlogClientError::Timeout
+        ));
+        assert!(matches!(
+            BlogClientError::from(refused),
+            BlogClientError::Grpc(_)
+        ));
+    }
 }
@@ -1,31 +1,168 @@
-use tonic::Request;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures_util::{Stream, StreamExt};
 use tonic::metadata::MetadataValue;
-use tonic::transport::Channel;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
+use tonic::{Request, Status};
 
 use crate::error::BlogClientError;
 use crate::proto::blog_service_client::BlogServiceClient;
 use crate::proto::{
-    CreatePostRequest, DeletePostRequest, GetPostRequest, ListPostsRequest,
-    LoginRequest, RegisterRequest, UpdatePostRequest,
+    CreateCommentRequest, CreatePostRequest, DeletePostRequest, GetPostRequest,
+    HealthCheckRequest, ListCommentsRequest, ListPostsRequest, LoginRequest,
+    PublishPostRequest, RegisterRequest, StreamPostsRequest,
+    UnpublishPostRequest, UpdatePostRequest,
+};
+use crate::{
+    AuthResponse, Comment, CommentsList, HealthStatus, Post, PostsList, User,
+    parse_timestamp,
 };
-use crate::{AuthResponse, Post, PostsList, User};
+
+/// How `GrpcBlogClient` connects and keeps the connection healthy.
+///
+/// The channel connects on the first call and reconnects on its own after
+/// the connection drops, e.g. when the server restarts. HTTP/2 pings
+/// notice a dead connection even while no call is running.
+///
+/// `https://` endpoints are reached over TLS, trusting the system's root
+/// certificates plus [`ca_cert`](Self::ca_cert); `http://` ones over
+/// plaintext.
+///
+/// ```
+/// use std::time::Duration;
+/// use blog_client::GrpcConfig;
+///
+/// let grpc = GrpcConfig::default()
+///     .connect_timeout(Duration::from_secs(2))
+///     .keep_alive_interval(Some(Duration::from_secs(10)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct GrpcConfig {
+    connect_timeout: Duration,
+    keep_alive_interval: Option<Duration>,
+    keep_alive_timeout: Duration,
+    ca_cert: Option<PathBuf>,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            keep_alive_interval: Some(Duration::from_secs(30)),
+            keep_alive_timeout: Duration::from_secs(10),
+            ca_cert: None,
+        }
+    }
+}
+
+impl GrpcConfig {
+    /// How long a connection attempt may take before the call fails with
+    /// `BlogClientError::Timeout`
+    #[must_use]
+    pub const fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Time between pings on the connection; `None` disables them
+    #[must_use]
+    pub const fn keep_alive_interval(
+        mut self,
+        keep_alive_interval: Option<Duration>,
+    ) -> Self {
+        self.keep_alive_interval = keep_alive_interval;
+        self
+    }
+
+    /// How long a ping may go unanswered before the connection is
+    /// considered dead and dropped
+    #[must_use]
+    pub const fn keep_alive_timeout(
+        mut self,
+        keep_alive_timeout: Duration,
+    ) -> Self {
+        self.keep_alive_timeout = keep_alive_timeout;
+        self
+    }
+
+    /// PEM file of an extra certificate authority to trust over TLS, e.g.
+    /// the one that signed a self-signed server certificate
+    #[must_use]
+    pub fn ca_cert(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_cert = Some(path.into());
+        self
+    }
+
+    fn tls(&self) -> Result<ClientTlsConfig, BlogClientError> {
+        let tls = ClientTlsConfig::new().with_native_roots();
+        let Some(path) = &self.ca_cert else {
+            return Ok(tls);
+        };
+        let pem = std::fs::read(path).map_err(|source| {
+            BlogClientError::CaCertificate {
+                path: path.clone(),
+                source,
+            }
+        })?;
+        Ok(tls.ca_certificate(Certificate::from_pem(pem)))
+    }
+
+    fn endpoint(&self, endpoint: &str) -> Result<Endpoint, BlogClientError> {
+        let mut endpoint = Endpoint::from_shared(endpoint.to_string())?
+            .connect_timeout(self.connect_timeout);
+        if endpoint.uri().scheme_str() == Some("https") {
+            endpoint = endpoint.tls_config(self.tls()?)?;
+        }
+        if let Some(interval) = self.keep_alive_interval {
+            endpoint = endpoint
+                .http2_keep_alive_interval(interval)
+                .keep_alive_timeout(self.keep_alive_timeout)
+                .keep_alive_while_idle(true);
+        }
+        Ok(endpoint)
+    }
+}
 
 pub struct GrpcBlogClient {
     client: BlogServiceClient<Channel>,
     token: Option<String>,
+    /// Deadline of each call while set; calls are unbounded otherwise
+    request_timeout: Option<Duration>,
+}
+
+/// Awaits `call`, giving up with `Timeout` once `timeout` passes
+async fn within<T>(
+    timeout: Option<Duration>,
+    call: impl Future<Output = Result<T, Status>>,
+) -> Result<T, BlogClientError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, call)
+            .await
+            .map_err(|_| BlogClientError::Timeout)?
+            .map_err(Into::into),
+        None => Ok(call.await?),
+    }
 }
 
 impl GrpcBlogClient {
-    /// Create a new gRPC blog client.
+    /// Create a new gRPC blog client. Nothing is sent until the first
+    /// call, so an unreachable server only shows up then.
     ///
     /// # Errors
     ///
-    /// Returns `BlogClientError` if connection to the gRPC endpoint fails.
-    pub async fn new(endpoint: &str) -> Result<Self, BlogClientError> {
-        let client = BlogServiceClient::connect(endpoint.to_string()).await?;
+    /// Returns `BlogClientError` if `endpoint` is not a valid URI, or it is
+    /// an `https://` one and the CA certificate cannot be read.
+    pub fn new(
+        endpoint: &str,
+        config: &GrpcConfig,
+    ) -> Result<Self, BlogClientError> {
+        let channel = config.endpoint(endpoint)?.connect_lazy();
+        let client = BlogServiceClient::new(channel);
         Ok(Self {
             client,
             token: None,
+            request_timeout: None,
         })
     }
 
@@ -41,6 +178,11 @@ impl GrpcBlogClient {
         self.token = None;
     }
 
+    /// Deadline of every following call; `None` lifts it
+    pub const fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
     fn create_request<T>(&self, message: T) -> Request<T> {
         let mut request = Request::new(message);
 
@@ -71,7 +213,10 @@ impl GrpcBlogClient {
             password: password.to_string(),
         });
 
-        let response = self.client.register(request).await?.into_inner();
+        let response =
+            within(self.request_timeout, self.client.register(request))
+                .await?
+                .into_inner();
 
         let user = response.user.ok_or_else(|| {
             BlogClientError::InvalidRequest(
@@ -81,18 +226,7 @@ impl GrpcBlogClient {
 
         Ok(AuthResponse {
             token: response.token,
-            user: User {
-                id: user.id.parse().unwrap_or(0),
-                username: user.username,
-                email: user.email,
-                created_at: chrono::DateTime::parse_from_rfc3339(
-                    &user.created_at,
-                )
-                .map_or_else(
-                    |_| chrono::Utc::now(),
-                    |dt| dt.with_timezone(&chrono::Utc),
-                ),
-            },
+            user: grpc_user_to_user(user)?,
         })
     }
 
@@ -111,7 +245,9 @@ impl GrpcBlogClient {
             password: password.to_string(),
         });
 
-        let response = self.client.login(request).await?.into_inner();
+        let response = within(self.request_timeout, self.client.login(request))
+            .await?
+            .into_inner();
 
         let user = response.user.ok_or_else(|| {
             BlogClientError::InvalidRequest(
@@ -121,18 +257,7 @@ impl GrpcBlogClient {
 
         Ok(AuthResponse {
             token: response.token,
-            user: User {
-                id: user.id.parse().unwrap_or(0),
-                username: user.username,
-                email: user.email,
-                created_at: chrono::DateTime::parse_from_rfc3339(
-                    &user.created_at,
-                )
-                .map_or_else(
-                    |_| chrono::Utc::now(),
-                    |dt| dt.with_timezone(&chrono::Utc),
-                ),
-            },
+            user: grpc_user_to_user(user)?,
         })
     }
 
@@ -149,9 +274,13 @@ impl GrpcBlogClient {
         let request = self.create_request(CreatePostRequest {
             title: title.to_string(),
             content: content.to_string(),
+            status: None,
         });
 
-        let response = self.client.create_post(request).await?.into_inner();
+        let response =
+            within(self.request_timeout, self.client.create_post(request))
+                .await?
+                .into_inner();
 
         let post = response.post.ok_or_else(|| {
             BlogClientError::InvalidRequest(
@@ -159,7 +288,7 @@ impl GrpcBlogClient {
             )
         })?;
 
-        Ok(grpc_post_to_post(post))
+        grpc_post_to_post(post)
     }
 
     /// Get a post by ID.
@@ -172,7 +301,10 @@ impl GrpcBlogClient {
             post_id: id.to_string(),
         });
 
-        let response = self.client.get_post(request).await?.into_inner();
+        let response =
+            within(self.request_timeout, self.client.get_post(request))
+                .await?
+                .into_inner();
 
         let post = response.post.ok_or_else(|| {
             BlogClientError::InvalidRequest(
@@ -180,7 +312,28 @@ impl GrpcBlogClient {
             )
         })?;
 
-        Ok(grpc_post_to_post(post))
+        grpc_post_to_post(post)
+    }
+
+    /// Ask the server whether it is ready to serve requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the gRPC call fails.
+    pub async fn get_health(
+        &mut self,
+    ) -> Result<HealthStatus, BlogClientError> {
+        let request = Request::new(HealthCheckRequest {});
+
+        let response =
+            within(self.request_timeout, self.client.health_check(request))
+                .await?
+                .into_inner();
+
+        Ok(HealthStatus {
+            status: response.status,
+            timestamp: response.timestamp,
+        })
     }
 
     /// Update an existing post.
@@ -198,9 +351,13 @@ impl GrpcBlogClient {
             post_id: id.to_string(),
             title: title.to_string(),
             content: content.to_string(),
+            expected_updated_at: None,
         });
 
-        let response = self.client.update_post(request).await?.into_inner();
+        let response =
+            within(self.request_timeout, self.client.update_post(request))
+                .await?
+                .into_inner();
 
         let post = response.post.ok_or_else(|| {
             BlogClientError::InvalidRequest(
@@ -208,7 +365,7 @@ impl GrpcBlogClient {
             )
         })?;
 
-        Ok(grpc_post_to_post(post))
+        grpc_post_to_post(post)
     }
 
     /// Delete a post by ID.
@@ -224,10 +381,64 @@ impl GrpcBlogClient {
             post_id: id.to_string(),
         });
 
-        self.client.delete_post(request).await?;
+        within(self.request_timeout, self.client.delete_post(request)).await?;
         Ok(())
     }
 
+    /// Publish a draft of the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the gRPC call fails or the response is missing post data.
+    pub async fn publish_post(
+        &mut self,
+        id: i64,
+    ) -> Result<Post, BlogClientError> {
+        let request = self.create_request(PublishPostRequest {
+            post_id: id.to_string(),
+        });
+
+        let response =
+            within(self.request_timeout, self.client.publish_post(request))
+                .await?
+                .into_inner();
+
+        let post = response.post.ok_or_else(|| {
+            BlogClientError::InvalidRequest(
+                "Missing post in response".to_string(),
+            )
+        })?;
+
+        grpc_post_to_post(post)
+    }
+
+    /// Turn a post of the caller back into a draft.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the gRPC call fails or the response is missing post data.
+    pub async fn unpublish_post(
+        &mut self,
+        id: i64,
+    ) -> Result<Post, BlogClientError> {
+        let request = self.create_request(UnpublishPostRequest {
+            post_id: id.to_string(),
+        });
+
+        let response =
+            within(self.request_timeout, self.client.unpublish_post(request))
+                .await?
+                .into_inner();
+
+        let post = response.post.ok_or_else(|| {
+            BlogClientError::InvalidRequest(
+                "Missing post in response".to_string(),
+            )
+        })?;
+
+        grpc_post_to_post(post)
+    }
+
     /// List posts with pagination.
     ///
     /// # Errors
@@ -245,42 +456,250 @@ impl GrpcBlogClient {
             page,
             page_size,
             author_id: None,
+            sort: None,
+            cursor: None,
         });
 
-        let response = self.client.list_posts(request).await?.into_inner();
+        let response =
+            within(self.request_timeout, self.client.list_posts(request))
+                .await?
+                .into_inner();
 
-        let posts: Vec<Post> =
-            response.posts.into_iter().map(grpc_post_to_post).collect();
+        let posts = response
+            .posts
+            .into_iter()
+            .map(grpc_post_to_post)
+            .collect::<Result<_, _>>()?;
 
         Ok(PostsList {
             posts,
             total: response.total_count,
             limit,
             offset,
+            has_more: response.has_more,
+        })
+    }
+
+    /// Every post in the server's default order, as it sends them. The server
+    /// reads the listing in batches, so neither side holds all of it.
+    /// The request timeout only bounds starting the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the call fails; failures mid-stream
+    /// end the stream with an error item.
+    pub async fn stream_posts(
+        &mut self,
+    ) -> Result<
+        impl Stream<Item = Result<Post, BlogClientError>> + Send + 'static,
+        BlogClientError,
+    > {
+        let request = Request::new(StreamPostsRequest {
+            sort: None,
+            cursor: None,
+        });
+
+        let posts =
+            within(self.request_timeout, self.client.stream_posts(request))
+                .await?
+                .into_inner();
+
+        Ok(posts.map(|post| {
+            post.map_err(BlogClientError::from)
+                .and_then(grpc_post_to_post)
+        }))
+    }
+
+    /// Comment on a post (requires authentication).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the gRPC call fails or the response is missing comment data.
+    pub async fn create_comment(
+        &mut self,
+        post_id: i64,
+        content: &str,
+    ) -> Result<Comment, BlogClientError> {
+        let request = self.create_request(CreateCommentRequest {
+            post_id: post_id.to_string(),
+            content: content.to_string(),
+        });
+
+        let response =
+            within(self.request_timeout, self.client.create_comment(request))
+                .await?
+                .into_inner();
+
+        let comment = response.comment.ok_or_else(|| {
+            BlogClientError::InvalidRequest(
+                "Missing comment in response".to_string(),
+            )
+        })?;
+
+        grpc_comment_to_comment(comment)
+    }
+
+    /// List a post's comments with pagination.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the gRPC call fails.
+    pub async fn list_comments(
+        &mut self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<CommentsList, BlogClientError> {
+        let page = i32::try_from(offset / limit).unwrap_or(i32::MAX) + 1;
+        let page_size = i32::try_from(limit).unwrap_or(i32::MAX);
+
+        let request = Request::new(ListCommentsRequest {
+            post_id: post_id.to_string(),
+            page,
+            page_size,
+        });
+
+        let response =
+            within(self.request_timeout, self.client.list_comments(request))
+                .await?
+                .into_inner();
+
+        Ok(CommentsList {
+            comments: response
+                .comments
+                .into_iter()
+                .map(grpc_comment_to_comment)
+                .collect::<Result<_, _>>()?,
+            total: response.total_count,
+            limit,
+            offset,
+            has_more: response.has_more,
         })
     }
 }
 
-fn grpc_post_to_post(post: crate::proto::Post) -> Post {
-    Post {
-        id: post.id.parse().unwrap_or(0),
+/// A numeric id sent as a string. A malformed one is an error: read as 0
+/// it would look like a real id and fail confusingly later.
+fn parse_id(field: &str, value: &str) -> Result<i64, BlogClientError> {
+    value.parse().map_err(|_| {
+        BlogClientError::InvalidRequest(format!(
+            "unparseable {field} in response: {value:?}"
+        ))
+    })
+}
+
+fn grpc_user_to_user(
+    user: crate::proto::User,
+) -> Result<User, BlogClientError> {
+    Ok(User {
+        id: parse_id("user id", &user.id)?,
+        username: user.username,
+        email: user.email,
+        created_at: parse_timestamp(&user.created_at)?,
+    })
+}
+
+fn grpc_comment_to_comment(
+    comment: crate::proto::Comment,
+) -> Result<Comment, BlogClientError> {
+    Ok(Comment {
+        id: parse_id("comment id", &comment.id)?,
+        post_id: parse_id("comment post_id", &comment.post_id)?,
+        author_id: parse_id("comment author_id", &comment.author_id)?,
+        author_username: if comment.author_username.is_empty() {
+            None
+        } else {
+            Some(comment.author_username)
+        },
+        content: comment.content,
+        created_at: parse_timestamp(&comment.created_at)?,
+    })
+}
+
+fn grpc_post_to_post(
+    post: crate::proto::Post,
+) -> Result<Post, BlogClientError> {
+    Ok(Post {
+        id: parse_id("post id", &post.id)?,
         title: post.title,
         content: post.content,
-        author_id: post.author_id.parse().unwrap_or(0),
+        author_id: parse_id("post author_id", &post.author_id)?,
         author_username: if post.author_username.is_empty() {
             None
         } else {
             Some(post.author_username)
         },
-        created_at: chrono::DateTime::parse_from_rfc3339(&post.created_at)
-            .map_or_else(
-                |_| chrono::Utc::now(),
-                |dt| dt.with_timezone(&chrono::Utc),
-            ),
-        updated_at: chrono::DateTime::parse_from_rfc3339(&post.updated_at)
-            .map_or_else(
-                |_| chrono::Utc::now(),
-                |dt| dt.with_timezone(&chrono::Utc),
-            ),
+        created_at: parse_timestamp(&post.created_at)?,
+        updated_at: parse_timestamp(&post.updated_at)?,
+        status: post.status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(id: &str) -> crate::proto::Post {
+        crate::proto::Post {
+            id: id.to_string(),
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            author_id: "7".to_string(),
+            author_username: String::new(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            updated_at: "2025-01-01T00:00:00Z".to_string(),
+            status: "published".to_string(),
+        }
+    }
+
+    /// Answers every call with `NOT_FOUND`, as a stand-in server
+    async fn serve_not_found(listener: tokio::net::TcpListener) {
+        let app = axum::Router::new().fallback(|| async {
+            [("content-type", "application/grpc"), ("grpc-status", "5")]
+        });
+        axum::serve(listener, app).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_calls_reach_a_server_started_after_the_client() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let mut client = GrpcBlogClient::new(
+            &format!("http://{addr}"),
+            &GrpcConfig::default(),
+        )
+        .unwrap();
+
+        let down = client.get_post(1).await;
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        tokio::spawn(serve_not_found(listener));
+        let up = client.get_post(1).await;
+
+        assert!(matches!(
+            down.unwrap_err().inner(),
+            BlogClientError::Grpc(status)
+                if status.code() == tonic::Code::Unavailable
+        ));
+        assert!(matches!(
+            up.unwrap_err().inner(),
+            BlogClientError::Grpc(status)
+                if status.code() == tonic::Code::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_malformed_id_is_an_error() {
+        let parsed = grpc_post_to_post(post("42")).unwrap();
+        let malformed = grpc_post_to_post(post("forty-two"));
+
+        assert_eq!((parsed.id, parsed.author_id), (42, 7));
+        assert!(parsed.author_username.is_none());
+        assert!(matches!(
+            malformed,
+            Err(BlogClientError::InvalidRequest(message))
+                if message.contains("post id")
+        ));
     }
 }
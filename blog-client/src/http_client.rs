@@ -1,10 +1,17 @@
 use std::time::Duration;
 
-use reqwest::{Client, Response};
+use reqwest::{Client, RequestBuilder, Response};
 use serde::{Deserialize, Serialize};
 
-use crate::error::BlogClientError;
-use crate::{AuthResponse, Post, PostsList, User};
+use crate::error::{BlogClientError, REQUEST_ID_HEADER};
+use crate::retry::{
+    IDEMPOTENCY_KEY_HEADER, RetryConfig, is_transient_error,
+    is_transient_status,
+};
+use crate::{
+    AuthResponse, Comment, CommentsList, HealthStatus, Post, PostsList, User,
+    parse_timestamp,
+};
 
 #[derive(Debug, Serialize)]
 struct RegisterRequest<'a> {
@@ -31,6 +38,11 @@ struct UpdatePostRequest<'a> {
     content: &'a str,
 }
 
+#[derive(Debug, Serialize)]
+struct CreateCommentRequest<'a> {
+    content: &'a str,
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiAuthResponse {
     token: String,
@@ -54,6 +66,7 @@ struct ApiPost {
     author_username: Option<String>,
     created_at: String,
     updated_at: String,
+    status: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -62,63 +75,98 @@ struct ApiPostsList {
     total: i64,
     limit: i64,
     offset: i64,
+    has_more: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiComment {
+    id: i64,
+    post_id: i64,
+    author_id: i64,
+    author_username: Option<String>,
+    content: String,
+    created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiCommentsList {
+    comments: Vec<ApiComment>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    has_more: bool,
 }
 
 #[derive(Debug, Deserialize)]
 struct ApiError {
     error: String,
+    #[serde(default)]
+    code: Option<String>,
 }
 
-impl From<ApiAuthResponse> for AuthResponse {
-    fn from(api: ApiAuthResponse) -> Self {
-        Self {
+impl TryFrom<ApiAuthResponse> for AuthResponse {
+    type Error = BlogClientError;
+
+    fn try_from(api: ApiAuthResponse) -> Result<Self, Self::Error> {
+        Ok(Self {
             token: api.token,
             user: User {
                 id: api.user.id,
                 username: api.user.username,
                 email: api.user.email,
-                created_at: chrono::DateTime::parse_from_rfc3339(
-                    &api.user.created_at,
-                )
-                .map_or_else(
-                    |_| chrono::Utc::now(),
-                    |dt| dt.with_timezone(&chrono::Utc),
-                ),
+                created_at: parse_timestamp(&api.user.created_at)?,
             },
-        }
+        })
     }
 }
 
-impl From<ApiPost> for Post {
-    fn from(api: ApiPost) -> Self {
-        Self {
+impl TryFrom<ApiPost> for Post {
+    type Error = BlogClientError;
+
+    fn try_from(api: ApiPost) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: api.id,
             title: api.title,
             content: api.content,
             author_id: api.author_id,
             author_username: api.author_username,
-            created_at: chrono::DateTime::parse_from_rfc3339(&api.created_at)
-                .map_or_else(
-                    |_| chrono::Utc::now(),
-                    |dt| dt.with_timezone(&chrono::Utc),
-                ),
-            updated_at: chrono::DateTime::parse_from_rfc3339(&api.updated_at)
-                .map_or_else(
-                    |_| chrono::Utc::now(),
-                    |dt| dt.with_timezone(&chrono::Utc),
-                ),
-        }
+            created_at: parse_timestamp(&api.created_at)?,
+            updated_at: parse_timestamp(&api.updated_at)?,
+            status: api.status,
+        })
+    }
+}
+
+impl TryFrom<ApiComment> for Comment {
+    type Error = BlogClientError;
+
+    fn try_from(api: ApiComment) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: api.id,
+            post_id: api.post_id,
+            author_id: api.author_id,
+            author_username: api.author_username,
+            content: api.content,
+            created_at: parse_timestamp(&api.created_at)?,
+        })
     }
 }
 
 pub struct HttpBlogClient {
     client: Client,
     base_url: String,
+    /// Prefix the server mounts the API under (its `API_BASE_PATH`)
+    api_base_path: String,
     token: Option<String>,
+    retry: RetryConfig,
+    /// Replaces the client-wide timeout for each request while set
+    request_timeout: Option<Duration>,
 }
 
 impl HttpBlogClient {
     const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    const DEFAULT_API_BASE_PATH: &str = "/api/v1";
+
     pub fn new(base_url: &str) -> Self {
         let client = Client::builder()
             .timeout(Self::DEFAULT_TIMEOUT)
@@ -128,10 +176,42 @@ impl HttpBlogClient {
         Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
+            api_base_path: Self::DEFAULT_API_BASE_PATH.to_string(),
             token: None,
+            retry: RetryConfig::disabled(),
+            request_timeout: None,
         }
     }
 
+    /// Retries transient failures of idempotent requests per `retry`;
+    /// without it every request is sent once
+    #[must_use]
+    pub const fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sends requests under `base_path` instead of `/api/v1`, for servers
+    /// with a different `API_BASE_PATH` or behind a proxy that rewrites it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError::InvalidRequest` unless `base_path` starts
+    /// with `/` and does not end with one.
+    pub fn with_api_base_path(
+        mut self,
+        base_path: &str,
+    ) -> Result<Self, BlogClientError> {
+        if !base_path.starts_with('/') || base_path.ends_with('/') {
+            return Err(BlogClientError::InvalidRequest(format!(
+                "API base path must start with '/' and not end with one: \
+                 {base_path:?}"
+            )));
+        }
+        base_path.clone_into(&mut self.api_base_path);
+        Ok(self)
+    }
+
     pub fn set_token(&mut self, token: String) {
         self.token = Some(token);
     }
@@ -144,34 +224,85 @@ impl HttpBlogClient {
         self.token = None;
     }
 
+    /// Timeout of every following request (each retry attempt on its
+    /// own) instead of the default 30 seconds; `None` restores that
+    pub const fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
     fn url(&self, path: &str) -> String {
-        format!("{}/api/v1{}", self.base_url, path)
+        format!("{}{}{}", self.base_url, self.api_base_path, path)
     }
 
-    async fn handle_error_response(
+    /// Sends `request`, retrying connection errors and 5xx responses with
+    /// exponential backoff as far as the retry policy allows. The last
+    /// attempt's outcome is returned as is.
+    async fn send(
         &self,
-        response: reqwest::Response,
-    ) -> BlogClientError {
-        let status = response.status();
-
-        if status == reqwest::StatusCode::NOT_FOUND {
-            return BlogClientError::NotFound;
+        request: RequestBuilder,
+    ) -> Result<Response, BlogClientError> {
+        let mut request = request.build()?;
+        if let Some(timeout) = self.request_timeout {
+            *request.timeout_mut() = Some(timeout);
         }
-
-        if status == reqwest::StatusCode::UNAUTHORIZED {
-            let msg = response
-                .json::<ApiError>()
-                .await
-                .map_or_else(|_| "Unauthorized".to_string(), |e| e.error);
-            return BlogClientError::Unauthorized(msg);
+        let attempts = self.retry.attempts_for(&request);
+
+        for attempt in 1..attempts {
+            // Streaming bodies cannot be replayed
+            let Some(retryable) = request.try_clone() else {
+                break;
+            };
+            match self.client.execute(retryable).await {
+                Ok(response) if !is_transient_status(response.status()) => {
+                    return Ok(response);
+                }
+                Err(e) if !is_transient_error(&e) => return Err(e.into()),
+                _ => tokio::time::sleep(self.retry.delay(attempt)).await,
+            }
         }
 
-        let msg = response
-            .json::<ApiError>()
+        Ok(self.client.execute(request).await?)
+    }
+
+    async fn handle_error_response(
+        &self,
+        response: reqwest::Response,
+    ) -> BlogClientError {
+        let request_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .map(str::to_string);
+        Self::error_for_status(response)
             .await
-            .map_or_else(|_| format!("HTTP error: {status}"), |e| e.error);
+            .with_request_id(request_id.as_deref())
+    }
 
-        BlogClientError::InvalidRequest(msg)
+    async fn error_for_status(response: reqwest::Response) -> BlogClientError {
+        let status = response.status();
+        let (code, message) = match response.json::<ApiError>().await {
+            Ok(body) => (body.code, Some(body.error)),
+            Err(_) => (None, None),
+        };
+
+        match status {
+            reqwest::StatusCode::NOT_FOUND => {
+                BlogClientError::NotFound { code }
+            }
+            reqwest::StatusCode::UNAUTHORIZED => {
+                BlogClientError::Unauthorized {
+                    code,
+                    message: message
+                        .unwrap_or_else(|| "Unauthorized".to_string()),
+                }
+            }
+            _ => BlogClientError::Api {
+                status: status.as_u16(),
+                code,
+                message: message
+                    .unwrap_or_else(|| format!("HTTP error: {status}")),
+            },
+        }
     }
 
     /// Register a new user.
@@ -186,14 +317,13 @@ impl HttpBlogClient {
         password: &str,
     ) -> Result<AuthResponse, BlogClientError> {
         let response = self
-            .client
-            .post(self.url("/auth/register"))
-            .json(&RegisterRequest {
-                username,
-                email,
-                password,
-            })
-            .send()
+            .send(self.client.post(self.url("/auth/register")).json(
+                &RegisterRequest {
+                    username,
+                    email,
+                    password,
+                },
+            ))
             .await?;
 
         if !response.status().is_success() {
@@ -201,7 +331,7 @@ impl HttpBlogClient {
         }
 
         let api_response: ApiAuthResponse = response.json().await?;
-        Ok(api_response.into())
+        api_response.try_into()
     }
 
     /// Login with username and password.
@@ -215,10 +345,11 @@ impl HttpBlogClient {
         password: &str,
     ) -> Result<AuthResponse, BlogClientError> {
         let response = self
-            .client
-            .post(self.url("/auth/login"))
-            .json(&LoginRequest { username, password })
-            .send()
+            .send(
+                self.client
+                    .post(self.url("/auth/login"))
+                    .json(&LoginRequest { username, password }),
+            )
             .await?;
 
         if !response.status().is_success() {
@@ -226,11 +357,15 @@ impl HttpBlogClient {
         }
 
         let api_response: ApiAuthResponse = response.json().await?;
-        Ok(api_response.into())
+        api_response.try_into()
     }
 
     /// Create a new post.
     ///
+    /// With an `idempotency_key`, the server creates the post only once
+    /// however often the same key is sent, and answers repeats with that
+    /// post. Such a request is also retried like a GET.
+    ///
     /// # Errors
     ///
     /// Returns `BlogClientError` if no token is set, the HTTP request fails, or the server returns an error.
@@ -238,23 +373,46 @@ impl HttpBlogClient {
         &self,
         title: &str,
         content: &str,
+        idempotency_key: Option<&str>,
     ) -> Result<Post, BlogClientError> {
         let token = self.token.as_ref().ok_or(BlogClientError::NoToken)?;
 
-        let response = self
+        let mut request = self
             .client
             .post(self.url("/posts"))
             .bearer_auth(token)
-            .json(&CreatePostRequest { title, content })
-            .send()
-            .await?;
+            .json(&CreatePostRequest { title, content });
+        if let Some(key) = idempotency_key {
+            request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             return Err(self.handle_error_response(response).await);
         }
 
         let api_post: ApiPost = response.json().await?;
-        Ok(api_post.into())
+        api_post.try_into()
+    }
+
+    /// Ask the server whether it is ready to serve requests. The server
+    /// answers 503 with a health body when its database is down, which is
+    /// returned like a healthy answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the HTTP request fails or the server returns an error.
+    pub async fn get_health(&self) -> Result<HealthStatus, BlogClientError> {
+        let response = self.send(self.client.get(self.url("/health"))).await?;
+
+        let status = response.status();
+        if !status.is_success()
+            && status != reqwest::StatusCode::SERVICE_UNAVAILABLE
+        {
+            return Err(self.handle_error_response(response).await);
+        }
+
+        Ok(response.json().await?)
     }
 
     /// Get a post by ID.
@@ -264,9 +422,7 @@ impl HttpBlogClient {
     /// Returns `BlogClientError` if the HTTP request fails or the post is not found.
     pub async fn get_post(&self, id: i64) -> Result<Post, BlogClientError> {
         let response = self
-            .client
-            .get(self.url(&format!("/posts/{id}")))
-            .send()
+            .send(self.client.get(self.url(&format!("/posts/{id}"))))
             .await?;
 
         if !response.status().is_success() {
@@ -274,7 +430,7 @@ impl HttpBlogClient {
         }
 
         let api_post: ApiPost = response.json().await?;
-        Ok(api_post.into())
+        api_post.try_into()
     }
 
     /// Update an existing post.
@@ -291,11 +447,12 @@ impl HttpBlogClient {
         let token = self.token.as_ref().ok_or(BlogClientError::NoToken)?;
 
         let response = self
-            .client
-            .put(self.url(&format!("/posts/{id}")))
-            .bearer_auth(token)
-            .json(&UpdatePostRequest { title, content })
-            .send()
+            .send(
+                self.client
+                    .put(self.url(&format!("/posts/{id}")))
+                    .bearer_auth(token)
+                    .json(&UpdatePostRequest { title, content }),
+            )
             .await?;
 
         if !response.status().is_success() {
@@ -303,7 +460,7 @@ impl HttpBlogClient {
         }
 
         let api_post: ApiPost = response.json().await?;
-        Ok(api_post.into())
+        api_post.try_into()
     }
 
     /// Delete a post by ID.
@@ -315,10 +472,11 @@ impl HttpBlogClient {
         let token = self.token.as_ref().ok_or(BlogClientError::NoToken)?;
 
         let response = self
-            .client
-            .delete(self.url(&format!("/posts/{id}")))
-            .bearer_auth(token)
-            .send()
+            .send(
+                self.client
+                    .delete(self.url(&format!("/posts/{id}")))
+                    .bearer_auth(token),
+            )
             .await?;
 
         if !response.status().is_success() {
@@ -328,6 +486,51 @@ impl HttpBlogClient {
         Ok(())
     }
 
+    /// Publish a draft of the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if no token is set, the HTTP request fails, or the server returns an error.
+    pub async fn publish_post(&self, id: i64) -> Result<Post, BlogClientError> {
+        self.post_action(id, "publish").await
+    }
+
+    /// Turn a post of the caller back into a draft.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if no token is set, the HTTP request fails, or the server returns an error.
+    pub async fn unpublish_post(
+        &self,
+        id: i64,
+    ) -> Result<Post, BlogClientError> {
+        self.post_action(id, "unpublish").await
+    }
+
+    /// `POST /posts/{id}/{action}` without a body, answered with the post
+    async fn post_action(
+        &self,
+        id: i64,
+        action: &str,
+    ) -> Result<Post, BlogClientError> {
+        let token = self.token.as_ref().ok_or(BlogClientError::NoToken)?;
+
+        let response = self
+            .send(
+                self.client
+                    .post(self.url(&format!("/posts/{id}/{action}")))
+                    .bearer_auth(token),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+
+        let api_post: ApiPost = response.json().await?;
+        api_post.try_into()
+    }
+
     /// List posts with pagination.
     ///
     /// # Errors
@@ -338,11 +541,12 @@ impl HttpBlogClient {
         limit: i64,
         offset: i64,
     ) -> Result<PostsList, BlogClientError> {
-        let response: Response = self
-            .client
-            .get(self.url("/posts"))
-            .query(&[("limit", limit), ("offset", offset)])
-            .send()
+        let response = self
+            .send(
+                self.client
+                    .get(self.url("/posts"))
+                    .query(&[("limit", limit), ("offset", offset)]),
+            )
             .await?;
 
         if !response.status().is_success() {
@@ -351,10 +555,369 @@ impl HttpBlogClient {
 
         let api_list: ApiPostsList = response.json().await?;
         Ok(PostsList {
-            posts: api_list.posts.into_iter().map(Post::from).collect(),
+            posts: api_list
+                .posts
+                .into_iter()
+                .map(Post::try_from)
+                .collect::<Result<_, _>>()?,
+            total: api_list.total,
+            limit: api_list.limit,
+            offset: api_list.offset,
+            has_more: api_list.has_more,
+        })
+    }
+
+    /// Comment on a post (requires authentication).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the HTTP request fails, the post is not found or no token is set.
+    pub async fn create_comment(
+        &self,
+        post_id: i64,
+        content: &str,
+    ) -> Result<Comment, BlogClientError> {
+        let token = self.token.as_ref().ok_or(BlogClientError::NoToken)?;
+
+        let response = self
+            .send(
+                self.client
+                    .post(self.url(&format!("/posts/{post_id}/comments")))
+                    .bearer_auth(token)
+                    .json(&CreateCommentRequest { content }),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+
+        let api_comment: ApiComment = response.json().await?;
+        api_comment.try_into()
+    }
+
+    /// List a post's comments with pagination.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the HTTP request fails or the post is not found.
+    pub async fn list_comments(
+        &self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<CommentsList, BlogClientError> {
+        let response = self
+            .send(
+                self.client
+                    .get(self.url(&format!("/posts/{post_id}/comments")))
+                    .query(&[("limit", limit), ("offset", offset)]),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+
+        let api_list: ApiCommentsList = response.json().await?;
+        Ok(CommentsList {
+            comments: api_list
+                .comments
+                .into_iter()
+                .map(Comment::try_from)
+                .collect::<Result<_, _>>()?,
             total: api_list.total,
             limit: api_list.limit,
             offset: api_list.offset,
+            has_more: api_list.has_more,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{
+        Json, Router, extract::State, http::StatusCode, response::IntoResponse,
+        routing::get,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    /// Answers 503 to the first `failures` requests, then a post
+    #[derive(Clone)]
+    struct Flaky {
+        failures: usize,
+        hits: Arc<AtomicUsize>,
+    }
+
+    async fn flaky(State(flaky): State<Flaky>) -> impl IntoResponse {
+        if flaky.hits.fetch_add(1, Ordering::SeqCst) < flaky.failures {
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+        Json(json!({
+            "id": 1,
+            "title": "Title",
+            "content": "Content",
+            "author_id": 1,
+            "author_username": "author",
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-01T00:00:00Z",
+            "status": "published",
+        }))
+        .into_response()
+    }
+
+    async fn serve(failures: usize) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route("/api/v1/posts/{id}", get(flaky).put(flaky))
+            .route("/api/v1/posts", axum::routing::post(flaky))
+            .with_state(Flaky {
+                failures,
+                hits: hits.clone(),
+            });
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}"), hits)
+    }
+
+    fn retry() -> RetryConfig {
+        RetryConfig::default().base_delay(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn test_malformed_timestamp_is_an_error() {
+        let post = |created_at: &str| ApiPost {
+            id: 1,
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            author_id: 1,
+            author_username: None,
+            created_at: created_at.to_string(),
+            updated_at: "2025-01-01T00:00:00Z".to_string(),
+            status: "published".to_string(),
+        };
+
+        let parsed = Post::try_from(post("2025-01-01T03:00:00+03:00")).unwrap();
+        let malformed = Post::try_from(post("yesterday"));
+
+        assert_eq!(parsed.created_at, parsed.updated_at);
+        assert!(matches!(
+            malformed,
+            Err(BlogClientError::InvalidRequest(message))
+                if message.contains("unparseable timestamp")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_server_still_reports_health() {
+        let app = Router::new().route(
+            "/api/v1/health",
+            get(|| async {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(json!({
+                        "status": "unavailable",
+                        "timestamp": "2025-01-01 00:00:00",
+                    })),
+                )
+            }),
+        );
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = HttpBlogClient::new(&format!("http://{addr}"))
+            .with_retry(RetryConfig::disabled());
+
+        let health = client.get_health().await.unwrap();
+
+        assert_eq!(health.status, "unavailable");
+        assert!(!health.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_under_the_api_base_path() {
+        let app = Router::new().route(
+            "/custom/prefix/health",
+            get(|| async {
+                Json(json!({
+                    "status": "ok",
+                    "timestamp": "2025-01-01 00:00:00",
+                }))
+            }),
+        );
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = || HttpBlogClient::new(&format!("http://{addr}"));
+
+        let health = client()
+            .with_api_base_path("/custom/prefix")
+            .unwrap()
+            .get_health()
+            .await
+            .unwrap();
+
+        assert!(health.is_ok());
+        assert!(client().with_api_base_path("custom").is_err());
+        assert!(client().with_api_base_path("/custom/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_is_retried_until_it_succeeds() {
+        let (url, hits) = serve(2).await;
+        let client = HttpBlogClient::new(&url).with_retry(retry());
+
+        let post = client.get_post(1).await.unwrap();
+
+        assert_eq!(post.title, "Title");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_post_and_put_are_not_retried_by_default() {
+        let (url, hits) = serve(usize::MAX).await;
+        let mut client = HttpBlogClient::new(&url).with_retry(retry());
+        client.set_token("token".to_string());
+
+        let created = client.create_post("Title", "Content", None).await;
+        let updated = client.update_post(1, "Title", "Content").await;
+
+        assert!(created.is_err());
+        assert!(updated.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_post_with_idempotency_key_is_retried() {
+        let (url, hits) = serve(1).await;
+        let mut client = HttpBlogClient::new(&url).with_retry(retry());
+        client.set_token("token".to_string());
+
+        let post = client
+            .create_post("Title", "Content", Some("retry-1"))
+            .await
+            .unwrap();
+
+        assert_eq!(post.id, 1);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_opted_in_put_gives_up_after_max_attempts() {
+        let (url, hits) = serve(usize::MAX).await;
+        let mut client = HttpBlogClient::new(&url)
+            .with_retry(retry().max_attempts(4).retry_idempotent_writes(true));
+        client.set_token("token".to_string());
+
+        let result = client.update_post(1, "Title", "Content").await;
+
+        assert!(matches!(
+            result,
+            Err(BlogClientError::Api { status: 503, .. })
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_error_response_keeps_status_and_code() {
+        let app = Router::new().route(
+            "/api/v1/auth/register",
+            axum::routing::post(|| async {
+                (
+                    StatusCode::CONFLICT,
+                    Json(json!({
+                        "error": "User already exists",
+                        "code": "user_already_exists",
+                    })),
+                )
+            }),
+        );
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = HttpBlogClient::new(&format!("http://{addr}"));
+
+        let error = client
+            .register("ivan", "ivan@example.com", "secret123")
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code(), Some("user_already_exists"));
+        assert_eq!(error.http_status(), Some(409));
+        assert_eq!(error.to_string(), "HTTP 409: User already exists");
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_times_out() {
+        let app = Router::new().route(
+            "/api/v1/posts",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                StatusCode::OK
+            }),
+        );
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let mut client =
+            crate::BlogClient::new(crate::Transport::Http(url)).unwrap();
+
+        let result = client
+            .list_posts_with_timeout(10, 0, Duration::from_millis(50))
+            .await;
+
+        assert!(matches!(result, Err(BlogClientError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_error_carries_the_request_id() {
+        let app = Router::new().route(
+            "/api/v1/posts/{id}",
+            get(|| async {
+                (
+                    StatusCode::NOT_FOUND,
+                    [(REQUEST_ID_HEADER, "req-42")],
+                    Json(json!({
+                        "error": "Post not found",
+                        "code": "post_not_found",
+                    })),
+                )
+            }),
+        );
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let error = HttpBlogClient::new(&url).get_post(1).await.unwrap_err();
+
+        assert_eq!(error.request_id(), Some("req-42"));
+        assert!(matches!(error.inner(), BlogClientError::NotFound { .. }));
+        assert_eq!(error.code(), Some("post_not_found"));
+        assert!(error.to_string().contains("req-42"));
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_the_cap() {
+        let retry = RetryConfig::default()
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(300))
+            .jitter(0.0);
+
+        assert_eq!(retry.delay(1), Duration::from_millis(100));
+        assert_eq!(retry.delay(2), Duration::from_millis(200));
+        assert_eq!(retry.delay(3), Duration::from_millis(300));
+        assert!(retry.jitter(1.0).delay(1) <= Duration::from_millis(100));
+    }
+}
@@ -1,10 +1,14 @@
 pub mod error;
 pub mod grpc_client;
 pub mod http_client;
+pub mod retry;
+pub mod token;
 
 pub use error::BlogClientError;
-pub use grpc_client::GrpcBlogClient;
+pub use grpc_client::{GrpcBlogClient, GrpcConfig};
 pub use http_client::HttpBlogClient;
+pub use retry::RetryConfig;
+pub use token::UnverifiedClaims;
 
 // Generated protobuf code — allow clippy lints that cannot be fixed in auto-generated tonic/prost output
 #[allow(clippy::missing_errors_doc, clippy::derive_partial_eq_without_eq)]
@@ -12,9 +16,27 @@ pub mod proto {
     tonic::include_proto!("blog");
 }
 
+use std::pin::Pin;
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
+use futures_util::{Stream, TryStreamExt, stream};
 use serde::{Deserialize, Serialize};
 
+/// An RFC 3339 timestamp from the server. A malformed one is an error
+/// rather than some made-up time.
+pub(crate) fn parse_timestamp(
+    value: &str,
+) -> Result<DateTime<Utc>, BlogClientError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| {
+            BlogClientError::InvalidRequest(format!(
+                "unparseable timestamp: {value:?}"
+            ))
+        })
+}
+
 /// Transport type for the client
 #[derive(Debug, Clone)]
 pub enum Transport {
@@ -48,6 +70,8 @@ pub struct Post {
     pub author_username: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// `draft` or `published`; drafts are only visible to their author
+    pub status: String,
 }
 
 /// Paginated list of posts
@@ -57,6 +81,81 @@ pub struct PostsList {
     pub total: i64,
     pub limit: i64,
     pub offset: i64,
+    /// Whether a page after this one has posts
+    pub has_more: bool,
+}
+
+/// Page arithmetic over `total`, `limit` and `offset`. A `limit` of zero
+/// (or less) pages nowhere: there is no next page and no page count.
+impl PostsList {
+    /// Whether posts follow this page
+    #[must_use]
+    pub const fn has_next_page(&self) -> bool {
+        self.limit > 0 && self.offset.saturating_add(self.limit) < self.total
+    }
+
+    /// Whether posts precede this page
+    #[must_use]
+    pub const fn has_prev_page(&self) -> bool {
+        self.offset > 0
+    }
+
+    /// 1-based number of this page; an offset that is not a multiple of
+    /// `limit` counts as the page it starts in
+    #[must_use]
+    pub const fn current_page(&self) -> i64 {
+        if self.limit <= 0 || self.offset <= 0 {
+            return 1;
+        }
+        self.offset / self.limit + 1
+    }
+
+    /// Pages needed to cover `total` posts, 0 when there are none
+    #[must_use]
+    pub const fn total_pages(&self) -> i64 {
+        if self.limit <= 0 || self.total <= 0 {
+            return 0;
+        }
+        (self.total - 1) / self.limit + 1
+    }
+}
+
+/// Comment on a post
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: i64,
+    pub post_id: i64,
+    pub author_id: i64,
+    pub author_username: Option<String>,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Paginated list of a post's comments, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentsList {
+    pub comments: Vec<Comment>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    /// Whether a page after this one has comments
+    pub has_more: bool,
+}
+
+/// Server health as reported by its health probe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthStatus {
+    /// `"ok"`, or `"unavailable"` when the server cannot reach its database
+    pub status: String,
+    /// Server's local time when it answered
+    pub timestamp: String,
+}
+
+impl HealthStatus {
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
 }
 
 /// Unified blog client that can use either HTTP or gRPC transport
@@ -64,25 +163,46 @@ pub struct BlogClient {
     inner: ClientImpl,
 }
 
+/// Posts per request when [`BlogClient::stream_posts`] pages over HTTP
+pub const STREAM_PAGE_SIZE: i64 = 100;
+
+/// The HTTP listing as a stream, one page request at a time
+fn page_through(
+    client: &HttpBlogClient,
+) -> impl Stream<Item = Result<Post, BlogClientError>> + Send + '_ {
+    stream::try_unfold(Some(0), move |offset| async move {
+        let Some(offset) = offset else {
+            return Ok::<_, BlogClientError>(None);
+        };
+        let page = client.list_posts(STREAM_PAGE_SIZE, offset).await?;
+        let fetched = i64::try_from(page.posts.len()).unwrap_or(i64::MAX);
+        let next = (page.has_more && fetched > 0).then_some(offset + fetched);
+        Ok(Some((stream::iter(page.posts.into_iter().map(Ok)), next)))
+    })
+    .try_flatten()
+}
+
 enum ClientImpl {
     Http(HttpBlogClient),
     Grpc(GrpcBlogClient),
 }
 
 impl BlogClient {
-    /// Create a new client with the specified transport.
+    /// Create a new client with the specified transport. Nothing is sent
+    /// yet; the gRPC channel connects on the first call.
     ///
     /// # Errors
     ///
-    /// Returns `BlogClientError` if the connection to the server fails.
-    pub async fn new(transport: Transport) -> Result<Self, BlogClientError> {
+    /// Returns `BlogClientError` if the gRPC endpoint is not a valid URI.
+    pub fn new(transport: Transport) -> Result<Self, BlogClientError> {
         let inner = match transport {
             Transport::Http(base_url) => {
                 ClientImpl::Http(HttpBlogClient::new(&base_url))
             }
-            Transport::Grpc(endpoint) => {
-                ClientImpl::Grpc(GrpcBlogClient::new(&endpoint).await?)
-            }
+            Transport::Grpc(endpoint) => ClientImpl::Grpc(GrpcBlogClient::new(
+                &endpoint,
+                &GrpcConfig::default(),
+            )?),
         };
         Ok(Self { inner })
     }
@@ -122,6 +242,34 @@ impl BlogClient {
         email: &str,
         password: &str,
     ) -> Result<AuthResponse, BlogClientError> {
+        self.register_within(username, email, password, None).await
+    }
+
+    /// [`register`](Self::register), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the registration request fails or times out.
+    pub async fn register_with_timeout(
+        &mut self,
+        username: &str,
+        email: &str,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<AuthResponse, BlogClientError> {
+        self.register_within(username, email, password, Some(timeout))
+            .await
+    }
+
+    async fn register_within(
+        &mut self,
+        username: &str,
+        email: &str,
+        password: &str,
+        timeout: Option<Duration>,
+    ) -> Result<AuthResponse, BlogClientError> {
+        self.set_request_timeout(timeout);
         let response = match &mut self.inner {
             ClientImpl::Http(c) => {
                 c.register(username, email, password).await?
@@ -145,6 +293,31 @@ impl BlogClient {
         username: &str,
         password: &str,
     ) -> Result<AuthResponse, BlogClientError> {
+        self.login_within(username, password, None).await
+    }
+
+    /// [`login`](Self::login), failing with `BlogClientError::Timeout`
+    /// once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the login request fails or times out.
+    pub async fn login_with_timeout(
+        &mut self,
+        username: &str,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<AuthResponse, BlogClientError> {
+        self.login_within(username, password, Some(timeout)).await
+    }
+
+    async fn login_within(
+        &mut self,
+        username: &str,
+        password: &str,
+        timeout: Option<Duration>,
+    ) -> Result<AuthResponse, BlogClientError> {
+        self.set_request_timeout(timeout);
         let response = match &mut self.inner {
             ClientImpl::Http(c) => c.login(username, password).await?,
             ClientImpl::Grpc(c) => c.login(username, password).await?,
@@ -154,7 +327,8 @@ impl BlogClient {
         Ok(response)
     }
 
-    /// Create a new post (requires authentication).
+    /// Create a new post (requires authentication). The post starts as a
+    /// draft; see [`publish_post`](Self::publish_post).
     ///
     /// # Errors
     ///
@@ -164,8 +338,33 @@ impl BlogClient {
         title: &str,
         content: &str,
     ) -> Result<Post, BlogClientError> {
+        self.create_post_within(title, content, None).await
+    }
+
+    /// [`create_post`](Self::create_post), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out or authentication is missing.
+    pub async fn create_post_with_timeout(
+        &mut self,
+        title: &str,
+        content: &str,
+        timeout: Duration,
+    ) -> Result<Post, BlogClientError> {
+        self.create_post_within(title, content, Some(timeout)).await
+    }
+
+    async fn create_post_within(
+        &mut self,
+        title: &str,
+        content: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Post, BlogClientError> {
+        self.set_request_timeout(timeout);
         match &mut self.inner {
-            ClientImpl::Http(c) => c.create_post(title, content).await,
+            ClientImpl::Http(c) => c.create_post(title, content, None).await,
             ClientImpl::Grpc(c) => c.create_post(title, content).await,
         }
     }
@@ -176,6 +375,29 @@ impl BlogClient {
     ///
     /// Returns `BlogClientError` if the request fails or the post is not found.
     pub async fn get_post(&mut self, id: i64) -> Result<Post, BlogClientError> {
+        self.get_post_within(id, None).await
+    }
+
+    /// [`get_post`](Self::get_post), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out or the post is not found.
+    pub async fn get_post_with_timeout(
+        &mut self,
+        id: i64,
+        timeout: Duration,
+    ) -> Result<Post, BlogClientError> {
+        self.get_post_within(id, Some(timeout)).await
+    }
+
+    async fn get_post_within(
+        &mut self,
+        id: i64,
+        timeout: Option<Duration>,
+    ) -> Result<Post, BlogClientError> {
+        self.set_request_timeout(timeout);
         match &mut self.inner {
             ClientImpl::Http(c) => c.get_post(id).await,
             ClientImpl::Grpc(c) => c.get_post(id).await,
@@ -193,6 +415,34 @@ impl BlogClient {
         title: &str,
         content: &str,
     ) -> Result<Post, BlogClientError> {
+        self.update_post_within(id, title, content, None).await
+    }
+
+    /// [`update_post`](Self::update_post), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out or authentication is missing.
+    pub async fn update_post_with_timeout(
+        &mut self,
+        id: i64,
+        title: &str,
+        content: &str,
+        timeout: Duration,
+    ) -> Result<Post, BlogClientError> {
+        self.update_post_within(id, title, content, Some(timeout))
+            .await
+    }
+
+    async fn update_post_within(
+        &mut self,
+        id: i64,
+        title: &str,
+        content: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Post, BlogClientError> {
+        self.set_request_timeout(timeout);
         match &mut self.inner {
             ClientImpl::Http(c) => c.update_post(id, title, content).await,
             ClientImpl::Grpc(c) => c.update_post(id, title, content).await,
@@ -208,12 +458,104 @@ impl BlogClient {
         &mut self,
         id: i64,
     ) -> Result<(), BlogClientError> {
+        self.delete_post_within(id, None).await
+    }
+
+    /// [`delete_post`](Self::delete_post), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out or authentication is missing.
+    pub async fn delete_post_with_timeout(
+        &mut self,
+        id: i64,
+        timeout: Duration,
+    ) -> Result<(), BlogClientError> {
+        self.delete_post_within(id, Some(timeout)).await
+    }
+
+    async fn delete_post_within(
+        &mut self,
+        id: i64,
+        timeout: Option<Duration>,
+    ) -> Result<(), BlogClientError> {
+        self.set_request_timeout(timeout);
         match &mut self.inner {
             ClientImpl::Http(c) => c.delete_post(id).await,
             ClientImpl::Grpc(c) => c.delete_post(id).await,
         }
     }
 
+    /// Make a draft of the caller visible to everyone (requires
+    /// authentication).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails or authentication is missing.
+    pub async fn publish_post(
+        &mut self,
+        id: i64,
+    ) -> Result<Post, BlogClientError> {
+        self.set_post_status_within(id, true, None).await
+    }
+
+    /// [`publish_post`](Self::publish_post), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out or authentication is missing.
+    pub async fn publish_post_with_timeout(
+        &mut self,
+        id: i64,
+        timeout: Duration,
+    ) -> Result<Post, BlogClientError> {
+        self.set_post_status_within(id, true, Some(timeout)).await
+    }
+
+    /// Turn a post of the caller back into a draft (requires
+    /// authentication).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails or authentication is missing.
+    pub async fn unpublish_post(
+        &mut self,
+        id: i64,
+    ) -> Result<Post, BlogClientError> {
+        self.set_post_status_within(id, false, None).await
+    }
+
+    /// [`unpublish_post`](Self::unpublish_post), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out or authentication is missing.
+    pub async fn unpublish_post_with_timeout(
+        &mut self,
+        id: i64,
+        timeout: Duration,
+    ) -> Result<Post, BlogClientError> {
+        self.set_post_status_within(id, false, Some(timeout)).await
+    }
+
+    async fn set_post_status_within(
+        &mut self,
+        id: i64,
+        publish: bool,
+        timeout: Option<Duration>,
+    ) -> Result<Post, BlogClientError> {
+        self.set_request_timeout(timeout);
+        match (&mut self.inner, publish) {
+            (ClientImpl::Http(c), true) => c.publish_post(id).await,
+            (ClientImpl::Http(c), false) => c.unpublish_post(id).await,
+            (ClientImpl::Grpc(c), true) => c.publish_post(id).await,
+            (ClientImpl::Grpc(c), false) => c.unpublish_post(id).await,
+        }
+    }
+
     /// List posts with pagination.
     ///
     /// # Errors
@@ -224,9 +566,246 @@ impl BlogClient {
         limit: i64,
         offset: i64,
     ) -> Result<PostsList, BlogClientError> {
+        self.list_posts_within(limit, offset, None).await
+    }
+
+    /// [`list_posts`](Self::list_posts), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails or times out.
+    pub async fn list_posts_with_timeout(
+        &mut self,
+        limit: i64,
+        offset: i64,
+        timeout: Duration,
+    ) -> Result<PostsList, BlogClientError> {
+        self.list_posts_within(limit, offset, Some(timeout)).await
+    }
+
+    async fn list_posts_within(
+        &mut self,
+        limit: i64,
+        offset: i64,
+        timeout: Option<Duration>,
+    ) -> Result<PostsList, BlogClientError> {
+        self.set_request_timeout(timeout);
         match &mut self.inner {
             ClientImpl::Http(c) => c.list_posts(limit, offset).await,
             ClientImpl::Grpc(c) => c.list_posts(limit, offset).await,
         }
     }
+
+    /// Every post, one at a time. Over gRPC the server streams them; over
+    /// HTTP pages of [`STREAM_PAGE_SIZE`] are fetched as the stream is
+    /// read, so posts created meanwhile may shift between pages.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the gRPC stream cannot be started;
+    /// later failures end the stream with an error item.
+    pub async fn stream_posts(
+        &mut self,
+    ) -> Result<
+        Pin<Box<dyn Stream<Item = Result<Post, BlogClientError>> + Send + '_>>,
+        BlogClientError,
+    > {
+        self.set_request_timeout(None);
+        match &mut self.inner {
+            ClientImpl::Http(c) => Ok(Box::pin(page_through(c))),
+            ClientImpl::Grpc(c) => Ok(Box::pin(c.stream_posts().await?)),
+        }
+    }
+
+    /// Comment on a post (requires authentication).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, the post is not found or authentication is missing.
+    pub async fn create_comment(
+        &mut self,
+        post_id: i64,
+        content: &str,
+    ) -> Result<Comment, BlogClientError> {
+        self.create_comment_within(post_id, content, None).await
+    }
+
+    /// [`create_comment`](Self::create_comment), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out, the post is not found or authentication is missing.
+    pub async fn create_comment_with_timeout(
+        &mut self,
+        post_id: i64,
+        content: &str,
+        timeout: Duration,
+    ) -> Result<Comment, BlogClientError> {
+        self.create_comment_within(post_id, content, Some(timeout))
+            .await
+    }
+
+    async fn create_comment_within(
+        &mut self,
+        post_id: i64,
+        content: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Comment, BlogClientError> {
+        self.set_request_timeout(timeout);
+        match &mut self.inner {
+            ClientImpl::Http(c) => c.create_comment(post_id, content).await,
+            ClientImpl::Grpc(c) => c.create_comment(post_id, content).await,
+        }
+    }
+
+    /// List a post's comments with pagination.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails or the post is not found.
+    pub async fn list_comments(
+        &mut self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<CommentsList, BlogClientError> {
+        self.list_comments_within(post_id, limit, offset, None)
+            .await
+    }
+
+    /// [`list_comments`](Self::list_comments), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails, times out or the post is not found.
+    pub async fn list_comments_with_timeout(
+        &mut self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+        timeout: Duration,
+    ) -> Result<CommentsList, BlogClientError> {
+        self.list_comments_within(post_id, limit, offset, Some(timeout))
+            .await
+    }
+
+    async fn list_comments_within(
+        &mut self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+        timeout: Option<Duration>,
+    ) -> Result<CommentsList, BlogClientError> {
+        self.set_request_timeout(timeout);
+        match &mut self.inner {
+            ClientImpl::Http(c) => {
+                c.list_comments(post_id, limit, offset).await
+            }
+            ClientImpl::Grpc(c) => {
+                c.list_comments(post_id, limit, offset).await
+            }
+        }
+    }
+
+    /// Ask the server whether it is ready to serve requests. A server
+    /// whose database is down still answers, with a status other than
+    /// `"ok"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails.
+    pub async fn get_health(
+        &mut self,
+    ) -> Result<HealthStatus, BlogClientError> {
+        self.get_health_within(None).await
+    }
+
+    /// [`get_health`](Self::get_health), failing with
+    /// `BlogClientError::Timeout` once `timeout` passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError` if the request fails or times out.
+    pub async fn get_health_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<HealthStatus, BlogClientError> {
+        self.get_health_within(Some(timeout)).await
+    }
+
+    async fn get_health_within(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<HealthStatus, BlogClientError> {
+        self.set_request_timeout(timeout);
+        match &mut self.inner {
+            ClientImpl::Http(c) => c.get_health().await,
+            ClientImpl::Grpc(c) => c.get_health().await,
+        }
+    }
+
+    /// Every operation sets its own timeout, so one left behind by a
+    /// cancelled call never leaks into the next
+    const fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        match &mut self.inner {
+            ClientImpl::Http(c) => c.set_request_timeout(timeout),
+            ClientImpl::Grpc(c) => c.set_request_timeout(timeout),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(total: i64, limit: i64, offset: i64) -> PostsList {
+        PostsList {
+            posts: Vec::new(),
+            total,
+            limit,
+            offset,
+            has_more: offset + limit < total,
+        }
+    }
+
+    #[test]
+    fn test_pages_of_exact_multiple() {
+        let first = page(30, 10, 0);
+        let last = page(30, 10, 20);
+
+        assert_eq!(first.total_pages(), 3);
+        assert_eq!(first.current_page(), 1);
+        assert!(first.has_next_page());
+        assert!(!first.has_prev_page());
+        assert_eq!(last.current_page(), 3);
+        assert!(!last.has_next_page());
+        assert!(last.has_prev_page());
+    }
+
+    #[test]
+    fn test_partial_final_page() {
+        let middle = page(25, 10, 10);
+        let last = page(25, 10, 20);
+
+        assert_eq!(last.total_pages(), 3);
+        assert!(middle.has_next_page());
+        assert_eq!(last.current_page(), 3);
+        assert!(!last.has_next_page());
+    }
+
+    #[test]
+    fn test_zero_limit_and_empty_list() {
+        let zero = page(25, 0, 0);
+        let empty = page(0, 10, 0);
+
+        assert_eq!(zero.total_pages(), 0);
+        assert_eq!(zero.current_page(), 1);
+        assert!(!zero.has_next_page());
+        assert_eq!(empty.total_pages(), 0);
+        assert!(!empty.has_next_page());
+        assert!(!empty.has_prev_page());
+    }
 }
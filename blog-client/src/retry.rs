@@ -0,0 +1,131 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use reqwest::{Method, Request, StatusCode};
+
+/// Header that lets the server recognize a repeated POST
+pub(crate) const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// When and how `HttpBlogClient` repeats failed requests.
+///
+/// Only connection errors, timeouts and 5xx responses are retried, and only
+/// for idempotent requests: GET and requests carrying an `Idempotency-Key`
+/// (`create_post` given a key) always, PUT and DELETE when
+/// [`retry_idempotent_writes`](Self::retry_idempotent_writes) is set. Other
+/// POSTs, such as register, login and `create_post` without a key, are
+/// never repeated.
+///
+/// ```
+/// use std::time::Duration;
+/// use blog_client::RetryConfig;
+///
+/// let retry = RetryConfig::default()
+///     .max_attempts(5)
+///     .base_delay(Duration::from_millis(100))
+///     .retry_idempotent_writes(true);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    idempotent_writes: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: 0.5,
+            idempotent_writes: false,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// A single attempt, i.e. no retries
+    pub fn disabled() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Attempts in total, including the first one; at least 1
+    #[must_use]
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry, doubled for each one after it
+    #[must_use]
+    pub const fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Upper bound of a single delay
+    #[must_use]
+    pub const fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Fraction of each delay that is randomized, from 0.0 (fixed delays)
+    /// to 1.0 (anywhere between zero and the full delay). Spreads out
+    /// clients that failed at the same moment.
+    #[must_use]
+    pub const fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Also retry PUT and DELETE, which the server treats as idempotent
+    #[must_use]
+    pub const fn retry_idempotent_writes(mut self, enabled: bool) -> Self {
+        self.idempotent_writes = enabled;
+        self
+    }
+
+    /// Attempts `request` gets
+    pub(crate) fn attempts_for(&self, request: &Request) -> u32 {
+        let method = request.method();
+        let retryable = *method == Method::GET
+            || *method == Method::HEAD
+            || request.headers().contains_key(IDEMPOTENCY_KEY_HEADER)
+            || (self.idempotent_writes
+                && (*method == Method::PUT || *method == Method::DELETE));
+        if retryable { self.max_attempts } else { 1 }
+    }
+
+    /// Delay after the failed attempt number `attempt` (starting at 1)
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        delay.mul_f64(random_fraction().mul_add(-self.jitter, 1.0))
+    }
+}
+
+/// Whether a failure may go away on its own
+pub(crate) fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error()
+}
+
+pub(crate) fn is_transient_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout()
+}
+
+/// Uniform in `[0, 1)`, good enough to spread retries; std seeds every
+/// `RandomState` randomly
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    // The top 53 bits fill an f64 mantissa exactly
+    #[allow(clippy::cast_precision_loss)]
+    let fraction = (random >> 11) as f64 / (1u64 << 53) as f64;
+    fraction
+}
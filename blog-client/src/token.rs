@@ -0,0 +1,109 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::BlogClientError;
+
+/// Claims of an access token, read WITHOUT checking its signature.
+///
+/// Verifying needs the server's secret, so anyone can forge a token that
+/// decodes fine here. Use it to show what a stored token says, never to
+/// decide whether to trust it; only the server can do that.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnverifiedClaims {
+    pub user_id: i64,
+    pub username: String,
+    #[serde(default)]
+    pub email_verified: bool,
+    /// Missing from tokens issued before roles existed
+    #[serde(default)]
+    pub role: Option<String>,
+    /// Admin acting as the user, on impersonation tokens
+    #[serde(default)]
+    pub impersonator_id: Option<i64>,
+    /// Expiry as a Unix timestamp
+    pub exp: i64,
+    /// Issue time as a Unix timestamp
+    pub iat: i64,
+}
+
+impl UnverifiedClaims {
+    /// Decodes the payload of `token` without verifying it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlogClientError::MalformedToken` if `token` is not a JWT
+    /// carrying access token claims.
+    pub fn decode(token: &str) -> Result<Self, BlogClientError> {
+        let malformed =
+            |reason: &str| BlogClientError::MalformedToken(reason.to_string());
+
+        let mut parts = token.trim().split('.');
+        let (Some(_header), Some(payload), Some(_signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed("expected three dot-separated parts"));
+        };
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| malformed("payload is not base64url"))?;
+
+        serde_json::from_slice(&payload)
+            .map_err(|e| malformed(&format!("unexpected claims: {e}")))
+    }
+
+    pub const fn expires_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.exp, 0)
+    }
+
+    pub const fn issued_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.iat, 0)
+    }
+
+    /// Whether the expiry has passed by `now`. The server allows a little
+    /// clock skew on top, so a token that just expired may still work.
+    pub const fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.exp <= now.timestamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(payload: &str) -> String {
+        format!("eyJhbGciOiJIUzI1NiJ9.{}.c2ln", URL_SAFE_NO_PAD.encode(payload))
+    }
+
+    #[test]
+    fn test_decodes_claims_without_the_secret() {
+        let claims = UnverifiedClaims::decode(&token(
+            r#"{"user_id":7,"username":"ivan","role":"admin","exp":1000,"iat":10}"#,
+        ))
+        .unwrap();
+
+        assert_eq!(claims.user_id, 7);
+        assert_eq!(claims.username, "ivan");
+        assert_eq!(claims.role.as_deref(), Some("admin"));
+        assert!(
+            claims.is_expired_at(DateTime::from_timestamp(1000, 0).unwrap())
+        );
+        assert!(
+            !claims.is_expired_at(DateTime::from_timestamp(999, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_tokens() {
+        for token in ["", "a.b", "a.!!!.c", "a.b.c.d", &token(r#"{"exp":1}"#)] {
+            assert!(
+                matches!(
+                    UnverifiedClaims::decode(token),
+                    Err(BlogClientError::MalformedToken(_))
+                ),
+                "{token}"
+            );
+        }
+    }
+}
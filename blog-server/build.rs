@@ -2,10 +2,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Recompile if proto file changes
     println!("cargo:rerun-if-changed=proto/blog.proto");
 
-    // Generate both server and client code
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+
+    // Generate both server and client code, plus the descriptor set that
+    // gRPC reflection serves
     tonic_prost_build::configure()
         .build_server(true)
         .build_client(true)
+        .file_descriptor_set_path(out_dir.join("blog_descriptor.bin"))
         .compile_protos(&["proto/blog.proto"], &["proto/"])?;
 
     Ok(())
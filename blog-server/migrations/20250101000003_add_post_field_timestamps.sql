@@ -0,0 +1,14 @@
+-- Track title and content edits separately
+ALTER TABLE posts
+    ADD COLUMN title_updated_at TIMESTAMPTZ,
+    ADD COLUMN content_updated_at TIMESTAMPTZ;
+
+UPDATE posts
+SET title_updated_at = updated_at,
+    content_updated_at = updated_at;
+
+ALTER TABLE posts
+    ALTER COLUMN title_updated_at SET NOT NULL,
+    ALTER COLUMN title_updated_at SET DEFAULT NOW(),
+    ALTER COLUMN content_updated_at SET NOT NULL,
+    ALTER COLUMN content_updated_at SET DEFAULT NOW();
@@ -0,0 +1,2 @@
+-- Whether the user confirmed ownership of their email address
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Bumped on every password change; tokens carrying an older value are rejected
+ALTER TABLE users ADD COLUMN password_version INTEGER NOT NULL DEFAULT 0;
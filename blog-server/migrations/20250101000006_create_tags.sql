@@ -0,0 +1,14 @@
+-- Tags are stored normalized (trimmed, lowercase)
+CREATE TABLE tags (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(64) NOT NULL UNIQUE
+);
+
+CREATE TABLE post_tags (
+    post_id BIGINT NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    tag_id BIGINT NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (post_id, tag_id)
+);
+
+-- The primary key covers lookups by post; filtering goes by tag
+CREATE INDEX idx_post_tags_tag_id ON post_tags(tag_id);
@@ -0,0 +1,2 @@
+-- Serves prev/next lookups comparing (created_at, id) row values
+CREATE INDEX idx_posts_created_at_id ON posts(created_at, id);
@@ -0,0 +1,7 @@
+-- Full-text search document, title words ranked above content words
+ALTER TABLE posts ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A')
+        || setweight(to_tsvector('english', content), 'B')
+) STORED;
+
+CREATE INDEX idx_posts_search_vector ON posts USING GIN (search_vector);
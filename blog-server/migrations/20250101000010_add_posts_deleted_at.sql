@@ -0,0 +1,2 @@
+-- Soft delete: deleted posts keep their row (and tags) until restored
+ALTER TABLE posts ADD COLUMN deleted_at TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- Admins may update and delete any post; promote with
+-- UPDATE users SET role = 'admin' WHERE username = '...'
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'user'
+    CHECK (role IN ('user', 'admin'));
@@ -0,0 +1,11 @@
+-- Comments on posts; they go away with the post or the author
+CREATE TABLE comments (
+    id BIGSERIAL PRIMARY KEY,
+    post_id BIGINT NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    author_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Serves the per-post listing in creation order
+CREATE INDEX idx_comments_post_id_created_at ON comments(post_id, created_at, id);
@@ -0,0 +1,3 @@
+-- Serve listings and keyset pages sorted by last update or by title
+CREATE INDEX idx_posts_updated_at_id ON posts(updated_at, id);
+CREATE INDEX idx_posts_title_id ON posts(title, id);
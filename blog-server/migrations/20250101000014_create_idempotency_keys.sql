@@ -0,0 +1,10 @@
+-- Idempotency-Key values sent with POST /posts, so a retried create returns
+-- the post made the first time. post_id is set in the transaction that
+-- inserts the key; the primary key makes concurrent retries wait for it.
+CREATE TABLE idempotency_keys (
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    key VARCHAR(255) NOT NULL,
+    post_id BIGINT REFERENCES posts(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, key)
+);
@@ -0,0 +1,5 @@
+-- Drafts are only visible to their author. Posts written before drafts
+-- existed stay published; new ones start as drafts.
+ALTER TABLE posts ADD COLUMN status TEXT NOT NULL DEFAULT 'published'
+    CHECK (status IN ('draft', 'published'));
+ALTER TABLE posts ALTER COLUMN status SET DEFAULT 'draft';
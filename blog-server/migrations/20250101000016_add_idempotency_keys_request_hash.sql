@@ -0,0 +1,3 @@
+-- Fingerprint of the create request a key came with, so reusing the key
+-- for a different request is refused. NULL for keys stored before it.
+ALTER TABLE idempotency_keys ADD COLUMN request_hash TEXT;
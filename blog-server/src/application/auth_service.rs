@@ -1,45 +1,497 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::data::PostgresUserRepository;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use super::cache::TtlMap;
+use crate::data::{SessionRepository, UserRepository};
 use crate::domain::{
-    AuthResult, DomainError, LoginCommand, Password, RegisterCommand,
+    AuthResult, DomainError, LoginCommand, Password, PasswordParams,
+    RegisterCommand, Role, Session, User, validate_new_password,
 };
-use crate::infrastructure::JwtService;
+use crate::infrastructure::config::{FromEnv, env_list, env_or};
+use crate::infrastructure::{Claims, JwtService, PwnedPasswordCheck};
+
+#[derive(Clone)]
+pub struct AuthConfig {
+    /// How long a user's password version is cached when checking tokens.
+    /// Password changes on another instance take up to this long to
+    /// revoke old tokens here; 0 looks it up on every request.
+    pub password_version_cache_ttl: Duration,
+    /// Lifetime of tokens issued by `impersonate`
+    pub impersonation_ttl: Duration,
+    /// Argon2 cost of new password hashes
+    pub password_params: PasswordParams,
+    /// Whether a successful login upgrades a hash made with weaker
+    /// parameters than `password_params`
+    pub rehash_on_login: bool,
+    /// How often expired sessions of all users are deleted; 0 leaves
+    /// them until their user logs in again
+    pub session_cleanup_interval: Duration,
+}
+
+impl FromEnv for AuthConfig {
+    fn from_env() -> Self {
+        if !env_list("ADMIN_USER_IDS").is_empty() {
+            tracing::warn!(
+                "ADMIN_USER_IDS is no longer read; give admins the admin role"
+            );
+        }
+        let defaults = PasswordParams::default();
+
+        Self {
+            password_version_cache_ttl: Duration::from_secs(env_or(
+                "PASSWORD_VERSION_CACHE_TTL_SECS",
+                30,
+            )),
+            impersonation_ttl: Duration::from_mins(env_or(
+                "IMPERSONATION_TOKEN_MINUTES",
+                15,
+            )),
+            password_params: PasswordParams {
+                memory_kib: env_or("ARGON2_MEMORY_KIB", defaults.memory_kib),
+                iterations: env_or("ARGON2_ITERATIONS", defaults.iterations),
+                lanes: env_or("ARGON2_PARALLELISM", defaults.lanes),
+            },
+            rehash_on_login: env_or("PASSWORD_REHASH_ON_LOGIN", true),
+            session_cleanup_interval: Duration::from_mins(env_or(
+                "SESSION_CLEANUP_INTERVAL_MINUTES",
+                60,
+            )),
+        }
+    }
+}
 
 pub struct AuthService {
-    user_repository: Arc<PostgresUserRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    session_repository: Arc<dyn SessionRepository>,
     jwt_service: Arc<JwtService>,
+    password_versions: TtlMap<i64, i32>,
+    pwned_passwords: Option<PwnedPasswordCheck>,
+    impersonation_ttl: Duration,
+    password_params: PasswordParams,
+    rehash_on_login: bool,
 }
 
 impl AuthService {
-    pub const fn new(
-        user_repository: Arc<PostgresUserRepository>,
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        session_repository: Arc<dyn SessionRepository>,
         jwt_service: Arc<JwtService>,
+        config: &AuthConfig,
     ) -> Self {
         Self {
             user_repository,
+            session_repository,
             jwt_service,
+            password_versions: TtlMap::new(config.password_version_cache_ttl),
+            pwned_passwords: None,
+            impersonation_ttl: config.impersonation_ttl,
+            password_params: config.password_params,
+            rehash_on_login: config.rehash_on_login,
+        }
+    }
+
+    /// Rejects new passwords that appear in known data breaches
+    pub fn with_pwned_password_check(
+        mut self,
+        check: PwnedPasswordCheck,
+    ) -> Self {
+        self.pwned_passwords = Some(check);
+        self
+    }
+
+    /// Fails with an error on `field` when `password` is breached
+    async fn reject_pwned_password(
+        &self,
+        field: &str,
+        password: &str,
+    ) -> Result<(), DomainError> {
+        let Some(check) = &self.pwned_passwords else {
+            return Ok(());
+        };
+
+        if check.is_pwned(password).await {
+            return Err(DomainError::invalid_field(
+                field,
+                "has appeared in a data breach, choose another",
+            ));
+        }
+        Ok(())
+    }
+
+    /// User id of a well-signed, unexpired access token. Unlike
+    /// `authenticate` this skips the revocation checks, so it is cheap
+    /// enough for every request but not enough to authorize one.
+    pub fn token_user_id(&self, token: &str) -> Option<i64> {
+        self.jwt_service
+            .verify_token(token)
+            .ok()
+            .map(|claims| claims.user_id)
+    }
+
+    /// Verifies an access token and rejects it if the user's password
+    /// changed after it was issued
+    pub async fn authenticate(
+        &self,
+        token: &str,
+    ) -> Result<Claims, DomainError> {
+        let claims = self.jwt_service.verify_token(token)?;
+
+        if claims.password_version
+            != self.password_version(claims.user_id).await?
+        {
+            return Err(DomainError::JwtError(
+                "Token was revoked by a password change".to_string(),
+            ));
+        }
+
+        if let Some(jti) = claims.jti
+            && !self
+                .session_repository
+                .is_active(jti, claims.user_id)
+                .await?
+        {
+            return Err(DomainError::JwtError(
+                "Session was revoked".to_string(),
+            ));
+        }
+
+        // Inside the request span, so the method and path are recorded too
+        if let Some(impersonator_id) = claims.impersonator_id {
+            tracing::info!(
+                target: "audit",
+                impersonator_id,
+                user_id = claims.user_id,
+                "Request made with an impersonation token"
+            );
+        }
+
+        Ok(claims)
+    }
+
+    /// Deletes expired sessions. Their tokens are already refused, so
+    /// this only keeps the table small. Returns how many were deleted.
+    pub async fn purge_expired_sessions(&self) -> Result<u64, DomainError> {
+        self.session_repository.delete_expired().await
+    }
+
+    /// Purges expired sessions every `interval` for as long as the
+    /// process runs; a zero interval starts nothing
+    pub fn spawn_session_cleanup(
+        self: &Arc<Self>,
+        interval: Duration,
+    ) -> Option<JoinHandle<()>> {
+        if interval.is_zero() {
+            return None;
+        }
+
+        let service = self.clone();
+        Some(tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                match service.purge_expired_sessions().await {
+                    Ok(0) => {}
+                    Ok(deleted) => {
+                        tracing::info!(deleted, "Purged expired sessions");
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to purge expired sessions: {e}");
+                    }
+                }
+            }
+        }))
+    }
+
+    /// The user's unexpired sessions, newest first
+    pub async fn sessions(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<Session>, DomainError> {
+        self.session_repository.list_by_user(user_id).await
+    }
+
+    /// Revokes one of the user's own sessions; other users' sessions
+    /// are reported as not found
+    pub async fn revoke_session(
+        &self,
+        user_id: i64,
+        jti: Uuid,
+    ) -> Result<(), DomainError> {
+        if self.session_repository.delete(jti, user_id).await? {
+            Ok(())
+        } else {
+            Err(DomainError::SessionNotFound)
+        }
+    }
+
+    /// Issues a short-lived token acting as `user_id` for support work,
+    /// if `role` lets `admin_id` do so. The token names `admin_id` as
+    /// impersonator, so every request made with it is attributed to the
+    /// admin in the audit log.
+    pub async fn impersonate(
+        &self,
+        admin_id: i64,
+        role: Role,
+        user_id: i64,
+    ) -> Result<AuthResult, DomainError> {
+        role.require_role(Role::Admin)?;
+
+        let user = self
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        let token = self.jwt_service.generate_impersonation_token(
+            &user,
+            admin_id,
+            self.impersonation_ttl,
+        )?;
+
+        tracing::warn!(
+            target: "audit",
+            impersonator_id = admin_id,
+            user_id,
+            "Impersonation token issued"
+        );
+        Ok(AuthResult {
+            token,
+            refresh_token: None,
+            user,
+        })
+    }
+
+    async fn password_version(&self, user_id: i64) -> Result<i32, DomainError> {
+        if let Some(version) = self.password_versions.get(&user_id) {
+            return Ok(version);
+        }
+
+        let version = self
+            .user_repository
+            .password_version(user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        self.password_versions.set(user_id, version);
+        Ok(version)
+    }
+
+    /// Changes or resets a password, revoking all existing tokens.
+    /// Returns the new password version.
+    pub async fn set_password(
+        &self,
+        user_id: i64,
+        new_password: &str,
+    ) -> Result<i32, DomainError> {
+        self.reject_pwned_password("new_password", new_password)
+            .await?;
+
+        let password_hash =
+            Password::hash(new_password, &self.password_params)?;
+        let version = self
+            .user_repository
+            .update_password(user_id, &password_hash)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        self.password_versions.invalidate(&user_id);
+        // The tokens are already revoked; this clears the session list
+        self.session_repository.delete_by_user(user_id).await?;
+        Ok(version)
+    }
+
+    /// Changes the password of a signed-in user who knows the current
+    /// one. Every existing session ends, the caller's included, so the
+    /// result carries a fresh token in a new session.
+    pub async fn change_password(
+        &self,
+        user_id: i64,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<AuthResult, DomainError> {
+        validate_new_password(new_password)?;
+
+        let user = self
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        if !user.password_hash.verify(current_password) {
+            return Err(DomainError::InvalidCredentials);
         }
+
+        let version = self.set_password(user_id, new_password).await?;
+        tracing::info!(target: "audit", user_id, "Password changed");
+
+        let user = user.with_password_version(version);
+        self.authenticated(user, None).await
     }
 
+    /// Registers a new user.
+    ///
+    /// When the command carries an idempotency key and the user already
+    /// exists with the same username, email and password, the request is
+    /// treated as a retry and a fresh token for the existing account is
+    /// returned. This grants nothing a login with the same credentials
+    /// would not; any mismatch keeps the `UserAlreadyExists` conflict, so
+    /// a key cannot be used to probe or take over someone else's account.
     pub async fn register(
         &self,
         command: RegisterCommand,
     ) -> Result<AuthResult, DomainError> {
+        command.validate()?;
+        self.reject_pwned_password("password", &command.password)
+            .await?;
+
         // Hash password
-        let password_hash = Password::hash(&command.password)?;
+        let password_hash =
+            Password::hash(&command.password, &self.password_params)?;
 
-        // Create user - DB will reject duplicates via UNIQUE constraints
-        // Error code 23505 is converted to UserAlreadyExists in From<sqlx::Error>
-        let user = self
+        // Create user - the repository rejects duplicate usernames/emails
+        // with UserAlreadyExists
+        let user = match self
             .user_repository
             .create(&command.username, &command.email, &password_hash)
+            .await
+        {
+            Ok(user) => user,
+            Err(DomainError::UserAlreadyExists)
+                if command.idempotency_key.is_some() =>
+            {
+                self.replayed_registration(&command).await?
+            }
+            Err(e) => return Err(e),
+        };
+
+        if !user.email_verified {
+            self.issue_email_verification(&user)?;
+        }
+
+        self.authenticated(user, None).await
+    }
+
+    /// Creates a verification token for the user's email. Until mail
+    /// delivery exists the token is only logged.
+    fn issue_email_verification(&self, user: &User) -> Result<(), DomainError> {
+        let token = self
+            .jwt_service
+            .generate_email_verification_token(user.id, &user.email)?;
+        tracing::debug!(
+            user_id = user.id,
+            "Email verification token for {}: {}",
+            user.email,
+            token
+        );
+        Ok(())
+    }
+
+    /// Marks the email from a verification token as verified and returns
+    /// a fresh token that carries the verified status. A token works only
+    /// once: after the email is verified it is rejected.
+    pub async fn verify_email(
+        &self,
+        token: &str,
+    ) -> Result<AuthResult, DomainError> {
+        let invalid = || {
+            DomainError::ValidationError(
+                "Invalid or expired verification token".to_string(),
+            )
+        };
+
+        let claims = self
+            .jwt_service
+            .verify_email_verification_token(token)
+            .map_err(|_| invalid())?;
+
+        // Fails when the email changed since the token was issued or is
+        // already verified, so the token cannot be replayed for a session
+        let user = self
+            .user_repository
+            .mark_email_verified(claims.user_id, &claims.email)
+            .await?
+            .ok_or_else(invalid)?;
+
+        self.authenticated(user, None).await
+    }
+
+    /// Issues an access and a refresh token for `user` and records them
+    /// as a new session, which lasts as long as the refresh token
+    async fn authenticated(
+        &self,
+        user: User,
+        label: Option<String>,
+    ) -> Result<AuthResult, DomainError> {
+        let jti = Uuid::new_v4();
+        let issued = self.jwt_service.generate_token(&user, Some(jti))?;
+        let refresh = self.jwt_service.generate_refresh_token(&user, jti)?;
+
+        self.session_repository
+            .create(&Session {
+                jti,
+                user_id: user.id,
+                label,
+                issued_at: issued.issued_at,
+                expires_at: refresh.expires_at,
+            })
             .await?;
 
-        // Generate token
-        let token = self.jwt_service.generate_token(user.id, &user.username)?;
+        Ok(AuthResult {
+            token: issued.token,
+            refresh_token: Some(refresh.token),
+            user,
+        })
+    }
+
+    /// Exchanges a refresh token for a new access token in the same
+    /// session. Expired or wrong-type tokens, revoked sessions and tokens
+    /// older than the last password change all fail with `InvalidToken`.
+    pub async fn refresh(
+        &self,
+        refresh_token: &str,
+    ) -> Result<AuthResult, DomainError> {
+        let claims = self
+            .jwt_service
+            .verify_refresh_token(refresh_token)
+            .map_err(|_| DomainError::InvalidToken)?;
+
+        let user = self
+            .user_repository
+            .find_by_id(claims.user_id)
+            .await?
+            .filter(|user| user.password_version == claims.password_version)
+            .ok_or(DomainError::InvalidToken)?;
+        if !self
+            .session_repository
+            .is_active(claims.jti, user.id)
+            .await?
+        {
+            return Err(DomainError::InvalidToken);
+        }
 
-        Ok(AuthResult { token, user })
+        let issued =
+            self.jwt_service.generate_token(&user, Some(claims.jti))?;
+        Ok(AuthResult {
+            token: issued.token,
+            refresh_token: None,
+            user,
+        })
+    }
+
+    /// Existing user matching a retried registration exactly
+    async fn replayed_registration(
+        &self,
+        command: &RegisterCommand,
+    ) -> Result<User, DomainError> {
+        self.user_repository
+            .find_by_username(&command.username)
+            .await?
+            .filter(|user| {
+                user.email == command.email
+                    && user.password_hash.verify(&command.password)
+            })
+            .ok_or(DomainError::UserAlreadyExists)
     }
 
     pub async fn login(
@@ -58,9 +510,505 @@ impl AuthService {
             return Err(DomainError::InvalidCredentials);
         }
 
-        // Generate token
-        let token = self.jwt_service.generate_token(user.id, &user.username)?;
+        if self.rehash_on_login
+            && user.password_hash.needs_rehash(&self.password_params)
+        {
+            self.upgrade_password_hash(&user, &command.password).await;
+        }
+
+        self.authenticated(user, command.device).await
+    }
+
+    /// Rehashes a just-verified password with the current parameters.
+    /// Failures are only logged; the old hash still works, and the next
+    /// login tries again. A password change since `user` was read wins
+    /// over the rehash.
+    async fn upgrade_password_hash(&self, user: &User, password: &str) {
+        let user_id = user.id;
+        let result = match Password::hash(password, &self.password_params) {
+            Ok(hash) => {
+                self.user_repository
+                    .upgrade_password_hash(
+                        user_id,
+                        user.password_version,
+                        &hash,
+                    )
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        match result {
+            Ok(true) => tracing::info!(user_id, "Upgraded password hash"),
+            Ok(false) => tracing::info!(
+                user_id,
+                "Skipped password hash upgrade; the password changed"
+            ),
+            Err(e) => {
+                tracing::warn!(user_id, "Failed to upgrade password hash: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{InMemorySessionRepository, InMemoryUserRepository};
+    use crate::infrastructure::JwtConfig;
+    use crate::infrastructure::pwned_passwords::PwnedRange;
+
+    /// Impersonating admin; no such user needs to exist
+    const ADMIN_ID: i64 = 1000;
+
+    /// Target hash parameters, cheap enough to keep the tests fast
+    const TEST_PARAMS: PasswordParams = PasswordParams {
+        memory_kib: 1024,
+        iterations: 2,
+        lanes: 1,
+    };
+
+    fn auth_service() -> AuthService {
+        let jwt_service = Arc::new(JwtService::new(&JwtConfig {
+            secret: "test-secret-key-minimum-32-characters".to_string(),
+            previous_secrets: Vec::new(),
+            token_expiry_hours: 1,
+            email_verification_expiry_hours: 24,
+            refresh_token_expiry_days: 30,
+            leeway_secs: 60,
+            issuer: "blog-server".to_string(),
+            audience: "blog-api".to_string(),
+            validate_issuer_audience: true,
+        }));
+        AuthService::new(
+            Arc::new(InMemoryUserRepository::new()),
+            Arc::new(InMemorySessionRepository::new()),
+            jwt_service,
+            &AuthConfig {
+                password_version_cache_ttl: Duration::from_secs(60),
+                impersonation_ttl: Duration::from_mins(15),
+                password_params: TEST_PARAMS,
+                rehash_on_login: true,
+                session_cleanup_interval: Duration::ZERO,
+            },
+        )
+    }
+
+    fn command(
+        password: &str,
+        idempotency_key: Option<&str>,
+    ) -> RegisterCommand {
+        RegisterCommand {
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password: password.to_string(),
+            idempotency_key: idempotency_key.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retried_registration_returns_existing_user() {
+        let service = auth_service();
+        let first = service
+            .register(command("password123", Some("key-1")))
+            .await
+            .unwrap();
+
+        let retry = service
+            .register(command("password123", Some("key-1")))
+            .await
+            .unwrap();
+
+        let claims = service.jwt_service.verify_token(&retry.token).unwrap();
+        assert_eq!(retry.user.id, first.user.id);
+        assert_eq!(claims.user_id, first.user.id);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_different_password_conflicts() {
+        let service = auth_service();
+        service
+            .register(command("password123", Some("key-1")))
+            .await
+            .unwrap();
+
+        let result = service
+            .register(command("other-password", Some("key-1")))
+            .await;
+
+        assert!(matches!(result, Err(DomainError::UserAlreadyExists)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_email_flags_user_and_token() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        let token = service
+            .jwt_service
+            .generate_email_verification_token(
+                registered.user.id,
+                &registered.user.email,
+            )
+            .unwrap();
+
+        let verified = service.verify_email(&token).await.unwrap();
+
+        let claims = service.jwt_service.verify_token(&verified.token).unwrap();
+        assert!(verified.user.email_verified);
+        assert!(claims.email_verified);
+        assert!(!registered.user.email_verified);
+    }
+
+    #[tokio::test]
+    async fn test_verify_email_token_works_once() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        let token = service
+            .jwt_service
+            .generate_email_verification_token(
+                registered.user.id,
+                &registered.user.email,
+            )
+            .unwrap();
+        service.verify_email(&token).await.unwrap();
+
+        let result = service.verify_email(&token).await;
+
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_email_rejects_access_token() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+
+        let result = service.verify_email(&registered.token).await;
+
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_token_names_the_admin() {
+        let service = auth_service();
+        let target = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+
+        let impersonated = service
+            .impersonate(ADMIN_ID, Role::Admin, target.user.id)
+            .await
+            .unwrap();
+        let claims = service.authenticate(&impersonated.token).await.unwrap();
+
+        assert_eq!(claims.user_id, target.user.id);
+        assert_eq!(claims.impersonator_id, Some(ADMIN_ID));
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_cannot_impersonate() {
+        let service = auth_service();
+        let user = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+
+        let result = service
+            .impersonate(user.user.id, Role::User, user.user.id)
+            .await;
+
+        assert!(matches!(result, Err(DomainError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_session_token_is_rejected() {
+        let service = auth_service();
+        service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        let login = service
+            .login(LoginCommand {
+                username: "alice".to_string(),
+                password: "password123".to_string(),
+                device: Some("curl/8.0".to_string()),
+            })
+            .await
+            .unwrap();
+        let claims = service.authenticate(&login.token).await.unwrap();
+        let jti = claims.jti.unwrap();
+
+        let sessions = service.sessions(login.user.id).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].label.as_deref(), Some("curl/8.0"));
+
+        service.revoke_session(login.user.id, jti).await.unwrap();
+
+        assert!(service.authenticate(&login.token).await.is_err());
+        assert_eq!(service.sessions(login.user.id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cannot_revoke_another_users_session() {
+        let service = auth_service();
+        let alice = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        let bob = service
+            .register(RegisterCommand {
+                username: "bob".to_string(),
+                email: "bob@example.com".to_string(),
+                ..command("password123", None)
+            })
+            .await
+            .unwrap();
+        let jti = service
+            .authenticate(&alice.token)
+            .await
+            .unwrap()
+            .jti
+            .unwrap();
+
+        let result = service.revoke_session(bob.user.id, jti).await;
+
+        assert!(matches!(result, Err(DomainError::SessionNotFound)));
+        assert!(service.authenticate(&alice.token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_password_change_revokes_existing_tokens() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        assert!(service.authenticate(&registered.token).await.is_ok());
+
+        service
+            .set_password(registered.user.id, "new-password")
+            .await
+            .unwrap();
+
+        assert!(service.authenticate(&registered.token).await.is_err());
+        let relogin = service
+            .login(LoginCommand {
+                username: "alice".to_string(),
+                password: "new-password".to_string(),
+                device: None,
+            })
+            .await
+            .unwrap();
+        assert!(service.authenticate(&relogin.token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_change_password_checks_the_current_one() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        let user_id = registered.user.id;
+
+        let wrong = service
+            .change_password(user_id, "wrong-password", "new-password")
+            .await;
+        assert!(matches!(wrong, Err(DomainError::InvalidCredentials)));
+        let weak = service
+            .change_password(user_id, "password123", "short")
+            .await;
+        assert!(matches!(weak, Err(DomainError::InvalidFields(_))));
+        assert!(service.authenticate(&registered.token).await.is_ok());
+
+        let changed = service
+            .change_password(user_id, "password123", "new-password")
+            .await
+            .unwrap();
+
+        assert!(service.authenticate(&registered.token).await.is_err());
+        assert!(service.authenticate(&changed.token).await.is_ok());
+        assert_eq!(service.sessions(user_id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_renews_access_in_the_same_session() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        let refresh_token = registered.refresh_token.unwrap();
+
+        let refreshed = service.refresh(&refresh_token).await.unwrap();
+
+        let old = service.authenticate(&registered.token).await.unwrap();
+        let new = service.authenticate(&refreshed.token).await.unwrap();
+        assert_eq!(new.jti, old.jti);
+        assert!(refreshed.refresh_token.is_none());
+        assert!(matches!(
+            service.refresh(&registered.token).await,
+            Err(DomainError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_fails_after_revocation() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+        let refresh_token = registered.refresh_token.unwrap();
+        let jti = service
+            .authenticate(&registered.token)
+            .await
+            .unwrap()
+            .jti
+            .unwrap();
+
+        service
+            .revoke_session(registered.user.id, jti)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            service.refresh(&refresh_token).await,
+            Err(DomainError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_password_change_revokes_refresh_tokens() {
+        let service = auth_service();
+        let registered = service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+
+        service
+            .set_password(registered.user.id, "new-password")
+            .await
+            .unwrap();
+
+        let result = service.refresh(&registered.refresh_token.unwrap()).await;
+        assert!(matches!(result, Err(DomainError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_without_key_conflicts() {
+        let service = auth_service();
+        service
+            .register(command("password123", None))
+            .await
+            .unwrap();
+
+        let result = service.register(command("password123", None)).await;
+
+        assert!(matches!(result, Err(DomainError::UserAlreadyExists)));
+    }
+
+    /// Reports only "password" as breached
+    struct BreachedRange;
+
+    #[async_trait::async_trait]
+    impl PwnedRange for BreachedRange {
+        async fn range(&self, _prefix: &str) -> anyhow::Result<String> {
+            Ok("1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_breached_password_rejected_at_registration() {
+        let service =
+            auth_service().with_pwned_password_check(PwnedPasswordCheck::new(
+                Arc::new(BreachedRange),
+                Duration::from_secs(1),
+            ));
+
+        let breached = service.register(command("password", None)).await;
+        let clean = service.register(command("password123", None)).await;
+
+        assert!(matches!(breached, Err(DomainError::InvalidFields(_))));
+        assert!(clean.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_login_upgrades_weak_password_hash() {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let weak = PasswordParams {
+            memory_kib: 64,
+            iterations: 1,
+            lanes: 1,
+        };
+        let user = users
+            .create(
+                "alice",
+                "alice@example.com",
+                &Password::hash("password123", &weak).unwrap(),
+            )
+            .await
+            .unwrap();
+        let service = AuthService {
+            user_repository: users.clone(),
+            ..auth_service()
+        };
+
+        service
+            .login(LoginCommand {
+                username: "alice".to_string(),
+                password: "password123".to_string(),
+                device: None,
+            })
+            .await
+            .unwrap();
+
+        let stored = users.find_by_id(user.id).await.unwrap().unwrap();
+        assert!(user.password_hash.needs_rehash(&TEST_PARAMS));
+        assert!(!stored.password_hash.needs_rehash(&TEST_PARAMS));
+        assert!(stored.password_hash.verify("password123"));
+        assert_eq!(stored.password_version, user.password_version);
+    }
+
+    #[tokio::test]
+    async fn test_rehash_does_not_undo_a_concurrent_password_change() {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let weak = PasswordParams {
+            memory_kib: 64,
+            iterations: 1,
+            lanes: 1,
+        };
+        let user = users
+            .create(
+                "alice",
+                "alice@example.com",
+                &Password::hash("password123", &weak).unwrap(),
+            )
+            .await
+            .unwrap();
+        let service = AuthService {
+            user_repository: users.clone(),
+            ..auth_service()
+        };
+
+        // The login read `user` and verified the old password, then the
+        // password changed before the rehash was written
+        let changed = Password::hash("new-password456", &TEST_PARAMS).unwrap();
+        users.update_password(user.id, &changed).await.unwrap();
+        service.upgrade_password_hash(&user, "password123").await;
 
-        Ok(AuthResult { token, user })
+        let stored = users.find_by_id(user.id).await.unwrap().unwrap();
+        assert!(stored.password_hash.verify("new-password456"));
+        assert!(!stored.password_hash.verify("password123"));
+        assert_eq!(stored.password_version, user.password_version + 1);
     }
 }
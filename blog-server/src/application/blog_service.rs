@@ -1,82 +1,1346 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::data::PostgresPostRepository;
-use crate::domain::{CreatePostCommand, DomainError, Post, UpdatePostCommand};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::{Stream, stream};
+use tokio::sync::broadcast;
+
+use super::cache::TtlCache;
+use super::sanitizer::ContentSanitizer;
+use crate::data::PostRepository;
+use crate::domain::{
+    BulkTagResult, CreatePostCommand, DomainError, IdempotencyKey, Post,
+    PostCursor, PostNeighbors, PostSort, PostStatus, Role, TagAction,
+    TagFilter, UpdatePostCommand, normalize_tags,
+};
+use crate::infrastructure::config::{FromEnv, env_list, env_or};
+
+#[derive(Clone)]
+pub struct BlogConfig {
+    /// Maximum posts a single user may own; 0 means unlimited
+    pub max_posts_per_user: u32,
+    /// How long the unfiltered post total is cached; 0 disables caching
+    pub count_cache_ttl: Duration,
+    /// Only users with a verified email may create posts
+    pub require_verified_email: bool,
+    /// Applied to post content before it is stored
+    pub sanitizer: ContentSanitizer,
+    /// How long an `Idempotency-Key` keeps returning the post it created
+    pub idempotency_key_ttl: Duration,
+}
+
+impl BlogConfig {
+    /// Whether a user who already owns `existing_posts` may create another
+    pub fn allows_new_post(&self, existing_posts: i64) -> bool {
+        self.max_posts_per_user == 0
+            || existing_posts < i64::from(self.max_posts_per_user)
+    }
+}
+
+impl FromEnv for BlogConfig {
+    fn from_env() -> Self {
+        Self {
+            max_posts_per_user: env_or("MAX_POSTS_PER_USER", 0),
+            count_cache_ttl: Duration::from_secs(env_or(
+                "POST_COUNT_CACHE_TTL_SECS",
+                5,
+            )),
+            require_verified_email: env_or("REQUIRE_EMAIL_VERIFICATION", false),
+            sanitizer: ContentSanitizer::new(
+                env_or("SANITIZE_CONTENT", false),
+                env_list("SANITIZE_ALLOWED_TAGS"),
+            )
+            .unwrap_or_else(|e| panic!("Invalid SANITIZE_ALLOWED_TAGS: {e}")),
+            idempotency_key_ttl: Duration::from_secs(
+                env_or("IDEMPOTENCY_KEY_TTL_HOURS", 24) * 60 * 60,
+            ),
+        }
+    }
+}
+
+/// Longest tag name accepted, matching the `tags.name` column
+const MAX_TAG_CHARS: usize = 64;
+
+fn check_tag_lengths(tags: &[String]) -> Result<(), DomainError> {
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_CHARS) {
+        return Err(DomainError::ValidationError(format!(
+            "tags must be at most {MAX_TAG_CHARS} characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Longest search query accepted, in characters
+const MAX_SEARCH_QUERY_CHARS: usize = 200;
+
+/// A cursor only continues a listing sorted on the column it came from
+fn check_cursor(
+    cursor: &PostCursor,
+    sort: PostSort,
+) -> Result<(), DomainError> {
+    if cursor.fits(sort) {
+        Ok(())
+    } else {
+        Err(DomainError::ValidationError(format!(
+            "cursor does not belong to a listing sorted by {sort}"
+        )))
+    }
+}
+
+/// Number of posts fetched per round trip when exporting or streaming
+const EXPORT_BATCH_SIZE: i64 = 100;
+
+/// Where a batched traversal of the listing continues
+enum Position {
+    Start,
+    After(PostCursor),
+    End,
+}
+
+/// Buffered feed events per subscriber; slower subscribers lose the oldest
+const FEED_CHANNEL_CAPACITY: usize = 100;
 
 pub struct BlogService {
-    post_repository: Arc<PostgresPostRepository>,
+    post_repository: Arc<dyn PostRepository>,
+    config: BlogConfig,
+    new_posts: broadcast::Sender<Post>,
+    /// Cached `COUNT(*)` for the unfiltered listing. Writes on this
+    /// instance invalidate it; writes elsewhere show up after the TTL.
+    /// Filtered listings must not use it.
+    total_count: TtlCache<i64>,
 }
 
 impl BlogService {
-    pub const fn new(post_repository: Arc<PostgresPostRepository>) -> Self {
-        Self { post_repository }
+    pub fn new(
+        post_repository: Arc<dyn PostRepository>,
+        config: BlogConfig,
+    ) -> Self {
+        let (new_posts, _) = broadcast::channel(FEED_CHANNEL_CAPACITY);
+        let total_count = TtlCache::new(config.count_cache_ttl);
+
+        Self {
+            post_repository,
+            config,
+            new_posts,
+            total_count,
+        }
+    }
+
+    /// Subscribes to posts published after this call, whether created
+    /// published or published from a draft
+    pub fn subscribe(&self) -> broadcast::Receiver<Post> {
+        self.new_posts.subscribe()
     }
 
     pub async fn create_post(
         &self,
         author_id: i64,
+        author_email_verified: bool,
         command: CreatePostCommand,
     ) -> Result<Post, DomainError> {
-        self.post_repository
-            .create(&command.title, &command.content, author_id)
+        let (post, _) = self
+            .create(author_id, author_email_verified, command, None)
+            .await?;
+        Ok(post)
+    }
+
+    /// Like [`Self::create_post`], except that reusing `key` within the
+    /// configured TTL returns the post created with it the first time,
+    /// paired with `false`, instead of creating another one
+    pub async fn create_post_idempotent(
+        &self,
+        author_id: i64,
+        author_email_verified: bool,
+        command: CreatePostCommand,
+        key: &str,
+    ) -> Result<(Post, bool), DomainError> {
+        self.create(author_id, author_email_verified, command, Some(key))
             .await
     }
 
-    pub async fn get_post(&self, id: i64) -> Result<Post, DomainError> {
+    async fn create(
+        &self,
+        author_id: i64,
+        author_email_verified: bool,
+        command: CreatePostCommand,
+        key: Option<&str>,
+    ) -> Result<(Post, bool), DomainError> {
+        command.validate()?;
+        if self.config.require_verified_email && !author_email_verified {
+            return Err(DomainError::EmailNotVerified);
+        }
+
+        // A retry must not trip the quota that its first attempt filled up
+        let fingerprint = key.map(|_| command.fingerprint());
+        let key = key.zip(fingerprint.as_deref()).map(|(key, fingerprint)| {
+            IdempotencyKey {
+                key,
+                fingerprint,
+                since: Utc::now() - self.config.idempotency_key_ttl,
+            }
+        });
+        if let Some(key) = key {
+            let existing = self
+                .post_repository
+                .find_by_idempotency_key(author_id, key)
+                .await?;
+            if let Some(post) = existing {
+                return Ok((post, false));
+            }
+        }
+
+        // Count and insert are separate statements, so concurrent creates
+        // may overshoot the quota slightly; it is an abuse guard, not a
+        // hard invariant
+        if self.config.max_posts_per_user > 0 {
+            let existing =
+                self.post_repository.count_by_author(author_id).await?;
+            if !self.config.allows_new_post(existing) {
+                return Err(DomainError::PostQuotaExceeded(
+                    self.config.max_posts_per_user,
+                ));
+            }
+        }
+
+        let tags = normalize_tags(command.tags);
+        check_tag_lengths(&tags)?;
+
+        let content = self.config.sanitizer.clean(command.content);
+        let (post, created) = if let Some(key) = key {
+            self.post_repository
+                .create_with_idempotency_key(
+                    &command.title,
+                    &content,
+                    author_id,
+                    &tags,
+                    command.status,
+                    key,
+                )
+                .await?
+        } else {
+            let post = self
+                .post_repository
+                .create(
+                    &command.title,
+                    &content,
+                    author_id,
+                    &tags,
+                    command.status,
+                )
+                .await?;
+            (post, true)
+        };
+        if created {
+            self.total_count.invalidate();
+            if post.status.is_published() {
+                self.announce(&post);
+            }
+        }
+
+        Ok((post, created))
+    }
+
+    /// Tells feed subscribers about a newly published post
+    fn announce(&self, post: &Post) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.new_posts.send(post.clone());
+    }
+
+    /// With `include_author` the post carries an
+    /// [`AuthorSummary`](crate::domain::AuthorSummary) whose email the
+    /// caller has to hide from readers who may not see it. Drafts are
+    /// reported as not found to anyone but their author.
+    pub async fn get_post(
+        &self,
+        id: i64,
+        include_author: bool,
+        viewer: Option<i64>,
+    ) -> Result<Post, DomainError> {
+        self.post_repository
+            .find_by_id(id, include_author)
+            .await?
+            .filter(|post| post.is_visible_to(viewer))
+            .ok_or(DomainError::PostNotFound)
+    }
+
+    /// Posts created right before and after the given one
+    pub async fn post_neighbors(
+        &self,
+        id: i64,
+    ) -> Result<PostNeighbors, DomainError> {
+        let post = self.get_post(id, false, None).await?;
+        self.post_repository.find_neighbors(&post).await
+    }
+
+    pub async fn random_post(&self) -> Result<Post, DomainError> {
         self.post_repository
-            .find_by_id(id)
+            .find_random()
             .await?
             .ok_or(DomainError::PostNotFound)
     }
 
+    /// Updates a post of `user_id`, or any post when `role` is admin
     pub async fn update_post(
         &self,
         id: i64,
-        author_id: i64,
+        user_id: i64,
+        role: Role,
         command: UpdatePostCommand,
     ) -> Result<Post, DomainError> {
+        command.validate()?;
+        let tags = command.tags.map(normalize_tags);
+        if let Some(tags) = &tags {
+            check_tag_lengths(tags)?;
+        }
+        let content = self.config.sanitizer.clean(command.content);
+
+        let expected = command.expected_updated_at;
+
         // Try to update - one query in happy path
         if let Some(post) = self
             .post_repository
-            .update_by_author(id, author_id, &command.title, &command.content)
+            .update_by_author(
+                id,
+                user_id,
+                &command.title,
+                &content,
+                tags.as_deref(),
+                expected,
+            )
             .await?
         {
             return Ok(post);
         }
 
         // Failed - check why (only on error path)
-        if self.post_repository.find_by_id(id).await?.is_some() {
-            Err(DomainError::Forbidden)
-        } else {
-            Err(DomainError::PostNotFound)
+        if expected.is_some()
+            && let Some(post) =
+                self.post_repository.find_by_id(id, false).await?
+            && post.author_id == user_id
+        {
+            // Own post, so only the version can have failed the update
+            return Err(DomainError::Conflict);
+        }
+        let post = self.moderated_post(id, user_id, role).await?;
+        if expected.is_some_and(|at| !post.is_version(at)) {
+            return Err(DomainError::Conflict);
         }
+        self.post_repository
+            .update_by_author(
+                id,
+                post.author_id,
+                &command.title,
+                &content,
+                tags.as_deref(),
+                expected,
+            )
+            .await?
+            // Changed or deleted since the check above
+            .ok_or_else(|| {
+                if expected.is_some() {
+                    DomainError::Conflict
+                } else {
+                    DomainError::PostNotFound
+                }
+            })
     }
 
+    /// Deletes a post of `user_id`, or any post when `role` is admin
     pub async fn delete_post(
         &self,
         id: i64,
-        author_id: i64,
+        user_id: i64,
+        role: Role,
     ) -> Result<(), DomainError> {
         // Try to delete - one query in happy path
-        if self.post_repository.delete_by_author(id, author_id).await? {
-            return Ok(());
+        if !self.post_repository.delete_by_author(id, user_id).await? {
+            // Failed - check why (only on error path)
+            let post = self.moderated_post(id, user_id, role).await?;
+            if !self
+                .post_repository
+                .delete_by_author(id, post.author_id)
+                .await?
+            {
+                return Err(DomainError::PostNotFound);
+            }
         }
 
-        // Failed - check why (only on error path)
-        if self.post_repository.find_by_id(id).await?.is_some() {
-            Err(DomainError::Forbidden)
-        } else {
-            Err(DomainError::PostNotFound)
+        self.total_count.invalidate();
+        Ok(())
+    }
+
+    /// Someone else's post that `role` lets `user_id` change anyway
+    async fn moderated_post(
+        &self,
+        id: i64,
+        user_id: i64,
+        role: Role,
+    ) -> Result<Post, DomainError> {
+        let post = self
+            .post_repository
+            .find_by_id(id, false)
+            .await?
+            .ok_or(DomainError::PostNotFound)?;
+        role.require_role(Role::Admin)?;
+
+        tracing::info!(
+            target: "audit",
+            admin_id = user_id,
+            author_id = post.author_id,
+            post_id = id,
+            "Admin changing another user's post"
+        );
+        Ok(post)
+    }
+
+    /// Brings back a post removed with `delete_post`, as its author or as
+    /// an admin. Posts that are not deleted are reported as not found.
+    pub async fn restore_post(
+        &self,
+        id: i64,
+        user_id: i64,
+        role: Role,
+    ) -> Result<Post, DomainError> {
+        let mut restored =
+            self.post_repository.restore_by_author(id, user_id).await?;
+        if restored.is_none() {
+            // Not the user's own deleted post - only an admin may go on
+            let author_id = self
+                .post_repository
+                .find_deleted_author(id)
+                .await?
+                .ok_or(DomainError::PostNotFound)?;
+            role.require_role(Role::Admin)?;
+            tracing::info!(
+                target: "audit",
+                admin_id = user_id,
+                author_id,
+                post_id = id,
+                "Admin restoring another user's post"
+            );
+            restored = self
+                .post_repository
+                .restore_by_author(id, author_id)
+                .await?;
+        }
+
+        let post = restored.ok_or(DomainError::PostNotFound)?;
+        self.total_count.invalidate();
+        Ok(post)
+    }
+
+    /// Moves a post of the author between draft and published. Publishing
+    /// announces the post to feed subscribers as if it were new; setting
+    /// the status it already has changes nothing. Posts that are not the
+    /// author's are reported as not found.
+    pub async fn set_post_status(
+        &self,
+        id: i64,
+        author_id: i64,
+        status: PostStatus,
+    ) -> Result<Post, DomainError> {
+        let post = self
+            .post_repository
+            .find_by_id(id, false)
+            .await?
+            .filter(|post| post.author_id == author_id)
+            .ok_or(DomainError::PostNotFound)?;
+        if post.status == status {
+            return Ok(post);
+        }
+
+        let post = self
+            .post_repository
+            .set_status_by_author(id, author_id, status)
+            .await?
+            // Deleted since the check above
+            .ok_or(DomainError::PostNotFound)?;
+        self.total_count.invalidate();
+        if post.status.is_published() {
+            self.announce(&post);
+        }
+        Ok(post)
+    }
+
+    /// Adds or removes one tag on several of the author's posts at once.
+    /// Ids of missing or foreign posts are skipped, not rejected.
+    pub async fn bulk_tag_posts(
+        &self,
+        author_id: i64,
+        mut post_ids: Vec<i64>,
+        tag: String,
+        action: TagAction,
+    ) -> Result<BulkTagResult, DomainError> {
+        let Some(tag) = normalize_tags(vec![tag]).pop() else {
+            return Err(DomainError::ValidationError(
+                "tag must not be empty".to_string(),
+            ));
+        };
+        check_tag_lengths(std::slice::from_ref(&tag))?;
+
+        post_ids.sort_unstable();
+        post_ids.dedup();
+        self.post_repository
+            .update_tag_by_author(author_id, &post_ids, &tag, action)
+            .await
+    }
+
+    /// Streams all posts of an author in id order, fetching them in
+    /// batches so large exports never sit in memory at once.
+    pub fn export_posts(
+        &self,
+        author_id: i64,
+    ) -> impl Stream<Item = Result<Vec<Post>, DomainError>> + Send + 'static
+    {
+        self.export_posts_in_batches(author_id, EXPORT_BATCH_SIZE)
+    }
+
+    fn export_posts_in_batches(
+        &self,
+        author_id: i64,
+        batch_size: i64,
+    ) -> impl Stream<Item = Result<Vec<Post>, DomainError>> + Send + 'static
+    {
+        let repository = self.post_repository.clone();
+
+        stream::try_unfold(Some(0), move |after_id| {
+            let repository = repository.clone();
+            async move {
+                let Some(after_id) = after_id else {
+                    return Ok(None);
+                };
+
+                let batch = repository
+                    .list_by_author_after(author_id, after_id, batch_size)
+                    .await?;
+
+                let Some(last) = batch.last() else {
+                    return Ok(None);
+                };
+
+                let next = (i64::try_from(batch.len()).unwrap_or(i64::MAX)
+                    == batch_size)
+                    .then_some(last.id);
+                Ok(Some((batch, next)))
+            }
+        })
+    }
+
+    /// Streams the whole listing in `sort` order, starting after `cursor`
+    /// when given. Batches are keyset pages, so posts created meanwhile
+    /// are neither skipped nor repeated.
+    pub fn stream_posts(
+        &self,
+        sort: PostSort,
+        cursor: Option<PostCursor>,
+    ) -> Result<
+        impl Stream<Item = Result<Vec<Post>, DomainError>> + Send + 'static,
+        DomainError,
+    > {
+        if let Some(cursor) = &cursor {
+            check_cursor(cursor, sort)?;
         }
+        Ok(self.stream_posts_in_batches(sort, cursor, EXPORT_BATCH_SIZE))
     }
 
+    fn stream_posts_in_batches(
+        &self,
+        sort: PostSort,
+        cursor: Option<PostCursor>,
+        batch_size: i64,
+    ) -> impl Stream<Item = Result<Vec<Post>, DomainError>> + Send + 'static
+    {
+        let repository = self.post_repository.clone();
+        let start = cursor.map_or(Position::Start, Position::After);
+
+        stream::try_unfold(start, move |position| {
+            let repository = repository.clone();
+            async move {
+                let batch = match position {
+                    Position::Start => {
+                        repository
+                            .list(batch_size, 0, sort, None, false, None)
+                            .await?
+                            .0
+                    }
+                    Position::After(cursor) => {
+                        repository
+                            .list_after(
+                                &cursor, batch_size, sort, None, false, None,
+                            )
+                            .await?
+                    }
+                    Position::End => return Ok(None),
+                };
+
+                let Some(last) = batch.last() else {
+                    return Ok(None);
+                };
+
+                let next = if i64::try_from(batch.len()).unwrap_or(i64::MAX)
+                    == batch_size
+                {
+                    Position::After(PostCursor::new(last, sort))
+                } else {
+                    Position::End
+                };
+                Ok(Some((batch, next)))
+            }
+        })
+    }
+
+    /// A page of the published posts plus the drafts of `viewer`, when
+    /// given, and how many there are overall
     pub async fn list_posts(
         &self,
         limit: i64,
         offset: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
     ) -> Result<(Vec<Post>, i64), DomainError> {
-        let posts = self.post_repository.list(limit, offset).await?;
-        let total = self.post_repository.count().await?;
+        let (posts, total) = self
+            .post_repository
+            .list(limit, offset, sort, filter, include_author, viewer)
+            .await?;
+        if filter.is_none() && viewer.is_none() {
+            self.total_count.set(total);
+        }
         Ok((posts, total))
     }
+
+    /// Tags in use with their post counts, by name
+    pub async fn tag_counts(&self) -> Result<Vec<(String, i64)>, DomainError> {
+        self.post_repository.tag_counts().await
+    }
+
+    /// A page of an author's posts plus their total. An unknown author
+    /// simply has no posts.
+    pub async fn list_author_posts(
+        &self,
+        author_id: i64,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        self.post_repository
+            .list_by_author(author_id, limit, offset, sort, false)
+            .await
+    }
+
+    /// Like [`Self::list_author_posts`], drafts included, for the author
+    /// themselves
+    pub async fn list_my_posts(
+        &self,
+        author_id: i64,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        self.post_repository
+            .list_by_author(author_id, limit, offset, sort, true)
+            .await
+    }
+
+    /// Full-text search over titles and contents, best matches first,
+    /// plus the number of matches
+    pub async fn search_posts(
+        &self,
+        query: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(DomainError::ValidationError(
+                "search query must not be empty".to_string(),
+            ));
+        }
+        if query.chars().count() > MAX_SEARCH_QUERY_CHARS {
+            return Err(DomainError::ValidationError(format!(
+                "search query must be at most {MAX_SEARCH_QUERY_CHARS} characters"
+            )));
+        }
+
+        self.post_repository.search(query, limit, offset).await
+    }
+
+    /// Keyset page after `cursor`, plus whether more posts follow. Posts
+    /// created during a traversal are neither skipped nor repeated.
+    pub async fn list_posts_after(
+        &self,
+        cursor: &PostCursor,
+        limit: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
+    ) -> Result<(Vec<Post>, bool), DomainError> {
+        check_cursor(cursor, sort)?;
+        // One extra row tells whether another page exists
+        let mut posts = self
+            .post_repository
+            .list_after(cursor, limit + 1, sort, filter, include_author, viewer)
+            .await?;
+        let has_more = i64::try_from(posts.len()).unwrap_or(i64::MAX) > limit;
+        posts.truncate(usize::try_from(limit).unwrap_or(0));
+        Ok((posts, has_more))
+    }
+
+    /// Per-day post counts of an author since `since`, days without
+    /// posts omitted
+    pub async fn post_activity(
+        &self,
+        author_id: i64,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(NaiveDate, i64)>, DomainError> {
+        self.post_repository.count_by_day(author_id, since).await
+    }
+
+    /// Number of posts matching `filter` that `viewer` sees; the
+    /// anonymous, unfiltered total is cached
+    pub async fn count_posts(
+        &self,
+        filter: Option<&TagFilter>,
+        viewer: Option<i64>,
+    ) -> Result<i64, DomainError> {
+        if filter.is_none() && viewer.is_none() {
+            return self.cached_total().await;
+        }
+        self.post_repository.count(filter, viewer).await
+    }
+
+    async fn cached_total(&self) -> Result<i64, DomainError> {
+        if let Some(total) = self.total_count.get() {
+            return Ok(total);
+        }
+
+        let total = self.post_repository.count(None, None).await?;
+        self.total_count.set(total);
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{InMemoryPostRepository, InMemoryUserRepository};
+    use crate::fixtures::{blog_config, create_user};
+    use futures_util::TryStreamExt;
+
+    #[test]
+    fn test_quota_boundary() {
+        let config = BlogConfig {
+            max_posts_per_user: 3,
+            ..blog_config()
+        };
+
+        assert!(config.allows_new_post(0));
+        assert!(config.allows_new_post(2));
+        assert!(!config.allows_new_post(3));
+        assert!(!config.allows_new_post(4));
+    }
+
+    #[test]
+    fn test_zero_quota_is_unlimited() {
+        let config = blog_config();
+
+        assert!(config.allows_new_post(i64::MAX));
+    }
+
+    fn service(
+        max_posts_per_user: u32,
+    ) -> (Arc<InMemoryUserRepository>, Arc<InMemoryPostRepository>, BlogService)
+    {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let service = BlogService::new(
+            posts.clone(),
+            BlogConfig {
+                max_posts_per_user,
+                ..blog_config()
+            },
+        );
+        (users, posts, service)
+    }
+
+    fn command(title: &str) -> CreatePostCommand {
+        CreatePostCommand {
+            title: title.to_string(),
+            content: "Content".to_string(),
+            tags: Vec::new(),
+            status: PostStatus::Published,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_post_enforces_quota() {
+        let (users, _, service) = service(1);
+        let author_id = create_user(&users, "author").await;
+
+        service
+            .create_post(author_id, true, command("First"))
+            .await
+            .unwrap();
+        let result = service
+            .create_post(author_id, true, command("Second"))
+            .await;
+
+        assert!(matches!(result, Err(DomainError::PostQuotaExceeded(1))));
+    }
+
+    #[tokio::test]
+    async fn test_unverified_author_blocked_when_required() {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let service = BlogService::new(
+            posts,
+            BlogConfig {
+                require_verified_email: true,
+                ..blog_config()
+            },
+        );
+        let author_id = create_user(&users, "author").await;
+
+        let unverified =
+            service.create_post(author_id, false, command("Spam")).await;
+        let verified =
+            service.create_post(author_id, true, command("Hello")).await;
+
+        assert!(matches!(unverified, Err(DomainError::EmailNotVerified)));
+        assert!(verified.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cached_count_is_refreshed_after_the_ttl() {
+        let ttl = Duration::from_millis(50);
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let service = BlogService::new(
+            posts.clone(),
+            BlogConfig {
+                count_cache_ttl: ttl,
+                ..blog_config()
+            },
+        );
+        let author_id = create_user(&users, "author").await;
+        service
+            .create_post(author_id, true, command("First"))
+            .await
+            .unwrap();
+        let cached = service.count_posts(None, None).await.unwrap();
+
+        // As if created through another server: only the TTL catches it
+        posts
+            .create("Second", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let stale = service.count_posts(None, None).await.unwrap();
+        tokio::time::sleep(ttl).await;
+        let refreshed = service.count_posts(None, None).await.unwrap();
+        service
+            .create_post(author_id, true, command("Third"))
+            .await
+            .unwrap();
+        let after_own_create = service.count_posts(None, None).await.unwrap();
+
+        assert_eq!(cached, 1);
+        assert_eq!(stale, 1);
+        assert_eq!(refreshed, 2);
+        assert_eq!(after_own_create, 3);
+    }
+
+    #[tokio::test]
+    async fn test_create_post_normalizes_tags() {
+        let (users, _, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let tagged = |tags: &[&str]| CreatePostCommand {
+            tags: tags.iter().map(ToString::to_string).collect(),
+            ..command("Tagged")
+        };
+
+        let post = service
+            .create_post(author_id, true, tagged(&["Rust", " rust ", "async"]))
+            .await
+            .unwrap();
+        let too_long = service
+            .create_post(author_id, true, tagged(&[&"x".repeat(65)]))
+            .await;
+
+        assert_eq!(post.tags, ["async", "rust"]);
+        assert!(matches!(too_long, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_only_admins_change_posts_of_others() {
+        let (users, _, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let other_id = create_user(&users, "other").await;
+        let post = service
+            .create_post(author_id, true, command("Mine"))
+            .await
+            .unwrap();
+        let edit = || UpdatePostCommand {
+            title: "Moderated".to_string(),
+            content: "Content".to_string(),
+            tags: None,
+            expected_updated_at: None,
+        };
+
+        let update_by_user = service
+            .update_post(post.id, other_id, Role::User, edit())
+            .await;
+        let delete_by_user =
+            service.delete_post(post.id, other_id, Role::User).await;
+        let updated = service
+            .update_post(post.id, other_id, Role::Admin, edit())
+            .await
+            .unwrap();
+        service
+            .delete_post(post.id, other_id, Role::Admin)
+            .await
+            .unwrap();
+        let missing = service.delete_post(post.id, other_id, Role::Admin).await;
+
+        assert!(matches!(update_by_user, Err(DomainError::Forbidden)));
+        assert!(matches!(delete_by_user, Err(DomainError::Forbidden)));
+        assert_eq!(updated.title, "Moderated");
+        assert_eq!(updated.author_id, author_id);
+        assert!(matches!(missing, Err(DomainError::PostNotFound)));
+        assert!(service.get_post(post.id, false, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_based_on_a_stale_version_conflicts() {
+        let (users, _, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let post = service
+            .create_post(author_id, true, command("Original"))
+            .await
+            .unwrap();
+        let edit = |title: &str| UpdatePostCommand {
+            title: title.to_string(),
+            content: "Content".to_string(),
+            tags: None,
+            expected_updated_at: Some(post.updated_at),
+        };
+
+        let first = service
+            .update_post(post.id, author_id, Role::User, edit("First"))
+            .await
+            .unwrap();
+        let second = service
+            .update_post(post.id, author_id, Role::User, edit("Second"))
+            .await;
+        let missing = service
+            .update_post(post.id + 1, author_id, Role::User, edit("Second"))
+            .await;
+
+        assert_eq!(first.title, "First");
+        assert!(matches!(second, Err(DomainError::Conflict)));
+        assert!(matches!(missing, Err(DomainError::PostNotFound)));
+        let current = service.get_post(post.id, false, None).await.unwrap();
+        assert_eq!(current.title, "First");
+    }
+
+    #[tokio::test]
+    async fn test_deleted_post_is_restorable_by_its_author_or_an_admin() {
+        let (users, _, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let other_id = create_user(&users, "other").await;
+        let post = service
+            .create_post(author_id, true, command("Oops"))
+            .await
+            .unwrap();
+        let delete = || service.delete_post(post.id, author_id, Role::User);
+
+        delete().await.unwrap();
+        let hidden = service.get_post(post.id, false, None).await;
+        let by_other =
+            service.restore_post(post.id, other_id, Role::User).await;
+        let restored = service
+            .restore_post(post.id, author_id, Role::User)
+            .await
+            .unwrap();
+        let again = service.restore_post(post.id, author_id, Role::User).await;
+        delete().await.unwrap();
+        let by_admin = service
+            .restore_post(post.id, other_id, Role::Admin)
+            .await
+            .unwrap();
+
+        assert!(matches!(hidden, Err(DomainError::PostNotFound)));
+        assert!(matches!(by_other, Err(DomainError::Forbidden)));
+        assert_eq!(restored.title, "Oops");
+        assert!(matches!(again, Err(DomainError::PostNotFound)));
+        assert_eq!(by_admin.author_id, author_id);
+        assert!(service.get_post(post.id, false, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_drafts_are_announced_once_published() {
+        let (users, _, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let reader_id = create_user(&users, "reader").await;
+        let mut feed = service.subscribe();
+        let draft = CreatePostCommand {
+            status: PostStatus::Draft,
+            ..command("Draft")
+        };
+
+        let post = service.create_post(author_id, true, draft).await.unwrap();
+
+        assert!(feed.try_recv().is_err());
+        assert!(matches!(
+            service.get_post(post.id, false, Some(reader_id)).await,
+            Err(DomainError::PostNotFound)
+        ));
+        assert!(
+            service
+                .get_post(post.id, false, Some(author_id))
+                .await
+                .is_ok()
+        );
+        assert_eq!(service.count_posts(None, None).await.unwrap(), 0);
+        assert_eq!(
+            service
+                .list_my_posts(author_id, 10, 0, PostSort::default())
+                .await
+                .unwrap()
+                .1,
+            1
+        );
+        assert!(matches!(
+            service
+                .set_post_status(post.id, reader_id, PostStatus::Published)
+                .await,
+            Err(DomainError::PostNotFound)
+        ));
+
+        let published = service
+            .set_post_status(post.id, author_id, PostStatus::Published)
+            .await
+            .unwrap();
+        service
+            .set_post_status(post.id, author_id, PostStatus::Published)
+            .await
+            .unwrap();
+
+        assert_eq!(published.status, PostStatus::Published);
+        assert_eq!(feed.try_recv().unwrap().id, post.id);
+        assert!(feed.try_recv().is_err());
+        assert!(service.get_post(post.id, false, None).await.is_ok());
+        assert_eq!(service.count_posts(None, None).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_tag_skips_posts_of_other_authors() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let other_id = create_user(&users, "other").await;
+        let tags = vec!["rust".to_string()];
+        let own = repository
+            .create("Own", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let tagged = repository
+            .create(
+                "Tagged",
+                "Content",
+                author_id,
+                &tags,
+                PostStatus::Published,
+            )
+            .await
+            .unwrap();
+        let foreign = repository
+            .create("Foreign", "Content", other_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let result = service
+            .bulk_tag_posts(
+                author_id,
+                vec![own.id, tagged.id, foreign.id, own.id],
+                " Rust ".to_string(),
+                TagAction::Add,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result,
+            BulkTagResult {
+                updated: 1,
+                unchanged: 1,
+                skipped: 1,
+            }
+        );
+        let own = service.get_post(own.id, false, None).await.unwrap();
+        let foreign = service.get_post(foreign.id, false, None).await.unwrap();
+        assert_eq!(own.tags, tags);
+        assert_eq!(foreign.tags, Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn test_ascending_cursor_traversal_sees_posts_added_meanwhile() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        for title in ["First", "Second", "Third"] {
+            repository
+                .create(title, "Content", author_id, &[], PostStatus::Published)
+                .await
+                .unwrap();
+        }
+        let titles = |posts: &[Post]| -> Vec<String> {
+            posts.iter().map(|post| post.title.clone()).collect()
+        };
+
+        let (first_page, _) = service
+            .list_posts(2, 0, PostSort::CreatedAtAsc, None, false, None)
+            .await
+            .unwrap();
+        let cursor =
+            PostCursor::new(first_page.last().unwrap(), PostSort::CreatedAtAsc);
+        // Arrives mid-traversal and must still be reached at the end
+        repository
+            .create("Newest", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let (second_page, has_more) = service
+            .list_posts_after(
+                &cursor,
+                2,
+                PostSort::CreatedAtAsc,
+                None,
+                false,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(titles(&first_page), ["First", "Second"]);
+        assert_eq!(titles(&second_page), ["Third", "Newest"]);
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_descending_cursor_does_not_repeat_posts() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        for title in ["First", "Second", "Third"] {
+            repository
+                .create(title, "Content", author_id, &[], PostStatus::Published)
+                .await
+                .unwrap();
+        }
+
+        let (first_page, _) = service
+            .list_posts(2, 0, PostSort::CreatedAtDesc, None, false, None)
+            .await
+            .unwrap();
+        // With offsets this would push "Second" onto the next page again
+        repository
+            .create("Newest", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let cursor = PostCursor::new(
+            first_page.last().unwrap(),
+            PostSort::CreatedAtDesc,
+        );
+        let (second_page, has_more) = service
+            .list_posts_after(
+                &cursor,
+                2,
+                PostSort::CreatedAtDesc,
+                None,
+                false,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let titles: Vec<&str> =
+            second_page.iter().map(|post| post.title.as_str()).collect();
+        assert_eq!(titles, ["First"]);
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_title_cursor_pages_alphabetically() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        for title in ["Cherry", "Apple", "Banana", "Apple"] {
+            repository
+                .create(title, "Content", author_id, &[], PostStatus::Published)
+                .await
+                .unwrap();
+        }
+
+        let (first_page, _) = service
+            .list_posts(2, 0, PostSort::TitleAsc, None, false, None)
+            .await
+            .unwrap();
+        let cursor =
+            PostCursor::new(first_page.last().unwrap(), PostSort::TitleAsc);
+        let (second_page, _) = service
+            .list_posts_after(&cursor, 2, PostSort::TitleAsc, None, false, None)
+            .await
+            .unwrap();
+        let mismatched = service
+            .list_posts_after(
+                &cursor,
+                2,
+                PostSort::CreatedAtAsc,
+                None,
+                false,
+                None,
+            )
+            .await;
+
+        let titles: Vec<&str> = first_page
+            .iter()
+            .chain(&second_page)
+            .map(|post| post.title.as_str())
+            .collect();
+        assert_eq!(titles, ["Apple", "Apple", "Banana", "Cherry"]);
+        assert!(matches!(mismatched, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_export_streams_all_author_posts() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let other_id = create_user(&users, "other").await;
+
+        let mut expected = Vec::new();
+        for i in 0..5 {
+            let post = repository
+                .create(
+                    &format!("Post {i}"),
+                    "Content",
+                    author_id,
+                    &[],
+                    PostStatus::Published,
+                )
+                .await
+                .unwrap();
+            expected.push(post.id);
+        }
+        repository
+            .create("Other", "Content", other_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let batches: Vec<Vec<Post>> = service
+            .export_posts_in_batches(author_id, 2)
+            .try_collect()
+            .await
+            .unwrap();
+        let exported: Vec<i64> =
+            batches.into_iter().flatten().map(|p| p.id).collect();
+
+        assert_eq!(exported, expected);
+    }
+
+    #[tokio::test]
+    async fn test_stream_visits_every_post_once_from_the_cursor() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let mut created = Vec::new();
+        for i in 0..5 {
+            let post = repository
+                .create(
+                    &format!("Post {i}"),
+                    "Content",
+                    author_id,
+                    &[],
+                    PostStatus::Published,
+                )
+                .await
+                .unwrap();
+            created.push(post);
+        }
+        let ids = |batches: Vec<Vec<Post>>| -> Vec<i64> {
+            batches.into_iter().flatten().map(|p| p.id).collect()
+        };
+
+        let all = service
+            .stream_posts_in_batches(PostSort::CreatedAtAsc, None, 2)
+            .try_collect()
+            .await
+            .unwrap();
+        let rest = service
+            .stream_posts_in_batches(
+                PostSort::CreatedAtAsc,
+                Some(PostCursor::new(&created[2], PostSort::CreatedAtAsc)),
+                2,
+            )
+            .try_collect()
+            .await
+            .unwrap();
+
+        let expected: Vec<i64> = created.iter().map(|p| p.id).collect();
+        assert_eq!(ids(all), expected);
+        assert_eq!(ids(rest), expected[3..]);
+    }
+
+    #[tokio::test]
+    async fn test_author_posts_are_filtered_and_counted() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        let other_id = create_user(&users, "other").await;
+        for title in ["First", "Second", "Third"] {
+            repository
+                .create(title, "Content", author_id, &[], PostStatus::Published)
+                .await
+                .unwrap();
+        }
+        repository
+            .create("Other", "Content", other_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let (page, total) = service
+            .list_author_posts(author_id, 2, 0, PostSort::CreatedAtDesc)
+            .await
+            .unwrap();
+        let (unknown, unknown_total) = service
+            .list_author_posts(9999, 10, 0, PostSort::default())
+            .await
+            .unwrap();
+
+        let titles: Vec<_> = page.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, ["Third", "Second"]);
+        assert_eq!(total, 3);
+        assert!(unknown.is_empty());
+        assert_eq!(unknown_total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_title_matches_first() {
+        let (users, repository, service) = service(0);
+        let author_id = create_user(&users, "author").await;
+        for (title, content) in [
+            ("Notes", "Borrowing in Rust"),
+            ("Rust borrowing", "Explained"),
+            ("Gardening", "Tomatoes"),
+        ] {
+            repository
+                .create(title, content, author_id, &[], PostStatus::Published)
+                .await
+                .unwrap();
+        }
+
+        let (found, total) =
+            service.search_posts("  borrow RUST ", 10, 0).await.unwrap();
+        let blank = service.search_posts("   ", 10, 0).await;
+
+        let titles: Vec<&str> =
+            found.iter().map(|post| post.title.as_str()).collect();
+        assert_eq!(titles, ["Rust borrowing", "Notes"]);
+        assert_eq!(total, 2);
+        assert!(matches!(blank, Err(DomainError::ValidationError(_))));
+    }
 }
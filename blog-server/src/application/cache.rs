@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Entry count above which `TtlMap::set` drops expired entries
+const TTL_MAP_PRUNE_THRESHOLD: usize = 1024;
+
+/// Single-value cache that expires after a fixed TTL.
+/// A zero TTL disables caching entirely.
+pub struct TtlCache<T> {
+    ttl: Duration,
+    entry: Mutex<Option<(T, Instant)>>,
+}
+
+impl<T: Copy> TtlCache<T> {
+    pub const fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// Returns the cached value if it is still fresh
+    pub fn get(&self) -> Option<T> {
+        let entry = self.entry.lock().ok()?;
+        entry
+            .filter(|(_, stored_at)| stored_at.elapsed() < self.ttl)
+            .map(|(value, _)| value)
+    }
+
+    pub fn set(&self, value: T) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = Some((value, Instant::now()));
+        }
+    }
+
+    pub fn invalidate(&self) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = None;
+        }
+    }
+}
+
+/// Per-key variant of `TtlCache`. A zero TTL disables caching entirely.
+pub struct TtlMap<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (V, Instant)>>,
+}
+
+impl<K: Eq + Hash, V: Copy> TtlMap<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached value for `key` if it is still fresh
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|(_, stored_at)| stored_at.elapsed() < self.ttl)
+            .map(|(value, _)| *value)
+    }
+
+    pub fn set(&self, key: K, value: V) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() >= TTL_MAP_PRUNE_THRESHOLD {
+                entries
+                    .retain(|_, (_, stored_at)| stored_at.elapsed() < self.ttl);
+            }
+            entries.insert(key, (value, Instant::now()));
+        }
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_expires_after_ttl() {
+        let cache = TtlCache::new(Duration::from_millis(20));
+        cache.set(1);
+        assert_eq!(cache.get(), Some(1));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(), None);
+
+        cache.set(2);
+        assert_eq!(cache.get(), Some(2));
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_cache() {
+        let cache = TtlCache::new(Duration::ZERO);
+        cache.set(1);
+        assert_eq!(cache.get(), None);
+    }
+
+    #[test]
+    fn test_map_entries_are_independent() {
+        let cache = TtlMap::new(Duration::from_secs(60));
+        cache.set(1, 10);
+        cache.set(2, 20);
+
+        cache.invalidate(&1);
+
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some(20));
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use super::sanitizer::ContentSanitizer;
+use crate::data::{CommentRepository, PostRepository};
+use crate::domain::{Comment, CreateCommentCommand, DomainError};
+
+pub struct CommentService {
+    comment_repository: Arc<dyn CommentRepository>,
+    post_repository: Arc<dyn PostRepository>,
+    /// Same sanitizer as post content, since both end up rendered as HTML
+    sanitizer: ContentSanitizer,
+}
+
+impl CommentService {
+    pub fn new(
+        comment_repository: Arc<dyn CommentRepository>,
+        post_repository: Arc<dyn PostRepository>,
+        sanitizer: ContentSanitizer,
+    ) -> Self {
+        Self {
+            comment_repository,
+            post_repository,
+            sanitizer,
+        }
+    }
+
+    pub async fn add_comment(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        command: CreateCommentCommand,
+    ) -> Result<Comment, DomainError> {
+        command.validate()?;
+        self.ensure_post_exists(post_id).await?;
+
+        let content = self.sanitizer.clean(command.content);
+        self.comment_repository
+            .create(post_id, author_id, &content)
+            .await
+    }
+
+    /// A page of a post's comments, oldest first, plus their total
+    pub async fn list_comments(
+        &self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Comment>, i64), DomainError> {
+        self.ensure_post_exists(post_id).await?;
+        self.comment_repository
+            .list_by_post(post_id, limit, offset)
+            .await
+    }
+
+    /// Comments of a soft-deleted or unpublished post are hidden along
+    /// with it, and come back if the post is restored or published again
+    async fn ensure_post_exists(
+        &self,
+        post_id: i64,
+    ) -> Result<(), DomainError> {
+        match self.post_repository.find_by_id(post_id, false).await? {
+            Some(post) if post.status.is_published() => Ok(()),
+            _ => Err(DomainError::PostNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{
+        InMemoryCommentRepository, InMemoryPostRepository,
+        InMemoryUserRepository, UserRepository,
+    };
+    use crate::domain::{Password, PostStatus};
+
+    fn command(content: &str) -> CreateCommentCommand {
+        CreateCommentCommand {
+            content: content.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_comments_follow_their_post() {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let service = CommentService::new(
+            Arc::new(InMemoryCommentRepository::new(users.clone())),
+            posts.clone(),
+            ContentSanitizer::default(),
+        );
+        let author = users
+            .create(
+                "author",
+                "author@example.com",
+                &Password::from_hash(String::new()),
+            )
+            .await
+            .unwrap();
+        let post = posts
+            .create("Title", "Content", author.id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let comment = service
+            .add_comment(post.id, author.id, command("Nice post"))
+            .await
+            .unwrap();
+        service
+            .add_comment(post.id, author.id, command("Me again"))
+            .await
+            .unwrap();
+        let (page, total) = service.list_comments(post.id, 1, 1).await.unwrap();
+
+        assert_eq!(comment.author_username.as_deref(), Some("author"));
+        assert_eq!(total, 2);
+        assert_eq!(page[0].content, "Me again");
+        assert!(matches!(
+            service.add_comment(post.id, author.id, command("  ")).await,
+            Err(DomainError::InvalidFields(_))
+        ));
+
+        posts.delete_by_author(post.id, author.id).await.unwrap();
+
+        assert!(matches!(
+            service.list_comments(post.id, 10, 0).await,
+            Err(DomainError::PostNotFound)
+        ));
+        assert!(matches!(
+            service
+                .add_comment(post.id, author.id, command("Too late"))
+                .await,
+            Err(DomainError::PostNotFound)
+        ));
+    }
+}
@@ -2,6 +2,10 @@
 
 pub mod auth_service;
 pub mod blog_service;
+pub mod cache;
+pub mod comment_service;
+pub mod sanitizer;
 
-pub use auth_service::AuthService;
-pub use blog_service::BlogService;
+pub use auth_service::{AuthConfig, AuthService};
+pub use blog_service::{BlogConfig, BlogService};
+pub use comment_service::CommentService;
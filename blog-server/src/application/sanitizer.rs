@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+/// Tags ammonia drops together with their content. Allowing one of them
+/// too is a contradiction ammonia panics on when cleaning.
+const CONTENT_STRIPPED_TAGS: [&str; 2] = ["script", "style"];
+
+/// Strips scripts, event handlers and dangerous URLs from user-supplied
+/// HTML before it is stored. Disabled sanitizers pass content through
+/// untouched, so plain-text deployments keep characters like `<` as is.
+#[derive(Clone, Default)]
+pub struct ContentSanitizer {
+    enabled: bool,
+    /// Overrides ammonia's default safe tag set when non-empty
+    allowed_tags: Vec<String>,
+}
+
+impl ContentSanitizer {
+    /// Fails when `allowed_tags` names a tag whose content is always
+    /// stripped
+    pub fn new(
+        enabled: bool,
+        allowed_tags: Vec<String>,
+    ) -> anyhow::Result<Self> {
+        if let Some(tag) = allowed_tags.iter().find(|tag| {
+            CONTENT_STRIPPED_TAGS
+                .iter()
+                .any(|stripped| tag.eq_ignore_ascii_case(stripped))
+        }) {
+            anyhow::bail!("<{tag}> cannot be allowed; its content is removed");
+        }
+
+        Ok(Self {
+            enabled,
+            allowed_tags,
+        })
+    }
+
+    pub fn clean(&self, content: String) -> String {
+        if !self.enabled {
+            return content;
+        }
+
+        let mut builder = ammonia::Builder::default();
+        if !self.allowed_tags.is_empty() {
+            let tags: HashSet<&str> =
+                self.allowed_tags.iter().map(String::as_str).collect();
+            builder.tags(tags);
+        }
+        builder.clean(&content).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_is_removed() {
+        let sanitizer = ContentSanitizer::new(true, Vec::new()).unwrap();
+
+        let cleaned = sanitizer
+            .clean("<p>Hello</p><script>alert('xss')</script>".to_string());
+
+        assert_eq!(cleaned, "<p>Hello</p>");
+    }
+
+    #[test]
+    fn test_event_handler_is_removed() {
+        let sanitizer = ContentSanitizer::new(true, Vec::new()).unwrap();
+
+        let cleaned =
+            sanitizer.clean(r#"<b onclick="steal()">bold</b>"#.to_string());
+
+        assert_eq!(cleaned, "<b>bold</b>");
+    }
+
+    #[test]
+    fn test_allowed_tags_override_defaults() {
+        let sanitizer =
+            ContentSanitizer::new(true, vec!["p".to_string()]).unwrap();
+
+        let cleaned = sanitizer.clean("<p><b>bold</b></p>".to_string());
+
+        assert_eq!(cleaned, "<p>bold</p>");
+    }
+
+    #[test]
+    fn test_content_stripped_tags_cannot_be_allowed() {
+        for tag in ["script", "STYLE"] {
+            let tags = vec!["p".to_string(), tag.to_string()];
+
+            assert!(ContentSanitizer::new(true, tags).is_err());
+        }
+    }
+
+    #[test]
+    fn test_disabled_sanitizer_keeps_content() {
+        let sanitizer = ContentSanitizer::default();
+
+        let cleaned = sanitizer.clean("1 < 2 <script>".to_string());
+
+        assert_eq!(cleaned, "1 < 2 <script>");
+    }
+}
@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::{CommentRepository, QueryLog};
+use crate::domain::{Comment, DomainError};
+use crate::infrastructure::DbPools;
+
+pub struct PostgresCommentRepository {
+    pools: DbPools,
+    query_log: QueryLog,
+}
+
+impl PostgresCommentRepository {
+    pub const fn new(pools: DbPools, query_log: QueryLog) -> Self {
+        Self { pools, query_log }
+    }
+}
+
+#[async_trait]
+impl CommentRepository for PostgresCommentRepository {
+    async fn create(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        content: &str,
+    ) -> Result<Comment, DomainError> {
+        let query = sqlx::query_as::<_, CommentRow>(
+            r"
+            WITH inserted AS (
+                INSERT INTO comments (post_id, author_id, content)
+                VALUES ($1, $2, $3)
+                RETURNING id, post_id, author_id, content, created_at
+            )
+            SELECT c.id, c.post_id, c.author_id, u.username AS author_username,
+                c.content, c.created_at
+            FROM inserted c
+            LEFT JOIN users u ON c.author_id = u.id
+            ",
+        )
+        .bind(post_id)
+        .bind(author_id)
+        .bind(content)
+        .fetch_one(&self.pools.primary);
+        let row = self.query_log.time("comments.create", query).await?;
+
+        Ok(row.into())
+    }
+
+    async fn list_by_post(
+        &self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Comment>, i64), DomainError> {
+        // The window runs before LIMIT, so every row carries the total
+        let query = sqlx::query_as::<_, CountedCommentRow>(
+            r"
+            SELECT c.id, c.post_id, c.author_id, u.username AS author_username,
+                c.content, c.created_at, COUNT(*) OVER () AS total
+            FROM comments c
+            LEFT JOIN users u ON c.author_id = u.id
+            WHERE c.post_id = $1
+            ORDER BY c.created_at, c.id
+            LIMIT $2 OFFSET $3
+            ",
+        )
+        .bind(post_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pools.replica);
+        let rows = self.query_log.time("comments.list_by_post", query).await?;
+
+        // A page past the end has no row to carry the total
+        let total = match rows.first() {
+            Some(row) => row.total,
+            None if offset > 0 => {
+                let count = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM comments WHERE post_id = $1",
+                )
+                .bind(post_id)
+                .fetch_one(&self.pools.replica);
+                self.query_log.time("comments.count_by_post", count).await?
+            }
+            None => 0,
+        };
+        let comments = rows.into_iter().map(|row| row.comment.into()).collect();
+        Ok((comments, total))
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct CommentRow {
+    id: i64,
+    post_id: i64,
+    author_id: i64,
+    /// Null for comments whose author row is gone
+    author_username: Option<String>,
+    content: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CountedCommentRow {
+    #[sqlx(flatten)]
+    comment: CommentRow,
+    total: i64,
+}
+
+impl From<CommentRow> for Comment {
+    fn from(row: CommentRow) -> Self {
+        Self {
+            id: row.id,
+            post_id: row.post_id,
+            author_id: row.author_id,
+            author_username: row.author_username,
+            content: row.content,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::insert_user;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_comments_go_away_with_their_post(pool: PgPool) {
+        let repo = PostgresCommentRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let (post_id,): (i64,) = sqlx::query_as(
+            r"
+            INSERT INTO posts (title, content, author_id, status)
+            VALUES ('Title', 'Content', $1, 'published')
+            RETURNING id
+            ",
+        )
+        .bind(author_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let comment = repo.create(post_id, author_id, "First").await.unwrap();
+        repo.create(post_id, author_id, "Second").await.unwrap();
+        let (comments, total) =
+            repo.list_by_post(post_id, 10, 0).await.unwrap();
+
+        assert_eq!(comment.author_username.as_deref(), Some("author"));
+        assert_eq!(total, 2);
+        assert_eq!(comments[0].content, "First");
+
+        sqlx::query("DELETE FROM posts WHERE id = $1")
+            .bind(post_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (_, total) = repo.list_by_post(post_id, 10, 0).await.unwrap();
+
+        assert_eq!(total, 0);
+    }
+}
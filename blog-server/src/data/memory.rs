@@ -0,0 +1,1331 @@
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::{
+    CommentRepository, PostRepository, SessionRepository, UserRepository,
+};
+use crate::domain::{
+    AuthorSummary, BulkTagResult, Comment, DomainError, IdempotencyKey,
+    Password, Post, PostCursor, PostNeighbors, PostSort, PostStatus, Session,
+    TagAction, TagFilter, User,
+};
+
+/// Process-local user storage for demos and tests. Data is lost on restart.
+#[derive(Default)]
+pub struct InMemoryUserRepository {
+    users: RwLock<HashMap<i64, User>>,
+    next_id: AtomicI64,
+}
+
+impl InMemoryUserRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn username_of(&self, id: i64) -> Option<String> {
+        self.users
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .map(|user| user.username.clone())
+    }
+
+    fn author_summary_of(&self, id: i64) -> Option<AuthorSummary> {
+        self.users
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .map(|user| AuthorSummary {
+                username: user.username.clone(),
+                email: Some(user.email.clone()),
+                created_at: user.created_at,
+            })
+    }
+
+    fn exists(&self, id: i64) -> bool {
+        self.users
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(&id)
+    }
+
+    fn find_by(&self, matches: impl Fn(&User) -> bool) -> Option<User> {
+        self.users
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .find(|user| matches(user))
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn create(
+        &self,
+        username: &str,
+        email: &str,
+        password_hash: &Password,
+    ) -> Result<User, DomainError> {
+        let mut users =
+            self.users.write().unwrap_or_else(PoisonError::into_inner);
+
+        // Mirrors the UNIQUE constraints on users.username and users.email
+        if users
+            .values()
+            .any(|user| user.username == username || user.email == email)
+        {
+            return Err(DomainError::UserAlreadyExists);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let user = User::new(
+            id,
+            username.to_string(),
+            email.to_string(),
+            password_hash.clone(),
+            Utc::now(),
+        );
+        users.insert(id, user.clone());
+        drop(users);
+
+        Ok(user)
+    }
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, DomainError> {
+        Ok(self.find_by(|user| user.id == id))
+    }
+
+    async fn find_by_email(
+        &self,
+        email: &str,
+    ) -> Result<Option<User>, DomainError> {
+        Ok(self.find_by(|user| user.email == email))
+    }
+
+    async fn find_by_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<User>, DomainError> {
+        Ok(self.find_by(|user| user.username == username))
+    }
+
+    async fn mark_email_verified(
+        &self,
+        id: i64,
+        email: &str,
+    ) -> Result<Option<User>, DomainError> {
+        let mut users =
+            self.users.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(user) = users
+            .get_mut(&id)
+            .filter(|user| user.email == email && !user.email_verified)
+        else {
+            return Ok(None);
+        };
+        user.email_verified = true;
+        let user = user.clone();
+        drop(users);
+
+        Ok(Some(user))
+    }
+
+    async fn password_version(
+        &self,
+        id: i64,
+    ) -> Result<Option<i32>, DomainError> {
+        Ok(self
+            .find_by(|user| user.id == id)
+            .map(|user| user.password_version))
+    }
+
+    async fn update_password(
+        &self,
+        id: i64,
+        password_hash: &Password,
+    ) -> Result<Option<i32>, DomainError> {
+        let mut users =
+            self.users.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(user) = users.get_mut(&id) else {
+            return Ok(None);
+        };
+        user.password_hash = password_hash.clone();
+        user.password_version += 1;
+        let version = user.password_version;
+        drop(users);
+
+        Ok(Some(version))
+    }
+
+    async fn upgrade_password_hash(
+        &self,
+        id: i64,
+        expected_version: i32,
+        password_hash: &Password,
+    ) -> Result<bool, DomainError> {
+        let mut users =
+            self.users.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(user) = users
+            .get_mut(&id)
+            .filter(|user| user.password_version == expected_version)
+        else {
+            return Ok(false);
+        };
+        user.password_hash = password_hash.clone();
+        drop(users);
+
+        Ok(true)
+    }
+}
+
+/// Position of a post in a listing; smaller sorts first. Ids break ties
+/// between posts with the same sort column.
+#[derive(PartialEq, Eq)]
+struct SortKey {
+    cursor: PostCursor,
+    descending: bool,
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        let ascending = (&self.cursor.key, self.cursor.id)
+            .cmp(&(&other.cursor.key, other.cursor.id));
+        if self.descending {
+            ascending.reverse()
+        } else {
+            ascending
+        }
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn sort_key(post: &Post, sort: PostSort) -> SortKey {
+    SortKey {
+        cursor: PostCursor::new(post, sort),
+        descending: sort.is_descending(),
+    }
+}
+
+/// Lowercase words of a search query, standing in for `plainto_tsquery`
+fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Rough `ts_rank` stand-in: title hits weigh more than content hits.
+/// `None` unless every term occurs somewhere, like the `@@` match.
+fn search_score(post: &Post, terms: &[String]) -> Option<i64> {
+    if terms.is_empty() {
+        return None;
+    }
+    let title = post.title.to_lowercase();
+    let content = post.content.to_lowercase();
+
+    terms.iter().try_fold(0, |score, term| {
+        match (title.contains(term.as_str()), content.contains(term.as_str())) {
+            (false, false) => None,
+            (in_title, in_content) => {
+                Some(score + 2 * i64::from(in_title) + i64::from(in_content))
+            }
+        }
+    })
+}
+
+/// Whether listings show the post to everyone, like
+/// `status = 'published'` in SQL
+const fn is_public(post: &Post) -> bool {
+    post.status.is_published()
+}
+
+/// Use of an idempotency key
+struct KeyUse {
+    post_id: i64,
+    fingerprint: String,
+    used_at: DateTime<Utc>,
+}
+
+/// Uses by `(author_id, key)`
+type IdempotencyKeys = HashMap<(i64, String), KeyUse>;
+
+/// Process-local post storage for demos and tests. Author usernames are
+/// resolved through the shared user repository, like the SQL join does.
+pub struct InMemoryPostRepository {
+    users: Arc<InMemoryUserRepository>,
+    posts: RwLock<HashMap<i64, Post>>,
+    /// Soft-deleted posts, kept apart so reads never see them
+    deleted: RwLock<HashMap<i64, Post>>,
+    /// Async, because it stays locked while the post is created to
+    /// serialize retries
+    idempotency_keys: Mutex<IdempotencyKeys>,
+    next_id: AtomicI64,
+}
+
+impl InMemoryPostRepository {
+    pub fn new(users: Arc<InMemoryUserRepository>) -> Self {
+        Self {
+            users,
+            posts: RwLock::default(),
+            deleted: RwLock::default(),
+            idempotency_keys: Mutex::default(),
+            next_id: AtomicI64::default(),
+        }
+    }
+
+    /// The post an earlier use of `key` created. Refused when the request
+    /// differs or the post has been deleted since.
+    async fn replay(
+        &self,
+        used: &KeyUse,
+        key: IdempotencyKey<'_>,
+    ) -> Result<Post, DomainError> {
+        if used.fingerprint != key.fingerprint {
+            return Err(DomainError::IdempotencyKeyReused);
+        }
+        self.find_by_id(used.post_id, false)
+            .await?
+            .ok_or(DomainError::IdempotencyKeyReused)
+    }
+
+    fn with_author(&self, post: Post) -> Post {
+        match self.users.username_of(post.author_id) {
+            Some(username) => post.with_author_username(username),
+            None => post,
+        }
+    }
+
+    fn with_author_summary(&self, post: Post, include_author: bool) -> Post {
+        let summary = include_author
+            .then(|| self.users.author_summary_of(post.author_id))
+            .flatten();
+        match summary {
+            Some(summary) => post.with_author(summary),
+            None => post,
+        }
+    }
+
+    fn collect<K: Ord>(
+        &self,
+        filter: impl Fn(&Post) -> bool,
+        sort_key: impl Fn(&Post) -> K,
+    ) -> Vec<Post> {
+        let mut posts: Vec<Post> = self
+            .posts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|post| filter(post))
+            .cloned()
+            .collect();
+        posts.sort_by_cached_key(sort_key);
+        posts
+    }
+
+    fn page(&self, posts: Vec<Post>, offset: i64, limit: i64) -> Vec<Post> {
+        posts
+            .into_iter()
+            .skip(usize::try_from(offset).unwrap_or(0))
+            .take(usize::try_from(limit).unwrap_or(0))
+            .map(|post| self.with_author(post))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl PostRepository for InMemoryPostRepository {
+    async fn create(
+        &self,
+        title: &str,
+        content: &str,
+        author_id: i64,
+        tags: &[String],
+        status: PostStatus,
+    ) -> Result<Post, DomainError> {
+        // Mirrors the posts.author_id foreign key
+        if !self.users.exists(author_id) {
+            return Err(DomainError::UserNotFound);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let now = Utc::now();
+        let post = Post::new(
+            id,
+            title.to_string(),
+            content.to_string(),
+            author_id,
+            now,
+            now,
+        )
+        .with_tags(tags.to_vec())
+        .with_status(status);
+        self.posts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, post.clone());
+
+        Ok(self.with_author(post))
+    }
+
+    async fn find_by_idempotency_key(
+        &self,
+        author_id: i64,
+        key: IdempotencyKey<'_>,
+    ) -> Result<Option<Post>, DomainError> {
+        let keys = self.idempotency_keys.lock().await;
+        match keys.get(&(author_id, key.key.to_string())) {
+            Some(used) if used.used_at >= key.since => {
+                self.replay(used, key).await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn create_with_idempotency_key(
+        &self,
+        title: &str,
+        content: &str,
+        author_id: i64,
+        tags: &[String],
+        status: PostStatus,
+        key: IdempotencyKey<'_>,
+    ) -> Result<(Post, bool), DomainError> {
+        let mut keys = self.idempotency_keys.lock().await;
+        keys.retain(|&(user_id, _), &mut KeyUse { used_at, .. }| {
+            user_id != author_id || used_at >= key.since
+        });
+
+        if let Some(used) = keys.get(&(author_id, key.key.to_string())) {
+            return self.replay(used, key).await.map(|post| (post, false));
+        }
+
+        let post = self.create(title, content, author_id, tags, status).await?;
+        keys.insert(
+            (author_id, key.key.to_string()),
+            KeyUse {
+                post_id: post.id,
+                fingerprint: key.fingerprint.to_string(),
+                used_at: Utc::now(),
+            },
+        );
+        drop(keys);
+
+        Ok((post, true))
+    }
+
+    async fn find_by_id(
+        &self,
+        id: i64,
+        include_author: bool,
+    ) -> Result<Option<Post>, DomainError> {
+        let post = self
+            .posts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .cloned();
+        Ok(post.map(|post| {
+            self.with_author_summary(self.with_author(post), include_author)
+        }))
+    }
+
+    async fn find_random(&self) -> Result<Option<Post>, DomainError> {
+        let mut posts = self.collect(is_public, |post| post.id);
+        if posts.is_empty() {
+            return Ok(None);
+        }
+        let index =
+            usize::try_from(OsRng.next_u64()).unwrap_or(0) % posts.len();
+        let post = Some(posts.swap_remove(index));
+        Ok(post.map(|post| self.with_author(post)))
+    }
+
+    async fn find_neighbors(
+        &self,
+        post: &Post,
+    ) -> Result<PostNeighbors, DomainError> {
+        let key = |post: &Post| (post.created_at, post.id);
+        let anchor = key(post);
+        let (newer, older) = {
+            let posts =
+                self.posts.read().unwrap_or_else(PoisonError::into_inner);
+            let newer = posts
+                .values()
+                .filter(|post| is_public(post) && key(post) > anchor)
+                .min_by_key(|post| key(post))
+                .cloned();
+            let older = posts
+                .values()
+                .filter(|post| is_public(post) && key(post) < anchor)
+                .max_by_key(|post| key(post))
+                .cloned();
+            drop(posts);
+            (newer, older)
+        };
+
+        Ok(PostNeighbors {
+            newer: newer.map(|post| self.with_author(post)),
+            older: older.map(|post| self.with_author(post)),
+        })
+    }
+
+    async fn update_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+        title: &str,
+        content: &str,
+        tags: Option<&[String]>,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<Post>, DomainError> {
+        let mut posts =
+            self.posts.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(post) = posts.get_mut(&id).filter(|post| {
+            post.author_id == author_id
+                && expected_updated_at.is_none_or(|at| post.is_version(at))
+        }) else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        if post.title != title {
+            title.clone_into(&mut post.title);
+            post.title_updated_at = now;
+            post.updated_at = now;
+        }
+        if post.content != content {
+            content.clone_into(&mut post.content);
+            post.content_updated_at = now;
+            post.updated_at = now;
+        }
+        if let Some(tags) = tags.filter(|tags| post.tags != *tags) {
+            post.tags = tags.to_vec();
+            post.updated_at = now;
+        }
+        let updated = post.clone();
+        drop(posts);
+
+        Ok(Some(self.with_author(updated)))
+    }
+
+    async fn update_tag_by_author(
+        &self,
+        author_id: i64,
+        post_ids: &[i64],
+        tag: &str,
+        action: TagAction,
+    ) -> Result<BulkTagResult, DomainError> {
+        let mut posts =
+            self.posts.write().unwrap_or_else(PoisonError::into_inner);
+        let mut owned = 0;
+        let mut updated = 0;
+        for id in post_ids {
+            let Some(post) =
+                posts.get_mut(id).filter(|post| post.author_id == author_id)
+            else {
+                continue;
+            };
+            owned += 1;
+
+            // Tags stay sorted, as normalize_tags leaves them
+            let position = post.tags.binary_search_by(|t| t.as_str().cmp(tag));
+            match (action, position) {
+                (TagAction::Add, Err(index)) => {
+                    post.tags.insert(index, tag.to_string());
+                }
+                (TagAction::Remove, Ok(index)) => {
+                    post.tags.remove(index);
+                }
+                _ => continue,
+            }
+            updated += 1;
+        }
+        drop(posts);
+
+        Ok(BulkTagResult::new(post_ids.len(), owned, updated))
+    }
+
+    async fn delete_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+    ) -> Result<bool, DomainError> {
+        let mut posts =
+            self.posts.write().unwrap_or_else(PoisonError::into_inner);
+        let owned = posts
+            .get(&id)
+            .is_some_and(|post| post.author_id == author_id);
+        if owned && let Some(post) = posts.remove(&id) {
+            self.deleted
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(id, post);
+        }
+        drop(posts);
+
+        Ok(owned)
+    }
+
+    async fn restore_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+    ) -> Result<Option<Post>, DomainError> {
+        let mut posts =
+            self.posts.write().unwrap_or_else(PoisonError::into_inner);
+        let mut deleted =
+            self.deleted.write().unwrap_or_else(PoisonError::into_inner);
+        let owned = deleted
+            .get(&id)
+            .is_some_and(|post| post.author_id == author_id);
+        let restored = if owned { deleted.remove(&id) } else { None };
+        drop(deleted);
+        if let Some(post) = &restored {
+            posts.insert(id, post.clone());
+        }
+        drop(posts);
+
+        Ok(restored.map(|post| self.with_author(post)))
+    }
+
+    async fn find_deleted_author(
+        &self,
+        id: i64,
+    ) -> Result<Option<i64>, DomainError> {
+        Ok(self
+            .deleted
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .map(|post| post.author_id))
+    }
+
+    async fn set_status_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+        status: PostStatus,
+    ) -> Result<Option<Post>, DomainError> {
+        let post = self
+            .posts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(&id)
+            .filter(|post| post.author_id == author_id)
+            .map(|post| {
+                post.status = status;
+                post.clone()
+            });
+
+        Ok(post.map(|post| self.with_author(post)))
+    }
+
+    async fn list(
+        &self,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        let posts = self.collect(
+            |post| {
+                post.is_visible_to(viewer)
+                    && filter.is_none_or(|filter| filter.matches(&post.tags))
+            },
+            |post| sort_key(post, sort),
+        );
+        let total = i64::try_from(posts.len()).unwrap_or(i64::MAX);
+        let page = self
+            .page(posts, offset, limit)
+            .into_iter()
+            .map(|post| self.with_author_summary(post, include_author))
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        let terms = search_terms(query);
+        let posts = self.collect(
+            |post| is_public(post) && search_score(post, &terms).is_some(),
+            |post| {
+                let score = search_score(post, &terms).unwrap_or(0);
+                (-score, sort_key(post, PostSort::CreatedAtDesc))
+            },
+        );
+        let total = i64::try_from(posts.len()).unwrap_or(i64::MAX);
+        Ok((self.page(posts, offset, limit), total))
+    }
+
+    async fn list_after(
+        &self,
+        cursor: &PostCursor,
+        limit: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
+    ) -> Result<Vec<Post>, DomainError> {
+        let after = SortKey {
+            cursor: cursor.clone(),
+            descending: sort.is_descending(),
+        };
+        let posts = self.collect(
+            |post| {
+                post.is_visible_to(viewer)
+                    && sort_key(post, sort) > after
+                    && filter.is_none_or(|filter| filter.matches(&post.tags))
+            },
+            |post| sort_key(post, sort),
+        );
+        Ok(self
+            .page(posts, 0, limit)
+            .into_iter()
+            .map(|post| self.with_author_summary(post, include_author))
+            .collect())
+    }
+
+    async fn list_by_author(
+        &self,
+        author_id: i64,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+        include_drafts: bool,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        let posts = self.collect(
+            |post| {
+                post.author_id == author_id
+                    && (include_drafts || is_public(post))
+            },
+            |post| sort_key(post, sort),
+        );
+        let total = i64::try_from(posts.len()).unwrap_or(i64::MAX);
+        Ok((self.page(posts, offset, limit), total))
+    }
+
+    async fn list_by_author_after(
+        &self,
+        author_id: i64,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Post>, DomainError> {
+        let posts = self.collect(
+            |post| post.author_id == author_id && post.id > after_id,
+            |post| post.id,
+        );
+        Ok(self.page(posts, 0, limit))
+    }
+
+    async fn count(
+        &self,
+        filter: Option<&TagFilter>,
+        viewer: Option<i64>,
+    ) -> Result<i64, DomainError> {
+        let count = self
+            .posts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|post| {
+                post.is_visible_to(viewer)
+                    && filter.is_none_or(|filter| filter.matches(&post.tags))
+            })
+            .count();
+        Ok(i64::try_from(count).unwrap_or(i64::MAX))
+    }
+
+    async fn count_by_author(
+        &self,
+        author_id: i64,
+    ) -> Result<i64, DomainError> {
+        let count = self
+            .posts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|post| post.author_id == author_id)
+            .count();
+        Ok(i64::try_from(count).unwrap_or(i64::MAX))
+    }
+
+    async fn count_by_day(
+        &self,
+        author_id: i64,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(NaiveDate, i64)>, DomainError> {
+        let mut days = BTreeMap::new();
+        for post in self
+            .posts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|post| post.author_id == author_id)
+            .filter(|post| is_public(post) && post.created_at >= since)
+        {
+            *days.entry(post.created_at.date_naive()).or_insert(0) += 1;
+        }
+        Ok(days.into_iter().collect())
+    }
+
+    async fn tag_counts(&self) -> Result<Vec<(String, i64)>, DomainError> {
+        let mut counts = BTreeMap::new();
+        for tag in self
+            .posts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|post| is_public(post))
+            .flat_map(|post| &post.tags)
+        {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+        Ok(counts.into_iter().collect())
+    }
+}
+
+/// Process-local comment storage for demos and tests. Comments of a
+/// deleted post stay, but the service never lists them.
+pub struct InMemoryCommentRepository {
+    users: Arc<InMemoryUserRepository>,
+    /// In creation order, since ids only grow
+    comments: RwLock<Vec<Comment>>,
+    next_id: AtomicI64,
+}
+
+impl InMemoryCommentRepository {
+    pub fn new(users: Arc<InMemoryUserRepository>) -> Self {
+        Self {
+            users,
+            comments: RwLock::default(),
+            next_id: AtomicI64::default(),
+        }
+    }
+
+    fn with_author(&self, mut comment: Comment) -> Comment {
+        comment.author_username = self.users.username_of(comment.author_id);
+        comment
+    }
+}
+
+#[async_trait]
+impl CommentRepository for InMemoryCommentRepository {
+    async fn create(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        content: &str,
+    ) -> Result<Comment, DomainError> {
+        // Mirrors the comments.author_id foreign key
+        if !self.users.exists(author_id) {
+            return Err(DomainError::UserNotFound);
+        }
+
+        let comment = Comment {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            post_id,
+            author_id,
+            author_username: None,
+            content: content.to_string(),
+            created_at: Utc::now(),
+        };
+        self.comments
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(comment.clone());
+
+        Ok(self.with_author(comment))
+    }
+
+    async fn list_by_post(
+        &self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Comment>, i64), DomainError> {
+        let comments: Vec<Comment> = self
+            .comments
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|comment| comment.post_id == post_id)
+            .cloned()
+            .collect();
+        let total = i64::try_from(comments.len()).unwrap_or(i64::MAX);
+        let page = comments
+            .into_iter()
+            .skip(usize::try_from(offset).unwrap_or(0))
+            .take(usize::try_from(limit).unwrap_or(0))
+            .map(|comment| self.with_author(comment))
+            .collect();
+
+        Ok((page, total))
+    }
+}
+
+/// Process-local session storage for demos and tests
+#[derive(Default)]
+pub struct InMemorySessionRepository {
+    sessions: RwLock<HashMap<Uuid, Session>>,
+}
+
+impl InMemorySessionRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionRepository for InMemorySessionRepository {
+    async fn create(&self, session: &Session) -> Result<(), DomainError> {
+        let now = Utc::now();
+        let mut sessions = self
+            .sessions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        sessions.retain(|_, existing| {
+            existing.user_id != session.user_id || existing.expires_at > now
+        });
+        sessions.insert(session.jti, session.clone());
+        drop(sessions);
+        Ok(())
+    }
+
+    async fn is_active(
+        &self,
+        jti: Uuid,
+        user_id: i64,
+    ) -> Result<bool, DomainError> {
+        let now = Utc::now();
+        Ok(self
+            .sessions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&jti)
+            .is_some_and(|session| {
+                session.user_id == user_id && session.expires_at > now
+            }))
+    }
+
+    async fn list_by_user(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<Session>, DomainError> {
+        let now = Utc::now();
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|session| {
+                session.user_id == user_id && session.expires_at > now
+            })
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.issued_at));
+        Ok(sessions)
+    }
+
+    async fn delete(
+        &self,
+        jti: Uuid,
+        user_id: i64,
+    ) -> Result<bool, DomainError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let owned = sessions
+            .get(&jti)
+            .is_some_and(|session| session.user_id == user_id);
+        if owned {
+            sessions.remove(&jti);
+        }
+        drop(sessions);
+        Ok(owned)
+    }
+
+    async fn delete_by_user(&self, user_id: i64) -> Result<(), DomainError> {
+        self.sessions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, session| session.user_id != user_id);
+        Ok(())
+    }
+
+    async fn delete_expired(&self) -> Result<u64, DomainError> {
+        let now = Utc::now();
+        let mut sessions = self
+            .sessions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let before = sessions.len();
+        sessions.retain(|_, session| session.expires_at > now);
+        let deleted = before - sessions.len();
+        drop(sessions);
+        Ok(deleted as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::TagMode;
+    use crate::fixtures::create_user;
+
+    fn repositories() -> (Arc<InMemoryUserRepository>, InMemoryPostRepository) {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = InMemoryPostRepository::new(users.clone());
+        (users, posts)
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_username_or_email_rejected() {
+        let (users, _) = repositories();
+        create_user(&users, "alice").await;
+
+        let hash = Password::from_hash(String::new());
+        let same_username =
+            users.create("alice", "other@example.com", &hash).await;
+        let same_email = users.create("bob", "alice@example.com", &hash).await;
+
+        assert!(matches!(same_username, Err(DomainError::UserAlreadyExists)));
+        assert!(matches!(same_email, Err(DomainError::UserAlreadyExists)));
+    }
+
+    #[tokio::test]
+    async fn test_only_author_can_modify_post() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let other = create_user(&users, "other").await;
+        let post = posts
+            .create("Title", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        assert!(
+            posts
+                .update_by_author(post.id, other, "New", "New", None, None)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(!posts.delete_by_author(post.id, other).await.unwrap());
+        assert!(posts.delete_by_author(post.id, author).await.unwrap());
+        assert!(posts.find_by_id(post.id, false).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_replays_until_it_expires() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let other = create_user(&users, "other").await;
+        let since = Utc::now() - chrono::Duration::hours(1);
+        let create = |author_id, since| {
+            posts.create_with_idempotency_key(
+                "Title",
+                "Content",
+                author_id,
+                &[],
+                PostStatus::Published,
+                IdempotencyKey {
+                    key: "key",
+                    fingerprint: "request",
+                    since,
+                },
+            )
+        };
+
+        let (first, created) = create(author, since).await.unwrap();
+        let (replayed, replay_created) = create(author, since).await.unwrap();
+        let (_, other_created) = create(other, since).await.unwrap();
+        let (renewed, renewed_created) =
+            create(author, Utc::now() + chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        assert!(created);
+        assert!(!replay_created);
+        assert_eq!(replayed.id, first.id);
+        assert!(renewed_created, "an expired key starts afresh");
+        assert_ne!(renewed.id, first.id);
+        assert!(other_created, "keys are per author");
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_is_not_reused_for_other_requests() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let create = |fingerprint| {
+            posts.create_with_idempotency_key(
+                "Title",
+                "Content",
+                author,
+                &[],
+                PostStatus::Published,
+                IdempotencyKey {
+                    key: "key",
+                    fingerprint,
+                    since: Utc::now() - chrono::Duration::hours(1),
+                },
+            )
+        };
+
+        let (post, _) = create("request").await.unwrap();
+        let other_request = create("other").await;
+        posts.delete_by_author(post.id, author).await.unwrap();
+        let after_delete = create("request").await;
+
+        assert!(matches!(
+            other_request,
+            Err(DomainError::IdempotencyKeyReused)
+        ));
+        assert!(matches!(after_delete, Err(DomainError::IdempotencyKeyReused)));
+    }
+
+    #[tokio::test]
+    async fn test_created_post_carries_author_username() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+
+        let post = posts
+            .create("Title", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        assert_eq!(post.author_username.as_deref(), Some("author"));
+    }
+
+    #[tokio::test]
+    async fn test_author_summary_only_on_request() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let post = posts
+            .create("Title", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let plain = posts.find_by_id(post.id, false).await.unwrap().unwrap();
+        let (listed, _) = posts
+            .list(10, 0, PostSort::default(), None, false, None)
+            .await
+            .unwrap();
+        assert!(plain.author.is_none());
+        assert!(listed[0].author.is_none());
+
+        let found = posts.find_by_id(post.id, true).await.unwrap().unwrap();
+        let author = found.author.unwrap();
+        assert_eq!(author.username, "author");
+        assert_eq!(author.email.as_deref(), Some("author@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_post_requires_existing_author() {
+        let (_, posts) = repositories();
+
+        let result = posts
+            .create("Title", "Content", 42, &[], PostStatus::Published)
+            .await;
+
+        assert!(matches!(result, Err(DomainError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_noop_update_keeps_updated_at() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let post = posts
+            .create("Title", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let updated = posts
+            .update_by_author(post.id, author, "Title", "Content", None, None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(updated.updated_at, post.updated_at);
+        assert_eq!(updated.author_username.as_deref(), Some("author"));
+    }
+
+    #[tokio::test]
+    async fn test_tag_change_bumps_updated_at() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let tags = vec!["rust".to_string()];
+        let post = posts
+            .create("Title", "Content", author, &tags, PostStatus::Published)
+            .await
+            .unwrap();
+
+        let same = posts
+            .update_by_author(
+                post.id,
+                author,
+                "Title",
+                "Content",
+                Some(&tags),
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(same.updated_at, post.updated_at);
+
+        let retagged = vec!["rust".to_string(), "web".to_string()];
+        let changed = posts
+            .update_by_author(
+                post.id,
+                author,
+                "Title",
+                "Content",
+                Some(&retagged),
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(changed.updated_at > post.updated_at);
+        assert_eq!(changed.tags, retagged);
+    }
+
+    #[tokio::test]
+    async fn test_title_change_keeps_content_updated_at() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let post = posts
+            .create("Title", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let changed = posts
+            .update_by_author(
+                post.id,
+                author,
+                "New title",
+                "Content",
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(changed.title_updated_at > post.title_updated_at);
+        assert_eq!(changed.content_updated_at, post.content_updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_are_empty_at_the_ends() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        let first = posts
+            .create("First", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let second = posts
+            .create("Second", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let third = posts
+            .create("Third", "Content", author, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let middle = posts.find_neighbors(&second).await.unwrap();
+        let oldest = posts.find_neighbors(&first).await.unwrap();
+        let newest = posts.find_neighbors(&third).await.unwrap();
+
+        assert_eq!(middle.newer.map(|post| post.id), Some(third.id));
+        assert_eq!(middle.older.map(|post| post.id), Some(first.id));
+        assert!(oldest.older.is_none());
+        assert!(newest.newer.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_tags() {
+        let (users, posts) = repositories();
+        let author = create_user(&users, "author").await;
+        for (title, tags) in [
+            ("Both", vec!["async", "rust"]),
+            ("Rust", vec!["rust"]),
+            ("Web", vec!["web"]),
+        ] {
+            let tags: Vec<String> = tags.into_iter().map(Into::into).collect();
+            posts
+                .create(title, "Content", author, &tags, PostStatus::Published)
+                .await
+                .unwrap();
+        }
+        let wanted = vec!["rust".to_string(), "async".to_string()];
+        let all = TagFilter::new(wanted.clone(), TagMode::All).unwrap();
+        let any = TagFilter::new(wanted, TagMode::Any).unwrap();
+
+        let titles = |posts: Vec<Post>| -> Vec<String> {
+            posts.into_iter().map(|post| post.title).collect()
+        };
+        let (matching_all, all_total) = posts
+            .list(10, 0, PostSort::CreatedAtAsc, Some(&all), false, None)
+            .await
+            .unwrap();
+        let (matching_any, any_total) = posts
+            .list(10, 0, PostSort::CreatedAtAsc, Some(&any), false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(titles(matching_all), ["Both"]);
+        assert_eq!(titles(matching_any), ["Both", "Rust"]);
+        assert_eq!((all_total, any_total), (1, 2));
+        assert_eq!(posts.count(Some(&all), None).await.unwrap(), 1);
+        assert_eq!(posts.count(Some(&any), None).await.unwrap(), 2);
+        assert_eq!(posts.count(None, None).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired_keeps_live_sessions() {
+        let sessions = InMemorySessionRepository::new();
+        let now = Utc::now();
+        // Creating prunes the user's expired sessions, so those go last
+        for (user_id, expires_at) in [
+            (2, now + chrono::Duration::hours(1)),
+            (1, now - chrono::Duration::hours(1)),
+            (2, now - chrono::Duration::minutes(1)),
+        ] {
+            sessions
+                .create(&Session {
+                    jti: Uuid::new_v4(),
+                    user_id,
+                    label: None,
+                    issued_at: now - chrono::Duration::days(1),
+                    expires_at,
+                })
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(sessions.delete_expired().await.unwrap(), 2);
+        assert_eq!(sessions.list_by_user(2).await.unwrap().len(), 1);
+        assert_eq!(sessions.delete_expired().await.unwrap(), 0);
+    }
+}
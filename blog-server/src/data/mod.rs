@@ -1,12 +1,117 @@
 // Data layer - repositories and database interactions
 
+pub mod comment_repository;
+pub mod memory;
 pub mod post_repository;
+pub mod repository;
+pub mod session_repository;
 pub mod user_repository;
 
+pub use comment_repository::PostgresCommentRepository;
+pub use memory::{
+    InMemoryCommentRepository, InMemoryPostRepository,
+    InMemorySessionRepository, InMemoryUserRepository,
+};
 pub use post_repository::PostgresPostRepository;
+pub use repository::{
+    CommentRepository, PostRepository, SessionRepository, UserRepository,
+};
+pub use session_repository::PostgresSessionRepository;
 pub use user_repository::PostgresUserRepository;
 
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
 use crate::domain::DomainError;
+use crate::infrastructure::config::{FromEnv, env_or};
+
+/// Which repository implementation backs the services
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageBackend {
+    Postgres,
+    /// Keeps everything in process memory; no database required
+    Memory,
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "postgres" => Ok(Self::Postgres),
+            "memory" => Ok(Self::Memory),
+            other => Err(format!(
+                "unknown storage backend {other:?}, expected postgres or memory"
+            )),
+        }
+    }
+}
+
+impl FromEnv for StorageBackend {
+    /// Postgres when unset. Anything else unknown stops startup rather
+    /// than silently picking a backend.
+    fn from_env() -> Self {
+        std::env::var("STORAGE_BACKEND").map_or(Self::Postgres, |value| {
+            value
+                .parse()
+                .unwrap_or_else(|e| panic!("Invalid STORAGE_BACKEND: {e}"))
+        })
+    }
+}
+
+/// Times repository queries: slower than the threshold logs a warning,
+/// everything else is logged at debug level.
+#[derive(Clone, Copy)]
+pub struct QueryLog {
+    pub slow_query_threshold: Duration,
+}
+
+impl Default for QueryLog {
+    fn default() -> Self {
+        Self {
+            slow_query_threshold: Duration::from_millis(500),
+        }
+    }
+}
+
+impl FromEnv for QueryLog {
+    fn from_env() -> Self {
+        Self {
+            slow_query_threshold: Duration::from_millis(env_or(
+                "SLOW_QUERY_MS",
+                500,
+            )),
+        }
+    }
+}
+
+impl QueryLog {
+    pub async fn time<F: Future>(
+        self,
+        operation: &'static str,
+        query: F,
+    ) -> F::Output {
+        let started = Instant::now();
+        let result = query.await;
+        let elapsed = started.elapsed();
+
+        if elapsed >= self.slow_query_threshold {
+            tracing::warn!(
+                operation,
+                elapsed_ms = elapsed.as_millis(),
+                "Slow query"
+            );
+        } else {
+            tracing::debug!(
+                operation,
+                elapsed_ms = elapsed.as_millis(),
+                "Query finished"
+            );
+        }
+
+        result
+    }
+}
 
 impl From<sqlx::Error> for DomainError {
     fn from(err: sqlx::Error) -> Self {
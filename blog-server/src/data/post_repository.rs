@@ -1,131 +1,940 @@
-use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, SubsecRound, Utc};
+use sqlx::{PgConnection, Postgres, QueryBuilder};
 
-use crate::domain::{DomainError, Post};
+use super::{PostRepository, QueryLog};
+use crate::domain::{
+    AuthorSummary, BulkTagResult, CursorKey, DomainError, IdempotencyKey, Post,
+    PostCursor, PostNeighbors, PostSort, PostStatus, TagAction, TagFilter,
+    TagMode,
+};
+use crate::infrastructure::DbPools;
 
 pub struct PostgresPostRepository {
-    pool: PgPool,
+    pools: DbPools,
+    query_log: QueryLog,
 }
 
 impl PostgresPostRepository {
-    pub const fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub const fn new(pools: DbPools, query_log: QueryLog) -> Self {
+        Self { pools, query_log }
     }
 
-    pub async fn create(
+    async fn insert_post(
         &self,
+        conn: &mut PgConnection,
         title: &str,
         content: &str,
         author_id: i64,
+        tags: &[String],
+        status: PostStatus,
     ) -> Result<Post, DomainError> {
-        let row = sqlx::query_as::<_, PostRow>(
+        // Joined in the same statement, so the post comes back with its
+        // author's username like reads do
+        let query = sqlx::query_as::<_, PostWithAuthorRow>(
             r"
-            INSERT INTO posts (title, content, author_id)
-            VALUES ($1, $2, $3)
-            RETURNING id, title, content, author_id, created_at, updated_at
+            WITH inserted AS (
+                INSERT INTO posts (title, content, author_id, status)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, title, content, author_id, created_at,
+                    updated_at, title_updated_at, content_updated_at, status
+            )
+            SELECT i.id, i.title, i.content, i.author_id,
+                u.username AS author_username, i.created_at, i.updated_at,
+                i.title_updated_at, i.content_updated_at, i.status
+            FROM inserted i
+            LEFT JOIN users u ON u.id = i.author_id
             ",
         )
         .bind(title)
         .bind(content)
         .bind(author_id)
-        .fetch_one(&self.pool)
-        .await?;
+        .bind(status.as_str())
+        .fetch_one(&mut *conn);
+        let row = self.query_log.time("posts.create", query).await?;
+
+        if !tags.is_empty() {
+            self.attach_tags(conn, &[row.id], tags).await?;
+        }
+
+        Ok(Post::from(row).with_tags(tags.to_vec()))
+    }
+
+    /// The live post recorded for an idempotency key used at or after
+    /// `since`
+    async fn find_keyed(
+        &self,
+        conn: &mut PgConnection,
+        author_id: i64,
+        key: IdempotencyKey<'_>,
+    ) -> Result<Option<Post>, DomainError> {
+        let sql = format!(
+            r"
+            SELECT {POST_COLUMNS}, k.request_hash,
+                p.deleted_at IS NOT NULL AS deleted
+            FROM idempotency_keys k
+            JOIN posts p ON p.id = k.post_id
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE k.user_id = $1 AND k.key = $2 AND k.created_at >= $3
+            ",
+        );
+        let query = sqlx::query_as::<_, KeyedPostRow>(&sql)
+            .bind(author_id)
+            .bind(key.key)
+            .bind(key.since)
+            .fetch_optional(conn);
+        let row = self
+            .query_log
+            .time("posts.find_by_idempotency_key", query)
+            .await?;
+
+        match row {
+            Some(row)
+                if row.deleted
+                    || row
+                        .request_hash
+                        .as_deref()
+                        .is_some_and(|hash| hash != key.fingerprint) =>
+            {
+                Err(DomainError::IdempotencyKeyReused)
+            }
+            row => Ok(row.map(|row| row.post.into())),
+        }
+    }
+
+    /// Links the posts to `tags`, creating tags that do not exist yet.
+    /// Returns the number of links added.
+    async fn attach_tags(
+        &self,
+        conn: &mut PgConnection,
+        post_ids: &[i64],
+        tags: &[String],
+    ) -> Result<u64, DomainError> {
+        let query = sqlx::query(
+            r"
+            INSERT INTO tags (name)
+            SELECT UNNEST($1::text[])
+            ON CONFLICT (name) DO NOTHING
+            ",
+        )
+        .bind(tags)
+        .execute(&mut *conn);
+        self.query_log.time("tags.create", query).await?;
+
+        let query = sqlx::query(
+            r"
+            INSERT INTO post_tags (post_id, tag_id)
+            SELECT p.id, t.id
+            FROM UNNEST($1::bigint[]) AS p(id)
+            CROSS JOIN tags t
+            WHERE t.name = ANY($2)
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(post_ids)
+        .bind(tags)
+        .execute(&mut *conn);
+        let result = self.query_log.time("post_tags.create", query).await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Unlinks the posts from `tag`. Returns the number of links removed.
+    async fn detach_tag(
+        &self,
+        conn: &mut PgConnection,
+        post_ids: &[i64],
+        tag: &str,
+    ) -> Result<u64, DomainError> {
+        let query = sqlx::query(
+            r"
+            DELETE FROM post_tags
+            WHERE post_id = ANY($1)
+                AND tag_id IN (SELECT id FROM tags WHERE name = $2)
+            ",
+        )
+        .bind(post_ids)
+        .bind(tag)
+        .execute(&mut *conn);
+        let result = self.query_log.time("post_tags.delete", query).await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Select list of a post read from `posts p LEFT JOIN users u`: the post,
+/// its author's username and its tags. Extra columns go after it.
+const POST_COLUMNS: &str = r"
+                p.id, p.title, p.content, p.author_id,
+                u.username AS author_username, p.created_at, p.updated_at,
+                p.title_updated_at, p.content_updated_at, p.status,
+                ARRAY(
+                    SELECT t.name::text
+                    FROM post_tags pt
+                    JOIN tags t ON t.id = pt.tag_id
+                    WHERE pt.post_id = p.id
+                    ORDER BY t.name
+                ) AS tags";
+
+/// `RETURNING` list of an `UPDATE posts`: the post and its tags, without
+/// the author's username
+const UPDATED_POST_COLUMNS: &str = r"
+                id, title, content, author_id, created_at, updated_at,
+                title_updated_at, content_updated_at, status,
+                ARRAY(
+                    SELECT t.name::text
+                    FROM post_tags pt
+                    JOIN tags t ON t.id = pt.tag_id
+                    WHERE pt.post_id = posts.id
+                    ORDER BY t.name
+                ) AS tags";
+
+/// ORDER BY clause of `sort`. Only these fixed clauses are interpolated,
+/// never client input; each order is served by an `(column, id)` index.
+const fn order_by(sort: PostSort) -> &'static str {
+    match sort {
+        PostSort::CreatedAtDesc => "p.created_at DESC, p.id DESC",
+        PostSort::CreatedAtAsc => "p.created_at ASC, p.id ASC",
+        PostSort::UpdatedAtDesc => "p.updated_at DESC, p.id DESC",
+        PostSort::UpdatedAtAsc => "p.updated_at ASC, p.id ASC",
+        PostSort::TitleAsc => "p.title ASC, p.id ASC",
+        PostSort::TitleDesc => "p.title DESC, p.id DESC",
+    }
+}
+
+/// Condition selecting the posts after `cursor` in `sort` order, as a
+/// row-value comparison that walks the sort column's index
+fn push_after_cursor(
+    query: &mut QueryBuilder<'_, Postgres>,
+    cursor: &PostCursor,
+    sort: PostSort,
+) {
+    let column = match cursor.key {
+        CursorKey::CreatedAt(_) => "p.created_at",
+        CursorKey::UpdatedAt(_) => "p.updated_at",
+        CursorKey::Title(_) => "p.title",
+    };
+    let after = if sort.is_descending() { "<" } else { ">" };
+    query.push(format!(" ({column}, p.id) {after} ("));
+    match &cursor.key {
+        CursorKey::CreatedAt(at) | CursorKey::UpdatedAt(at) => {
+            query.push_bind(*at);
+        }
+        CursorKey::Title(title) => {
+            query.push_bind(title.clone());
+        }
+    }
+    query.push(", ");
+    query.push_bind(cursor.id);
+    query.push(")");
+}
+
+/// Narrows `posts p` to posts that are not deleted, published or drafts
+/// of `viewer`, and match the filter, if any
+fn push_listing_filter(
+    query: &mut QueryBuilder<'_, Postgres>,
+    filter: Option<&TagFilter>,
+    viewer: Option<i64>,
+) {
+    query.push(" WHERE p.deleted_at IS NULL AND (p.status = 'published'");
+    if let Some(viewer) = viewer {
+        query.push(" OR p.author_id = ");
+        query.push_bind(viewer);
+    }
+    query.push(")");
+    if let Some(filter) = filter {
+        query.push(" AND");
+        push_tag_condition(query, filter);
+    }
+}
+
+/// Condition on `p.id` matching the filter. AND groups each post's
+/// matching tags and requires all of them; OR needs a single match.
+fn push_tag_condition(
+    query: &mut QueryBuilder<'_, Postgres>,
+    filter: &TagFilter,
+) {
+    query.push(
+        r"
+        p.id IN (
+            SELECT pt.post_id
+            FROM post_tags pt
+            JOIN tags t ON t.id = pt.tag_id
+            WHERE t.name = ANY(",
+    );
+    query.push_bind(filter.tags.clone());
+    query.push(")");
+    if filter.mode == TagMode::All {
+        // Tags are deduplicated, so a full match hits each one exactly once
+        query.push(" GROUP BY pt.post_id HAVING COUNT(*) = ");
+        query.push_bind(i64::try_from(filter.tags.len()).unwrap_or(i64::MAX));
+    }
+    query.push(")");
+}
+
+#[async_trait]
+impl PostRepository for PostgresPostRepository {
+    async fn create(
+        &self,
+        title: &str,
+        content: &str,
+        author_id: i64,
+        tags: &[String],
+        status: PostStatus,
+    ) -> Result<Post, DomainError> {
+        let mut tx = self.pools.primary.begin().await?;
+        let post = self
+            .insert_post(&mut tx, title, content, author_id, tags, status)
+            .await?;
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    async fn find_by_idempotency_key(
+        &self,
+        author_id: i64,
+        key: IdempotencyKey<'_>,
+    ) -> Result<Option<Post>, DomainError> {
+        // The primary, as a retry usually follows the first call closely
+        let mut conn = self.pools.primary.acquire().await?;
+        self.find_keyed(&mut conn, author_id, key).await
+    }
+
+    async fn create_with_idempotency_key(
+        &self,
+        title: &str,
+        content: &str,
+        author_id: i64,
+        tags: &[String],
+        status: PostStatus,
+        key: IdempotencyKey<'_>,
+    ) -> Result<(Post, bool), DomainError> {
+        let mut tx = self.pools.primary.begin().await?;
+
+        let query = sqlx::query(
+            r"
+            DELETE FROM idempotency_keys
+            WHERE user_id = $1 AND created_at < $2
+            ",
+        )
+        .bind(author_id)
+        .bind(key.since)
+        .execute(&mut *tx);
+        self.query_log
+            .time("idempotency_keys.expire", query)
+            .await?;
+
+        // A concurrent transaction holding the same key blocks this insert
+        // until it commits (conflict) or rolls back (insert goes through)
+        let query = sqlx::query(
+            r"
+            INSERT INTO idempotency_keys (user_id, key, request_hash)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, key) DO NOTHING
+            ",
+        )
+        .bind(author_id)
+        .bind(key.key)
+        .bind(key.fingerprint)
+        .execute(&mut *tx);
+        let claimed =
+            self.query_log.time("idempotency_keys.claim", query).await?;
+
+        if claimed.rows_affected() == 0 {
+            let post = self.find_keyed(&mut tx, author_id, key).await?;
+            tx.commit().await?;
+            return post
+                .map(|post| (post, false))
+                .ok_or(DomainError::PostNotFound);
+        }
+
+        let post = self
+            .insert_post(&mut tx, title, content, author_id, tags, status)
+            .await?;
+        let query = sqlx::query(
+            r"
+            UPDATE idempotency_keys SET post_id = $3
+            WHERE user_id = $1 AND key = $2
+            ",
+        )
+        .bind(author_id)
+        .bind(key.key)
+        .bind(post.id)
+        .execute(&mut *tx);
+        self.query_log.time("idempotency_keys.link", query).await?;
+        tx.commit().await?;
 
-        Ok(row.into())
+        Ok((post, true))
     }
 
-    pub async fn find_by_id(
+    async fn find_by_id(
         &self,
         id: i64,
+        include_author: bool,
     ) -> Result<Option<Post>, DomainError> {
-        let row = sqlx::query_as::<_, PostWithAuthorRow>(
+        let author_columns = author_columns(include_author);
+        let sql = format!(
             r"
-            SELECT p.id, p.title, p.content, p.author_id, u.username as author_username, p.created_at, p.updated_at
+            SELECT {POST_COLUMNS}{author_columns}
             FROM posts p
-            JOIN users u ON p.author_id = u.id
-            WHERE p.id = $1
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE p.id = $1 AND p.deleted_at IS NULL
             ",
-        )
-        .bind(id)
-        .fetch_optional(&self.pool)
-        .await?;
+        );
+        let query = sqlx::query_as::<_, PostWithAuthorRow>(&sql)
+            .bind(id)
+            .fetch_optional(&self.pools.replica);
+        let row = self.query_log.time("posts.find_by_id", query).await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    // `ORDER BY RANDOM()` scans and sorts the whole table, which is fine
+    // for a blog-sized dataset; `TABLESAMPLE` would be cheaper on huge
+    // tables but is page-based (biased) and can return no rows at all.
+    async fn find_random(&self) -> Result<Option<Post>, DomainError> {
+        let sql = format!(
+            r"
+            SELECT {POST_COLUMNS}
+            FROM posts p
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE p.deleted_at IS NULL AND p.status = 'published'
+            ORDER BY RANDOM()
+            LIMIT 1
+            ",
+        );
+        let query = sqlx::query_as::<_, PostWithAuthorRow>(&sql)
+            .fetch_optional(&self.pools.replica);
+        let row = self.query_log.time("posts.find_random", query).await?;
 
         Ok(row.map(Into::into))
     }
 
-    /// Updates post only if it belongs to the author.
-    /// Returns None if post not found or doesn't belong to author.
-    pub async fn update_by_author(
+    // Row-value comparisons on (created_at, id) walk
+    // idx_posts_created_at_id one step in either direction
+    async fn find_neighbors(
+        &self,
+        post: &Post,
+    ) -> Result<PostNeighbors, DomainError> {
+        let sql = format!(
+            r"
+            SELECT {POST_COLUMNS}
+            FROM posts p
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE (p.created_at, p.id) > ($1, $2) AND p.deleted_at IS NULL
+                AND p.status = 'published'
+            ORDER BY p.created_at ASC, p.id ASC
+            LIMIT 1
+            ",
+        );
+        let query = sqlx::query_as::<_, PostWithAuthorRow>(&sql)
+            .bind(post.created_at)
+            .bind(post.id)
+            .fetch_optional(&self.pools.replica);
+        let newer = self.query_log.time("posts.find_newer", query).await?;
+
+        let sql = format!(
+            r"
+            SELECT {POST_COLUMNS}
+            FROM posts p
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE (p.created_at, p.id) < ($1, $2) AND p.deleted_at IS NULL
+                AND p.status = 'published'
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT 1
+            ",
+        );
+        let query = sqlx::query_as::<_, PostWithAuthorRow>(&sql)
+            .bind(post.created_at)
+            .bind(post.id)
+            .fetch_optional(&self.pools.replica);
+        let older = self.query_log.time("posts.find_older", query).await?;
+
+        Ok(PostNeighbors {
+            newer: newer.map(Into::into),
+            older: older.map(Into::into),
+        })
+    }
+
+    async fn update_by_author(
         &self,
         id: i64,
         author_id: i64,
         title: &str,
         content: &str,
+        tags: Option<&[String]>,
+        expected_updated_at: Option<DateTime<Utc>>,
     ) -> Result<Option<Post>, DomainError> {
-        let row = sqlx::query_as::<_, PostRow>(
+        let mut tx = self.pools.primary.begin().await?;
+
+        // A new tag set counts as a change too; the links are replaced
+        // below in the same transaction
+        let sql = format!(
             r"
             UPDATE posts
-            SET title = $3, content = $4, updated_at = NOW()
-            WHERE id = $1 AND author_id = $2
-            RETURNING id, title, content, author_id, created_at, updated_at
+            SET title = $3,
+                content = $4,
+                title_updated_at = CASE
+                    WHEN title IS DISTINCT FROM $3 THEN NOW()
+                    ELSE title_updated_at
+                END,
+                content_updated_at = CASE
+                    WHEN content IS DISTINCT FROM $4 THEN NOW()
+                    ELSE content_updated_at
+                END,
+                updated_at = CASE
+                    WHEN title IS DISTINCT FROM $3
+                      OR content IS DISTINCT FROM $4
+                      OR ($6::text[] IS NOT NULL AND NOT (
+                          stored.tags @> $6 AND $6 @> stored.tags
+                      ))
+                    THEN NOW()
+                    ELSE updated_at
+                END
+            FROM (
+                SELECT ARRAY(
+                    SELECT t.name::text
+                    FROM post_tags pt
+                    JOIN tags t ON t.id = pt.tag_id
+                    WHERE pt.post_id = $1
+                ) AS tags
+            ) AS stored
+            WHERE id = $1 AND author_id = $2 AND deleted_at IS NULL
+              AND ($5::timestamptz IS NULL OR updated_at = $5)
+            RETURNING {UPDATED_POST_COLUMNS}
+            ",
+        );
+        let query = sqlx::query_as::<_, PostRow>(&sql)
+            .bind(id)
+            .bind(author_id)
+            .bind(title)
+            .bind(content)
+            .bind(expected_updated_at.map(|at| at.trunc_subsecs(6)))
+            .bind(tags)
+            .fetch_optional(&mut *tx);
+        let Some(row) =
+            self.query_log.time("posts.update_by_author", query).await?
+        else {
+            return Ok(None);
+        };
+
+        let mut post = Post::from(row);
+        if let Some(tags) = tags {
+            let query = sqlx::query("DELETE FROM post_tags WHERE post_id = $1")
+                .bind(id)
+                .execute(&mut *tx);
+            self.query_log.time("post_tags.delete", query).await?;
+            if !tags.is_empty() {
+                self.attach_tags(&mut tx, &[id], tags).await?;
+            }
+            post = post.with_tags(tags.to_vec());
+        }
+        tx.commit().await?;
+
+        Ok(Some(post))
+    }
+
+    async fn update_tag_by_author(
+        &self,
+        author_id: i64,
+        post_ids: &[i64],
+        tag: &str,
+        action: TagAction,
+    ) -> Result<BulkTagResult, DomainError> {
+        let mut tx = self.pools.primary.begin().await?;
+
+        // Locks the owned posts so a concurrent delete cannot interleave
+        let query = sqlx::query_scalar(
+            r"
+            SELECT id FROM posts
+            WHERE author_id = $1 AND id = ANY($2) AND deleted_at IS NULL
+            FOR UPDATE
             ",
         )
-        .bind(id)
         .bind(author_id)
-        .bind(title)
-        .bind(content)
-        .fetch_optional(&self.pool)
-        .await?;
+        .bind(post_ids)
+        .fetch_all(&mut *tx);
+        let owned: Vec<i64> =
+            self.query_log.time("posts.lock_by_author", query).await?;
 
-        Ok(row.map(Into::into))
+        let updated = match action {
+            _ if owned.is_empty() => 0,
+            TagAction::Add => {
+                self.attach_tags(&mut tx, &owned, &[tag.to_string()])
+                    .await?
+            }
+            TagAction::Remove => self.detach_tag(&mut tx, &owned, tag).await?,
+        };
+        tx.commit().await?;
+
+        Ok(BulkTagResult::new(post_ids.len(), owned.len(), updated))
     }
 
-    /// Deletes post only if it belongs to the author.
-    /// Returns true if deleted, false if not found or doesn't belong to author.
-    pub async fn delete_by_author(
+    async fn delete_by_author(
         &self,
         id: i64,
         author_id: i64,
     ) -> Result<bool, DomainError> {
+        let query = sqlx::query(
+            r"
+            UPDATE posts SET deleted_at = NOW()
+            WHERE id = $1 AND author_id = $2 AND deleted_at IS NULL
+            ",
+        )
+        .bind(id)
+        .bind(author_id)
+        .execute(&self.pools.primary);
         let result =
-            sqlx::query("DELETE FROM posts WHERE id = $1 AND author_id = $2")
-                .bind(id)
-                .bind(author_id)
-                .execute(&self.pool)
-                .await?;
+            self.query_log.time("posts.delete_by_author", query).await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn list(
+    async fn restore_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+    ) -> Result<Option<Post>, DomainError> {
+        let sql = format!(
+            r"
+            UPDATE posts SET deleted_at = NULL
+            WHERE id = $1 AND author_id = $2 AND deleted_at IS NOT NULL
+            RETURNING {UPDATED_POST_COLUMNS}
+            ",
+        );
+        let query = sqlx::query_as::<_, PostRow>(&sql)
+            .bind(id)
+            .bind(author_id)
+            .fetch_optional(&self.pools.primary);
+        let row = self
+            .query_log
+            .time("posts.restore_by_author", query)
+            .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn find_deleted_author(
+        &self,
+        id: i64,
+    ) -> Result<Option<i64>, DomainError> {
+        let query = sqlx::query_scalar::<_, i64>(
+            r"
+            SELECT author_id FROM posts
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&self.pools.primary);
+
+        Ok(self
+            .query_log
+            .time("posts.find_deleted_author", query)
+            .await?)
+    }
+
+    async fn set_status_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+        status: PostStatus,
+    ) -> Result<Option<Post>, DomainError> {
+        let sql = format!(
+            r"
+            UPDATE posts SET status = $3
+            WHERE id = $1 AND author_id = $2 AND deleted_at IS NULL
+            RETURNING {UPDATED_POST_COLUMNS}
+            ",
+        );
+        let query = sqlx::query_as::<_, PostRow>(&sql)
+            .bind(id)
+            .bind(author_id)
+            .bind(status.as_str())
+            .fetch_optional(&self.pools.primary);
+        let row = self
+            .query_log
+            .time("posts.set_status_by_author", query)
+            .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn list(
+        &self,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        let order_by = order_by(sort);
+        let author_columns = author_columns(include_author);
+        // The window runs before LIMIT, so every row carries the total
+        let mut query = QueryBuilder::new(format!(
+            r"
+            SELECT {POST_COLUMNS}{author_columns}, COUNT(*) OVER () AS total
+            FROM posts p
+            LEFT JOIN users u ON p.author_id = u.id
+            ",
+        ));
+        push_listing_filter(&mut query, filter, viewer);
+        query.push(format!(" ORDER BY {order_by} LIMIT "));
+        query.push_bind(limit);
+        query.push(" OFFSET ");
+        query.push_bind(offset);
+
+        let query = query
+            .build_query_as::<CountedPostRow>()
+            .fetch_all(&self.pools.replica);
+        let rows = self.query_log.time("posts.list", query).await?;
+
+        // A page past the end has no row to carry the total
+        let total = match rows.first() {
+            Some(row) => row.total,
+            None if offset > 0 => self.count(filter, viewer).await?,
+            None => 0,
+        };
+        let posts = rows.into_iter().map(|row| row.post.into()).collect();
+        Ok((posts, total))
+    }
+
+    async fn search(
         &self,
+        query: &str,
         limit: i64,
         offset: i64,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        // The term is only ever bound; plainto_tsquery ignores operators
+        let sql = format!(
+            r"
+            SELECT {POST_COLUMNS}, COUNT(*) OVER () AS total
+            FROM posts p
+            CROSS JOIN plainto_tsquery('english', $1) AS query
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE p.search_vector @@ query AND p.deleted_at IS NULL
+                AND p.status = 'published'
+            ORDER BY ts_rank(p.search_vector, query) DESC, p.created_at DESC, p.id DESC
+            LIMIT $2 OFFSET $3
+            ",
+        );
+        let search = sqlx::query_as::<_, CountedPostRow>(&sql)
+            .bind(query)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pools.replica);
+        let rows = self.query_log.time("posts.search", search).await?;
+
+        let total = match rows.first() {
+            Some(row) => row.total,
+            None if offset > 0 => {
+                let count = sqlx::query_scalar(
+                    r"
+                    SELECT COUNT(*) FROM posts
+                    WHERE search_vector @@ plainto_tsquery('english', $1)
+                        AND deleted_at IS NULL AND status = 'published'
+                    ",
+                )
+                .bind(query)
+                .fetch_one(&self.pools.replica);
+                self.query_log.time("posts.search_count", count).await?
+            }
+            None => 0,
+        };
+        let posts = rows.into_iter().map(|row| row.post.into()).collect();
+        Ok((posts, total))
+    }
+
+    async fn list_after(
+        &self,
+        cursor: &PostCursor,
+        limit: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
     ) -> Result<Vec<Post>, DomainError> {
-        let rows = sqlx::query_as::<_, PostWithAuthorRow>(
+        let author_columns = author_columns(include_author);
+        let mut query = QueryBuilder::new(format!(
             r"
-            SELECT p.id, p.title, p.content, p.author_id, u.username as author_username, p.created_at, p.updated_at
+            SELECT {POST_COLUMNS}{author_columns}
             FROM posts p
-            JOIN users u ON p.author_id = u.id
-            ORDER BY p.created_at DESC
-            LIMIT $1 OFFSET $2
+            LEFT JOIN users u ON p.author_id = u.id
             ",
-        )
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&self.pool)
-        .await?;
+        ));
+        push_listing_filter(&mut query, filter, viewer);
+        query.push(" AND");
+        push_after_cursor(&mut query, cursor, sort);
+        query.push(format!(" ORDER BY {} LIMIT ", order_by(sort)));
+        query.push_bind(limit);
+
+        let query = query
+            .build_query_as::<PostWithAuthorRow>()
+            .fetch_all(&self.pools.replica);
+        let rows = self.query_log.time("posts.list_after", query).await?;
 
         Ok(rows.into_iter().map(Into::into).collect())
     }
 
-    pub async fn count(&self) -> Result<i64, DomainError> {
-        let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts")
-            .fetch_one(&self.pool)
+    async fn list_by_author(
+        &self,
+        author_id: i64,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+        include_drafts: bool,
+    ) -> Result<(Vec<Post>, i64), DomainError> {
+        let order_by = order_by(sort);
+        let sql = format!(
+            r"
+            SELECT {POST_COLUMNS}, COUNT(*) OVER () AS total
+            FROM posts p
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE p.author_id = $1 AND p.deleted_at IS NULL
+                AND ($4 OR p.status = 'published')
+            ORDER BY {order_by}
+            LIMIT $2 OFFSET $3
+            "
+        );
+        let query = sqlx::query_as::<_, CountedPostRow>(&sql)
+            .bind(author_id)
+            .bind(limit)
+            .bind(offset)
+            .bind(include_drafts)
+            .fetch_all(&self.pools.replica);
+        let rows = self.query_log.time("posts.list_by_author", query).await?;
+
+        // A page past the end has no row to carry the total
+        let total = match rows.first() {
+            Some(row) => row.total,
+            None if offset > 0 => {
+                let count = sqlx::query_scalar(
+                    r"
+                    SELECT COUNT(*) FROM posts
+                    WHERE author_id = $1 AND deleted_at IS NULL
+                        AND ($2 OR status = 'published')
+                    ",
+                )
+                .bind(author_id)
+                .bind(include_drafts)
+                .fetch_one(&self.pools.replica);
+                self.query_log.time("posts.count_by_author", count).await?
+            }
+            None => 0,
+        };
+        let posts = rows.into_iter().map(|row| row.post.into()).collect();
+        Ok((posts, total))
+    }
+
+    async fn list_by_author_after(
+        &self,
+        author_id: i64,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Post>, DomainError> {
+        let sql = format!(
+            r"
+            SELECT {POST_COLUMNS}
+            FROM posts p
+            LEFT JOIN users u ON p.author_id = u.id
+            WHERE p.author_id = $1 AND p.id > $2 AND p.deleted_at IS NULL
+            ORDER BY p.id
+            LIMIT $3
+            ",
+        );
+        let query = sqlx::query_as::<_, PostWithAuthorRow>(&sql)
+            .bind(author_id)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(&self.pools.replica);
+        let rows = self
+            .query_log
+            .time("posts.list_by_author_after", query)
             .await?;
 
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn count(
+        &self,
+        filter: Option<&TagFilter>,
+        viewer: Option<i64>,
+    ) -> Result<i64, DomainError> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM posts p");
+        push_listing_filter(&mut query, filter, viewer);
+
+        let query = query
+            .build_query_as::<(i64,)>()
+            .fetch_one(&self.pools.replica);
+        let row = self.query_log.time("posts.count", query).await?;
+
         Ok(row.0)
     }
+
+    async fn count_by_author(
+        &self,
+        author_id: i64,
+    ) -> Result<i64, DomainError> {
+        // Feeds the quota check before a write, so it must not lag behind
+        let query = sqlx::query_as(
+            r"
+            SELECT COUNT(*) FROM posts
+            WHERE author_id = $1 AND deleted_at IS NULL
+            ",
+        )
+        .bind(author_id)
+        .fetch_one(&self.pools.primary);
+        let row: (i64,) =
+            self.query_log.time("posts.count_by_author", query).await?;
+
+        Ok(row.0)
+    }
+
+    async fn count_by_day(
+        &self,
+        author_id: i64,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(NaiveDate, i64)>, DomainError> {
+        // Days are cut in UTC regardless of the session time zone
+        let query = sqlx::query_as(
+            r"
+            SELECT date_trunc('day', created_at AT TIME ZONE 'UTC')::date AS day,
+                COUNT(*) AS count
+            FROM posts
+            WHERE author_id = $1 AND created_at >= $2 AND deleted_at IS NULL
+                AND status = 'published'
+            GROUP BY day
+            ORDER BY day
+            ",
+        )
+        .bind(author_id)
+        .bind(since)
+        .fetch_all(&self.pools.replica);
+
+        let rows = self.query_log.time("posts.count_by_day", query).await?;
+
+        Ok(rows)
+    }
+
+    async fn tag_counts(&self) -> Result<Vec<(String, i64)>, DomainError> {
+        let query = sqlx::query_as(
+            r"
+            SELECT t.name::text AS name, COUNT(*) AS count
+            FROM tags t
+            JOIN post_tags pt ON pt.tag_id = t.id
+            JOIN posts p ON p.id = pt.post_id AND p.deleted_at IS NULL
+                AND p.status = 'published'
+            GROUP BY t.name
+            ORDER BY t.name
+            ",
+        )
+        .fetch_all(&self.pools.replica);
+
+        let rows = self.query_log.time("tags.count_posts", query).await?;
+
+        Ok(rows)
+    }
 }
 
 #[derive(sqlx::FromRow)]
@@ -136,6 +945,12 @@ struct PostRow {
     author_id: i64,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
+    title_updated_at: DateTime<Utc>,
+    content_updated_at: DateTime<Utc>,
+    status: String,
+    /// Not returned by `INSERT`, where the tags are known up front
+    #[sqlx(default)]
+    tags: Vec<String>,
 }
 
 impl From<PostRow> for Post {
@@ -148,6 +963,10 @@ impl From<PostRow> for Post {
             row.created_at,
             row.updated_at,
         )
+        .with_field_timestamps(row.title_updated_at, row.content_updated_at)
+        // The column is constrained to known statuses
+        .with_status(row.status.parse().unwrap_or_default())
+        .with_tags(row.tags)
     }
 }
 
@@ -157,14 +976,54 @@ struct PostWithAuthorRow {
     title: String,
     content: String,
     author_id: i64,
-    author_username: String,
+    /// Null for posts whose author row is gone
+    author_username: Option<String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
+    title_updated_at: DateTime<Utc>,
+    content_updated_at: DateTime<Utc>,
+    status: String,
+    /// Not returned by `posts.create`, where the tags are known up front
+    #[sqlx(default)]
+    tags: Vec<String>,
+    /// Only selected with [`author_columns`]
+    #[sqlx(default)]
+    author_email: Option<String>,
+    #[sqlx(default)]
+    author_created_at: Option<DateTime<Utc>>,
+}
+
+/// Extra `users` columns for an [`AuthorSummary`], spliced into the
+/// select list after [`POST_COLUMNS`]
+const fn author_columns(include_author: bool) -> &'static str {
+    if include_author {
+        ", u.email AS author_email, u.created_at AS author_created_at"
+    } else {
+        ""
+    }
+}
+
+/// A `posts.list` or `posts.search` row with the windowed count of all matching posts
+#[derive(sqlx::FromRow)]
+struct CountedPostRow {
+    #[sqlx(flatten)]
+    post: PostWithAuthorRow,
+    total: i64,
+}
+
+/// A post found by its idempotency key, deleted ones included
+#[derive(sqlx::FromRow)]
+struct KeyedPostRow {
+    #[sqlx(flatten)]
+    post: PostWithAuthorRow,
+    /// Null for keys stored before requests were fingerprinted
+    request_hash: Option<String>,
+    deleted: bool,
 }
 
 impl From<PostWithAuthorRow> for Post {
     fn from(row: PostWithAuthorRow) -> Self {
-        Self::new(
+        let post = Self::new(
             row.id,
             row.title,
             row.content,
@@ -172,6 +1031,798 @@ impl From<PostWithAuthorRow> for Post {
             row.created_at,
             row.updated_at,
         )
-        .with_author_username(row.author_username)
+        .with_field_timestamps(row.title_updated_at, row.content_updated_at)
+        // The column is constrained to known statuses
+        .with_status(row.status.parse().unwrap_or_default())
+        .with_tags(row.tags);
+
+        // Null email and registration date mean the columns were not
+        // selected or the author row is gone
+        let post = match (
+            &row.author_username,
+            row.author_email,
+            row.author_created_at,
+        ) {
+            (Some(username), Some(email), Some(created_at)) => post
+                .with_author(AuthorSummary {
+                    username: username.clone(),
+                    email: Some(email),
+                    created_at,
+                }),
+            _ => post,
+        };
+
+        match row.author_username {
+            Some(username) => post.with_author_username(username),
+            None => post,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::fixtures::insert_user;
+    use sqlx::PgPool;
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+    /// Operations `QueryLog` reports, in the order the queries ran
+    #[derive(Clone, Default)]
+    struct QueryOperations(Arc<Mutex<Vec<String>>>);
+
+    impl QueryOperations {
+        /// Records on this thread until the guard is dropped
+        fn record(&self) -> tracing::subscriber::DefaultGuard {
+            tracing::subscriber::set_default(
+                tracing_subscriber::registry().with(self.clone()),
+            )
+        }
+
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut self.0.lock().unwrap())
+        }
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for QueryOperations {
+        fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+            struct Operation(Option<String>);
+            impl Visit for Operation {
+                fn record_str(&mut self, field: &Field, value: &str) {
+                    if field.name() == "operation" {
+                        self.0 = Some(value.to_string());
+                    }
+                }
+                fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {
+                }
+            }
+
+            let mut operation = Operation(None);
+            event.record(&mut operation);
+            if let Some(operation) = operation.0 {
+                self.0.lock().unwrap().push(operation);
+            }
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_noop_update_keeps_updated_at(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let post = repo
+            .create("Title", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let updated = repo
+            .update_by_author(
+                post.id, author_id, "Title", "Content", None, None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.updated_at, post.updated_at);
+
+        let changed = repo
+            .update_by_author(
+                post.id,
+                author_id,
+                "New title",
+                "Content",
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(changed.updated_at > post.updated_at);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_tag_change_bumps_updated_at(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let tags = vec!["rust".to_string()];
+        let post = repo
+            .create("Title", "Content", author_id, &tags, PostStatus::Published)
+            .await
+            .unwrap();
+
+        let same = repo
+            .update_by_author(
+                post.id,
+                author_id,
+                "Title",
+                "Content",
+                Some(&tags),
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(same.updated_at, post.updated_at);
+
+        let retagged = vec!["rust".to_string(), "web".to_string()];
+        let changed = repo
+            .update_by_author(
+                post.id,
+                author_id,
+                "Title",
+                "Content",
+                Some(&retagged),
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(changed.updated_at > post.updated_at);
+        assert_eq!(changed.tags, retagged);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_update_of_a_stale_version_is_skipped(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let post = repo
+            .create("Title", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let update = |title: &'static str| {
+            repo.update_by_author(
+                post.id,
+                author_id,
+                title,
+                "Content",
+                None,
+                Some(post.updated_at),
+            )
+        };
+
+        let first = update("First").await.unwrap();
+        let second = update("Second").await.unwrap();
+
+        assert_eq!(first.unwrap().title, "First");
+        assert!(second.is_none());
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_created_post_carries_author_username(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+
+        let post = repo
+            .create(
+                "Title",
+                "Content",
+                author_id,
+                &["rust".to_string()],
+                PostStatus::Published,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(post.author_username.as_deref(), Some("author"));
+        assert_eq!(post.tags, ["rust"]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_author_summary_only_on_request(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let post = repo
+            .create("Title", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let plain = repo.find_by_id(post.id, false).await.unwrap().unwrap();
+        let (listed, _) = repo
+            .list(10, 0, PostSort::default(), None, false, None)
+            .await
+            .unwrap();
+        assert!(plain.author.is_none());
+        assert!(listed[0].author.is_none());
+
+        let (listed, _) = repo
+            .list(10, 0, PostSort::default(), None, true, None)
+            .await
+            .unwrap();
+        let author = listed[0].author.clone().unwrap();
+        assert_eq!(author.username, "author");
+        assert_eq!(author.email.as_deref(), Some("author@example.com"));
+        assert!(
+            repo.find_by_id(post.id, true)
+                .await
+                .unwrap()
+                .unwrap()
+                .author
+                .is_some()
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_list_includes_the_drafts_of_the_viewer(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let other_id = insert_user(&pool, "other").await;
+        let published = repo
+            .create("Public", "Content", other_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let draft = repo
+            .create("Draft", "Content", author_id, &[], PostStatus::Draft)
+            .await
+            .unwrap();
+        repo.create("Hidden", "Content", other_id, &[], PostStatus::Draft)
+            .await
+            .unwrap();
+
+        let (anonymous, anonymous_total) = repo
+            .list(10, 0, PostSort::CreatedAtAsc, None, false, None)
+            .await
+            .unwrap();
+        let (own, own_total) = repo
+            .list(10, 0, PostSort::CreatedAtAsc, None, false, Some(author_id))
+            .await
+            .unwrap();
+        let cursor = PostCursor::new(&published, PostSort::CreatedAtAsc);
+        let after = repo
+            .list_after(
+                &cursor,
+                10,
+                PostSort::CreatedAtAsc,
+                None,
+                false,
+                Some(author_id),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(anonymous.len(), 1);
+        assert_eq!(anonymous_total, 1);
+        assert_eq!(own.len(), 2);
+        assert_eq!(own_total, 2);
+        assert_eq!(repo.count(None, Some(author_id)).await.unwrap(), 2);
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].id, draft.id);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_every_read_carries_tags_and_author(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let tags = ["rust".to_string()];
+        let key = IdempotencyKey {
+            key: "key",
+            fingerprint: "request",
+            since: Utc::now() - chrono::Duration::hours(1),
+        };
+        let (older, _) = repo
+            .create_with_idempotency_key(
+                "Older",
+                "Content",
+                author_id,
+                &tags,
+                PostStatus::Published,
+                key,
+            )
+            .await
+            .unwrap();
+        let newer = repo
+            .create("Newer", "Content", author_id, &tags, PostStatus::Draft)
+            .await
+            .unwrap();
+
+        let published = repo
+            .set_status_by_author(newer.id, author_id, PostStatus::Published)
+            .await
+            .unwrap()
+            .unwrap();
+        let keyed = repo
+            .find_by_idempotency_key(author_id, key)
+            .await
+            .unwrap()
+            .unwrap();
+        let random = repo.find_random().await.unwrap().unwrap();
+        let neighbors = repo.find_neighbors(&older).await.unwrap();
+        let exported =
+            repo.list_by_author_after(author_id, 0, 10).await.unwrap();
+
+        assert_eq!(published.tags, tags);
+        assert_eq!(keyed.id, older.id);
+        for post in [&keyed, &random, neighbors.newer.as_ref().unwrap()]
+            .into_iter()
+            .chain(&exported)
+        {
+            assert_eq!(post.tags, tags);
+            assert_eq!(post.author_username.as_deref(), Some("author"));
+        }
+        assert_eq!(exported.len(), 2);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_concurrent_creates_with_one_key_insert_once(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let key = IdempotencyKey {
+            key: "key",
+            fingerprint: "request",
+            since: Utc::now() - chrono::Duration::hours(1),
+        };
+        let create = || {
+            repo.create_with_idempotency_key(
+                "Title",
+                "Content",
+                author_id,
+                &[],
+                PostStatus::Published,
+                key,
+            )
+        };
+
+        let (first, second) = tokio::join!(create(), create());
+        let (first, first_created) = first.unwrap();
+        let (second, second_created) = second.unwrap();
+
+        assert_ne!(first_created, second_created);
+        assert_eq!(first.id, second.id);
+        assert_eq!(repo.count_by_author(author_id).await.unwrap(), 1);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_idempotency_key_is_not_reused_for_other_requests(
+        pool: PgPool,
+    ) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let key = |key, fingerprint| IdempotencyKey {
+            key,
+            fingerprint,
+            since: Utc::now() - chrono::Duration::hours(1),
+        };
+        let create = |key| {
+            repo.create_with_idempotency_key(
+                "Title",
+                "Content",
+                author_id,
+                &[],
+                PostStatus::Published,
+                key,
+            )
+        };
+        let (kept, _) = create(key("kept", "request")).await.unwrap();
+        let (deleted, _) = create(key("deleted", "request")).await.unwrap();
+        repo.delete_by_author(deleted.id, author_id).await.unwrap();
+
+        let other_request = create(key("kept", "other")).await;
+        let after_delete = create(key("deleted", "request")).await;
+        let found = repo
+            .find_by_idempotency_key(author_id, key("deleted", "request"))
+            .await;
+
+        assert!(matches!(
+            other_request,
+            Err(DomainError::IdempotencyKeyReused)
+        ));
+        assert!(matches!(after_delete, Err(DomainError::IdempotencyKeyReused)));
+        assert!(matches!(found, Err(DomainError::IdempotencyKeyReused)));
+        assert_eq!(repo.count_by_author(author_id).await.unwrap(), 1);
+        assert!(repo.find_by_id(kept.id, false).await.unwrap().is_some());
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_title_change_keeps_content_updated_at(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let post = repo
+            .create("Title", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let changed = repo
+            .update_by_author(
+                post.id,
+                author_id,
+                "New title",
+                "Content",
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(changed.title_updated_at > post.title_updated_at);
+        assert_eq!(changed.content_updated_at, post.content_updated_at);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_list_filters_by_tags(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let both = vec!["async".to_string(), "rust".to_string()];
+        repo.create("Both", "Content", author_id, &both, PostStatus::Published)
+            .await
+            .unwrap();
+        repo.create(
+            "Rust",
+            "Content",
+            author_id,
+            &["rust".to_string()],
+            PostStatus::Published,
+        )
+        .await
+        .unwrap();
+        repo.create("Plain", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let all = TagFilter::new(both.clone(), TagMode::All).unwrap();
+        let any = TagFilter::new(both, TagMode::Any).unwrap();
+
+        let (matching_all, _) = repo
+            .list(10, 0, PostSort::CreatedAtAsc, Some(&all), false, None)
+            .await
+            .unwrap();
+        let (matching_any, _) = repo
+            .list(10, 0, PostSort::CreatedAtAsc, Some(&any), false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(matching_all.len(), 1);
+        assert_eq!(matching_all[0].tags, ["async", "rust"]);
+        assert_eq!(matching_any.len(), 2);
+        assert_eq!(repo.count(Some(&all), None).await.unwrap(), 1);
+        assert_eq!(repo.count(Some(&any), None).await.unwrap(), 2);
+        assert_eq!(repo.count(None, None).await.unwrap(), 3);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_list_counts_in_the_same_query(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        sqlx::query(
+            r"
+            INSERT INTO posts (title, content, author_id, status)
+            SELECT 'Post ' || n, 'Content', $1, 'published'
+            FROM generate_series(1, 25) n
+            ",
+        )
+        .bind(author_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let queries = QueryOperations::default();
+        let guard = queries.record();
+        let (page, total) = repo
+            .list(10, 20, PostSort::default(), None, false, None)
+            .await
+            .unwrap();
+        let page_queries = queries.take();
+        let (past_end, past_end_total) = repo
+            .list(10, 30, PostSort::default(), None, false, None)
+            .await
+            .unwrap();
+        drop(guard);
+
+        assert_eq!(page.len(), 5);
+        assert_eq!(total, 25);
+        assert_eq!(page_queries, ["posts.list"]);
+        assert_eq!(past_end.len(), 0);
+        assert_eq!(past_end_total, 25);
+        assert_eq!(queries.take(), ["posts.list", "posts.count"]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_list_by_author_skips_other_authors(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let other_id = insert_user(&pool, "other").await;
+        repo.create("Mine", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        repo.create("Theirs", "Content", other_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        let (posts, total) = repo
+            .list_by_author(author_id, 10, 0, PostSort::default(), false)
+            .await
+            .unwrap();
+        let (none, none_total) = repo
+            .list_by_author(other_id + 1, 10, 0, PostSort::default(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(total, 1);
+        assert_eq!(posts[0].title, "Mine");
+        assert!(none.is_empty());
+        assert_eq!(none_total, 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_deleted_post_is_hidden_until_restored(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let post = repo
+            .create(
+                "Title",
+                "Content",
+                author_id,
+                &["rust".to_string()],
+                PostStatus::Published,
+            )
+            .await
+            .unwrap();
+
+        assert!(repo.delete_by_author(post.id, author_id).await.unwrap());
+        assert!(!repo.delete_by_author(post.id, author_id).await.unwrap());
+        assert!(repo.find_by_id(post.id, false).await.unwrap().is_none());
+        assert_eq!(repo.count(None, None).await.unwrap(), 0);
+        assert_eq!(repo.count_by_author(author_id).await.unwrap(), 0);
+        let (listed, _) = repo
+            .list(10, 0, PostSort::default(), None, false, None)
+            .await
+            .unwrap();
+        assert!(listed.is_empty());
+        assert_eq!(
+            repo.find_deleted_author(post.id).await.unwrap(),
+            Some(author_id)
+        );
+
+        let restored = repo
+            .restore_by_author(post.id, author_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.tags, ["rust"]);
+        assert!(repo.find_by_id(post.id, false).await.unwrap().is_some());
+        assert!(repo.find_deleted_author(post.id).await.unwrap().is_none());
+        assert!(
+            repo.restore_by_author(post.id, author_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_search_ranks_title_matches_first(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        repo.create(
+            "Notes",
+            "Borrowing in Rust",
+            author_id,
+            &[],
+            PostStatus::Published,
+        )
+        .await
+        .unwrap();
+        repo.create(
+            "Rust borrowing",
+            "Explained",
+            author_id,
+            &[],
+            PostStatus::Published,
+        )
+        .await
+        .unwrap();
+        repo.create(
+            "Gardening",
+            "Tomatoes",
+            author_id,
+            &[],
+            PostStatus::Published,
+        )
+        .await
+        .unwrap();
+
+        let (found, total) = repo.search("borrow rust", 10, 0).await.unwrap();
+        let (injected, _) =
+            repo.search("'; DROP TABLE posts; --", 10, 0).await.unwrap();
+
+        let titles: Vec<&str> =
+            found.iter().map(|post| post.title.as_str()).collect();
+        assert_eq!(titles, ["Rust borrowing", "Notes"]);
+        assert_eq!(total, 2);
+        assert!(injected.is_empty());
+        assert_eq!(repo.count(None, None).await.unwrap(), 3);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_bulk_tag_ignores_foreign_posts(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let other_id = insert_user(&pool, "other").await;
+        let own = repo
+            .create(
+                "Own",
+                "Content",
+                author_id,
+                &["async".to_string()],
+                PostStatus::Published,
+            )
+            .await
+            .unwrap();
+        let foreign = repo
+            .create("Foreign", "Content", other_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let ids = [own.id, foreign.id];
+
+        let added = repo
+            .update_tag_by_author(author_id, &ids, "rust", TagAction::Add)
+            .await
+            .unwrap();
+        let removed = repo
+            .update_tag_by_author(author_id, &ids, "async", TagAction::Remove)
+            .await
+            .unwrap();
+
+        assert_eq!(added, BulkTagResult::new(2, 1, 1));
+        assert_eq!(removed, BulkTagResult::new(2, 1, 1));
+        let own = repo.find_by_id(own.id, false).await.unwrap().unwrap();
+        let foreign =
+            repo.find_by_id(foreign.id, false).await.unwrap().unwrap();
+        assert_eq!(own.tags, ["rust"]);
+        assert_eq!(foreign.tags, Vec::<String>::new());
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_orphaned_post_is_still_returned(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let post = repo
+            .create("Title", "Content", author_id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+
+        // Orphan the post the way a manual cleanup bypassing the
+        // foreign key would
+        sqlx::query("ALTER TABLE posts DROP CONSTRAINT posts_author_id_fkey")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(author_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let found = repo.find_by_id(post.id, false).await.unwrap().unwrap();
+        let (listed, _) = repo
+            .list(10, 0, PostSort::default(), None, false, None)
+            .await
+            .unwrap();
+
+        assert!(found.author_username.is_none());
+        assert_eq!(listed.len(), 1);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires a PostgreSQL database (DATABASE_URL)"]
+    async fn test_count_by_day_groups_by_utc_day(pool: PgPool) {
+        let repo = PostgresPostRepository::new(
+            DbPools::single(pool.clone()),
+            QueryLog::default(),
+        );
+        let author_id = insert_user(&pool, "author").await;
+        let other_id = insert_user(&pool, "other").await;
+        for (author, created_at) in [
+            (author_id, "2025-03-01T00:30:00Z"),
+            (author_id, "2025-03-01T23:30:00Z"),
+            (author_id, "2025-03-03T12:00:00Z"),
+            (other_id, "2025-03-01T12:00:00Z"),
+            (author_id, "2025-02-27T12:00:00Z"),
+        ] {
+            let post = repo
+                .create("Title", "Content", author, &[], PostStatus::Published)
+                .await
+                .unwrap();
+            sqlx::query(
+                "UPDATE posts SET created_at = $1::timestamptz WHERE id = $2",
+            )
+            .bind(created_at)
+            .bind(post.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let since = "2025-03-01T00:00:00Z".parse().unwrap();
+
+        let days = repo.count_by_day(author_id, since).await.unwrap();
+
+        let day = |d: &str| d.parse::<NaiveDate>().unwrap();
+        assert_eq!(days, [(day("2025-03-01"), 2), (day("2025-03-03"), 1)]);
     }
 }
@@ -0,0 +1,328 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use uuid::Uuid;
+
+use crate::domain::{
+    BulkTagResult, Comment, DomainError, IdempotencyKey, Password, Post,
+    PostCursor, PostNeighbors, PostSort, PostStatus, Session, TagAction,
+    TagFilter, User,
+};
+
+/// Storage for user accounts. Implementations must reject duplicate
+/// usernames and emails with `DomainError::UserAlreadyExists`.
+// async_trait marks the boxed futures #[must_use] on top of Result
+#[allow(clippy::double_must_use)]
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn create(
+        &self,
+        username: &str,
+        email: &str,
+        password_hash: &Password,
+    ) -> Result<User, DomainError>;
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, DomainError>;
+
+    #[allow(dead_code)]
+    async fn find_by_email(
+        &self,
+        email: &str,
+    ) -> Result<Option<User>, DomainError>;
+
+    async fn find_by_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<User>, DomainError>;
+
+    /// Flags the email as verified if the user still has that address and
+    /// it is not verified yet. Returns the updated user, or None when no
+    /// such unverified user/email pair exists.
+    async fn mark_email_verified(
+        &self,
+        id: i64,
+        email: &str,
+    ) -> Result<Option<User>, DomainError>;
+
+    /// Current password version, or None for an unknown user
+    async fn password_version(
+        &self,
+        id: i64,
+    ) -> Result<Option<i32>, DomainError>;
+
+    /// Replaces the password hash and bumps the password version, which
+    /// revokes every token issued before. Returns the new version.
+    async fn update_password(
+        &self,
+        id: i64,
+        password_hash: &Password,
+    ) -> Result<Option<i32>, DomainError>;
+
+    /// Replaces the password hash with a rehash of the same password,
+    /// keeping the password version so issued tokens stay valid. Only
+    /// applies while the password version is still `expected_version`, so
+    /// a password change in between wins. Returns whether the hash was
+    /// replaced.
+    async fn upgrade_password_hash(
+        &self,
+        id: i64,
+        expected_version: i32,
+        password_hash: &Password,
+    ) -> Result<bool, DomainError>;
+}
+
+/// Storage for posts. Reads return posts with `author_username` and
+/// `tags` filled in. Tags are expected to be normalized already. Drafts
+/// are left out of listings and counts unless stated otherwise; reads of
+/// a single post by id return them.
+// async_trait marks the boxed futures #[must_use] on top of Result
+#[allow(clippy::double_must_use)]
+#[async_trait]
+pub trait PostRepository: Send + Sync {
+    async fn create(
+        &self,
+        title: &str,
+        content: &str,
+        author_id: i64,
+        tags: &[String],
+        status: PostStatus,
+    ) -> Result<Post, DomainError>;
+
+    /// The post `author_id` created with `key`, unless the key has
+    /// expired. `DomainError::IdempotencyKeyReused` if the key came with
+    /// another request or its post has been deleted since.
+    async fn find_by_idempotency_key(
+        &self,
+        author_id: i64,
+        key: IdempotencyKey<'_>,
+    ) -> Result<Option<Post>, DomainError>;
+
+    /// Creates a post like `create` and records `key` for it atomically.
+    /// If the author already used `key`, including in a concurrent call
+    /// (which this one waits for), nothing is created and the earlier post
+    /// is returned with `false`, or the error `find_by_idempotency_key`
+    /// would give. Expired uses of the key are forgotten.
+    async fn create_with_idempotency_key(
+        &self,
+        title: &str,
+        content: &str,
+        author_id: i64,
+        tags: &[String],
+        status: PostStatus,
+        key: IdempotencyKey<'_>,
+    ) -> Result<(Post, bool), DomainError>;
+
+    /// With `include_author` the post carries an
+    /// [`AuthorSummary`](crate::domain::AuthorSummary), loaded in the same
+    /// query
+    async fn find_by_id(
+        &self,
+        id: i64,
+        include_author: bool,
+    ) -> Result<Option<Post>, DomainError>;
+
+    /// Picks a random post, or `None` when there are no posts
+    async fn find_random(&self) -> Result<Option<Post>, DomainError>;
+
+    /// Posts immediately before and after `post` by `created_at`,
+    /// with ids breaking ties
+    async fn find_neighbors(
+        &self,
+        post: &Post,
+    ) -> Result<PostNeighbors, DomainError>;
+
+    /// Updates post only if it belongs to the author, replacing its tags
+    /// in the same transaction when `tags` is given.
+    /// Returns None if post not found or doesn't belong to author, or if
+    /// `expected_updated_at` is given and is not the post's `updated_at`.
+    /// `updated_at` is only bumped when the title or content actually changes.
+    async fn update_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+        title: &str,
+        content: &str,
+        tags: Option<&[String]>,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<Post>, DomainError>;
+
+    /// Adds or removes `tag` on those of `post_ids` that belong to the
+    /// author, atomically. Other ids are counted as skipped. `post_ids`
+    /// must not contain duplicates.
+    async fn update_tag_by_author(
+        &self,
+        author_id: i64,
+        post_ids: &[i64],
+        tag: &str,
+        action: TagAction,
+    ) -> Result<BulkTagResult, DomainError>;
+
+    /// Soft-deletes post only if it belongs to the author. Deleted posts
+    /// are left out of every other read and write until restored.
+    /// Returns true if deleted, false if not found or doesn't belong to author.
+    async fn delete_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+    ) -> Result<bool, DomainError>;
+
+    /// Undoes `delete_by_author`. Returns None unless the post is deleted
+    /// and belongs to the author.
+    async fn restore_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+    ) -> Result<Option<Post>, DomainError>;
+
+    /// Author of the post if it is soft-deleted, None otherwise
+    async fn find_deleted_author(
+        &self,
+        id: i64,
+    ) -> Result<Option<i64>, DomainError>;
+
+    /// Moves the post to `status` only if it belongs to the author.
+    /// Returns None if post not found or doesn't belong to author.
+    async fn set_status_by_author(
+        &self,
+        id: i64,
+        author_id: i64,
+        status: PostStatus,
+    ) -> Result<Option<Post>, DomainError>;
+
+    /// A page of posts plus the number matching `filter` overall, so
+    /// callers need no separate `count` for it. `include_author` is as
+    /// for `find_by_id`. The drafts of `viewer`, when given, are listed
+    /// alongside the published posts.
+    async fn list(
+        &self,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
+    ) -> Result<(Vec<Post>, i64), DomainError>;
+
+    /// Full-text search: posts containing every word of `query` in title
+    /// or content, best matches first, plus how many match overall
+    async fn search(
+        &self,
+        query: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Post>, i64), DomainError>;
+
+    /// Keyset page: up to `limit` posts after `cursor` in `sort` order.
+    /// Unlike offsets, posts created meanwhile do not shift the page.
+    async fn list_after(
+        &self,
+        cursor: &PostCursor,
+        limit: i64,
+        sort: PostSort,
+        filter: Option<&TagFilter>,
+        include_author: bool,
+        viewer: Option<i64>,
+    ) -> Result<Vec<Post>, DomainError>;
+
+    /// A page of an author's posts in `sort` order, plus how many they
+    /// have overall. Drafts are only included with `include_drafts`.
+    async fn list_by_author(
+        &self,
+        author_id: i64,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+        include_drafts: bool,
+    ) -> Result<(Vec<Post>, i64), DomainError>;
+
+    /// Keyset-paginated fetch of an author's posts ordered by id, drafts
+    /// included, used to stream exports without loading everything at once.
+    async fn list_by_author_after(
+        &self,
+        author_id: i64,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Post>, DomainError>;
+
+    /// Number of posts `list` would page through with the same filter
+    /// and viewer
+    async fn count(
+        &self,
+        filter: Option<&TagFilter>,
+        viewer: Option<i64>,
+    ) -> Result<i64, DomainError>;
+
+    /// All of an author's posts, drafts included
+    async fn count_by_author(&self, author_id: i64)
+    -> Result<i64, DomainError>;
+
+    /// An author's posts created at or after `since`, counted per UTC day
+    /// in ascending order. Days without posts are left out.
+    async fn count_by_day(
+        &self,
+        author_id: i64,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(NaiveDate, i64)>, DomainError>;
+
+    /// Tags on published posts with how many posts carry each,
+    /// by name. Tags no post uses any more are left out.
+    async fn tag_counts(&self) -> Result<Vec<(String, i64)>, DomainError>;
+}
+
+/// Comments on posts, returned with `author_username` filled in. Whether
+/// the post is visible is up to the caller to check.
+// async_trait marks the boxed futures #[must_use] on top of Result
+#[allow(clippy::double_must_use)]
+#[async_trait]
+pub trait CommentRepository: Send + Sync {
+    async fn create(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        content: &str,
+    ) -> Result<Comment, DomainError>;
+
+    /// A page of a post's comments, oldest first, plus how many it has
+    async fn list_by_post(
+        &self,
+        post_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Comment>, i64), DomainError>;
+}
+
+/// Issued access tokens by `jti`. Expired sessions may linger in storage
+/// but are never reported as active or listed.
+// async_trait marks the boxed futures #[must_use] on top of Result
+#[allow(clippy::double_must_use)]
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    /// Stores a new session, pruning the user's expired ones
+    async fn create(&self, session: &Session) -> Result<(), DomainError>;
+
+    /// Whether `jti` is an unexpired session of `user_id`
+    async fn is_active(
+        &self,
+        jti: Uuid,
+        user_id: i64,
+    ) -> Result<bool, DomainError>;
+
+    /// Unexpired sessions of a user, newest first
+    async fn list_by_user(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<Session>, DomainError>;
+
+    /// Deletes the session only if it belongs to the user.
+    /// Returns true if deleted, false if not found or owned by someone else.
+    async fn delete(
+        &self,
+        jti: Uuid,
+        user_id: i64,
+    ) -> Result<bool, DomainError>;
+
+    /// Deletes every session of the user
+    async fn delete_by_user(&self, user_id: i64) -> Result<(), DomainError>;
+
+    /// Deletes the expired sessions of all users. Returns how many.
+    async fn delete_expired(&self) -> Result<u64, DomainError>;
+}
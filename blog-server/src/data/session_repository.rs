@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::{QueryLog, SessionRepository};
+use crate::domain::{DomainError, Session};
+use crate::infrastructure::DbPools;
+
+pub struct PostgresSessionRepository {
+    pools: DbPools,
+    query_log: QueryLog,
+}
+
+impl PostgresSessionRepository {
+    pub const fn new(pools: DbPools, query_log: QueryLog) -> Self {
+        Self { pools, query_log }
+    }
+}
+
+// Every query goes to the primary: a revoked session must stop working
+// right away, not once the replica catches up
+#[async_trait]
+impl SessionRepository for PostgresSessionRepository {
+    async fn create(&self, session: &Session) -> Result<(), DomainError> {
+        let prune = sqlx::query(
+            "DELETE FROM sessions WHERE user_id = $1 AND expires_at <= NOW()",
+        )
+        .bind(session.user_id)
+        .execute(&self.pools.primary);
+        self.query_log.time("sessions.prune", prune).await?;
+
+        let query = sqlx::query(
+            r"
+            INSERT INTO sessions (jti, user_id, label, issued_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ",
+        )
+        .bind(session.jti)
+        .bind(session.user_id)
+        .bind(session.label.as_deref())
+        .bind(session.issued_at)
+        .bind(session.expires_at)
+        .execute(&self.pools.primary);
+        self.query_log.time("sessions.create", query).await?;
+
+        Ok(())
+    }
+
+    async fn is_active(
+        &self,
+        jti: Uuid,
+        user_id: i64,
+    ) -> Result<bool, DomainError> {
+        let query = sqlx::query_as(
+            r"
+            SELECT EXISTS (
+                SELECT 1 FROM sessions
+                WHERE jti = $1 AND user_id = $2 AND expires_at > NOW()
+            )
+            ",
+        )
+        .bind(jti)
+        .bind(user_id)
+        .fetch_one(&self.pools.primary);
+        let row: (bool,) =
+            self.query_log.time("sessions.is_active", query).await?;
+
+        Ok(row.0)
+    }
+
+    async fn list_by_user(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<Session>, DomainError> {
+        let query = sqlx::query_as::<_, SessionRow>(
+            r"
+            SELECT jti, user_id, label, issued_at, expires_at
+            FROM sessions
+            WHERE user_id = $1 AND expires_at > NOW()
+            ORDER BY issued_at DESC
+            ",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pools.primary);
+        let rows = self.query_log.time("sessions.list_by_user", query).await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn delete(
+        &self,
+        jti: Uuid,
+        user_id: i64,
+    ) -> Result<bool, DomainError> {
+        let query =
+            sqlx::query("DELETE FROM sessions WHERE jti = $1 AND user_id = $2")
+                .bind(jti)
+                .bind(user_id)
+                .execute(&self.pools.primary);
+        let result = self.query_log.time("sessions.delete", query).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_by_user(&self, user_id: i64) -> Result<(), DomainError> {
+        let query = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pools.primary);
+        self.query_log
+            .time("sessions.delete_by_user", query)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_expired(&self) -> Result<u64, DomainError> {
+        let query =
+            sqlx::query("DELETE FROM sessions WHERE expires_at <= NOW()")
+                .execute(&self.pools.primary);
+        let result = self
+            .query_log
+            .time("sessions.delete_expired", query)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SessionRow {
+    jti: Uuid,
+    user_id: i64,
+    label: Option<String>,
+    issued_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl From<SessionRow> for Session {
+    fn from(row: SessionRow) -> Self {
+        Self {
+            jti: row.jti,
+            user_id: row.user_id,
+            label: row.label,
+            issued_at: row.issued_at,
+            expires_at: row.expires_at,
+        }
+    }
+}
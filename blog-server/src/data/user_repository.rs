@@ -1,94 +1,184 @@
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
 
+use super::{QueryLog, UserRepository};
 use crate::domain::{DomainError, Password, User};
+use crate::infrastructure::DbPools;
 
 pub struct PostgresUserRepository {
-    pool: PgPool,
+    pools: DbPools,
+    query_log: QueryLog,
 }
 
 impl PostgresUserRepository {
-    pub const fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub const fn new(pools: DbPools, query_log: QueryLog) -> Self {
+        Self { pools, query_log }
     }
+}
 
-    pub async fn create(
+#[async_trait]
+impl UserRepository for PostgresUserRepository {
+    async fn create(
         &self,
         username: &str,
         email: &str,
         password_hash: &Password,
     ) -> Result<User, DomainError> {
-        let row = sqlx::query_as::<_, UserRow>(
+        let query = sqlx::query_as::<_, UserRow>(
             r"
             INSERT INTO users (username, email, password_hash)
             VALUES ($1, $2, $3)
-            RETURNING id, username, email, password_hash, created_at
+            RETURNING id, username, email, password_hash, email_verified, password_version, role, created_at
             ",
         )
         .bind(username)
         .bind(email)
         .bind(password_hash.as_ref())
-        .fetch_one(&self.pool)
-        .await?;
+        .fetch_one(&self.pools.primary);
+        let row = self.query_log.time("users.create", query).await?;
 
         Ok(row.into())
     }
 
-    #[allow(dead_code)]
-    pub async fn find_by_id(
-        &self,
-        id: i64,
-    ) -> Result<Option<User>, DomainError> {
-        let row = sqlx::query_as::<_, UserRow>(
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, DomainError> {
+        let query = sqlx::query_as::<_, UserRow>(
             r"
-            SELECT id, username, email, password_hash, created_at
+            SELECT id, username, email, password_hash, email_verified, password_version, role, created_at
             FROM users
             WHERE id = $1
             ",
         )
         .bind(id)
-        .fetch_optional(&self.pool)
-        .await?;
+        .fetch_optional(&self.pools.replica);
+        let row = self.query_log.time("users.find_by_id", query).await?;
 
         Ok(row.map(Into::into))
     }
 
-    #[allow(dead_code)]
-    pub async fn find_by_email(
+    async fn find_by_email(
         &self,
         email: &str,
     ) -> Result<Option<User>, DomainError> {
-        let row = sqlx::query_as::<_, UserRow>(
+        let query = sqlx::query_as::<_, UserRow>(
             r"
-            SELECT id, username, email, password_hash, created_at
+            SELECT id, username, email, password_hash, email_verified, password_version, role, created_at
             FROM users
             WHERE email = $1
             ",
         )
         .bind(email)
-        .fetch_optional(&self.pool)
-        .await?;
+        .fetch_optional(&self.pools.replica);
+        let row = self.query_log.time("users.find_by_email", query).await?;
 
         Ok(row.map(Into::into))
     }
 
-    pub async fn find_by_username(
+    async fn find_by_username(
         &self,
         username: &str,
     ) -> Result<Option<User>, DomainError> {
-        let row = sqlx::query_as::<_, UserRow>(
+        // Login typically follows registration immediately; reading the
+        // primary avoids rejecting a user the replica has not seen yet
+        let query = sqlx::query_as::<_, UserRow>(
             r"
-            SELECT id, username, email, password_hash, created_at
+            SELECT id, username, email, password_hash, email_verified, password_version, role, created_at
             FROM users
             WHERE username = $1
             ",
         )
         .bind(username)
-        .fetch_optional(&self.pool)
-        .await?;
+        .fetch_optional(&self.pools.primary);
+        let row = self.query_log.time("users.find_by_username", query).await?;
 
         Ok(row.map(Into::into))
     }
+
+    async fn mark_email_verified(
+        &self,
+        id: i64,
+        email: &str,
+    ) -> Result<Option<User>, DomainError> {
+        let query = sqlx::query_as::<_, UserRow>(
+            r"
+            UPDATE users
+            SET email_verified = TRUE
+            WHERE id = $1 AND email = $2 AND email_verified = FALSE
+            RETURNING id, username, email, password_hash, email_verified, password_version, role, created_at
+            ",
+        )
+        .bind(id)
+        .bind(email)
+        .fetch_optional(&self.pools.primary);
+        let row = self
+            .query_log
+            .time("users.mark_email_verified", query)
+            .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn password_version(
+        &self,
+        id: i64,
+    ) -> Result<Option<i32>, DomainError> {
+        // Revocation must take effect immediately, so never the replica
+        let query =
+            sqlx::query_as("SELECT password_version FROM users WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pools.primary);
+        let row: Option<(i32,)> =
+            self.query_log.time("users.password_version", query).await?;
+
+        Ok(row.map(|(version,)| version))
+    }
+
+    async fn update_password(
+        &self,
+        id: i64,
+        password_hash: &Password,
+    ) -> Result<Option<i32>, DomainError> {
+        let query = sqlx::query_as(
+            r"
+            UPDATE users
+            SET password_hash = $2,
+                password_version = password_version + 1
+            WHERE id = $1
+            RETURNING password_version
+            ",
+        )
+        .bind(id)
+        .bind(password_hash.as_ref())
+        .fetch_optional(&self.pools.primary);
+        let row: Option<(i32,)> =
+            self.query_log.time("users.update_password", query).await?;
+
+        Ok(row.map(|(version,)| version))
+    }
+
+    async fn upgrade_password_hash(
+        &self,
+        id: i64,
+        expected_version: i32,
+        password_hash: &Password,
+    ) -> Result<bool, DomainError> {
+        let query = sqlx::query(
+            r"
+            UPDATE users
+            SET password_hash = $3
+            WHERE id = $1 AND password_version = $2
+            ",
+        )
+        .bind(id)
+        .bind(expected_version)
+        .bind(password_hash.as_ref())
+        .execute(&self.pools.primary);
+        let result = self
+            .query_log
+            .time("users.upgrade_password_hash", query)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
 }
 
 #[derive(sqlx::FromRow)]
@@ -97,6 +187,9 @@ struct UserRow {
     username: String,
     email: String,
     password_hash: String,
+    email_verified: bool,
+    password_version: i32,
+    role: String,
     created_at: DateTime<Utc>,
 }
 
@@ -109,5 +202,9 @@ impl From<UserRow> for User {
             Password::from_hash(row.password_hash),
             row.created_at,
         )
+        .with_email_verified(row.email_verified)
+        .with_password_version(row.password_version)
+        // The column is constrained to known roles
+        .with_role(row.role.parse().unwrap_or_default())
     }
 }
@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use validator::ValidationError;
+
+use super::DomainError;
+use super::error::rule_violation;
+
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub id: i64,
+    pub post_id: i64,
+    pub author_id: i64,
+    pub author_username: Option<String>,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Longest comment accepted, in characters
+const MAX_COMMENT_CHARS: usize = 2000;
+
+/// Domain command for commenting on a post
+#[derive(Debug, Clone)]
+pub struct CreateCommentCommand {
+    pub content: String,
+}
+
+impl CreateCommentCommand {
+    pub fn validate(&self) -> Result<(), DomainError> {
+        validate_comment(&self.content).map_err(|error| {
+            DomainError::invalid_field("content", error.to_string())
+        })
+    }
+}
+
+/// Comment rule, also run by the request DTO. Blank contents count as
+/// empty.
+pub fn validate_comment(content: &str) -> Result<(), ValidationError> {
+    if content.trim().is_empty() || content.chars().count() > MAX_COMMENT_CHARS
+    {
+        return Err(rule_violation(format!(
+            "must be 1 to {MAX_COMMENT_CHARS} characters"
+        )));
+    }
+    Ok(())
+}
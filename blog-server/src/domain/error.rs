@@ -1,4 +1,7 @@
+use std::collections::BTreeMap;
+
 use thiserror::Error;
+use validator::ValidationError;
 
 #[derive(Debug, Error)]
 pub enum DomainError {
@@ -11,18 +14,55 @@ pub enum DomainError {
     #[error("Invalid credentials")]
     InvalidCredentials,
 
+    /// Refresh token that is expired, malformed, of the wrong type, or
+    /// whose session was revoked
+    #[error("Invalid or expired token")]
+    InvalidToken,
+
+    #[error("Email address is not verified")]
+    EmailNotVerified,
+
     #[error("Post not found")]
     PostNotFound,
 
+    #[error("Session not found")]
+    SessionNotFound,
+
+    /// The post changed after the version the update was based on
+    #[error("Post was modified by someone else; reload it and try again")]
+    Conflict,
+
+    /// An idempotency key replayed with a different request, or after the
+    /// post it created was deleted
+    #[error("Idempotency key was already used for another request")]
+    IdempotencyKeyReused,
+
     #[error("Forbidden: you don't have permission to perform this action")]
     Forbidden,
 
+    #[error("Post quota exceeded: at most {0} posts per user")]
+    PostQuotaExceeded(u32),
+
+    #[error("Request body exceeds {0} bytes")]
+    PayloadTooLarge(usize),
+
+    #[error("Maintenance in progress: writes are disabled, try again later")]
+    Maintenance,
+
+    /// An optional feature switched off for this deployment
+    #[error("The {0} feature is disabled on this server")]
+    FeatureDisabled(&'static str),
+
     #[error("Database error: {0}")]
     DatabaseError(String),
 
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    /// Per-field messages, keyed by field name
+    #[error("Invalid fields: {}", field_names(.0))]
+    InvalidFields(BTreeMap<String, Vec<String>>),
+
     #[error("Password hash error: {0}")]
     PasswordHashError(String),
 
@@ -30,6 +70,54 @@ pub enum DomainError {
     JwtError(String),
 }
 
+/// Broken field rule, in the shape `#[validate(custom)]` on the request
+/// DTOs expects
+pub(super) fn rule_violation(message: String) -> ValidationError {
+    ValidationError::new("invalid").with_message(message.into())
+}
+
+impl DomainError {
+    /// `InvalidFields` with a single message for a single field
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        Self::InvalidFields(BTreeMap::from([(
+            field.to_string(),
+            vec![message.into()],
+        )]))
+    }
+
+    /// Stable machine-readable code, independent of the message wording
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::UserNotFound => "user_not_found",
+            Self::UserAlreadyExists => "user_already_exists",
+            Self::InvalidCredentials => "invalid_credentials",
+            Self::InvalidToken => "invalid_token",
+            Self::EmailNotVerified => "email_not_verified",
+            Self::PostNotFound => "post_not_found",
+            Self::SessionNotFound => "session_not_found",
+            Self::Conflict => "conflict",
+            Self::IdempotencyKeyReused => "idempotency_key_reused",
+            Self::Forbidden => "forbidden",
+            Self::PostQuotaExceeded(_) => "post_quota_exceeded",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::Maintenance => "maintenance",
+            Self::FeatureDisabled(_) => "feature_disabled",
+            Self::ValidationError(_) | Self::InvalidFields(_) => "validation",
+            Self::DatabaseError(_)
+            | Self::PasswordHashError(_)
+            | Self::JwtError(_) => "internal_error",
+        }
+    }
+}
+
+fn field_names(fields: &BTreeMap<String, Vec<String>>) -> String {
+    fields
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl From<password_hash::Error> for DomainError {
     fn from(err: password_hash::Error) -> Self {
         Self::PasswordHashError(err.to_string())
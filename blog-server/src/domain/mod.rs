@@ -1,11 +1,23 @@
 // Domain layer - business entities and logic
 
+pub mod comment;
 pub mod error;
 pub mod password;
 pub mod post;
+pub mod session;
 pub mod user;
 
+pub use comment::{Comment, CreateCommentCommand, validate_comment};
 pub use error::DomainError;
-pub use password::Password;
-pub use post::{CreatePostCommand, Post, UpdatePostCommand};
-pub use user::{AuthResult, LoginCommand, RegisterCommand, User};
+pub use password::{Password, PasswordParams};
+pub use post::{
+    AuthorSummary, BulkTagResult, CreatePostCommand, CursorKey, IdempotencyKey,
+    Post, PostCursor, PostNeighbors, PostSort, PostStatus, TagAction,
+    TagFilter, TagMode, UpdatePostCommand, normalize_tags, validate_content,
+    validate_title,
+};
+pub use session::Session;
+pub use user::{
+    AuthResult, LoginCommand, RegisterCommand, Role, User, validate_email,
+    validate_new_password, validate_password, validate_username,
+};
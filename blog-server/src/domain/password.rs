@@ -8,22 +8,40 @@ use argon2::{
     },
 };
 
+/// Argon2id cost of new password hashes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PasswordParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub lanes: u32,
+}
+
+impl Default for PasswordParams {
+    // OWASP recommended parameters (2023)
+    // https://cheatsheetseries.owasp.org/cheatsheets/Password_Storage_Cheat_Sheet.html
+    fn default() -> Self {
+        Self {
+            memory_kib: 65536, // 64 MiB
+            iterations: 3,
+            lanes: 4,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub struct Password(String);
 
 impl Password {
-    // OWASP recommended parameters (2023)
-    // https://cheatsheetseries.owasp.org/cheatsheets/Password_Storage_Cheat_Sheet.html
-    const ARGON2_MEMORY_KIB: u32 = 65536; // 64 MiB
-    const ARGON2_ITERATIONS: u32 = 3;
-    const ARGON2_LANES: u32 = 4;
     const ARGON2_OUTPUT_LEN: usize = 32;
 
-    pub fn hash<S: AsRef<str>>(input: S) -> Result<Self, password_hash::Error> {
+    pub fn hash<S: AsRef<str>>(
+        input: S,
+        params: &PasswordParams,
+    ) -> Result<Self, password_hash::Error> {
         let params = Params::new(
-            Self::ARGON2_MEMORY_KIB,
-            Self::ARGON2_ITERATIONS,
-            Self::ARGON2_LANES,
+            params.memory_kib,
+            params.iterations,
+            params.lanes,
             Some(Self::ARGON2_OUTPUT_LEN),
         )?;
 
@@ -35,13 +53,30 @@ impl Password {
     }
 
     pub fn verify(&self, password: &str) -> bool {
-        PasswordHash::new(&self.0)
-            .map(|hash| {
-                Argon2::default()
-                    .verify_password(password.as_bytes(), &hash)
-                    .is_ok()
-            })
-            .unwrap_or(false)
+        PasswordHash::new(&self.0).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+
+    /// Whether the hash is weaker than `params` or not Argon2id at all.
+    /// Unparseable hashes never verify, so they are not reported.
+    pub fn needs_rehash(&self, params: &PasswordParams) -> bool {
+        let Ok(hash) = PasswordHash::new(&self.0) else {
+            return false;
+        };
+        if hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        Params::try_from(&hash).is_ok_and(|current| {
+            current.m_cost() < params.memory_kib
+                || current.t_cost() < params.iterations
+                || current.p_cost() < params.lanes
+        })
     }
 
     pub const fn from_hash(hash: String) -> Self {
@@ -72,17 +107,37 @@ impl fmt::Debug for Password {
 mod tests {
     use super::*;
 
+    /// Cheap enough to keep the tests fast
+    const WEAK: PasswordParams = PasswordParams {
+        memory_kib: 8,
+        iterations: 1,
+        lanes: 1,
+    };
+
     #[test]
     fn test_password_hash_and_verify() {
-        let password = Password::hash("secret123").unwrap();
+        let password = Password::hash("secret123", &WEAK).unwrap();
         assert!(password.verify("secret123"));
         assert!(!password.verify("wrong_password"));
     }
 
     #[test]
     fn test_password_debug_hides_hash() {
-        let password = Password::hash("secret123").unwrap();
+        let password = Password::hash("secret123", &WEAK).unwrap();
         let debug_output = format!("{password:?}");
         assert_eq!(debug_output, "Password(\"********\")");
     }
+
+    #[test]
+    fn test_needs_rehash_below_target_params() {
+        let password = Password::hash("secret123", &WEAK).unwrap();
+        let stronger = PasswordParams {
+            memory_kib: 16,
+            ..WEAK
+        };
+
+        assert!(!password.needs_rehash(&WEAK));
+        assert!(password.needs_rehash(&stronger));
+        assert!(!Password::from_hash("not-a-hash".into()).needs_rehash(&WEAK));
+    }
 }
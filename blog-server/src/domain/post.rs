@@ -1,4 +1,12 @@
-use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, SubsecRound, Utc};
+use sha2::{Digest, Sha256};
+use validator::ValidationError;
+
+use super::DomainError;
+use super::error::rule_violation;
 
 #[derive(Debug, Clone)]
 pub struct Post {
@@ -9,6 +17,77 @@ pub struct Post {
     pub author_username: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Last time the title changed
+    pub title_updated_at: DateTime<Utc>,
+    /// Last time the content changed
+    pub content_updated_at: DateTime<Utc>,
+    /// Normalized tag names, sorted
+    pub tags: Vec<String>,
+    /// Only loaded when asked for, see [`AuthorSummary`]
+    pub author: Option<AuthorSummary>,
+    /// Drafts are only visible to their author
+    pub status: PostStatus,
+}
+
+/// Whether a post is visible to everyone yet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PostStatus {
+    #[default]
+    Draft,
+    Published,
+}
+
+impl PostStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Published => "published",
+        }
+    }
+
+    pub const fn is_published(self) -> bool {
+        matches!(self, Self::Published)
+    }
+}
+
+impl fmt::Display for PostStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PostStatus {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(Self::Draft),
+            "published" => Ok(Self::Published),
+            other => Err(DomainError::ValidationError(format!(
+                "unknown status: {other}"
+            ))),
+        }
+    }
+}
+
+/// What an author card shows about a post's author
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorSummary {
+    pub username: String,
+    /// `None` once hidden from a reader who may not see it
+    pub email: Option<String>,
+    /// When the author registered
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuthorSummary {
+    #[must_use]
+    pub fn without_email(self) -> Self {
+        Self {
+            email: None,
+            ..self
+        }
+    }
 }
 
 impl Post {
@@ -28,13 +107,212 @@ impl Post {
             author_username: None,
             created_at,
             updated_at,
+            title_updated_at: updated_at,
+            content_updated_at: updated_at,
+            tags: Vec::new(),
+            author: None,
+            status: PostStatus::Draft,
         }
     }
 
+    pub const fn with_field_timestamps(
+        mut self,
+        title_updated_at: DateTime<Utc>,
+        content_updated_at: DateTime<Utc>,
+    ) -> Self {
+        self.title_updated_at = title_updated_at;
+        self.content_updated_at = content_updated_at;
+        self
+    }
+
     pub fn with_author_username(mut self, username: String) -> Self {
         self.author_username = Some(username);
         self
     }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_author(mut self, author: AuthorSummary) -> Self {
+        self.author = Some(author);
+        self
+    }
+
+    pub const fn with_status(mut self, status: PostStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Whether `viewer` may read the post: published posts are public,
+    /// drafts only visible to their author
+    pub fn is_visible_to(&self, viewer: Option<i64>) -> bool {
+        self.status.is_published() || viewer == Some(self.author_id)
+    }
+
+    /// Whether `updated_at` is this post's, compared to the microsecond,
+    /// the precision Postgres keeps
+    pub fn is_version(&self, updated_at: DateTime<Utc>) -> bool {
+        self.updated_at.trunc_subsecs(6) == updated_at.trunc_subsecs(6)
+    }
+}
+
+/// Value of the sort column at a keyset position
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CursorKey {
+    CreatedAt(DateTime<Utc>),
+    UpdatedAt(DateTime<Utc>),
+    Title(String),
+}
+
+/// Keyset position in a listing: the sort column and id of the last post
+/// seen. Serialized as `<created_at micros>_<id>`, `u<updated_at
+/// micros>_<id>` or `t<hex title>_<id>` for clients, who should treat it
+/// as opaque.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostCursor {
+    pub key: CursorKey,
+    pub id: i64,
+}
+
+impl PostCursor {
+    /// Position of `post` in a listing ordered by `sort`. Timestamps are
+    /// cut to microseconds, the precision Postgres and the serialized
+    /// form keep.
+    pub fn new(post: &Post, sort: PostSort) -> Self {
+        let key = match sort {
+            PostSort::CreatedAtDesc | PostSort::CreatedAtAsc => {
+                CursorKey::CreatedAt(post.created_at.trunc_subsecs(6))
+            }
+            PostSort::UpdatedAtDesc | PostSort::UpdatedAtAsc => {
+                CursorKey::UpdatedAt(post.updated_at.trunc_subsecs(6))
+            }
+            PostSort::TitleAsc | PostSort::TitleDesc => {
+                CursorKey::Title(post.title.clone())
+            }
+        };
+        Self { key, id: post.id }
+    }
+
+    /// Whether the cursor came from a listing sorted on the same column
+    /// as `sort`; the direction may differ
+    pub const fn fits(&self, sort: PostSort) -> bool {
+        matches!(
+            (&self.key, sort),
+            (
+                CursorKey::CreatedAt(_),
+                PostSort::CreatedAtDesc | PostSort::CreatedAtAsc
+            ) | (
+                CursorKey::UpdatedAt(_),
+                PostSort::UpdatedAtDesc | PostSort::UpdatedAtAsc
+            ) | (CursorKey::Title(_), PostSort::TitleAsc | PostSort::TitleDesc)
+        )
+    }
+}
+
+impl fmt::Display for PostCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key {
+            CursorKey::CreatedAt(at) => write!(f, "{}", at.timestamp_micros()),
+            CursorKey::UpdatedAt(at) => {
+                write!(f, "u{}", at.timestamp_micros())
+            }
+            CursorKey::Title(title) => {
+                f.write_str("t")?;
+                title.bytes().try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+        }?;
+        write!(f, "_{}", self.id)
+    }
+}
+
+impl FromStr for PostCursor {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DomainError::ValidationError("invalid cursor".into());
+        let micros = |micros: &str| {
+            micros
+                .parse()
+                .ok()
+                .and_then(DateTime::from_timestamp_micros)
+                .ok_or_else(invalid)
+        };
+
+        let (key, id) = s.rsplit_once('_').ok_or_else(invalid)?;
+        let key = if let Some(at) = key.strip_prefix('u') {
+            CursorKey::UpdatedAt(micros(at)?)
+        } else if let Some(hex) = key.strip_prefix('t') {
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| {
+                    hex.get(i..i + 2)
+                        .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(invalid)?;
+            CursorKey::Title(String::from_utf8(bytes).map_err(|_| invalid())?)
+        } else {
+            CursorKey::CreatedAt(micros(key)?)
+        };
+        let id = id.parse().map_err(|_| invalid())?;
+
+        Ok(Self { key, id })
+    }
+}
+
+/// Posts adjacent to another one in creation order
+#[derive(Debug, Clone, Default)]
+pub struct PostNeighbors {
+    /// Next post created after the anchor, if any
+    pub newer: Option<Post>,
+    /// Previous post created before the anchor, if any
+    pub older: Option<Post>,
+}
+
+/// Trims and lowercases tags, dropping empty and duplicate ones
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Longest title accepted, in characters
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Title rule, also run by the request DTOs. Blank titles count as empty.
+pub fn validate_title(title: &str) -> Result<(), ValidationError> {
+    if title.trim().is_empty() || title.chars().count() > MAX_TITLE_CHARS {
+        return Err(rule_violation(format!(
+            "must be 1 to {MAX_TITLE_CHARS} characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Content rule, also run by the request DTOs. Blank contents count as
+/// empty.
+pub fn validate_content(content: &str) -> Result<(), ValidationError> {
+    if content.trim().is_empty() {
+        return Err(rule_violation("must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// Rules shared by create and update
+fn validate_post_fields(title: &str, content: &str) -> Result<(), DomainError> {
+    validate_title(title).map_err(|error| {
+        DomainError::ValidationError(format!("title {error}"))
+    })?;
+    validate_content(content).map_err(|error| {
+        DomainError::ValidationError(format!("content {error}"))
+    })
 }
 
 /// Domain command for creating a post
@@ -42,6 +320,39 @@ impl Post {
 pub struct CreatePostCommand {
     pub title: String,
     pub content: String,
+    pub tags: Vec<String>,
+    pub status: PostStatus,
+}
+
+impl CreatePostCommand {
+    pub fn validate(&self) -> Result<(), DomainError> {
+        validate_post_fields(&self.title, &self.content)
+    }
+
+    /// Digest of what is asked for, telling a retry from another request
+    /// sent with the same idempotency key. Tags count as normalized.
+    pub fn fingerprint(&self) -> String {
+        let tags = normalize_tags(self.tags.clone());
+        let parts = [self.status.as_str(), &self.title, &self.content]
+            .into_iter()
+            .chain(tags.iter().map(String::as_str));
+
+        let mut digest = Sha256::new();
+        for part in parts {
+            digest.update(part.len().to_le_bytes());
+            digest.update(part);
+        }
+        format!("{:x}", digest.finalize())
+    }
+}
+
+/// An `Idempotency-Key` sent with a create, with the fingerprint of that
+/// request. Uses of the key before `since` have expired.
+#[derive(Debug, Clone, Copy)]
+pub struct IdempotencyKey<'a> {
+    pub key: &'a str,
+    pub fingerprint: &'a str,
+    pub since: DateTime<Utc>,
 }
 
 /// Domain command for updating a post
@@ -49,4 +360,314 @@ pub struct CreatePostCommand {
 pub struct UpdatePostCommand {
     pub title: String,
     pub content: String,
+    /// Replaces the post's tags when set; None keeps them
+    pub tags: Option<Vec<String>>,
+    /// The post's `updated_at` the edit was based on. When set, the update
+    /// fails with `DomainError::Conflict` if the post has changed since.
+    pub expected_updated_at: Option<DateTime<Utc>>,
+}
+
+impl UpdatePostCommand {
+    pub fn validate(&self) -> Result<(), DomainError> {
+        validate_post_fields(&self.title, &self.content)
+    }
+}
+
+/// Order of post listings. Ties are broken by id in the same direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PostSort {
+    #[default]
+    CreatedAtDesc,
+    CreatedAtAsc,
+    UpdatedAtDesc,
+    UpdatedAtAsc,
+    TitleAsc,
+    TitleDesc,
+}
+
+impl PostSort {
+    /// Every accepted order; anything else is rejected when parsing
+    pub const ALL: [Self; 6] = [
+        Self::CreatedAtDesc,
+        Self::CreatedAtAsc,
+        Self::UpdatedAtDesc,
+        Self::UpdatedAtAsc,
+        Self::TitleAsc,
+        Self::TitleDesc,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::CreatedAtDesc => "created_at_desc",
+            Self::CreatedAtAsc => "created_at_asc",
+            Self::UpdatedAtDesc => "updated_at_desc",
+            Self::UpdatedAtAsc => "updated_at_asc",
+            Self::TitleAsc => "title_asc",
+            Self::TitleDesc => "title_desc",
+        }
+    }
+
+    pub const fn is_descending(self) -> bool {
+        matches!(
+            self,
+            Self::CreatedAtDesc | Self::UpdatedAtDesc | Self::TitleDesc
+        )
+    }
+}
+
+impl fmt::Display for PostSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PostSort {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|sort| sort.as_str() == s)
+            .ok_or_else(|| {
+                let allowed: Vec<_> =
+                    Self::ALL.iter().map(|sort| sort.as_str()).collect();
+                DomainError::ValidationError(format!(
+                    "unknown sort: {s} (expected one of {})",
+                    allowed.join(", ")
+                ))
+            })
+    }
+}
+
+/// How a multi-tag filter combines its tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagMode {
+    /// Posts carrying every listed tag
+    #[default]
+    All,
+    /// Posts carrying at least one listed tag
+    Any,
+}
+
+impl FromStr for TagMode {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "any" => Ok(Self::Any),
+            other => Err(DomainError::ValidationError(format!(
+                "unknown tag_mode: {other}"
+            ))),
+        }
+    }
+}
+
+/// Restricts post listings to posts with the given tags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    pub mode: TagMode,
+}
+
+impl TagFilter {
+    /// Normalizes `tags`; returns None when nothing is left to filter by
+    pub fn new(tags: Vec<String>, mode: TagMode) -> Option<Self> {
+        let tags = normalize_tags(tags);
+        (!tags.is_empty()).then_some(Self { tags, mode })
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        let mut wanted = self.tags.iter();
+        match self.mode {
+            TagMode::All => wanted.all(|tag| tags.contains(tag)),
+            TagMode::Any => wanted.any(|tag| tags.contains(tag)),
+        }
+    }
+}
+
+/// Change applied by a bulk tag update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagAction {
+    Add,
+    Remove,
+}
+
+impl FromStr for TagAction {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(Self::Add),
+            "remove" => Ok(Self::Remove),
+            other => Err(DomainError::ValidationError(format!(
+                "unknown action: {other}"
+            ))),
+        }
+    }
+}
+
+/// Outcome of a bulk tag update over a set of post ids
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BulkTagResult {
+    /// Owned posts whose tags changed
+    pub updated: u64,
+    /// Owned posts that already had (or lacked) the tag
+    pub unchanged: u64,
+    /// Ids that do not exist or belong to someone else
+    pub skipped: u64,
+}
+
+impl BulkTagResult {
+    /// Counts for `updated` changes among `owned` of `requested` ids
+    pub fn new(requested: usize, owned: usize, updated: u64) -> Self {
+        let count = |n: usize| u64::try_from(n).unwrap_or(u64::MAX);
+        Self {
+            updated,
+            unchanged: count(owned).saturating_sub(updated),
+            skipped: count(requested.saturating_sub(owned)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_post_title_boundaries() {
+        let update = |title: &str| UpdatePostCommand {
+            title: title.to_string(),
+            content: "Content".to_string(),
+            tags: None,
+            expected_updated_at: None,
+        };
+
+        assert!(update("T").validate().is_ok());
+        assert!(update(&"é".repeat(MAX_TITLE_CHARS)).validate().is_ok());
+        for title in ["", "   ", &"t".repeat(MAX_TITLE_CHARS + 1)] {
+            assert!(matches!(
+                update(title).validate(),
+                Err(DomainError::ValidationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_tells_requests_apart() {
+        let create = |content: &str, tags: &[&str]| CreatePostCommand {
+            title: "Title".to_string(),
+            content: content.to_string(),
+            tags: tags.iter().map(ToString::to_string).collect(),
+            status: PostStatus::Draft,
+        };
+        let fingerprint = create("Content", &["rust", "web"]).fingerprint();
+
+        assert_eq!(
+            create("Content", &["Web", "rust"]).fingerprint(),
+            fingerprint
+        );
+        assert_ne!(
+            create("Content!", &["rust", "web"]).fingerprint(),
+            fingerprint
+        );
+        assert_ne!(create("Content", &["rust"]).fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn test_post_content_must_not_be_blank() {
+        let create = |content: &str| CreatePostCommand {
+            title: "Title".to_string(),
+            content: content.to_string(),
+            tags: Vec::new(),
+            status: PostStatus::Draft,
+        };
+
+        assert!(create("x").validate().is_ok());
+        assert!(create("").validate().is_err());
+        assert!(create(" \n").validate().is_err());
+    }
+
+    #[test]
+    fn test_drafts_are_only_visible_to_their_author() {
+        let now = Utc::now();
+        let draft = Post::new(1, "T".into(), "C".into(), 7, now, now);
+
+        assert!(draft.is_visible_to(Some(7)));
+        assert!(!draft.is_visible_to(Some(8)));
+        assert!(!draft.is_visible_to(None));
+        assert!(draft.with_status(PostStatus::Published).is_visible_to(None));
+        assert_eq!("draft".parse::<PostStatus>().unwrap(), PostStatus::Draft);
+        assert!("hidden".parse::<PostStatus>().is_err());
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let normalized = normalize_tags(tags(&[" Rust", "async", "rust", ""]));
+
+        assert_eq!(normalized, tags(&["async", "rust"]));
+    }
+
+    #[test]
+    fn test_tag_filter_modes() {
+        let post_tags = tags(&["async", "rust"]);
+        let all = TagFilter::new(tags(&["rust", "web"]), TagMode::All).unwrap();
+        let any = TagFilter::new(tags(&["rust", "web"]), TagMode::Any).unwrap();
+
+        assert!(!all.matches(&post_tags));
+        assert!(any.matches(&post_tags));
+        assert!(TagFilter::new(tags(&[" "]), TagMode::Any).is_none());
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let at =
+            DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        for key in [
+            CursorKey::CreatedAt(at),
+            CursorKey::UpdatedAt(at),
+            CursorKey::Title("Привет_мир 42".to_string()),
+        ] {
+            let cursor = PostCursor { key, id: 42 };
+
+            assert_eq!(
+                cursor.to_string().parse::<PostCursor>().unwrap(),
+                cursor
+            );
+        }
+        assert_eq!(
+            PostCursor {
+                key: CursorKey::CreatedAt(at),
+                id: 42
+            }
+            .to_string(),
+            "1700000000123456_42"
+        );
+        for invalid in ["42", "x_42", "t4_42", "tzz_42", "tff_42", "u_42"] {
+            assert!(invalid.parse::<PostCursor>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_sort_allow_list() {
+        for sort in PostSort::ALL {
+            assert_eq!(sort.as_str().parse::<PostSort>().unwrap(), sort);
+        }
+        assert!(matches!(
+            "title; DROP TABLE posts".parse::<PostSort>(),
+            Err(DomainError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_tag_mode_rejected() {
+        assert!(matches!(
+            "both".parse::<TagMode>(),
+            Err(DomainError::ValidationError(_))
+        ));
+    }
 }
@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// An issued access token, tracked by its `jti` claim so the owner can
+/// list and revoke it
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub jti: Uuid,
+    pub user_id: i64,
+    /// Device label captured at login, usually the `User-Agent`
+    pub label: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -1,13 +1,91 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::{ValidateEmail, ValidationError};
 
+use super::DomainError;
+use super::error::rule_violation;
 use super::password::Password;
 
+/// Allowed lengths in characters, matching the `users` columns
+const USERNAME_CHARS: RangeInclusive<usize> = 3..=32;
+const MAX_EMAIL_CHARS: usize = 255;
+const PASSWORD_CHARS: RangeInclusive<usize> = 8..=128;
+
+/// What a user may do beyond their own content. Ordered, so a role
+/// includes every role below it.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[default]
+    User,
+    /// May update and delete any post
+    Admin,
+}
+
+impl Role {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Admin => "admin",
+        }
+    }
+
+    /// `Forbidden` unless this role includes `required`
+    pub fn require_role(self, required: Self) -> Result<(), DomainError> {
+        if self >= required {
+            Ok(())
+        } else {
+            Err(DomainError::Forbidden)
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Self::User),
+            "admin" => Ok(Self::Admin),
+            other => Err(DomainError::ValidationError(format!(
+                "unknown role: {other}"
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct User {
     pub id: i64,
     pub username: String,
     pub email: String,
     pub password_hash: Password,
+    pub email_verified: bool,
+    /// Incremented on every password change to revoke older tokens
+    pub password_version: i32,
+    pub role: Role,
     pub created_at: DateTime<Utc>,
 }
 
@@ -24,9 +102,30 @@ impl User {
             username,
             email,
             password_hash,
+            email_verified: false,
+            password_version: 0,
+            role: Role::User,
             created_at,
         }
     }
+
+    pub const fn with_email_verified(mut self, email_verified: bool) -> Self {
+        self.email_verified = email_verified;
+        self
+    }
+
+    pub const fn with_password_version(
+        mut self,
+        password_version: i32,
+    ) -> Self {
+        self.password_version = password_version;
+        self
+    }
+
+    pub const fn with_role(mut self, role: Role) -> Self {
+        self.role = role;
+        self
+    }
 }
 
 /// Domain command for user registration
@@ -35,6 +134,74 @@ pub struct RegisterCommand {
     pub username: String,
     pub email: String,
     pub password: String,
+    /// Client-supplied key marking the request as safe to replay. Only its
+    /// presence matters: it opts in to idempotent handling of retries.
+    pub idempotency_key: Option<String>,
+}
+
+impl RegisterCommand {
+    /// Checks every field and reports all the invalid ones together
+    pub fn validate(&self) -> Result<(), DomainError> {
+        let fields: BTreeMap<String, Vec<String>> = [
+            ("username", validate_username(&self.username)),
+            ("email", validate_email(&self.email)),
+            ("password", validate_password(&self.password)),
+        ]
+        .into_iter()
+        .filter_map(|(field, result)| {
+            let error = result.err()?;
+            Some((field.to_string(), vec![error.to_string()]))
+        })
+        .collect();
+
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(DomainError::InvalidFields(fields))
+        }
+    }
+}
+
+/// Checks a password chosen to replace the current one, reported under
+/// the `new_password` field
+pub fn validate_new_password(password: &str) -> Result<(), DomainError> {
+    validate_password(password).map_err(|error| {
+        DomainError::invalid_field("new_password", error.to_string())
+    })
+}
+
+// Field rules of an account. The request DTOs run the same functions
+// through `#[validate(custom)]`, so both layers accept the same input.
+
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    check_length(username, &USERNAME_CHARS)
+}
+
+pub fn validate_email(email: &str) -> Result<(), ValidationError> {
+    if email.chars().count() <= MAX_EMAIL_CHARS && email.validate_email() {
+        Ok(())
+    } else {
+        Err(rule_violation("must be a valid email address".to_string()))
+    }
+}
+
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    check_length(password, &PASSWORD_CHARS)
+}
+
+fn check_length(
+    value: &str,
+    allowed: &RangeInclusive<usize>,
+) -> Result<(), ValidationError> {
+    if allowed.contains(&value.chars().count()) {
+        Ok(())
+    } else {
+        Err(rule_violation(format!(
+            "must be {} to {} characters",
+            allowed.start(),
+            allowed.end()
+        )))
+    }
 }
 
 /// Domain command for user login
@@ -42,11 +209,99 @@ pub struct RegisterCommand {
 pub struct LoginCommand {
     pub username: String,
     pub password: String,
+    /// Label for the new session, e.g. the client's `User-Agent`
+    pub device: Option<String>,
 }
 
 /// Domain result for successful authentication
 #[derive(Debug, Clone)]
 pub struct AuthResult {
     pub token: String,
+    /// Renews `token` within the same session; `None` when the token
+    /// cannot be renewed, as with impersonation and refreshed tokens
+    pub refresh_token: Option<String>,
     pub user: User,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register(
+        username: &str,
+        email: &str,
+        password: &str,
+    ) -> RegisterCommand {
+        RegisterCommand {
+            username: username.to_string(),
+            email: email.to_string(),
+            password: password.to_string(),
+            idempotency_key: None,
+        }
+    }
+
+    fn is_invalid(command: &RegisterCommand) -> bool {
+        matches!(command.validate(), Err(DomainError::InvalidFields(_)))
+    }
+
+    #[test]
+    fn test_username_boundaries() {
+        let email = "ivan@example.com";
+
+        assert!(is_invalid(&register("iv", email, "secret123")));
+        assert!(register("iva", email, "secret123").validate().is_ok());
+        assert!(
+            register(&"i".repeat(32), email, "secret123")
+                .validate()
+                .is_ok()
+        );
+        assert!(is_invalid(&register(&"i".repeat(33), email, "secret123")));
+    }
+
+    #[test]
+    fn test_password_boundaries() {
+        let email = "ivan@example.com";
+
+        assert!(is_invalid(&register("ivan", email, "secret1")));
+        assert!(register("ivan", email, "secret12").validate().is_ok());
+        assert!(register("ivan", email, &"p".repeat(128)).validate().is_ok());
+        assert!(is_invalid(&register("ivan", email, &"p".repeat(129))));
+    }
+
+    #[test]
+    fn test_admin_includes_user_role() {
+        assert!(Role::Admin.require_role(Role::User).is_ok());
+        assert!(Role::Admin.require_role(Role::Admin).is_ok());
+        assert!(matches!(
+            Role::User.require_role(Role::Admin),
+            Err(DomainError::Forbidden)
+        ));
+        assert_eq!("admin".parse::<Role>().unwrap(), Role::Admin);
+        assert!("root".parse::<Role>().is_err());
+    }
+
+    #[test]
+    fn test_reports_every_invalid_field() {
+        let Err(DomainError::InvalidFields(fields)) =
+            register("iv", "ivan", "secret").validate()
+        else {
+            panic!("expected field errors");
+        };
+
+        assert_eq!(
+            fields.keys().collect::<Vec<_>>(),
+            ["email", "password", "username"]
+        );
+        assert_eq!(fields["password"], ["must be 8 to 128 characters"]);
+    }
+
+    #[test]
+    fn test_malformed_email() {
+        for email in ["", "ivan", "ivan@", "@example.com", "ivan example.com"] {
+            assert!(
+                is_invalid(&register("ivan", email, "secret123")),
+                "{email}"
+            );
+        }
+    }
+}
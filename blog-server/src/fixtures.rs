@@ -0,0 +1,131 @@
+// Builders shared by the unit tests of every layer
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::application::sanitizer::ContentSanitizer;
+use crate::application::{
+    AuthConfig, AuthService, BlogConfig, BlogService, CommentService,
+};
+use crate::data::{
+    InMemoryCommentRepository, InMemoryPostRepository,
+    InMemorySessionRepository, InMemoryUserRepository, PostRepository,
+    UserRepository,
+};
+use crate::domain::{Password, PasswordParams, Post, PostSort, PostStatus};
+use crate::infrastructure::{JwtConfig, JwtService};
+use crate::presentation::{
+    AppState, Features, MaintenanceConfig, MaintenanceMode, PaginationConfig,
+    RegistrationConfig,
+};
+
+/// Registers `name` with an empty password hash and returns its id
+pub async fn create_user(users: &InMemoryUserRepository, name: &str) -> i64 {
+    users
+        .create(
+            name,
+            &format!("{name}@example.com"),
+            &Password::from_hash(String::new()),
+        )
+        .await
+        .unwrap()
+        .id
+}
+
+/// Inserts `username` straight into the `users` table and returns its id
+pub async fn insert_user(pool: &PgPool, username: &str) -> i64 {
+    let row: (i64,) = sqlx::query_as(
+        r"
+        INSERT INTO users (username, email, password_hash)
+        VALUES ($1, $1 || '@example.com', 'hash')
+        RETURNING id
+        ",
+    )
+    .bind(username)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    row.0
+}
+
+/// Blog settings without quota or caching; idempotency keys last an hour
+pub fn blog_config() -> BlogConfig {
+    BlogConfig {
+        max_posts_per_user: 0,
+        count_cache_ttl: Duration::ZERO,
+        require_verified_email: false,
+        sanitizer: ContentSanitizer::default(),
+        idempotency_key_ttl: Duration::from_secs(3600),
+    }
+}
+
+/// App state over in-memory storage holding one published post by
+/// `author`
+pub async fn state_with_post() -> (AppState, Post) {
+    state_with_post_and(blog_config()).await
+}
+
+/// `state_with_post` with its blog service set up by `blog_config`
+pub async fn state_with_post_and(blog_config: BlogConfig) -> (AppState, Post) {
+    let users = Arc::new(InMemoryUserRepository::new());
+    let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+    let author_id = create_user(&users, "author").await;
+    let post = posts
+        .create("Title", "Content", author_id, &[], PostStatus::Published)
+        .await
+        .unwrap();
+
+    let jwt_service = Arc::new(JwtService::new(&JwtConfig {
+        secret: "test-secret-key-minimum-32-characters".to_string(),
+        previous_secrets: Vec::new(),
+        token_expiry_hours: 1,
+        email_verification_expiry_hours: 24,
+        refresh_token_expiry_days: 30,
+        leeway_secs: 60,
+        issuer: "blog-server".to_string(),
+        audience: "blog-api".to_string(),
+        validate_issuer_audience: true,
+    }));
+    let comments = Arc::new(InMemoryCommentRepository::new(users.clone()));
+    let state = AppState {
+        auth_service: Arc::new(AuthService::new(
+            users,
+            Arc::new(InMemorySessionRepository::new()),
+            jwt_service,
+            &AuthConfig {
+                password_version_cache_ttl: Duration::ZERO,
+                impersonation_ttl: Duration::from_mins(15),
+                password_params: PasswordParams::default(),
+                rehash_on_login: true,
+                session_cleanup_interval: Duration::ZERO,
+            },
+        )),
+        blog_service: Arc::new(BlogService::new(posts.clone(), blog_config)),
+        comment_service: Arc::new(CommentService::new(
+            comments,
+            posts,
+            ContentSanitizer::default(),
+        )),
+        pagination_config: PaginationConfig {
+            default_limit: 10,
+            max_limit: 100,
+            excerpt_chars: 200,
+            default_sort: PostSort::default(),
+        },
+        registration_config: RegistrationConfig {
+            require_trusted_origin: true,
+            trusted_origins: vec!["https://blog.example.com".to_string()],
+        },
+        maintenance: MaintenanceMode::new(&MaintenanceConfig {
+            enabled: false,
+            admin_token: Some("admin-secret".to_string()),
+        }),
+        raw_post_max_bytes: 64,
+        features: Features::default(),
+        database: None,
+    };
+    (state, post)
+}
@@ -18,3 +18,20 @@ pub fn env_or<T: FromStr>(key: &str, default: T) -> T {
 pub fn env_required(key: &str) -> String {
     std::env::var(key).unwrap_or_else(|_| panic!("{key} must be set"))
 }
+
+/// Helper to read a comma-separated list from environment variable.
+/// Returns an empty list when the variable is unset.
+pub fn env_list(key: &str) -> Vec<String> {
+    std::env::var(key)
+        .map(|v| split_list(&v))
+        .unwrap_or_default()
+}
+
+/// Splits a comma-separated value, trimming entries and dropping empty ones
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 
@@ -6,38 +8,111 @@ use super::config::{FromEnv, env_or, env_required};
 #[derive(Clone)]
 pub struct DatabaseConfig {
     pub url: String,
+    /// Read replica for read-only queries; reads go to `url` when unset
+    pub replica_url: Option<String>,
     pub max_connections: u32,
+    /// Connections each pool keeps open even when idle
+    pub min_connections: u32,
+    /// How long a query waits for a free connection before failing
+    pub acquire_timeout: Duration,
+    /// Idle connections above `min_connections` close after this long;
+    /// `None` keeps them open
+    pub idle_timeout: Option<Duration>,
 }
 
 impl FromEnv for DatabaseConfig {
     fn from_env() -> Self {
+        let idle_timeout_secs = env_or("DATABASE_IDLE_TIMEOUT_SECS", 600);
+
         Self {
             url: env_required("DATABASE_URL"),
+            replica_url: std::env::var("DATABASE_REPLICA_URL").ok(),
             max_connections: env_or("DATABASE_MAX_CONNECTIONS", 5),
+            min_connections: env_or("DATABASE_MIN_CONNECTIONS", 0),
+            acquire_timeout: Duration::from_secs(env_or(
+                "DATABASE_ACQUIRE_TIMEOUT_SECS",
+                30,
+            )),
+            idle_timeout: (idle_timeout_secs > 0)
+                .then(|| Duration::from_secs(idle_timeout_secs)),
+        }
+    }
+}
+
+/// Connection pools split by purpose. Without a replica both point at the
+/// same primary pool.
+#[derive(Clone)]
+pub struct DbPools {
+    /// Writes and reads that must see them immediately
+    pub primary: PgPool,
+    /// Read-only queries that tolerate replication lag
+    pub replica: PgPool,
+}
+
+impl DbPools {
+    pub fn single(pool: PgPool) -> Self {
+        Self {
+            primary: pool.clone(),
+            replica: pool,
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Database {
-    pool: PgPool,
+    pools: DbPools,
 }
 
 impl Database {
     pub async fn new(config: &DatabaseConfig) -> Result<Self, sqlx::Error> {
-        let pool = PgPoolOptions::new()
+        let primary = Self::connect(&config.url, config).await?;
+
+        let pools = match &config.replica_url {
+            Some(url) => DbPools {
+                primary,
+                replica: Self::connect(url, config).await?,
+            },
+            None => DbPools::single(primary),
+        };
+        Ok(Self { pools })
+    }
+
+    /// Wraps pools made elsewhere, such as lazy ones that never connect
+    #[cfg(test)]
+    pub const fn from_pools(pools: DbPools) -> Self {
+        Self { pools }
+    }
+
+    async fn connect(
+        url: &str,
+        config: &DatabaseConfig,
+    ) -> Result<PgPool, sqlx::Error> {
+        PgPoolOptions::new()
             .max_connections(config.max_connections)
-            .connect(&config.url)
-            .await?;
-        Ok(Self { pool })
+            .min_connections(config.min_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .connect(url)
+            .await
+    }
+
+    /// Pings the primary with `SELECT 1`, returning how long the round
+    /// trip took, including waiting for a free connection
+    pub async fn health_check(&self) -> Result<Duration, sqlx::Error> {
+        let started = Instant::now();
+        sqlx::query("SELECT 1").execute(&self.pools.primary).await?;
+        Ok(started.elapsed())
     }
 
     pub async fn run_migrations(
         &self,
     ) -> Result<(), sqlx::migrate::MigrateError> {
-        sqlx::migrate!("./migrations").run(&self.pool).await
+        sqlx::migrate!("./migrations")
+            .run(&self.pools.primary)
+            .await
     }
 
-    pub const fn pool(&self) -> &PgPool {
-        &self.pool
+    pub const fn pools(&self) -> &DbPools {
+        &self.pools
     }
 }
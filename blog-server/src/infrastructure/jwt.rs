@@ -1,12 +1,14 @@
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{
     DecodingKey, EncodingKey, Header, TokenData, Validation, decode, encode,
+    errors::ErrorKind,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use uuid::Uuid;
 
-use crate::domain::DomainError;
+use crate::domain::{DomainError, Role, User};
 
-use super::config::{FromEnv, env_or, env_required};
+use super::config::{FromEnv, env_list, env_or, env_required};
 
 impl From<jsonwebtoken::errors::Error> for DomainError {
     fn from(err: jsonwebtoken::errors::Error) -> Self {
@@ -17,22 +19,130 @@ impl From<jsonwebtoken::errors::Error> for DomainError {
 #[derive(Clone)]
 pub struct JwtConfig {
     pub secret: String,
+    /// Retired secrets still accepted for verification during rotation
+    pub previous_secrets: Vec<String>,
     pub token_expiry_hours: i64,
+    pub email_verification_expiry_hours: i64,
+    /// Lifetime of refresh tokens, and of the sessions they renew
+    pub refresh_token_expiry_days: i64,
+    /// Clock skew tolerated when checking `exp`, for hosts that drift
+    pub leeway_secs: u64,
+    /// `iss` of issued tokens
+    pub issuer: String,
+    /// `aud` of issued tokens
+    pub audience: String,
+    /// Reject tokens without the configured `iss` and `aud`. Off only while
+    /// tokens issued before they were set are still in use.
+    pub validate_issuer_audience: bool,
 }
 
 impl FromEnv for JwtConfig {
     fn from_env() -> Self {
         Self {
             secret: env_required("JWT_SECRET"),
+            previous_secrets: env_list("JWT_SECRET_PREVIOUS"),
             token_expiry_hours: env_or("JWT_TOKEN_EXPIRY_HOURS", 24),
+            email_verification_expiry_hours: env_or(
+                "EMAIL_VERIFICATION_EXPIRY_HOURS",
+                24,
+            ),
+            refresh_token_expiry_days: env_or(
+                "JWT_REFRESH_TOKEN_EXPIRY_DAYS",
+                30,
+            ),
+            leeway_secs: env_or("JWT_LEEWAY_SECS", 60),
+            issuer: env_or("JWT_ISSUER", "blog-server".to_string()),
+            audience: env_or("JWT_AUDIENCE", "blog-api".to_string()),
+            validate_issuer_audience: env_or(
+                "JWT_VALIDATE_ISSUER_AUDIENCE",
+                true,
+            ),
         }
     }
 }
 
+/// `token_type` claim, so that access and refresh tokens are only
+/// accepted where they belong
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    #[default]
+    Access,
+    Refresh,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub user_id: i64,
     pub username: String,
+    /// Tokens issued before verification existed count as unverified
+    #[serde(default)]
+    pub email_verified: bool,
+    /// Must match the user's current password version to be accepted
+    #[serde(default)]
+    pub password_version: i32,
+    /// Role at issue time; tokens issued before roles existed are `User`
+    #[serde(default)]
+    pub role: Role,
+    /// Admin acting as `user_id`; set only on impersonation tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator_id: Option<i64>,
+    /// Session id; tokens issued before sessions existed have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<Uuid>,
+    /// Tokens issued before refresh tokens existed carry none
+    #[serde(default)]
+    pub token_type: TokenType,
+    /// Tokens issued before issuer checks existed carry none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    pub exp: i64,
+    pub iat: i64,
+}
+
+/// Claims of a refresh token. It renews access tokens for session `jti`
+/// and is useless for anything else.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshClaims {
+    pub user_id: i64,
+    pub password_version: i32,
+    pub jti: Uuid,
+    pub token_type: TokenType,
+    /// Tokens issued before issuer checks existed carry none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    pub exp: i64,
+    pub iat: i64,
+}
+
+/// A freshly signed token and the validity it carries
+#[derive(Debug, Clone)]
+pub struct IssuedToken {
+    pub token: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+const EMAIL_VERIFICATION_PURPOSE: &str = "email_verification";
+
+/// Claims of a one-time email verification token. They share no shape
+/// with access `Claims`, so neither token decodes as the other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailVerificationClaims {
+    pub user_id: i64,
+    pub email: String,
+    pub purpose: String,
+    /// Tokens issued before issuer checks existed carry none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
     pub exp: i64,
     pub iat: i64,
 }
@@ -40,30 +150,175 @@ pub struct Claims {
 #[derive(Clone)]
 pub struct JwtService {
     encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    /// Primary key first, then previous secrets in configured order
+    decoding_keys: Vec<DecodingKey>,
+    validation: Validation,
+    issuer: String,
+    audience: String,
     token_expiry_hours: i64,
+    email_verification_expiry_hours: i64,
+    refresh_token_expiry_days: i64,
 }
 
 impl JwtService {
     pub fn new(config: &JwtConfig) -> Self {
+        let decoding_keys = std::iter::once(&config.secret)
+            .chain(&config.previous_secrets)
+            .map(|secret| DecodingKey::from_secret(secret.as_bytes()))
+            .collect();
+
+        let mut validation = Validation::default();
+        validation.leeway = config.leeway_secs;
+        if config.validate_issuer_audience {
+            validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+            validation.set_issuer(&[&config.issuer]);
+            validation.set_audience(&[&config.audience]);
+        } else {
+            validation.set_required_spec_claims(&["exp"]);
+            // Otherwise any `aud` is rejected as unexpected
+            validation.validate_aud = false;
+        }
+
         Self {
             encoding_key: EncodingKey::from_secret(config.secret.as_bytes()),
-            decoding_key: DecodingKey::from_secret(config.secret.as_bytes()),
+            decoding_keys,
+            validation,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
             token_expiry_hours: config.token_expiry_hours,
+            email_verification_expiry_hours: config
+                .email_verification_expiry_hours,
+            refresh_token_expiry_days: config.refresh_token_expiry_days,
         }
     }
 
+    /// Access token for `user`, bound to session `jti` when given
     pub fn generate_token(
         &self,
-        user_id: i64,
-        username: &str,
+        user: &User,
+        jti: Option<Uuid>,
+    ) -> Result<IssuedToken, DomainError> {
+        self.access_token(
+            user,
+            jti,
+            None,
+            Duration::hours(self.token_expiry_hours),
+        )
+    }
+
+    /// Access token for `user` carrying the admin who requested it, valid
+    /// for `ttl` instead of the regular token lifetime
+    pub fn generate_impersonation_token(
+        &self,
+        user: &User,
+        impersonator_id: i64,
+        ttl: std::time::Duration,
     ) -> Result<String, DomainError> {
+        let ttl = Duration::from_std(ttl)
+            .map_err(|e| DomainError::JwtError(e.to_string()))?;
+        self.access_token(user, None, Some(impersonator_id), ttl)
+            .map(|issued| issued.token)
+    }
+
+    fn access_token(
+        &self,
+        user: &User,
+        jti: Option<Uuid>,
+        impersonator_id: Option<i64>,
+        ttl: Duration,
+    ) -> Result<IssuedToken, DomainError> {
         let now = Utc::now();
-        let exp = now + Duration::hours(self.token_expiry_hours);
+        let exp = now + ttl;
 
         let claims = Claims {
+            user_id: user.id,
+            username: user.username.clone(),
+            email_verified: user.email_verified,
+            password_version: user.password_version,
+            role: user.role,
+            impersonator_id,
+            jti,
+            token_type: TokenType::Access,
+            iss: Some(self.issuer.clone()),
+            aud: Some(self.audience.clone()),
+            exp: exp.timestamp(),
+            iat: now.timestamp(),
+        };
+
+        let token = encode(&Header::default(), &claims, &self.encoding_key)?;
+        Ok(IssuedToken {
+            token,
+            issued_at: now,
+            expires_at: exp,
+        })
+    }
+
+    /// Verifies an access token's signature, expiry, issuer and audience.
+    /// Revocation by password change is checked by
+    /// `AuthService::authenticate`.
+    pub fn verify_token(&self, token: &str) -> Result<Claims, DomainError> {
+        let claims: Claims = self.decode(token)?;
+        if claims.token_type != TokenType::Access {
+            return Err(DomainError::JwtError("Wrong token type".into()));
+        }
+        Ok(claims)
+    }
+
+    /// Long-lived token renewing access tokens of session `jti`
+    pub fn generate_refresh_token(
+        &self,
+        user: &User,
+        jti: Uuid,
+    ) -> Result<IssuedToken, DomainError> {
+        let now = Utc::now();
+        let exp = now + Duration::days(self.refresh_token_expiry_days);
+
+        let claims = RefreshClaims {
+            user_id: user.id,
+            password_version: user.password_version,
+            jti,
+            token_type: TokenType::Refresh,
+            iss: Some(self.issuer.clone()),
+            aud: Some(self.audience.clone()),
+            exp: exp.timestamp(),
+            iat: now.timestamp(),
+        };
+
+        let token = encode(&Header::default(), &claims, &self.encoding_key)?;
+        Ok(IssuedToken {
+            token,
+            issued_at: now,
+            expires_at: exp,
+        })
+    }
+
+    /// Verifies a refresh token's signature, expiry and type. Whether its
+    /// session is still active is checked by `AuthService::refresh`.
+    pub fn verify_refresh_token(
+        &self,
+        token: &str,
+    ) -> Result<RefreshClaims, DomainError> {
+        let claims: RefreshClaims = self.decode(token)?;
+        if claims.token_type != TokenType::Refresh {
+            return Err(DomainError::JwtError("Wrong token type".into()));
+        }
+        Ok(claims)
+    }
+
+    pub fn generate_email_verification_token(
+        &self,
+        user_id: i64,
+        email: &str,
+    ) -> Result<String, DomainError> {
+        let now = Utc::now();
+        let exp = now + Duration::hours(self.email_verification_expiry_hours);
+
+        let claims = EmailVerificationClaims {
             user_id,
-            username: username.to_string(),
+            email: email.to_string(),
+            purpose: EMAIL_VERIFICATION_PURPOSE.to_string(),
+            iss: Some(self.issuer.clone()),
+            aud: Some(self.audience.clone()),
             exp: exp.timestamp(),
             iat: now.timestamp(),
         };
@@ -72,22 +327,67 @@ impl JwtService {
             .map_err(DomainError::from)
     }
 
-    pub fn verify_token(&self, token: &str) -> Result<Claims, DomainError> {
-        let token_data: TokenData<Claims> =
-            decode(token, &self.decoding_key, &Validation::default())?;
+    pub fn verify_email_verification_token(
+        &self,
+        token: &str,
+    ) -> Result<EmailVerificationClaims, DomainError> {
+        let claims: EmailVerificationClaims = self.decode(token)?;
+        if claims.purpose != EMAIL_VERIFICATION_PURPOSE {
+            return Err(DomainError::JwtError("Wrong token purpose".into()));
+        }
+        Ok(claims)
+    }
+
+    /// Verifies a token against the primary key, then the previous keys.
+    /// Only a signature mismatch moves on to the next key; any other
+    /// failure (expired, malformed) is returned immediately.
+    fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<T, DomainError> {
+        let mut last_error: jsonwebtoken::errors::Error =
+            ErrorKind::InvalidSignature.into();
 
-        Ok(token_data.claims)
+        for key in &self.decoding_keys {
+            match decode::<T>(token, key, &self.validation) {
+                Ok(TokenData { claims, .. }) => return Ok(claims),
+                Err(e) if *e.kind() == ErrorKind::InvalidSignature => {
+                    last_error = e;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Err(last_error.into())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::Password;
+
+    fn test_user() -> User {
+        User::new(
+            1,
+            "testuser".to_string(),
+            "test@example.com".to_string(),
+            Password::from_hash(String::new()),
+            Utc::now(),
+        )
+    }
 
     fn test_config() -> JwtConfig {
         JwtConfig {
             secret: "test-secret-key-that-is-at-least-32-chars".to_string(),
+            previous_secrets: Vec::new(),
             token_expiry_hours: 24,
+            email_verification_expiry_hours: 24,
+            refresh_token_expiry_days: 30,
+            leeway_secs: 60,
+            issuer: "blog-server".to_string(),
+            audience: "blog-api".to_string(),
+            validate_issuer_audience: true,
         }
     }
 
@@ -95,13 +395,88 @@ mod tests {
     fn test_generate_and_verify_token() {
         let jwt_service = JwtService::new(&test_config());
 
-        let token = jwt_service.generate_token(1, "testuser").unwrap();
+        let token = jwt_service
+            .generate_token(&test_user(), None)
+            .unwrap()
+            .token;
         let claims = jwt_service.verify_token(&token).unwrap();
 
         assert_eq!(claims.user_id, 1);
         assert_eq!(claims.username, "testuser");
     }
 
+    fn token_expired_secs_ago(secs: i64) -> String {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            user_id: 1,
+            username: "testuser".to_string(),
+            email_verified: false,
+            password_version: 0,
+            role: Role::User,
+            impersonator_id: None,
+            jti: None,
+            token_type: TokenType::Access,
+            iss: Some(test_config().issuer),
+            aud: Some(test_config().audience),
+            exp: now - secs,
+            iat: now - 3600,
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(test_config().secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_recently_expired_token_verifies_within_leeway() {
+        let jwt_service = JwtService::new(&test_config());
+
+        assert!(
+            jwt_service
+                .verify_token(&token_expired_secs_ago(30))
+                .is_ok()
+        );
+        assert!(
+            jwt_service
+                .verify_token(&token_expired_secs_ago(120))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_zero_leeway_rejects_expired_token() {
+        let jwt_service = JwtService::new(&JwtConfig {
+            leeway_secs: 0,
+            ..test_config()
+        });
+
+        assert!(
+            jwt_service
+                .verify_token(&token_expired_secs_ago(30))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_impersonation_token_carries_impersonator() {
+        let jwt_service = JwtService::new(&test_config());
+
+        let token = jwt_service
+            .generate_impersonation_token(
+                &test_user(),
+                42,
+                std::time::Duration::from_mins(15),
+            )
+            .unwrap();
+        let claims = jwt_service.verify_token(&token).unwrap();
+
+        assert_eq!(claims.user_id, 1);
+        assert_eq!(claims.impersonator_id, Some(42));
+        assert!(claims.exp - claims.iat <= 900);
+    }
+
     #[test]
     fn test_invalid_token() {
         let jwt_service = JwtService::new(&test_config());
@@ -109,4 +484,175 @@ mod tests {
         let result = jwt_service.verify_token("invalid-token");
         assert!(result.is_err());
     }
+
+    fn rotated_config() -> JwtConfig {
+        JwtConfig {
+            secret: "new-secret-key-that-is-at-least-32-chars".to_string(),
+            previous_secrets: vec![test_config().secret],
+            token_expiry_hours: 24,
+            email_verification_expiry_hours: 24,
+            refresh_token_expiry_days: 30,
+            leeway_secs: 60,
+            issuer: "blog-server".to_string(),
+            audience: "blog-api".to_string(),
+            validate_issuer_audience: true,
+        }
+    }
+
+    #[test]
+    fn test_token_signed_with_previous_secret_verifies() {
+        let old_service = JwtService::new(&test_config());
+        let old_token = old_service
+            .generate_token(&test_user(), None)
+            .unwrap()
+            .token;
+
+        let rotated = JwtService::new(&rotated_config());
+
+        let claims = rotated.verify_token(&old_token).unwrap();
+        assert_eq!(claims.user_id, 1);
+    }
+
+    #[test]
+    fn test_new_tokens_are_signed_with_primary_secret() {
+        let rotated = JwtService::new(&rotated_config());
+        let token = rotated.generate_token(&test_user(), None).unwrap().token;
+
+        let old_only = JwtService::new(&test_config());
+        assert!(old_only.verify_token(&token).is_err());
+
+        let primary_only = JwtService::new(&JwtConfig {
+            previous_secrets: Vec::new(),
+            ..rotated_config()
+        });
+        assert!(primary_only.verify_token(&token).is_ok());
+    }
+
+    #[test]
+    fn test_verification_and_access_tokens_are_not_interchangeable() {
+        let jwt_service = JwtService::new(&test_config());
+
+        let access = jwt_service
+            .generate_token(&test_user(), None)
+            .unwrap()
+            .token;
+        let verification = jwt_service
+            .generate_email_verification_token(1, "test@example.com")
+            .unwrap();
+
+        let claims = jwt_service
+            .verify_email_verification_token(&verification)
+            .unwrap();
+        assert_eq!(claims.email, "test@example.com");
+        assert!(
+            jwt_service
+                .verify_email_verification_token(&access)
+                .is_err()
+        );
+        assert!(jwt_service.verify_token(&verification).is_err());
+    }
+
+    #[test]
+    fn test_refresh_and_access_tokens_are_not_interchangeable() {
+        let jwt_service = JwtService::new(&test_config());
+        let jti = Uuid::new_v4();
+
+        let access = jwt_service
+            .generate_token(&test_user(), Some(jti))
+            .unwrap()
+            .token;
+        let refresh = jwt_service
+            .generate_refresh_token(&test_user(), jti)
+            .unwrap();
+
+        let claims = jwt_service.verify_refresh_token(&refresh.token).unwrap();
+        assert_eq!(claims.jti, jti);
+        assert!(refresh.expires_at - refresh.issued_at >= Duration::days(30));
+        assert!(jwt_service.verify_refresh_token(&access).is_err());
+        assert!(jwt_service.verify_token(&refresh.token).is_err());
+    }
+
+    #[test]
+    fn test_access_claims_with_refresh_type_are_rejected() {
+        let jwt_service = JwtService::new(&test_config());
+        let mut claims = jwt_service
+            .verify_token(
+                &jwt_service
+                    .generate_token(&test_user(), None)
+                    .unwrap()
+                    .token,
+            )
+            .unwrap();
+        claims.token_type = TokenType::Refresh;
+        let relabeled = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(test_config().secret.as_bytes()),
+        )
+        .unwrap();
+
+        assert!(jwt_service.verify_token(&relabeled).is_err());
+    }
+
+    /// Token for `test_user` from a service with `issuer` and `audience`
+    fn token_from(issuer: &str, audience: &str) -> String {
+        JwtService::new(&JwtConfig {
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            ..test_config()
+        })
+        .generate_token(&test_user(), None)
+        .unwrap()
+        .token
+    }
+
+    #[test]
+    fn test_token_from_another_issuer_is_rejected() {
+        let jwt_service = JwtService::new(&test_config());
+
+        let result = jwt_service.verify_token(&token_from("other", "blog-api"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_token_for_another_audience_is_rejected() {
+        let jwt_service = JwtService::new(&test_config());
+
+        let result =
+            jwt_service.verify_token(&token_from("blog-server", "other"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_tokens_without_issuer_pass_only_with_the_check_off() {
+        let mut claims = JwtService::new(&test_config())
+            .verify_token(&token_from("blog-server", "blog-api"))
+            .unwrap();
+        claims.iss = None;
+        claims.aud = None;
+        let legacy = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(test_config().secret.as_bytes()),
+        )
+        .unwrap();
+        let migrating = JwtService::new(&JwtConfig {
+            validate_issuer_audience: false,
+            ..test_config()
+        });
+
+        assert!(
+            JwtService::new(&test_config())
+                .verify_token(&legacy)
+                .is_err()
+        );
+        assert!(migrating.verify_token(&legacy).is_ok());
+        assert!(
+            migrating
+                .verify_token(&token_from("blog-server", "blog-api"))
+                .is_ok()
+        );
+    }
 }
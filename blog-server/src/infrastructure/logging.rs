@@ -0,0 +1,35 @@
+use std::str::FromStr;
+
+use super::config::{FromEnv, env_or};
+
+/// How log lines are written to stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One compact line per event, for terminals
+    #[default]
+    Text,
+    /// Multi-line, indented events; easiest to read while developing
+    Pretty,
+    /// One JSON object per event with the fields at the top level and the
+    /// enclosing span's fields, such as `request_id`, under `span`
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            other => Err(format!("unknown log format: {other}")),
+        }
+    }
+}
+
+impl FromEnv for LogFormat {
+    fn from_env() -> Self {
+        env_or("LOG_FORMAT", Self::default())
+    }
+}
@@ -3,7 +3,15 @@
 pub mod config;
 pub mod database;
 pub mod jwt;
+pub mod logging;
+pub mod pwned_passwords;
+pub mod webhook;
 
 pub use config::FromEnv;
-pub use database::{Database, DatabaseConfig};
-pub use jwt::{JwtConfig, JwtService};
+pub use database::{Database, DatabaseConfig, DbPools};
+pub use jwt::{Claims, JwtConfig, JwtService};
+pub use logging::LogFormat;
+pub use pwned_passwords::{
+    HibpRangeClient, PwnedPasswordCheck, PwnedPasswordsConfig,
+};
+pub use webhook::{WebhookConfig, WebhookDispatcher};
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use sha1::{Digest, Sha1};
+
+use super::config::{FromEnv, env_or};
+
+#[derive(Clone)]
+pub struct PwnedPasswordsConfig {
+    /// Reject passwords found in the Have I Been Pwned breach corpus
+    pub enabled: bool,
+    /// Range endpoint; the 5-character hash prefix is appended
+    pub api_url: String,
+    /// Lookups slower than this are skipped and the password allowed
+    pub timeout: Duration,
+}
+
+impl FromEnv for PwnedPasswordsConfig {
+    fn from_env() -> Self {
+        Self {
+            enabled: env_or("PWNED_PASSWORD_CHECK", false),
+            api_url: env_or(
+                "PWNED_PASSWORDS_API_URL",
+                "https://api.pwnedpasswords.com/range/".to_string(),
+            ),
+            timeout: Duration::from_millis(env_or(
+                "PWNED_PASSWORDS_TIMEOUT_MS",
+                2000,
+            )),
+        }
+    }
+}
+
+/// Source of k-anonymity range responses: every breached SHA-1 hash
+/// starting with a 5-character prefix, as `SUFFIX:COUNT` lines
+// async_trait marks the boxed futures #[must_use] on top of Result
+#[allow(clippy::double_must_use)]
+#[async_trait]
+pub trait PwnedRange: Send + Sync {
+    async fn range(&self, prefix: &str) -> anyhow::Result<String>;
+}
+
+/// The public Have I Been Pwned range API
+pub struct HibpRangeClient {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl HibpRangeClient {
+    pub fn new(config: &PwnedPasswordsConfig) -> reqwest::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .user_agent("blog-server")
+            .build()?;
+
+        Ok(Self {
+            client,
+            api_url: config.api_url.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl PwnedRange for HibpRangeClient {
+    async fn range(&self, prefix: &str) -> anyhow::Result<String> {
+        // Padding hides the real result size from on-path observers
+        let body = self
+            .client
+            .get(format!("{}{prefix}", self.api_url))
+            .header("Add-Padding", "true")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(body)
+    }
+}
+
+/// Checks passwords against breach data without sending them anywhere:
+/// only the first 5 hex characters of the SHA-1 hash leave the server.
+pub struct PwnedPasswordCheck {
+    source: Arc<dyn PwnedRange>,
+    timeout: Duration,
+}
+
+impl PwnedPasswordCheck {
+    pub fn new(source: Arc<dyn PwnedRange>, timeout: Duration) -> Self {
+        Self { source, timeout }
+    }
+
+    /// Whether the password appears in a known breach. Fails open: if
+    /// the lookup errors or times out the password counts as clean, so
+    /// an outage of the breach service does not block signups.
+    pub async fn is_pwned(&self, password: &str) -> bool {
+        let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(5);
+
+        let body =
+            match tokio::time::timeout(self.timeout, self.source.range(prefix))
+                .await
+            {
+                Ok(Ok(body)) => body,
+                Ok(Err(e)) => {
+                    tracing::warn!(
+                        "Pwned password lookup failed, allowing password: {e}"
+                    );
+                    return false;
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        "Pwned password lookup timed out, allowing password"
+                    );
+                    return false;
+                }
+            };
+
+        // Padding entries carry a count of 0
+        body.lines()
+            .filter_map(|line| line.trim().split_once(':'))
+            .any(|(candidate, count)| {
+                candidate.eq_ignore_ascii_case(suffix) && count != "0"
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// SHA-1 of "password" is 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+    const PASSWORD_SUFFIX: &str = "1E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    struct MockRange {
+        body: anyhow::Result<String>,
+        requested: Mutex<Vec<String>>,
+    }
+
+    impl MockRange {
+        fn new(body: anyhow::Result<String>) -> Arc<Self> {
+            Arc::new(Self {
+                body,
+                requested: Mutex::default(),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl PwnedRange for MockRange {
+        async fn range(&self, prefix: &str) -> anyhow::Result<String> {
+            self.requested.lock().unwrap().push(prefix.to_string());
+            match &self.body {
+                Ok(body) => Ok(body.clone()),
+                Err(e) => Err(anyhow::anyhow!("{e}")),
+            }
+        }
+    }
+
+    fn check(source: Arc<MockRange>) -> PwnedPasswordCheck {
+        PwnedPasswordCheck::new(source, Duration::from_secs(1))
+    }
+
+    #[tokio::test]
+    async fn test_breached_password_detected_by_prefix() {
+        let source = MockRange::new(Ok(format!(
+            "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n{PASSWORD_SUFFIX}:9545824"
+        )));
+
+        assert!(check(source.clone()).is_pwned("password").await);
+        assert_eq!(*source.requested.lock().unwrap(), ["5BAA6"]);
+    }
+
+    #[tokio::test]
+    async fn test_padding_and_other_hashes_are_clean() {
+        let source = MockRange::new(Ok(format!(
+            "{PASSWORD_SUFFIX}:0\r\n0018A45C4D1DEF81644B54AB7F969B88D65:1"
+        )));
+
+        assert!(!check(source).is_pwned("password").await);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_failure_fails_open() {
+        let source = MockRange::new(Err(anyhow::anyhow!("unreachable")));
+
+        assert!(!check(source).is_pwned("password").await);
+    }
+}
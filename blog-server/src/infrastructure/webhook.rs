@@ -0,0 +1,321 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use super::config::{FromEnv, env_or, env_required};
+use crate::domain::Post;
+
+#[derive(Clone)]
+pub struct WebhookConfig {
+    /// Receiver of `post.created` events; `None` disables webhooks
+    pub url: Option<String>,
+    /// Shared secret for the signature header, required with `url`
+    pub secret: String,
+    /// Per-attempt request timeout
+    pub timeout: Duration,
+    /// Attempts after the first failed one
+    pub max_retries: u32,
+}
+
+impl FromEnv for WebhookConfig {
+    fn from_env() -> Self {
+        let url = std::env::var("WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.is_empty());
+        let secret = if url.is_some() {
+            env_required("WEBHOOK_SECRET")
+        } else {
+            String::new()
+        };
+
+        Self {
+            url,
+            secret,
+            timeout: Duration::from_millis(env_or("WEBHOOK_TIMEOUT_MS", 5000)),
+            max_retries: env_or("WEBHOOK_MAX_RETRIES", 3),
+        }
+    }
+}
+
+/// `sha256=<hex HMAC of the raw body>`, keyed with `WEBHOOK_SECRET`
+pub const SIGNATURE_HEADER: &str = "x-blog-signature";
+pub const EVENT_HEADER: &str = "x-blog-event";
+
+const POST_CREATED: &str = "post.created";
+
+/// Delay before the first retry, doubled for each one after it
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    event: &'static str,
+    post: PostPayload<'a>,
+}
+
+#[derive(Serialize)]
+struct PostPayload<'a> {
+    id: i64,
+    title: &'a str,
+    content: &'a str,
+    author_id: i64,
+    author_username: Option<&'a str>,
+    tags: &'a [String],
+    created_at: DateTime<Utc>,
+}
+
+impl<'a> From<&'a Post> for PostPayload<'a> {
+    fn from(post: &'a Post) -> Self {
+        Self {
+            id: post.id,
+            title: &post.title,
+            content: &post.content,
+            author_id: post.author_id,
+            author_username: post.author_username.as_deref(),
+            tags: &post.tags,
+            created_at: post.created_at,
+        }
+    }
+}
+
+/// Posts new posts to the configured webhook in the background. Failed
+/// deliveries are retried with backoff and then logged; API callers never
+/// see them.
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+impl WebhookDispatcher {
+    pub fn new(url: String, config: &WebhookConfig) -> reqwest::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .user_agent("blog-server")
+            .build()?;
+
+        Ok(Self {
+            client,
+            url,
+            secret: config.secret.clone(),
+            max_retries: config.max_retries,
+            retry_delay: RETRY_BASE_DELAY,
+        })
+    }
+
+    /// Delivers every post from `events` until the channel closes. Each
+    /// delivery gets its own task, so a slow receiver only delays itself.
+    pub fn spawn(
+        self,
+        mut events: broadcast::Receiver<Post>,
+    ) -> JoinHandle<()> {
+        let dispatcher = Arc::new(self);
+
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(post) => {
+                        let dispatcher = dispatcher.clone();
+                        tokio::spawn(
+                            async move { dispatcher.deliver(&post).await },
+                        );
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            "Webhook dispatcher lagged, skipped {skipped} posts"
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    async fn deliver(&self, post: &Post) {
+        let payload = WebhookPayload {
+            event: POST_CREATED,
+            post: PostPayload::from(post),
+        };
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!("Failed to serialize webhook payload: {e}");
+                return;
+            }
+        };
+
+        if !self.send_with_retries(post.id, body).await {
+            tracing::error!(
+                "Giving up on webhook delivery of post {} after {} attempts",
+                post.id,
+                self.max_retries + 1
+            );
+        }
+    }
+
+    /// Whether any attempt was accepted
+    async fn send_with_retries(&self, post_id: i64, body: Vec<u8>) -> bool {
+        let signature = sign(&self.secret, &body);
+        let mut delay = self.retry_delay;
+
+        for attempt in 0..=self.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+
+            match self.send(body.clone(), &signature).await {
+                Ok(()) => return true,
+                Err(e) => tracing::warn!(
+                    "Webhook delivery of post {post_id} failed (attempt {}): {e}",
+                    attempt + 1
+                ),
+            }
+        }
+
+        false
+    }
+
+    async fn send(
+        &self,
+        body: Vec<u8>,
+        signature: &str,
+    ) -> reqwest::Result<()> {
+        self.client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, POST_CREATED)
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Value of [`SIGNATURE_HEADER`] for `body`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{
+        Router, body::Bytes, extract::State, http::HeaderMap, http::StatusCode,
+        routing::post,
+    };
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Receiver {
+        /// Requests answered with 500 before accepting
+        failures: usize,
+        hits: Arc<AtomicUsize>,
+        last: Arc<Mutex<Option<(HeaderMap, Bytes)>>>,
+    }
+
+    async fn receive(
+        State(receiver): State<Receiver>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        let hit = receiver.hits.fetch_add(1, Ordering::SeqCst);
+        *receiver.last.lock().unwrap() = Some((headers, body));
+        if hit < receiver.failures {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    async fn serve(receiver: Receiver) -> String {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}/hook")
+    }
+
+    fn dispatcher(url: String, max_retries: u32) -> WebhookDispatcher {
+        let config = WebhookConfig {
+            url: None,
+            secret: "webhook-secret".to_string(),
+            timeout: Duration::from_secs(5),
+            max_retries,
+        };
+        let mut dispatcher = WebhookDispatcher::new(url, &config).unwrap();
+        dispatcher.retry_delay = Duration::ZERO;
+        dispatcher
+    }
+
+    fn new_post() -> Post {
+        let now = Utc::now();
+        Post::new(7, "Title".into(), "Content".into(), 1, now, now)
+            .with_author_username("author".into())
+    }
+
+    #[tokio::test]
+    async fn test_delivers_signed_payload_after_retries() {
+        let receiver = Receiver {
+            failures: 2,
+            ..Receiver::default()
+        };
+        let url = serve(receiver.clone()).await;
+
+        dispatcher(url, 3).deliver(&new_post()).await;
+
+        assert_eq!(receiver.hits.load(Ordering::SeqCst), 3);
+        let (headers, body) = receiver.last.lock().unwrap().take().unwrap();
+        assert_eq!(headers[EVENT_HEADER], POST_CREATED);
+        assert_eq!(
+            headers[SIGNATURE_HEADER],
+            sign("webhook-secret", &body).as_str()
+        );
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["event"], POST_CREATED);
+        assert_eq!(json["post"]["id"], 7);
+        assert_eq!(json["post"]["author_username"], "author");
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let receiver = Receiver {
+            failures: usize::MAX,
+            ..Receiver::default()
+        };
+        let url = serve(receiver.clone()).await;
+
+        dispatcher(url, 2).deliver(&new_post()).await;
+
+        assert_eq!(receiver.hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_signature_depends_on_secret_and_body() {
+        let signature = sign("secret", b"{}");
+
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_ne!(signature, sign("other", b"{}"));
+        assert_ne!(signature, sign("secret", b"[]"));
+    }
+}
@@ -1,22 +1,45 @@
 mod application;
 mod data;
 mod domain;
+#[cfg(test)]
+mod fixtures;
 mod infrastructure;
 mod presentation;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tokio::sync::watch;
+use tokio::task::{JoinError, JoinHandle};
+use tracing_subscriber::{
+    Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt,
+};
 
-use crate::application::{AuthService, BlogService};
-use crate::data::{PostgresPostRepository, PostgresUserRepository};
+use crate::application::{
+    AuthConfig, AuthService, BlogConfig, BlogService, CommentService,
+};
+use crate::data::{
+    CommentRepository, InMemoryCommentRepository, InMemoryPostRepository,
+    InMemorySessionRepository, InMemoryUserRepository, PostRepository,
+    PostgresCommentRepository, PostgresPostRepository,
+    PostgresSessionRepository, PostgresUserRepository, QueryLog,
+    SessionRepository, StorageBackend, UserRepository,
+};
 use crate::infrastructure::{
-    Database, DatabaseConfig, FromEnv, JwtConfig, JwtService,
+    Database, DatabaseConfig, FromEnv, HibpRangeClient, JwtConfig, JwtService,
+    LogFormat, PwnedPasswordCheck, PwnedPasswordsConfig, WebhookConfig,
+    WebhookDispatcher,
 };
 use crate::presentation::{
-    AppState, BlogGrpcService, CorsConfig, PaginationConfig, ServerConfig,
-    proto::blog_service_server::BlogServiceServer, router,
+    AppState, BlogGrpcService, CorsConfig, Features, InFlight,
+    MaintenanceConfig, MaintenanceMode, Metrics, MetricsConfig,
+    PaginationConfig, RegistrationConfig, ServerConfig,
+    proto::{self, blog_service_server::BlogServiceServer},
+    request_id::{grpc_classifier, request_id_layers},
+    router,
+    shutdown::stopped,
+    shutdown_signal,
 };
 
 #[tokio::main]
@@ -24,120 +47,302 @@ async fn main() -> Result<()> {
     // Load environment variables first so .env values are available everywhere
     dotenvy::dotenv().ok();
 
-    // Initialize tracing (reads RUST_LOG from env)
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    init_tracing();
 
     tracing::info!("Starting blog server...");
 
     // Load configuration from environment
-    let db_config = DatabaseConfig::from_env();
     let jwt_config = JwtConfig::from_env();
+    let auth_config = AuthConfig::from_env();
+    let pwned_passwords_config = PwnedPasswordsConfig::from_env();
     let server_config = ServerConfig::from_env();
     let cors_config = CorsConfig::from_env();
     let pagination_config = PaginationConfig::from_env();
+    let registration_config = RegistrationConfig::from_env();
+    let maintenance_config = MaintenanceConfig::from_env();
+    let features = Features::from_env();
+    let blog_config = BlogConfig::from_env();
+    let webhook_config = WebhookConfig::from_env();
+    let storage_backend = StorageBackend::from_env();
+    let metrics_config = MetricsConfig::from_env();
 
-    // Create database connection
-    tracing::info!("Connecting to database...");
-    let database = Database::new(&db_config).await?;
+    // Initialize services
+    let jwt_service = Arc::new(JwtService::new(&jwt_config));
+    let repositories = create_repositories(storage_backend).await?;
+    let metrics =
+        install_metrics(metrics_config, repositories.database.as_ref())?;
 
-    // Run migrations
-    tracing::info!("Running migrations...");
-    database.run_migrations().await?;
+    let auth_service = Arc::new(create_auth_service(
+        AuthService::new(
+            repositories.users,
+            repositories.sessions,
+            jwt_service,
+            &auth_config,
+        ),
+        &pwned_passwords_config,
+    )?);
+    auth_service.spawn_session_cleanup(auth_config.session_cleanup_interval);
+    let comment_service = Arc::new(CommentService::new(
+        repositories.comments,
+        repositories.posts.clone(),
+        blog_config.sanitizer.clone(),
+    ));
+    let blog_service =
+        Arc::new(BlogService::new(repositories.posts, blog_config));
 
-    let pool = database.pool().clone();
+    if let Some(url) = webhook_config.url.clone() {
+        tracing::info!("Sending post.created webhooks to {}", url);
+        WebhookDispatcher::new(url, &webhook_config)?
+            .spawn(blog_service.subscribe());
+    }
 
-    // Initialize services
-    let jwt_service = Arc::new(JwtService::new(&jwt_config));
-    let user_repository = Arc::new(PostgresUserRepository::new(pool.clone()));
-    let post_repository = Arc::new(PostgresPostRepository::new(pool.clone()));
+    let maintenance = MaintenanceMode::new(&maintenance_config);
+    if maintenance.is_enabled() {
+        tracing::warn!("Starting in maintenance mode; writes are disabled");
+    }
 
-    let auth_service =
-        Arc::new(AuthService::new(user_repository, jwt_service.clone()));
-    let blog_service = Arc::new(BlogService::new(post_repository));
+    let state = AppState {
+        auth_service: auth_service.clone(),
+        blog_service: blog_service.clone(),
+        comment_service: comment_service.clone(),
+        pagination_config: pagination_config.clone(),
+        registration_config,
+        maintenance: maintenance.clone(),
+        raw_post_max_bytes: server_config.raw_post_max_bytes,
+        features,
+        database: repositories.database.clone(),
+    };
 
-    // Start HTTP and gRPC servers
+    // Start HTTP and gRPC servers, both stopping on the same signal
+    let (shutdown, _) = watch::channel(false);
+    let in_flight = InFlight::default();
     let http_handle = tokio::spawn(run_http_server(
-        auth_service.clone(),
-        blog_service.clone(),
-        jwt_service.clone(),
-        server_config,
+        state,
+        server_config.clone(),
         cors_config,
-        pagination_config.clone(),
+        metrics,
+        Lifecycle {
+            shutdown: shutdown.subscribe(),
+            in_flight: in_flight.clone(),
+        },
     ));
 
-    let grpc_handle = tokio::spawn(run_grpc_server(
+    let grpc_service = BlogGrpcService::new(
         auth_service,
         blog_service,
-        jwt_service,
-        server_config,
+        comment_service,
         pagination_config,
+        maintenance,
+    )
+    .with_features(features)
+    .with_database(repositories.database);
+    let shutdown_timeout = server_config.shutdown_timeout;
+    let grpc_handle = tokio::spawn(run_grpc_server(
+        grpc_service,
+        server_config,
+        Lifecycle {
+            shutdown: shutdown.subscribe(),
+            in_flight: in_flight.clone(),
+        },
     ));
 
-    // Wait for both servers
+    supervise(
+        http_handle,
+        grpc_handle,
+        &shutdown,
+        &in_flight,
+        shutdown_timeout,
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Logs to stdout, filtered by `RUST_LOG` and formatted per `LOG_FORMAT`
+fn init_tracing() {
+    let fmt_layer = match LogFormat::from_env() {
+        LogFormat::Text => fmt::layer().boxed(),
+        LogFormat::Pretty => fmt::layer().pretty().boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
+        ))
+        .with(fmt_layer)
+        .init();
+}
+
+/// Adds the optional password checks `AuthService::new` leaves out
+fn create_auth_service(
+    auth_service: AuthService,
+    pwned_passwords_config: &PwnedPasswordsConfig,
+) -> Result<AuthService> {
+    if !pwned_passwords_config.enabled {
+        return Ok(auth_service);
+    }
+
+    let source = HibpRangeClient::new(pwned_passwords_config)?;
+    Ok(auth_service.with_pwned_password_check(PwnedPasswordCheck::new(
+        Arc::new(source),
+        pwned_passwords_config.timeout,
+    )))
+}
+
+/// Installs the Prometheus recorder unless metrics are disabled
+fn install_metrics(
+    metrics_config: MetricsConfig,
+    database: Option<&Database>,
+) -> Result<Option<Metrics>> {
+    if !metrics_config.enabled {
+        return Ok(None);
+    }
+
+    tracing::info!("Serving Prometheus metrics at /metrics");
+    let pools = database.map(|db| db.pools().clone());
+    Ok(Some(Metrics::install(pools)?))
+}
+
+/// Runs until a shutdown signal arrives or either server stops on its
+/// own. After a signal both servers drain, for at most `drain_timeout`.
+async fn supervise(
+    mut http_handle: JoinHandle<Result<()>>,
+    mut grpc_handle: JoinHandle<Result<()>>,
+    shutdown: &watch::Sender<bool>,
+    in_flight: &InFlight,
+    drain_timeout: Duration,
+) {
+    if !shutdown_requested(&mut http_handle, &mut grpc_handle).await {
+        return;
+    }
+
+    tracing::info!(
+        in_flight = in_flight.count(),
+        "Shutdown signal received, draining in-flight requests"
+    );
+    shutdown.send_replace(true);
+
+    let servers = async {
+        let _ = tokio::join!(http_handle, grpc_handle);
+    };
+    if tokio::time::timeout(drain_timeout, servers).await.is_err() {
+        log_drain_timeout(in_flight);
+    } else {
+        tracing::info!("Servers stopped");
+    }
+}
+
+fn log_drain_timeout(in_flight: &InFlight) {
+    tracing::warn!(
+        in_flight = in_flight.count(),
+        "Shutdown timed out, dropping remaining requests"
+    );
+}
+
+/// Whether a shutdown signal arrived, as opposed to a server stopping
+/// on its own first
+async fn shutdown_requested(
+    http_handle: &mut JoinHandle<Result<()>>,
+    grpc_handle: &mut JoinHandle<Result<()>>,
+) -> bool {
     tokio::select! {
+        () = shutdown_signal() => true,
         result = http_handle => {
-            if let Err(e) = result {
-                tracing::error!("HTTP server error: {}", e);
-            }
+            log_server_exit("HTTP", result);
+            false
         }
         result = grpc_handle => {
-            if let Err(e) = result {
-                tracing::error!("gRPC server error: {}", e);
-            }
+            log_server_exit("gRPC", result);
+            false
         }
     }
+}
 
-    Ok(())
+fn log_server_exit(server: &str, result: Result<Result<()>, JoinError>) {
+    match result {
+        Ok(Ok(())) => tracing::warn!("{server} server stopped"),
+        Ok(Err(e)) => tracing::error!("{server} server error: {e}"),
+        Err(e) => tracing::error!("{server} server task failed: {e}"),
+    }
+}
+
+/// Shutdown wiring shared by both servers
+struct Lifecycle {
+    /// Flips to true when the servers should drain and stop
+    shutdown: watch::Receiver<bool>,
+    in_flight: InFlight,
+}
+
+struct Repositories {
+    users: Arc<dyn UserRepository>,
+    posts: Arc<dyn PostRepository>,
+    comments: Arc<dyn CommentRepository>,
+    sessions: Arc<dyn SessionRepository>,
+    /// Backing the Postgres repositories, for the metrics gauges and the
+    /// health check
+    database: Option<Database>,
+}
+
+async fn create_repositories(backend: StorageBackend) -> Result<Repositories> {
+    if backend == StorageBackend::Memory {
+        tracing::warn!("Using in-memory storage; data is lost on restart");
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let comments = Arc::new(InMemoryCommentRepository::new(users.clone()));
+        return Ok(Repositories {
+            users,
+            posts,
+            comments,
+            sessions: Arc::new(InMemorySessionRepository::new()),
+            database: None,
+        });
+    }
+
+    let db_config = DatabaseConfig::from_env();
+    let query_log = QueryLog::from_env();
+
+    // Create database connection
+    tracing::info!("Connecting to database...");
+    let database = Database::new(&db_config).await?;
+
+    // Run migrations
+    tracing::info!("Running migrations...");
+    database.run_migrations().await?;
+
+    let pools = database.pools().clone();
+    Ok(Repositories {
+        users: Arc::new(PostgresUserRepository::new(pools.clone(), query_log)),
+        posts: Arc::new(PostgresPostRepository::new(pools.clone(), query_log)),
+        comments: Arc::new(PostgresCommentRepository::new(
+            pools.clone(),
+            query_log,
+        )),
+        sessions: Arc::new(PostgresSessionRepository::new(pools, query_log)),
+        database: Some(database),
+    })
 }
 
 async fn run_http_server(
-    auth_service: Arc<AuthService>,
-    blog_service: Arc<BlogService>,
-    jwt_service: Arc<JwtService>,
+    state: AppState,
     server_config: ServerConfig,
     cors_config: CorsConfig,
-    pagination_config: PaginationConfig,
+    metrics: Option<Metrics>,
+    lifecycle: Lifecycle,
 ) -> Result<()> {
     use axum::Extension;
-    use axum::http::{HeaderValue, Method};
     use std::net::SocketAddr;
-    use std::time::Duration;
-    use tower_http::cors::{AllowOrigin, Any, CorsLayer};
-
-    let origins: Vec<HeaderValue> = cors_config
-        .allowed_origins
-        .iter()
-        .filter_map(|o| o.parse().ok())
-        .collect();
-
-    let cors = CorsLayer::new()
-        .allow_origin(AllowOrigin::list(origins))
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers(Any)
-        .max_age(Duration::from_secs(cors_config.max_age_secs));
-
-    let state = AppState {
-        auth_service,
-        blog_service,
-        pagination_config,
-    };
 
+    let auth_service = state.auth_service.clone();
     let addr = server_config.http_addr();
 
-    let app = router(state, server_config)
-        .layer(Extension(jwt_service))
-        .layer(cors);
+    let app = router(state, &server_config, &cors_config, metrics)
+        .layer(Extension(auth_service))
+        .layer(lifecycle.in_flight);
     tracing::info!("HTTP server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -145,33 +350,48 @@ async fn run_http_server(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
     )
+    .with_graceful_shutdown(stopped(lifecycle.shutdown))
     .await?;
 
     Ok(())
 }
 
 async fn run_grpc_server(
-    auth_service: Arc<AuthService>,
-    blog_service: Arc<BlogService>,
-    jwt_service: Arc<JwtService>,
+    grpc_service: BlogGrpcService,
     server_config: ServerConfig,
-    pagination_config: PaginationConfig,
+    lifecycle: Lifecycle,
 ) -> Result<()> {
     use tonic::transport::Server;
+    use tower_http::trace::TraceLayer;
 
     let addr = server_config.grpc_addr();
-    tracing::info!("gRPC server listening on {}", addr);
+    // Tonic drops a handler once its deadline passes, which also aborts
+    // queries still in flight
+    let mut server = Server::builder();
+    if let Some(timeout) = server_config.grpc_request_timeout {
+        server = server.timeout(timeout);
+    }
+    if let Some(tls) = &server_config.grpc_tls {
+        server = server.tls_config(tls.load()?)?;
+        tracing::info!("gRPC server listening on {} (TLS)", addr);
+    } else {
+        tracing::info!("gRPC server listening on {}", addr);
+    }
 
-    let grpc_service = BlogGrpcService::new(
-        auth_service,
-        blog_service,
-        jwt_service,
-        pagination_config,
-    );
+    // Lets tools such as grpcurl list and call the service without the
+    // .proto file; v1alpha is still what older clients ask for
+    let reflection = || {
+        tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+    };
 
-    Server::builder()
+    server
+        .layer(lifecycle.in_flight)
+        .layer(request_id_layers(TraceLayer::new(grpc_classifier())))
         .add_service(BlogServiceServer::new(grpc_service))
-        .serve(addr)
+        .add_service(reflection().build_v1()?)
+        .add_service(reflection().build_v1alpha()?)
+        .serve_with_shutdown(addr, stopped(lifecycle.shutdown))
         .await?;
 
     Ok(())
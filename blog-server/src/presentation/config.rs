@@ -1,15 +1,49 @@
+use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::infrastructure::config::{FromEnv, env_or, env_required};
+use anyhow::Context;
+use tonic::transport::{Identity, ServerTlsConfig};
 
-#[derive(Clone, Copy)]
+use crate::domain::{DomainError, PostSort};
+use crate::infrastructure::config::{
+    FromEnv, env_list, env_or, env_required, split_list,
+};
+
+#[derive(Clone)]
 pub struct ServerConfig {
+    /// Where the HTTP API is mounted, e.g. `/api/v1`
+    pub api_base_path: ApiBasePath,
     pub http_host: IpAddr,
     pub http_port: u16,
     pub grpc_host: IpAddr,
     pub grpc_port: u16,
+    /// Plaintext h2 when unset
+    pub grpc_tls: Option<GrpcTlsConfig>,
+    /// Longest a gRPC call may run; a shorter `grpc-timeout` from the
+    /// client wins. None leaves calls bounded by `grpc-timeout` only.
+    pub grpc_request_timeout: Option<Duration>,
+    /// Limit per client IP, for requests without a valid access token
     pub rate_limit_per_second: u64,
     pub rate_limit_burst: u32,
+    /// Limit per user, for requests with a valid access token
+    pub user_rate_limit_per_second: u64,
+    pub user_rate_limit_burst: u32,
+    /// In-flight HTTP requests beyond this are shed with 503; 0 disables
+    pub max_concurrent_requests: usize,
+    /// Compress HTTP responses for clients sending `Accept-Encoding`
+    pub compression_enabled: bool,
+    /// Responses smaller than this many bytes are sent uncompressed
+    pub compression_min_bytes: u16,
+    /// Dev-only: log redacted JSON request/response bodies at debug level
+    pub log_bodies: bool,
+    /// Largest body `POST /posts/raw` accepts
+    pub raw_post_max_bytes: usize,
+    /// How long shutdown waits for open requests and connections, such as
+    /// SSE subscribers, before dropping them
+    pub shutdown_timeout: Duration,
 }
 
 impl ServerConfig {
@@ -25,14 +59,132 @@ impl ServerConfig {
 impl FromEnv for ServerConfig {
     fn from_env() -> Self {
         let default_host = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let rate_limit_per_second = env_or("RATE_LIMIT_PER_SECOND", 10);
+        let rate_limit_burst = env_or("RATE_LIMIT_BURST", 20);
 
         Self {
+            api_base_path: std::env::var("API_BASE_PATH").map_or_else(
+                |_| ApiBasePath::default(),
+                |path| {
+                    path.parse().unwrap_or_else(|e| panic!("API_BASE_PATH {e}"))
+                },
+            ),
             http_host: env_or("HTTP_HOST", default_host),
             http_port: env_or("HTTP_PORT", 3000),
             grpc_host: env_or("GRPC_HOST", default_host),
             grpc_port: env_or("GRPC_PORT", 50051),
-            rate_limit_per_second: env_or("RATE_LIMIT_PER_SECOND", 10),
-            rate_limit_burst: env_or("RATE_LIMIT_BURST", 20),
+            grpc_tls: GrpcTlsConfig::from_env(),
+            grpc_request_timeout: Some(env_or("GRPC_REQUEST_TIMEOUT_SECS", 30))
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+            rate_limit_per_second,
+            rate_limit_burst,
+            user_rate_limit_per_second: env_or(
+                "USER_RATE_LIMIT_PER_SECOND",
+                rate_limit_per_second,
+            ),
+            user_rate_limit_burst: env_or(
+                "USER_RATE_LIMIT_BURST",
+                rate_limit_burst,
+            ),
+            max_concurrent_requests: env_or("MAX_CONCURRENT_REQUESTS", 0),
+            compression_enabled: env_or("COMPRESSION_ENABLED", false),
+            compression_min_bytes: env_or("COMPRESSION_MIN_BYTES", 1024),
+            log_bodies: env_or("LOG_BODIES", false),
+            raw_post_max_bytes: env_or("RAW_POST_MAX_BYTES", 8 * 1024 * 1024),
+            shutdown_timeout: Duration::from_secs(env_or(
+                "SHUTDOWN_TIMEOUT_SECS",
+                25,
+            )),
+        }
+    }
+}
+
+/// PEM files the gRPC server serves TLS with
+#[derive(Clone)]
+pub struct GrpcTlsConfig {
+    /// Certificate chain, leaf first
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl GrpcTlsConfig {
+    /// From `GRPC_TLS_CERT` and `GRPC_TLS_KEY`, which go together; None
+    /// when neither is set
+    fn from_env() -> Option<Self> {
+        match (std::env::var("GRPC_TLS_CERT"), std::env::var("GRPC_TLS_KEY")) {
+            (Ok(cert), Ok(key)) => Some(Self {
+                cert_path: cert.into(),
+                key_path: key.into(),
+            }),
+            (Err(_), Err(_)) => None,
+            _ => panic!("GRPC_TLS_CERT and GRPC_TLS_KEY must be set together"),
+        }
+    }
+
+    /// Reads the certificate and key.
+    ///
+    /// # Errors
+    ///
+    /// If either file cannot be read.
+    pub fn load(&self) -> anyhow::Result<ServerTlsConfig> {
+        let read = |path: &PathBuf| {
+            std::fs::read(path)
+                .with_context(|| format!("Failed to read {}", path.display()))
+        };
+        let identity =
+            Identity::from_pem(read(&self.cert_path)?, read(&self.key_path)?);
+        Ok(ServerTlsConfig::new().identity(identity))
+    }
+}
+
+/// Path prefix of the HTTP API: starts with `/` and has no trailing slash,
+/// so routes can be appended as is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiBasePath(String);
+
+impl ApiBasePath {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for ApiBasePath {
+    fn default() -> Self {
+        Self("/api/v1".to_string())
+    }
+}
+
+impl FromStr for ApiBasePath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.starts_with('/') {
+            return Err(format!("must start with '/': {s:?}"));
+        }
+        if s.ends_with('/') {
+            return Err(format!("must not end with '/': {s:?}"));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Display for ApiBasePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Opt-in Prometheus endpoint at `GET /metrics`
+#[derive(Clone, Copy)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+}
+
+impl FromEnv for MetricsConfig {
+    fn from_env() -> Self {
+        Self {
+            enabled: env_or("METRICS_ENABLED", false),
         }
     }
 }
@@ -40,19 +192,28 @@ impl FromEnv for ServerConfig {
 #[derive(Clone)]
 pub struct CorsConfig {
     pub allowed_origins: Vec<String>,
+    /// Stricter origin list for `/auth/*`; falls back to `allowed_origins`
+    pub auth_allowed_origins: Option<Vec<String>>,
     pub max_age_secs: u64,
 }
 
+impl CorsConfig {
+    /// Origins allowed to call the auth routes
+    pub fn auth_origins(&self) -> &[String] {
+        self.auth_allowed_origins
+            .as_deref()
+            .unwrap_or(&self.allowed_origins)
+    }
+}
+
 impl FromEnv for CorsConfig {
     fn from_env() -> Self {
-        let origins = env_required("CORS_ALLOWED_ORIGINS")
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+        let auth_origins = env_list("CORS_AUTH_ALLOWED_ORIGINS");
 
         Self {
-            allowed_origins: origins,
+            allowed_origins: split_list(&env_required("CORS_ALLOWED_ORIGINS")),
+            auth_allowed_origins: (!auth_origins.is_empty())
+                .then_some(auth_origins),
             max_age_secs: env_or("CORS_MAX_AGE", 3600),
         }
     }
@@ -62,6 +223,39 @@ impl FromEnv for CorsConfig {
 pub struct PaginationConfig {
     pub default_limit: i64,
     pub max_limit: i64,
+    /// Characters kept in list excerpts (`?fields=summary`)
+    pub excerpt_chars: usize,
+    /// Listing order when the client does not ask for one
+    pub default_sort: PostSort,
+}
+
+impl PaginationConfig {
+    /// `(limit, offset)` of a request: `default_limit` when no limit is
+    /// given, larger limits cut to `max_limit`, and offset 0 by default.
+    ///
+    /// # Errors
+    ///
+    /// `ValidationError` for a negative limit or offset.
+    pub fn page(
+        &self,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<(i64, i64), DomainError> {
+        if limit.is_some_and(i64::is_negative) {
+            return Err(DomainError::ValidationError(
+                "limit must not be negative".to_string(),
+            ));
+        }
+        if offset.is_some_and(i64::is_negative) {
+            return Err(DomainError::ValidationError(
+                "offset must not be negative".to_string(),
+            ));
+        }
+        let limit = limit
+            .unwrap_or(self.default_limit)
+            .clamp(1, self.max_limit.max(1));
+        Ok((limit, offset.unwrap_or(0)))
+    }
 }
 
 impl FromEnv for PaginationConfig {
@@ -69,6 +263,115 @@ impl FromEnv for PaginationConfig {
         Self {
             default_limit: env_or("PAGINATION_DEFAULT_LIMIT", 10),
             max_limit: env_or("PAGINATION_MAX_LIMIT", 100),
+            excerpt_chars: env_or("POST_EXCERPT_CHARS", 200),
+            default_sort: env_or("POST_DEFAULT_SORT", PostSort::default()),
+        }
+    }
+}
+
+/// Server-side check of the `Origin` header on registration, independent
+/// of CORS which only browsers enforce
+#[derive(Clone)]
+pub struct RegistrationConfig {
+    pub require_trusted_origin: bool,
+    pub trusted_origins: Vec<String>,
+}
+
+impl RegistrationConfig {
+    /// Whether a registration request with this `Origin` may proceed.
+    /// Requests without an `Origin` are rejected only while the check is on.
+    pub fn allows_origin(&self, origin: Option<&str>) -> bool {
+        !self.require_trusted_origin
+            || origin.is_some_and(|origin| {
+                self.trusted_origins.iter().any(|trusted| trusted == origin)
+            })
+    }
+}
+
+impl FromEnv for RegistrationConfig {
+    fn from_env() -> Self {
+        Self {
+            require_trusted_origin: env_or(
+                "REGISTER_REQUIRE_TRUSTED_ORIGIN",
+                false,
+            ),
+            trusted_origins: env_list("REGISTER_TRUSTED_ORIGINS"),
+        }
+    }
+}
+
+/// Optional features an operator can switch off per deployment. Routes
+/// of a disabled feature answer 404 as if they did not exist.
+// Independent switches, not a state machine
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Features {
+    /// Bulk tagging at `/users/me/posts/tags`, tags on new posts and the
+    /// `?tags=` listing filter
+    pub tags: bool,
+    /// `POST /posts/raw`
+    pub raw_posts: bool,
+    /// Full-text search at `/posts/search`
+    pub search: bool,
+    /// Comments at `/posts/{id}/comments`
+    pub comments: bool,
+    /// Live feed at `/posts/stream`
+    pub post_stream: bool,
+    /// NDJSON export at `/users/me/export`
+    pub export: bool,
+    /// Per-day counts at `/users/{id}/activity`
+    pub activity: bool,
+    /// API spec at `/api-docs/openapi.json` and Swagger UI at
+    /// `/swagger-ui`
+    pub api_docs: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            tags: true,
+            raw_posts: true,
+            search: true,
+            comments: true,
+            post_stream: true,
+            export: true,
+            activity: true,
+            api_docs: true,
+        }
+    }
+}
+
+impl FromEnv for Features {
+    fn from_env() -> Self {
+        Self {
+            tags: env_or("FEATURE_TAGS", true),
+            raw_posts: env_or("FEATURE_RAW_POSTS", true),
+            search: env_or("FEATURE_SEARCH", true),
+            comments: env_or("FEATURE_COMMENTS", true),
+            post_stream: env_or("FEATURE_POST_STREAM", true),
+            export: env_or("FEATURE_EXPORT", true),
+            activity: env_or("FEATURE_ACTIVITY", true),
+            api_docs: env_or("FEATURE_API_DOCS", true),
+        }
+    }
+}
+
+/// Read-only mode for migrations, see [`super::maintenance::MaintenanceMode`]
+#[derive(Clone)]
+pub struct MaintenanceConfig {
+    /// Start with writes disabled
+    pub enabled: bool,
+    /// Token for the runtime toggle at `/api/v1/admin/maintenance`
+    pub admin_token: Option<String>,
+}
+
+impl FromEnv for MaintenanceConfig {
+    fn from_env() -> Self {
+        Self {
+            enabled: env_or("MAINTENANCE_MODE", false),
+            admin_token: std::env::var("ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
         }
     }
 }
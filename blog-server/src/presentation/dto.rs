@@ -1,42 +1,141 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-use crate::domain::{Post, User};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeStruct};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationErrors};
+
+use uuid::Uuid;
+
+use crate::domain::{
+    AuthorSummary, BulkTagResult, Comment, DomainError, Post, PostNeighbors,
+    Session, User, validate_comment, validate_content, validate_email,
+    validate_password, validate_title, validate_username,
+};
 
 // ============ Request DTOs ============
+//
+// Handlers call `validate()` before building domain commands. The field
+// rules are the domain's own, so a DTO accepts exactly what the command
+// built from it will.
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct RegisterDto {
+    #[validate(custom(function = validate_username))]
     pub username: String,
+    #[validate(custom(function = validate_email))]
     pub email: String,
+    #[validate(custom(function = validate_password))]
     pub password: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct LoginDto {
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub username: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub password: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct ChangePasswordDto {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub current_password: String,
+    #[validate(custom(function = validate_password))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct VerifyEmailDto {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct RefreshTokenDto {
+    pub refresh_token: String,
+}
+
+/// Body and response of the admin maintenance toggle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceDto {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct CreatePostDto {
+    #[validate(custom(function = validate_title))]
     pub title: String,
+    #[validate(custom(function = validate_content))]
     pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `draft` (default) or `published`
+    #[serde(default)]
+    pub status: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct UpdatePostDto {
+    #[validate(custom(function = validate_title))]
     pub title: String,
+    #[validate(custom(function = validate_content))]
     pub content: String,
+    /// Replaces all tags when present; omitted keeps the current ones
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CreateCommentDto {
+    #[validate(custom(function = validate_comment))]
+    pub content: String,
+}
+
+/// Body of `POST /users/me/posts/tags`
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct BulkTagDto {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "must contain 1 to 100 post ids"
+    ))]
+    pub post_ids: Vec<i64>,
+    pub tag: String,
+    /// `add` or `remove`
+    pub action: String,
+}
+
+impl From<ValidationErrors> for DomainError {
+    fn from(errors: ValidationErrors) -> Self {
+        let fields = errors
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let messages = errors
+                    .iter()
+                    .map(|error| {
+                        error.message.as_ref().map_or_else(
+                            || error.code.to_string(),
+                            ToString::to_string,
+                        )
+                    })
+                    .collect();
+                (field.to_string(), messages)
+            })
+            .collect();
+
+        Self::InvalidFields(fields)
+    }
 }
 
 // ============ Response DTOs ============
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct UserDto {
     pub id: i64,
     pub username: String,
     pub email: String,
+    pub email_verified: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -46,6 +145,7 @@ impl From<User> for UserDto {
             id: user.id,
             username: user.username,
             email: user.email,
+            email_verified: user.email_verified,
             created_at: user.created_at,
         }
     }
@@ -57,26 +157,64 @@ impl From<&User> for UserDto {
             id: user.id,
             username: user.username.clone(),
             email: user.email.clone(),
+            email_verified: user.email_verified,
             created_at: user.created_at,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct AuthResponseDto {
     pub token: String,
+    /// Exchange at `/auth/refresh` for a new `token`; omitted when the
+    /// token cannot be renewed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
     pub user: UserDto,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct PostDto {
     pub id: i64,
     pub title: String,
-    pub content: String,
+    /// Omitted in summary listings, which carry `excerpt` instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
     pub author_id: i64,
     pub author_username: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub title_updated_at: DateTime<Utc>,
+    pub content_updated_at: DateTime<Utc>,
+    pub tags: Vec<String>,
+    /// Only with `?include_author=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<AuthorSummaryDto>,
+    /// `draft` or `published`; drafts are only shown to their author
+    pub status: String,
+}
+
+/// What an author card shows about a post's author
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuthorSummaryDto {
+    pub username: String,
+    /// Only shown to admins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// When the author registered
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuthorSummary> for AuthorSummaryDto {
+    fn from(author: AuthorSummary) -> Self {
+        Self {
+            username: author.username,
+            email: author.email,
+            created_at: author.created_at,
+        }
+    }
 }
 
 impl From<Post> for PostDto {
@@ -84,11 +222,17 @@ impl From<Post> for PostDto {
         Self {
             id: post.id,
             title: post.title,
-            content: post.content,
+            content: Some(post.content),
+            excerpt: None,
             author_id: post.author_id,
             author_username: post.author_username,
             created_at: post.created_at,
             updated_at: post.updated_at,
+            title_updated_at: post.title_updated_at,
+            content_updated_at: post.content_updated_at,
+            tags: post.tags,
+            author: post.author.map(Into::into),
+            status: post.status.to_string(),
         }
     }
 }
@@ -98,19 +242,538 @@ impl From<&Post> for PostDto {
         Self {
             id: post.id,
             title: post.title.clone(),
-            content: post.content.clone(),
+            content: Some(post.content.clone()),
+            excerpt: None,
             author_id: post.author_id,
             author_username: post.author_username.clone(),
             created_at: post.created_at,
             updated_at: post.updated_at,
+            title_updated_at: post.title_updated_at,
+            content_updated_at: post.content_updated_at,
+            tags: post.tags.clone(),
+            author: post.author.clone().map(Into::into),
+            status: post.status.to_string(),
+        }
+    }
+}
+
+impl PostDto {
+    /// List-view representation: `excerpt` instead of the full content
+    pub fn summary(post: Post, max_chars: usize) -> Self {
+        let excerpt = excerpt(&post.content, max_chars);
+        Self {
+            content: None,
+            excerpt: Some(excerpt),
+            ..Self::from(post)
+        }
+    }
+}
+
+/// Names accepted in `?fields=`, matching the keys of [`PostDto`]
+const POST_FIELD_NAMES: [&str; 13] = [
+    "id",
+    "title",
+    "content",
+    "excerpt",
+    "author_id",
+    "author_username",
+    "created_at",
+    "updated_at",
+    "title_updated_at",
+    "content_updated_at",
+    "tags",
+    "author",
+    "status",
+];
+
+/// How much of each post a response carries (`?fields=`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PostFields {
+    #[default]
+    Full,
+    /// Excerpt instead of the full content
+    Summary,
+    /// Only the listed [`PostDto`] keys, e.g. `id,title,created_at`
+    Only(Vec<&'static str>),
+}
+
+impl PostFields {
+    /// Short name of the field set, as used in a post's `ETag`
+    pub fn key(&self) -> String {
+        match self {
+            Self::Full => "full".to_string(),
+            Self::Summary => "summary".to_string(),
+            Self::Only(fields) => fields.join("+"),
+        }
+    }
+
+    /// `post` shaped as requested; `excerpt_chars` applies to excerpts
+    pub fn render(&self, post: Post, excerpt_chars: usize) -> PostItemDto {
+        match self {
+            Self::Full => PostItemDto::Full(PostDto::from(post)),
+            Self::Summary => {
+                PostItemDto::Full(PostDto::summary(post, excerpt_chars))
+            }
+            Self::Only(fields) => {
+                let excerpt = fields
+                    .contains(&"excerpt")
+                    .then(|| excerpt(&post.content, excerpt_chars));
+                let dto = PostDto {
+                    excerpt,
+                    ..PostDto::from(post)
+                };
+                let mut map = match serde_json::to_value(dto) {
+                    Ok(serde_json::Value::Object(map)) => map,
+                    _ => serde_json::Map::new(),
+                };
+                map.retain(|key, _| fields.contains(&key.as_str()));
+                PostItemDto::Partial(map)
+            }
         }
     }
 }
 
+impl FromStr for PostFields {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => return Ok(Self::Full),
+            "summary" => return Ok(Self::Summary),
+            _ => {}
+        }
+
+        let mut fields = Vec::new();
+        for name in s.split(',').map(str::trim) {
+            let Some(field) =
+                POST_FIELD_NAMES.iter().find(|field| **field == name)
+            else {
+                return Err(DomainError::ValidationError(format!(
+                    "unknown field: {name}"
+                )));
+            };
+            if !fields.contains(field) {
+                fields.push(*field);
+            }
+        }
+        Ok(Self::Only(fields))
+    }
+}
+
+/// A post as rendered for `?fields=`: the whole DTO or a subset of keys
+// Pages hold mostly `Full` items, so boxing them would only add an
+// allocation per post
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize)]
-pub struct PostsListDto {
-    pub posts: Vec<PostDto>,
+#[serde(untagged)]
+pub enum PostItemDto {
+    Full(PostDto),
+    Partial(serde_json::Map<String, serde_json::Value>),
+}
+
+impl PageItem for PostItemDto {
+    const KEY: &'static str = "posts";
+}
+
+/// Cuts `content` to at most `max_chars` characters, backing off to the
+/// last word boundary so words are not split. Truncated text ends in `…`.
+pub fn excerpt(content: &str, max_chars: usize) -> String {
+    let Some((cut, next)) = content.char_indices().nth(max_chars) else {
+        return content.to_string();
+    };
+
+    let head = &content[..cut];
+    let head = if next.is_whitespace() {
+        head
+    } else {
+        // Fall back to a hard cut for a single word longer than the limit
+        head.rfind(char::is_whitespace)
+            .map_or(head, |end| &head[..end])
+    };
+
+    format!("{}…", head.trim_end())
+}
+
+/// Items that can fill a [`Page`], with the key they are listed under
+pub trait PageItem: Serialize {
+    const KEY: &'static str;
+}
+
+impl PageItem for PostDto {
+    const KEY: &'static str = "posts";
+}
+
+/// Envelope shared by offset-paginated list endpoints. Serializes as
+/// `{<T::KEY>: [...], total, limit, offset, has_more}`; responses add
+/// their own fields next to it with `#[serde(flatten)]`.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
     pub total: i64,
     pub limit: i64,
     pub offset: i64,
+    /// Whether a page after this one has items
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    /// Page of `items` found at `offset` out of `total`
+    pub fn new(items: Vec<T>, total: i64, limit: i64, offset: i64) -> Self {
+        let has_more = has_more(offset, items.len(), total);
+        Self {
+            items,
+            total,
+            limit,
+            offset,
+            has_more,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            limit: self.limit,
+            offset: self.offset,
+            has_more: self.has_more,
+        }
+    }
+}
+
+impl<T: PageItem> Serialize for Page<T> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut page = serializer.serialize_struct("Page", 5)?;
+        page.serialize_field(T::KEY, &self.items)?;
+        page.serialize_field("total", &self.total)?;
+        page.serialize_field("limit", &self.limit)?;
+        page.serialize_field("offset", &self.offset)?;
+        page.serialize_field("has_more", &self.has_more)?;
+        page.end()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(bound = "T: PageItem")]
+pub struct PostsListDto<T = PostDto> {
+    #[serde(flatten)]
+    pub page: Page<T>,
+    /// Sort that was applied, including the default
+    pub sort: &'static str,
+    /// Pass as `cursor` to get the next page; unlike `offset` it is not
+    /// shifted by posts created in the meantime
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Matches the `sessions.label` column size
+const SESSION_LABEL_CHARS: usize = 255;
+
+/// Session label from a client's `User-Agent`, cut to fit the column
+pub fn session_label(user_agent: &str) -> String {
+    user_agent.chars().take(SESSION_LABEL_CHARS).collect()
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionDto {
+    /// The token's `jti`, used to revoke it
+    pub id: Uuid,
+    pub label: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+impl SessionDto {
+    pub fn new(session: Session, current: Option<Uuid>) -> Self {
+        Self {
+            id: session.jti,
+            label: session.label,
+            issued_at: session.issued_at,
+            expires_at: session.expires_at,
+            current: current == Some(session.jti),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CommentDto {
+    pub id: i64,
+    pub post_id: i64,
+    pub author_id: i64,
+    pub author_username: Option<String>,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Comment> for CommentDto {
+    fn from(comment: Comment) -> Self {
+        Self {
+            id: comment.id,
+            post_id: comment.post_id,
+            author_id: comment.author_id,
+            author_username: comment.author_username,
+            content: comment.content,
+            created_at: comment.created_at,
+        }
+    }
+}
+
+impl PageItem for CommentDto {
+    const KEY: &'static str = "comments";
+}
+
+/// Whether posts remain after a page of `page_len` posts at `offset`
+pub fn has_more(offset: i64, page_len: usize, total: i64) -> bool {
+    offset.saturating_add(i64::try_from(page_len).unwrap_or(i64::MAX)) < total
+}
+
+/// Prev/next navigation around a post; `null` at either end
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PostNeighborsDto {
+    pub newer: Option<PostDto>,
+    pub older: Option<PostDto>,
+}
+
+impl From<PostNeighbors> for PostNeighborsDto {
+    fn from(neighbors: PostNeighbors) -> Self {
+        Self {
+            newer: neighbors.newer.map(PostDto::from),
+            older: neighbors.older.map(PostDto::from),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ActivityDayDto {
+    pub date: NaiveDate,
+    pub count: i64,
+}
+
+/// Posts per UTC day, one entry for every day of the window, oldest first
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ActivityDto {
+    pub user_id: i64,
+    pub days: Vec<ActivityDayDto>,
+}
+
+/// Entry of `GET /tags`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TagCountDto {
+    pub name: String,
+    pub post_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkTagResultDto {
+    pub updated: u64,
+    pub unchanged: u64,
+    pub skipped: u64,
+}
+
+impl From<BulkTagResult> for BulkTagResultDto {
+    fn from(result: BulkTagResult) -> Self {
+        Self {
+            updated: result.updated,
+            unchanged: result.unchanged,
+            skipped: result.skipped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt_truncates_at_word_boundary() {
+        assert_eq!(excerpt("The quick brown fox", 12), "The quick…");
+        assert_eq!(excerpt("The quick brown fox", 9), "The quick…");
+    }
+
+    #[test]
+    fn test_excerpt_keeps_short_content() {
+        assert_eq!(excerpt("Short post", 10), "Short post");
+        assert_eq!(excerpt("Short post", 200), "Short post");
+    }
+
+    #[test]
+    fn test_excerpt_hard_cuts_long_word() {
+        assert_eq!(excerpt("Привет", 3), "При…");
+    }
+
+    fn register(username: &str, email: &str, password: &str) -> RegisterDto {
+        RegisterDto {
+            username: username.to_string(),
+            email: email.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    /// Names of the fields that failed validation
+    fn invalid_fields(dto: &impl Validate) -> Vec<String> {
+        match dto.validate().map_err(DomainError::from) {
+            Ok(()) => Vec::new(),
+            Err(DomainError::InvalidFields(fields)) => {
+                fields.into_keys().collect()
+            }
+            Err(other) => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn test_register_dto_rules() {
+        let valid = "ivan@example.com";
+
+        assert!(register("ivan", valid, "secret123").validate().is_ok());
+        assert_eq!(
+            invalid_fields(&register("iv", valid, "secret123")),
+            ["username"]
+        );
+        assert_eq!(
+            invalid_fields(&register(&"i".repeat(33), valid, "secret123")),
+            ["username"]
+        );
+        assert_eq!(
+            invalid_fields(&register("ivan", "not-an-email", "secret123")),
+            ["email"]
+        );
+        assert_eq!(
+            invalid_fields(&register("ivan", valid, "short")),
+            ["password"]
+        );
+        assert_eq!(
+            invalid_fields(&register("ivan", valid, &"p".repeat(129))),
+            ["password"]
+        );
+    }
+
+    #[test]
+    fn test_login_dto_rejects_empty_fields() {
+        let dto = LoginDto {
+            username: String::new(),
+            password: String::new(),
+        };
+
+        assert_eq!(invalid_fields(&dto), ["password", "username"]);
+    }
+
+    #[test]
+    fn test_post_dto_rules() {
+        let create = |title: &str, content: &str| CreatePostDto {
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: Vec::new(),
+            status: None,
+        };
+        let update = |title: &str, content: &str| UpdatePostDto {
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: None,
+        };
+        let long_title = "t".repeat(201);
+
+        assert!(create("Title", "Content").validate().is_ok());
+        assert!(create(&"t".repeat(200), "Content").validate().is_ok());
+        assert_eq!(invalid_fields(&create("", "Content")), ["title"]);
+        assert_eq!(invalid_fields(&create("   ", "Content")), ["title"]);
+        assert_eq!(invalid_fields(&create("Title", " \n")), ["content"]);
+        assert_eq!(invalid_fields(&create(&long_title, "Content")), ["title"]);
+        assert_eq!(invalid_fields(&create("Title", "")), ["content"]);
+        assert!(update("Title", "Content").validate().is_ok());
+        assert_eq!(
+            invalid_fields(&update(&long_title, "")),
+            ["content", "title"]
+        );
+    }
+
+    #[test]
+    fn test_validation_errors_keep_messages() {
+        let error = DomainError::from(
+            register("iv", "ivan@example.com", "secret123")
+                .validate()
+                .unwrap_err(),
+        );
+
+        let DomainError::InvalidFields(fields) = error else {
+            panic!("expected field errors");
+        };
+        assert_eq!(fields["username"], ["must be 3 to 32 characters"]);
+    }
+
+    #[test]
+    fn test_has_more_at_last_page_boundary() {
+        // 20 posts in pages of 10: the second page is the last one
+        assert!(has_more(0, 10, 20));
+        assert!(!has_more(10, 10, 20));
+        assert!(has_more(10, 10, 21));
+        assert!(!has_more(20, 0, 20));
+    }
+
+    #[test]
+    fn test_posts_list_keeps_its_json_shape() {
+        let now = Utc::now();
+        let post = Post::new(1, "Title".into(), "Content".into(), 2, now, now);
+        let list = PostsListDto {
+            page: Page::new(vec![PostDto::from(post)], 11, 10, 0),
+            sort: "created_at_desc",
+            next_cursor: Some("cursor".to_string()),
+        };
+
+        let json = serde_json::to_value(&list).unwrap();
+
+        let mut keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "has_more",
+                "limit",
+                "next_cursor",
+                "offset",
+                "posts",
+                "sort",
+                "total"
+            ]
+        );
+        assert_eq!(json["posts"][0]["id"], 1);
+        assert_eq!(json["total"], 11);
+        assert_eq!(json["has_more"], true);
+    }
+
+    fn post() -> Post {
+        let now = Utc::now();
+        Post::new(1, "Title".into(), "Some content".into(), 2, now, now)
+    }
+
+    #[test]
+    fn test_fields_projection_keeps_requested_keys() {
+        let fields: PostFields = "id, title,excerpt,id".parse().unwrap();
+
+        let json = serde_json::to_value(fields.render(post(), 4)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": 1, "title": "Title", "excerpt": "Some…" })
+        );
+    }
+
+    #[test]
+    fn test_fields_rejects_unknown_names() {
+        let result = "id,password_hash".parse::<PostFields>();
+
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+        assert_eq!(
+            "summary".parse::<PostFields>().unwrap(),
+            PostFields::Summary
+        );
+    }
 }
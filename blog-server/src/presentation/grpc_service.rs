@@ -1,14 +1,23 @@
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
 use std::sync::Arc;
-use tonic::{Request, Response, Status};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status};
+use tonic_types::{ErrorDetails, FieldViolation, StatusExt};
 
-use crate::application::{AuthService, BlogService};
+use crate::application::{AuthService, BlogService, CommentService};
 use crate::domain::{
-    CreatePostCommand, DomainError, LoginCommand, RegisterCommand,
+    Comment, CreateCommentCommand, CreatePostCommand, DomainError,
+    LoginCommand, Post, PostCursor, PostSort, PostStatus, RegisterCommand,
     UpdatePostCommand,
 };
-use crate::infrastructure::JwtService;
+use crate::infrastructure::{Claims, Database};
 
-use super::config::PaginationConfig;
+use super::config::{Features, PaginationConfig};
+use super::dto::{has_more, session_label};
+use super::health::HealthResponse;
+use super::maintenance::MaintenanceMode;
 
 // Generated protobuf code — allow clippy lints that cannot be fixed in auto-generated tonic/prost output
 #[allow(
@@ -17,44 +26,161 @@ use super::config::PaginationConfig;
     clippy::default_trait_access,
     clippy::missing_const_for_fn,
     clippy::too_many_lines,
-    clippy::doc_markdown
+    clippy::doc_markdown,
+    clippy::double_must_use
 )]
 pub mod proto {
     tonic::include_proto!("blog");
+
+    /// Encoded descriptors of `blog.proto`, served by gRPC reflection
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("blog_descriptor");
 }
 
 use proto::blog_service_server::BlogService as GrpcBlogService;
 use proto::{
-    AuthResponse, CreatePostRequest as GrpcCreatePostRequest,
-    DeletePostRequest, DeleteResponse, GetPostRequest, ListPostsRequest,
-    ListPostsResponse, LoginRequest as GrpcLoginRequest, Post as GrpcPost,
-    PostResponse, RegisterRequest as GrpcRegisterRequest,
+    AuthResponse, ChangePasswordRequest, Comment as GrpcComment,
+    CommentResponse, CreateCommentRequest,
+    CreatePostRequest as GrpcCreatePostRequest, DeletePostRequest,
+    DeleteResponse, GetPostRequest, HealthCheckRequest, HealthCheckResponse,
+    ListCommentsRequest, ListCommentsResponse, ListMyPostsRequest,
+    ListPostsRequest, ListPostsResponse, LoginRequest as GrpcLoginRequest,
+    Post as GrpcPost, PostResponse, PublishPostRequest, RefreshTokenRequest,
+    RegisterRequest as GrpcRegisterRequest, SearchPostsRequest,
+    SearchPostsResponse, StreamPostsRequest, UnpublishPostRequest,
     UpdatePostRequest as GrpcUpdatePostRequest, User as GrpcUser,
 };
 
+/// Posts buffered for a slow `StreamPosts` reader before fetching pauses
+const STREAM_CHANNEL_CAPACITY: usize = 100;
+
+impl From<Post> for GrpcPost {
+    fn from(post: Post) -> Self {
+        Self {
+            id: post.id.to_string(),
+            title: post.title,
+            content: post.content,
+            author_id: post.author_id.to_string(),
+            author_username: post.author_username.unwrap_or_default(),
+            created_at: post.created_at.to_rfc3339(),
+            updated_at: post.updated_at.to_rfc3339(),
+            status: post.status.to_string(),
+        }
+    }
+}
+
+impl From<Comment> for GrpcComment {
+    fn from(comment: Comment) -> Self {
+        Self {
+            id: comment.id.to_string(),
+            post_id: comment.post_id.to_string(),
+            author_id: comment.author_id.to_string(),
+            author_username: comment.author_username.unwrap_or_default(),
+            content: comment.content,
+            created_at: comment.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Posts of one page, the total and whether more follow
+type PostPage = (Vec<Post>, i64, bool);
+
 pub struct BlogGrpcService {
     auth_service: Arc<AuthService>,
     blog_service: Arc<BlogService>,
-    jwt_service: Arc<JwtService>,
+    comment_service: Arc<CommentService>,
     pagination_config: PaginationConfig,
+    maintenance: MaintenanceMode,
+    features: Features,
+    /// Pinged by `HealthCheck`
+    database: Option<Database>,
 }
 
 impl BlogGrpcService {
-    pub const fn new(
+    pub fn new(
         auth_service: Arc<AuthService>,
         blog_service: Arc<BlogService>,
-        jwt_service: Arc<JwtService>,
+        comment_service: Arc<CommentService>,
         pagination_config: PaginationConfig,
+        maintenance: MaintenanceMode,
     ) -> Self {
         Self {
             auth_service,
             blog_service,
-            jwt_service,
+            comment_service,
             pagination_config,
+            maintenance,
+            features: Features::default(),
+            database: None,
+        }
+    }
+
+    /// Turns off the RPCs of features switched off with `FEATURE_*`
+    #[must_use]
+    pub const fn with_features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Lets `HealthCheck` ping the database backing the repositories
+    #[must_use]
+    pub fn with_database(mut self, database: Option<Database>) -> Self {
+        self.database = database;
+        self
+    }
+
+    /// `sort` as requested, or the configured default when unset
+    fn sort(&self, sort: Option<&str>) -> Result<PostSort, Status> {
+        sort.filter(|sort| !sort.is_empty())
+            .map_or(Ok(self.pagination_config.default_sort), str::parse)
+            .map_err(Status::from)
+    }
+
+    /// Keyset page after `cursor` when given, else the page at `offset`;
+    /// only the posts of `author_id` when set, which takes no cursor
+    async fn list_page(
+        &self,
+        cursor: Option<&PostCursor>,
+        author_id: Option<i64>,
+        limit: i64,
+        offset: i64,
+        sort: PostSort,
+    ) -> Result<PostPage, DomainError> {
+        if let Some(author_id) = author_id {
+            if cursor.is_some() {
+                return Err(DomainError::ValidationError(
+                    "cursor cannot be combined with author_id".to_string(),
+                ));
+            }
+            let (posts, total) = self
+                .blog_service
+                .list_author_posts(author_id, limit, offset, sort)
+                .await?;
+            let has_more = has_more(offset, posts.len(), total);
+            return Ok((posts, total, has_more));
+        }
+
+        if let Some(cursor) = cursor {
+            let (posts, has_more) = self
+                .blog_service
+                .list_posts_after(cursor, limit, sort, None, false, None)
+                .await?;
+            let total = self.blog_service.count_posts(None, None).await?;
+            return Ok((posts, total, has_more));
         }
+
+        let (posts, total) = self
+            .blog_service
+            .list_posts(limit, offset, sort, None, false, None)
+            .await?;
+        let has_more = has_more(offset, posts.len(), total);
+        Ok((posts, total, has_more))
     }
 
-    fn extract_user_id<T>(&self, request: &Request<T>) -> Result<i64, Status> {
+    async fn extract_claims<T>(
+        &self,
+        request: &Request<T>,
+    ) -> Result<Claims, Status> {
         let auth_header = request
             .metadata()
             .get("authorization")
@@ -70,11 +196,64 @@ impl BlogGrpcService {
             Status::unauthenticated("Invalid authorization header format")
         })?;
 
-        let claims = self.jwt_service.verify_token(token).map_err(|e| {
-            Status::unauthenticated(format!("Invalid token: {e}"))
-        })?;
+        self.auth_service
+            .authenticate(token)
+            .await
+            .map_err(|e| Status::unauthenticated(format!("Invalid token: {e}")))
+    }
 
-        Ok(claims.user_id)
+    /// Claims of the caller if a token was sent; a bad token still fails
+    async fn optional_claims<T>(
+        &self,
+        request: &Request<T>,
+    ) -> Result<Option<Claims>, Status> {
+        if request.metadata().get("authorization").is_none() {
+            return Ok(None);
+        }
+        self.extract_claims(request).await.map(Some)
+    }
+
+    /// Moves a post of the caller to `status`
+    async fn set_post_status<T>(
+        &self,
+        request: Request<T>,
+        post_id: impl FnOnce(T) -> String,
+        status: PostStatus,
+    ) -> Result<Response<PostResponse>, Status> {
+        self.maintenance.ensure_writable()?;
+        let claims = self.extract_claims(&request).await?;
+        let post_id: i64 = post_id(request.into_inner())
+            .parse()
+            .map_err(|_| Status::invalid_argument("Invalid post_id"))?;
+
+        let post = self
+            .blog_service
+            .set_post_status(post_id, claims.user_id, status)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(PostResponse {
+            post: Some(post.into()),
+        }))
+    }
+}
+
+/// The listing position in `cursor`, if one was sent
+fn parse_cursor(cursor: Option<&str>) -> Result<Option<PostCursor>, Status> {
+    cursor
+        .filter(|cursor| !cursor.is_empty())
+        .map(str::parse::<PostCursor>)
+        .transpose()
+        .map_err(Status::from)
+}
+
+/// RPCs of a disabled feature answer `UNIMPLEMENTED`, as if the server
+/// did not know them
+fn require_feature(enabled: bool, feature: &'static str) -> Result<(), Status> {
+    if enabled {
+        Ok(())
+    } else {
+        Err(DomainError::FeatureDisabled(feature).into())
     }
 }
 
@@ -84,16 +263,50 @@ impl From<DomainError> for Status {
             DomainError::UserAlreadyExists => {
                 Self::already_exists(e.to_string())
             }
-            DomainError::InvalidCredentials => {
+            DomainError::InvalidCredentials | DomainError::InvalidToken => {
                 Self::unauthenticated(e.to_string())
             }
-            DomainError::PostNotFound | DomainError::UserNotFound => {
-                Self::not_found(e.to_string())
+            DomainError::Conflict => Self::aborted(e.to_string()),
+            DomainError::IdempotencyKeyReused => {
+                Self::failed_precondition(e.to_string())
+            }
+            DomainError::PostNotFound
+            | DomainError::UserNotFound
+            | DomainError::SessionNotFound => Self::not_found(e.to_string()),
+            DomainError::Forbidden | DomainError::EmailNotVerified => {
+                Self::permission_denied(e.to_string())
+            }
+            DomainError::PostQuotaExceeded(_)
+            | DomainError::PayloadTooLarge(_) => {
+                Self::resource_exhausted(e.to_string())
             }
-            DomainError::Forbidden => Self::permission_denied(e.to_string()),
             DomainError::ValidationError(_) => {
                 Self::invalid_argument(e.to_string())
             }
+            // Field messages travel as a standard `google.rpc.BadRequest`
+            // detail, which most gRPC clients know how to unpack
+            DomainError::InvalidFields(fields) => {
+                let violations = fields
+                    .iter()
+                    .flat_map(|(field, messages)| {
+                        messages.iter().map(|message| {
+                            FieldViolation::new(
+                                field.as_str(),
+                                message.as_str(),
+                            )
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Self::with_error_details(
+                    Code::InvalidArgument,
+                    e.to_string(),
+                    ErrorDetails::with_bad_request(violations),
+                )
+            }
+            DomainError::Maintenance => Self::unavailable(e.to_string()),
+            DomainError::FeatureDisabled(_) => {
+                Self::unimplemented(e.to_string())
+            }
             _ => Self::internal(e.to_string()),
         }
     }
@@ -105,12 +318,19 @@ impl GrpcBlogService for BlogGrpcService {
         &self,
         request: Request<GrpcRegisterRequest>,
     ) -> Result<Response<AuthResponse>, Status> {
+        self.maintenance.ensure_writable()?;
+        let idempotency_key = request
+            .metadata()
+            .get("idempotency-key")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
         let req = request.into_inner();
 
         let command = RegisterCommand {
             username: req.username,
             email: req.email,
             password: req.password,
+            idempotency_key,
         };
 
         let result = self
@@ -121,6 +341,7 @@ impl GrpcBlogService for BlogGrpcService {
 
         Ok(Response::new(AuthResponse {
             token: result.token,
+            refresh_token: result.refresh_token,
             user: Some(GrpcUser {
                 id: result.user.id.to_string(),
                 username: result.user.username,
@@ -134,11 +355,17 @@ impl GrpcBlogService for BlogGrpcService {
         &self,
         request: Request<GrpcLoginRequest>,
     ) -> Result<Response<AuthResponse>, Status> {
+        let device = request
+            .metadata()
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .map(session_label);
         let req = request.into_inner();
 
         let command = LoginCommand {
             username: req.username,
             password: req.password,
+            device,
         };
 
         let result = self
@@ -149,6 +376,66 @@ impl GrpcBlogService for BlogGrpcService {
 
         Ok(Response::new(AuthResponse {
             token: result.token,
+            refresh_token: result.refresh_token,
+            user: Some(GrpcUser {
+                id: result.user.id.to_string(),
+                username: result.user.username,
+                email: result.user.email,
+                created_at: result.user.created_at.to_rfc3339(),
+            }),
+        }))
+    }
+
+    async fn refresh_token(
+        &self,
+        request: Request<RefreshTokenRequest>,
+    ) -> Result<Response<AuthResponse>, Status> {
+        let req = request.into_inner();
+
+        let result = self
+            .auth_service
+            .refresh(&req.refresh_token)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(AuthResponse {
+            token: result.token,
+            refresh_token: result.refresh_token,
+            user: Some(GrpcUser {
+                id: result.user.id.to_string(),
+                username: result.user.username,
+                email: result.user.email,
+                created_at: result.user.created_at.to_rfc3339(),
+            }),
+        }))
+    }
+
+    async fn change_password(
+        &self,
+        request: Request<ChangePasswordRequest>,
+    ) -> Result<Response<AuthResponse>, Status> {
+        self.maintenance.ensure_writable()?;
+        let claims = self.extract_claims(&request).await?;
+        if claims.impersonator_id.is_some() {
+            return Err(Status::permission_denied(
+                "Impersonation tokens cannot change the password",
+            ));
+        }
+        let req = request.into_inner();
+
+        let result = self
+            .auth_service
+            .change_password(
+                claims.user_id,
+                &req.current_password,
+                &req.new_password,
+            )
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(AuthResponse {
+            token: result.token,
+            refresh_token: result.refresh_token,
             user: Some(GrpcUser {
                 id: result.user.id.to_string(),
                 username: result.user.username,
@@ -162,17 +449,27 @@ impl GrpcBlogService for BlogGrpcService {
         &self,
         request: Request<GrpcCreatePostRequest>,
     ) -> Result<Response<PostResponse>, Status> {
-        let user_id = self.extract_user_id(&request)?;
+        self.maintenance.ensure_writable()?;
+        let claims = self.extract_claims(&request).await?;
         let req = request.into_inner();
 
+        let status = req
+            .status
+            .as_deref()
+            .map_or_else(|| Ok(PostStatus::default()), str::parse)
+            .map_err(Status::from)?;
+
+        // Tags are not part of the gRPC API yet
         let command = CreatePostCommand {
             title: req.title,
             content: req.content,
+            tags: Vec::new(),
+            status,
         };
 
         let post = self
             .blog_service
-            .create_post(user_id, command)
+            .create_post(claims.user_id, claims.email_verified, command)
             .await
             .map_err(Status::from)?;
 
@@ -185,6 +482,7 @@ impl GrpcBlogService for BlogGrpcService {
                 author_username: post.author_username.unwrap_or_default(),
                 created_at: post.created_at.to_rfc3339(),
                 updated_at: post.updated_at.to_rfc3339(),
+                status: post.status.to_string(),
             }),
         }))
     }
@@ -193,6 +491,7 @@ impl GrpcBlogService for BlogGrpcService {
         &self,
         request: Request<GetPostRequest>,
     ) -> Result<Response<PostResponse>, Status> {
+        let viewer = self.optional_claims(&request).await?;
         let req = request.into_inner();
 
         let post_id: i64 = req
@@ -202,7 +501,7 @@ impl GrpcBlogService for BlogGrpcService {
 
         let post = self
             .blog_service
-            .get_post(post_id)
+            .get_post(post_id, false, viewer.map(|claims| claims.user_id))
             .await
             .map_err(Status::from)?;
 
@@ -215,6 +514,7 @@ impl GrpcBlogService for BlogGrpcService {
                 author_username: post.author_username.unwrap_or_default(),
                 created_at: post.created_at.to_rfc3339(),
                 updated_at: post.updated_at.to_rfc3339(),
+                status: post.status.to_string(),
             }),
         }))
     }
@@ -223,7 +523,8 @@ impl GrpcBlogService for BlogGrpcService {
         &self,
         request: Request<GrpcUpdatePostRequest>,
     ) -> Result<Response<PostResponse>, Status> {
-        let user_id = self.extract_user_id(&request)?;
+        self.maintenance.ensure_writable()?;
+        let claims = self.extract_claims(&request).await?;
         let req = request.into_inner();
 
         let post_id: i64 = req
@@ -231,14 +532,29 @@ impl GrpcBlogService for BlogGrpcService {
             .parse()
             .map_err(|_| Status::invalid_argument("Invalid post_id"))?;
 
+        let expected_updated_at = req
+            .expected_updated_at
+            .map(|at| {
+                DateTime::parse_from_rfc3339(&at)
+                    .map(|at| at.with_timezone(&Utc))
+                    .map_err(|_| {
+                        Status::invalid_argument(
+                            "Invalid expected_updated_at, expected RFC 3339",
+                        )
+                    })
+            })
+            .transpose()?;
+
         let command = UpdatePostCommand {
             title: req.title,
             content: req.content,
+            tags: None,
+            expected_updated_at,
         };
 
         let post = self
             .blog_service
-            .update_post(post_id, user_id, command)
+            .update_post(post_id, claims.user_id, claims.role, command)
             .await
             .map_err(Status::from)?;
 
@@ -251,6 +567,7 @@ impl GrpcBlogService for BlogGrpcService {
                 author_username: post.author_username.unwrap_or_default(),
                 created_at: post.created_at.to_rfc3339(),
                 updated_at: post.updated_at.to_rfc3339(),
+                status: post.status.to_string(),
             }),
         }))
     }
@@ -259,7 +576,13 @@ impl GrpcBlogService for BlogGrpcService {
         &self,
         request: Request<DeletePostRequest>,
     ) -> Result<Response<DeleteResponse>, Status> {
-        let user_id = self.extract_user_id(&request)?;
+        self.maintenance.ensure_writable()?;
+        let claims = self.extract_claims(&request).await?;
+        if claims.impersonator_id.is_some() {
+            return Err(Status::permission_denied(
+                "Impersonation tokens cannot delete posts",
+            ));
+        }
         let req = request.into_inner();
 
         let post_id: i64 = req
@@ -268,7 +591,7 @@ impl GrpcBlogService for BlogGrpcService {
             .map_err(|_| Status::invalid_argument("Invalid post_id"))?;
 
         self.blog_service
-            .delete_post(post_id, user_id)
+            .delete_post(post_id, claims.user_id, claims.role)
             .await
             .map_err(Status::from)?;
 
@@ -290,13 +613,117 @@ impl GrpcBlogService for BlogGrpcService {
         let page_size = req.page_size.clamp(1, max_page_size);
         let offset = i64::from((page - 1) * page_size);
         let limit = i64::from(page_size);
+        let sort = self.sort(req.sort.as_deref())?;
+        let cursor = parse_cursor(req.cursor.as_deref())?;
+        let author_id = req
+            .author_id
+            .as_deref()
+            .filter(|author_id| !author_id.is_empty())
+            .map(str::parse::<i64>)
+            .transpose()
+            .map_err(|_| Status::invalid_argument("Invalid author_id"))?;
+
+        let (posts, total, has_more) = self
+            .list_page(cursor.as_ref(), author_id, limit, offset, sort)
+            .await?;
+
+        let next_cursor = posts
+            .last()
+            .filter(|_| has_more)
+            .map(|post| PostCursor::new(post, sort).to_string());
+        let grpc_posts: Vec<GrpcPost> = posts
+            .into_iter()
+            .map(|post| GrpcPost {
+                id: post.id.to_string(),
+                title: post.title,
+                content: post.content,
+                author_id: post.author_id.to_string(),
+                author_username: post.author_username.unwrap_or_default(),
+                created_at: post.created_at.to_rfc3339(),
+                updated_at: post.updated_at.to_rfc3339(),
+                status: post.status.to_string(),
+            })
+            .collect();
+
+        Ok(Response::new(ListPostsResponse {
+            posts: grpc_posts,
+            total_count: total,
+            page,
+            page_size,
+            sort: sort.to_string(),
+            has_more,
+            next_cursor,
+        }))
+    }
+
+    async fn publish_post(
+        &self,
+        request: Request<PublishPostRequest>,
+    ) -> Result<Response<PostResponse>, Status> {
+        self.set_post_status(request, |req| req.post_id, PostStatus::Published)
+            .await
+    }
+
+    async fn unpublish_post(
+        &self,
+        request: Request<UnpublishPostRequest>,
+    ) -> Result<Response<PostResponse>, Status> {
+        self.set_post_status(request, |req| req.post_id, PostStatus::Draft)
+            .await
+    }
+
+    async fn list_my_posts(
+        &self,
+        request: Request<ListMyPostsRequest>,
+    ) -> Result<Response<ListPostsResponse>, Status> {
+        let claims = self.extract_claims(&request).await?;
+        let req = request.into_inner();
+
+        let max_page_size =
+            i32::try_from(self.pagination_config.max_limit).unwrap_or(100);
+        let page = req.page.max(1);
+        let page_size = req.page_size.clamp(1, max_page_size);
+        let offset = i64::from((page - 1) * page_size);
+        let limit = i64::from(page_size);
+        let sort = self.sort(req.sort.as_deref())?;
 
         let (posts, total) = self
             .blog_service
-            .list_posts(limit, offset)
-            .await
-            .map_err(Status::from)?;
+            .list_my_posts(claims.user_id, limit, offset, sort)
+            .await?;
+
+        let has_more = has_more(offset, posts.len(), total);
+        Ok(Response::new(ListPostsResponse {
+            posts: posts.into_iter().map(Into::into).collect(),
+            total_count: total,
+            page,
+            page_size,
+            sort: sort.to_string(),
+            has_more,
+            next_cursor: None,
+        }))
+    }
+
+    async fn search_posts(
+        &self,
+        request: Request<SearchPostsRequest>,
+    ) -> Result<Response<SearchPostsResponse>, Status> {
+        require_feature(self.features.search, "search")?;
+        let req = request.into_inner();
+
+        let max_page_size =
+            i32::try_from(self.pagination_config.max_limit).unwrap_or(100);
+        let page = req.page.max(1);
+        let page_size = req.page_size.clamp(1, max_page_size);
+        let offset = i64::from((page - 1) * page_size);
+        let limit = i64::from(page_size);
+
+        let (posts, total) = self
+            .blog_service
+            .search_posts(&req.query, limit, offset)
+            .await?;
 
+        let has_more = has_more(offset, posts.len(), total);
         let grpc_posts: Vec<GrpcPost> = posts
             .into_iter()
             .map(|post| GrpcPost {
@@ -307,14 +734,379 @@ impl GrpcBlogService for BlogGrpcService {
                 author_username: post.author_username.unwrap_or_default(),
                 created_at: post.created_at.to_rfc3339(),
                 updated_at: post.updated_at.to_rfc3339(),
+                status: post.status.to_string(),
             })
             .collect();
 
-        Ok(Response::new(ListPostsResponse {
+        Ok(Response::new(SearchPostsResponse {
             posts: grpc_posts,
             total_count: total,
             page,
             page_size,
+            has_more,
+        }))
+    }
+
+    type StreamPostsStream = ReceiverStream<Result<GrpcPost, Status>>;
+
+    /// Fetches batches on a task of its own, which stops once the client
+    /// goes away
+    async fn stream_posts(
+        &self,
+        request: Request<StreamPostsRequest>,
+    ) -> Result<Response<Self::StreamPostsStream>, Status> {
+        let req = request.into_inner();
+        let sort = self.sort(req.sort.as_deref())?;
+        let cursor = parse_cursor(req.cursor.as_deref())?;
+
+        let batches = self.blog_service.stream_posts(sort, cursor)?;
+        let (sender, receiver) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            let mut batches = std::pin::pin!(batches);
+            while let Some(batch) = batches.next().await {
+                let posts = match batch {
+                    Ok(posts) => posts,
+                    Err(e) => {
+                        let _ = sender.send(Err(Status::from(e))).await;
+                        return;
+                    }
+                };
+                for post in posts {
+                    if sender.send(Ok(post.into())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn create_comment(
+        &self,
+        request: Request<CreateCommentRequest>,
+    ) -> Result<Response<CommentResponse>, Status> {
+        require_feature(self.features.comments, "comments")?;
+        self.maintenance.ensure_writable()?;
+        let claims = self.extract_claims(&request).await?;
+        let req = request.into_inner();
+
+        let post_id: i64 = req
+            .post_id
+            .parse()
+            .map_err(|_| Status::invalid_argument("Invalid post_id"))?;
+        let command = CreateCommentCommand {
+            content: req.content,
+        };
+
+        let comment = self
+            .comment_service
+            .add_comment(post_id, claims.user_id, command)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CommentResponse {
+            comment: Some(comment.into()),
+        }))
+    }
+
+    async fn list_comments(
+        &self,
+        request: Request<ListCommentsRequest>,
+    ) -> Result<Response<ListCommentsResponse>, Status> {
+        require_feature(self.features.comments, "comments")?;
+        let req = request.into_inner();
+
+        let post_id: i64 = req
+            .post_id
+            .parse()
+            .map_err(|_| Status::invalid_argument("Invalid post_id"))?;
+        let max_page_size =
+            i32::try_from(self.pagination_config.max_limit).unwrap_or(100);
+        let page = req.page.max(1);
+        let page_size = req.page_size.clamp(1, max_page_size);
+        let offset = i64::from((page - 1) * page_size);
+        let limit = i64::from(page_size);
+
+        let (comments, total) = self
+            .comment_service
+            .list_comments(post_id, limit, offset)
+            .await?;
+
+        let has_more = has_more(offset, comments.len(), total);
+        Ok(Response::new(ListCommentsResponse {
+            comments: comments.into_iter().map(Into::into).collect(),
+            total_count: total,
+            page,
+            page_size,
+            has_more,
+        }))
+    }
+
+    async fn health_check(
+        &self,
+        _request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let health = HealthResponse::probe(self.database.as_ref()).await;
+        Ok(Response::new(HealthCheckResponse {
+            status: health.status.to_string(),
+            timestamp: health.timestamp,
+            database_latency_ms: health.database_latency_ms,
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_field_errors_become_bad_request_details() {
+        let status = Status::from(DomainError::invalid_field(
+            "email",
+            "must be a valid email address",
+        ));
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let details = status.get_error_details();
+        let violations = &details.bad_request().unwrap().field_violations;
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].field, "email");
+        assert_eq!(violations[0].description, "must be a valid email address");
+    }
+
+    /// What tonic's timeout layer does with a handler past the deadline
+    /// from `grpc-timeout` or `GRPC_REQUEST_TIMEOUT_SECS`: the future is
+    /// dropped, so queries it has not run yet never start
+    #[tokio::test(start_paused = true)]
+    async fn test_expired_deadline_stops_remaining_work() {
+        let second_query_ran = AtomicBool::new(false);
+
+        let result = tokio::time::timeout(Duration::from_millis(10), async {
+            // Stands in for the list query outliving the deadline
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            second_query_ran.store(true, Ordering::SeqCst);
+        })
+        .await;
+        tokio::time::advance(Duration::from_millis(100)).await;
+
+        assert!(result.is_err());
+        assert!(!second_query_ran.load(Ordering::SeqCst));
+    }
+
+    use crate::application::AuthConfig;
+    use crate::application::sanitizer::ContentSanitizer;
+    use crate::data::{
+        InMemoryCommentRepository, InMemoryPostRepository,
+        InMemorySessionRepository, InMemoryUserRepository, PostRepository,
+        UserRepository,
+    };
+    use crate::domain::{Password, PasswordParams, User};
+    use crate::fixtures::blog_config;
+    use crate::infrastructure::{JwtConfig, JwtService};
+
+    /// Service over in-memory `posts` by `users`
+    fn service(
+        users: Arc<InMemoryUserRepository>,
+        posts: Arc<InMemoryPostRepository>,
+    ) -> BlogGrpcService {
+        let comments = Arc::new(InMemoryCommentRepository::new(users.clone()));
+        let jwt_service = Arc::new(JwtService::new(&JwtConfig {
+            secret: "test-secret-key-minimum-32-characters".to_string(),
+            previous_secrets: Vec::new(),
+            token_expiry_hours: 1,
+            email_verification_expiry_hours: 24,
+            refresh_token_expiry_days: 30,
+            leeway_secs: 60,
+            issuer: "blog-server".to_string(),
+            audience: "blog-api".to_string(),
+            validate_issuer_audience: true,
+        }));
+        BlogGrpcService::new(
+            Arc::new(AuthService::new(
+                users,
+                Arc::new(InMemorySessionRepository::new()),
+                jwt_service,
+                &AuthConfig {
+                    password_version_cache_ttl: Duration::ZERO,
+                    impersonation_ttl: Duration::from_mins(15),
+                    password_params: PasswordParams::default(),
+                    rehash_on_login: false,
+                    session_cleanup_interval: Duration::ZERO,
+                },
+            )),
+            Arc::new(BlogService::new(posts.clone(), blog_config())),
+            Arc::new(CommentService::new(
+                comments,
+                posts,
+                ContentSanitizer::default(),
+            )),
+            PaginationConfig {
+                default_limit: 10,
+                max_limit: 100,
+                excerpt_chars: 200,
+                default_sort: PostSort::CreatedAtAsc,
+            },
+            MaintenanceMode::default(),
+        )
+    }
+
+    async fn author(users: &InMemoryUserRepository) -> User {
+        users
+            .create(
+                "author",
+                "author@example.com",
+                &Password::from_hash(String::new()),
+            )
+            .await
+            .unwrap()
+    }
+
+    fn list_request(cursor: Option<String>) -> Request<ListPostsRequest> {
+        Request::new(ListPostsRequest {
+            page: 1,
+            page_size: 2,
+            author_id: None,
+            sort: None,
+            cursor,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_posts_follows_next_cursor() {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let author = author(&users).await;
+        for title in ["First", "Second", "Third"] {
+            posts
+                .create(title, "Content", author.id, &[], PostStatus::Published)
+                .await
+                .unwrap();
+        }
+        let service = service(users, posts);
+
+        let first = service.list_posts(list_request(None)).await.unwrap();
+        let first = first.into_inner();
+        let second = service
+            .list_posts(list_request(first.next_cursor.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let titles = |page: &ListPostsResponse| -> Vec<String> {
+            page.posts.iter().map(|post| post.title.clone()).collect()
+        };
+        assert_eq!(titles(&first), ["First", "Second"]);
+        assert!(first.has_more);
+        assert_eq!(titles(&second), ["Third"]);
+        assert!(!second.has_more);
+        assert_eq!(second.next_cursor, None);
+        assert_eq!(second.total_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_drafts_are_hidden_from_anonymous_callers() {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let author = author(&users).await;
+        let draft = posts
+            .create("Draft", "Content", author.id, &[], PostStatus::Draft)
+            .await
+            .unwrap();
+        let service = service(users, posts);
+
+        let list = service.list_posts(list_request(None)).await.unwrap();
+        assert_eq!(list.into_inner().total_count, 0);
+
+        let status = service
+            .get_post(Request::new(GetPostRequest {
+                post_id: draft.id.to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_comments_are_unimplemented() {
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let author = author(&users).await;
+        let post = posts
+            .create("Post", "Content", author.id, &[], PostStatus::Published)
+            .await
+            .unwrap();
+        let service = service(users, posts).with_features(Features {
+            comments: false,
+            ..Features::default()
+        });
+
+        let status = service
+            .list_comments(Request::new(ListCommentsRequest {
+                post_id: post.id.to_string(),
+                page: 1,
+                page_size: 10,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+    }
+
+    #[tokio::test]
+    async fn test_tls_server_rejects_plaintext_clients() {
+        use blog_client::{BlogClientError, GrpcBlogClient, GrpcConfig};
+        use tokio_stream::wrappers::TcpListenerStream;
+        use tonic::transport::Server;
+
+        use crate::presentation::config::GrpcTlsConfig;
+        use proto::blog_service_server::BlogServiceServer;
+
+        let dir = std::env::temp_dir()
+            .join(format!("blog-grpc-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let certified =
+            rcgen::generate_simple_self_signed(["localhost".to_string()])
+                .unwrap();
+        let tls = GrpcTlsConfig {
+            cert_path: dir.join("cert.pem"),
+            key_path: dir.join("key.pem"),
+        };
+        std::fs::write(&tls.cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&tls.key_path, certified.signing_key.serialize_pem())
+            .unwrap();
+
+        let users = Arc::new(InMemoryUserRepository::new());
+        let posts = Arc::new(InMemoryPostRepository::new(users.clone()));
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = Server::builder()
+            .tls_config(tls.load().unwrap())
+            .unwrap()
+            .add_service(BlogServiceServer::new(service(users, posts)))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        tokio::spawn(server);
+
+        let config = GrpcConfig::default().ca_cert(&tls.cert_path);
+        let mut client =
+            GrpcBlogClient::new(&format!("https://localhost:{port}"), &config)
+                .unwrap();
+        assert!(client.get_health().await.unwrap().is_ok());
+
+        let mut plaintext = GrpcBlogClient::new(
+            &format!("http://localhost:{port}"),
+            &GrpcConfig::default(),
+        )
+        .unwrap();
+        let error = plaintext.get_health().await.unwrap_err();
+        assert!(
+            matches!(error, BlogClientError::ConnectionRejected(_)),
+            "{error:?}"
+        );
+        assert!(error.to_string().contains("https://"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
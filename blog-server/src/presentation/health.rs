@@ -0,0 +1,49 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::infrastructure::Database;
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    /// `"unavailable"` when the database does not answer
+    pub status: &'static str,
+    pub timestamp: String,
+    /// Round trip of the database ping; absent without a database or
+    /// when the ping failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database_latency_ms: Option<f64>,
+}
+
+impl HealthResponse {
+    pub fn new(status: &'static str, database_latency_ms: Option<f64>) -> Self {
+        Self {
+            status,
+            timestamp: chrono::Local::now()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            database_latency_ms,
+        }
+    }
+
+    /// Pings `database`, if the server has one. Shared by the HTTP
+    /// readiness probe and the gRPC `HealthCheck`.
+    pub async fn probe(database: Option<&Database>) -> Self {
+        let Some(database) = database else {
+            return Self::new("ok", None);
+        };
+
+        match database.health_check().await {
+            Ok(latency) => {
+                Self::new("ok", Some(latency.as_secs_f64() * 1000.0))
+            }
+            Err(e) => {
+                tracing::warn!("Database health check failed: {e}");
+                Self::new("unavailable", None)
+            }
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
@@ -1,230 +1,3030 @@
 use axum::{
-    Json, Router,
+    BoxError, Json, Router,
+    body::Body,
+    error_handling::HandleErrorLayer,
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{delete, get, post, put},
+    http::{
+        HeaderMap, HeaderValue, Method, StatusCode,
+        header::{
+            CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MATCH,
+            IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, ORIGIN,
+            USER_AGENT,
+        },
+    },
+    middleware,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{MethodRouter, any, delete, get, post, put},
 };
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use futures_util::{Stream, StreamExt, stream};
+use serde::Deserialize;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::IntoParams;
+use uuid::Uuid;
 
-use super::config::{PaginationConfig, ServerConfig};
+use super::config::{
+    CorsConfig, Features, PaginationConfig, RegistrationConfig, ServerConfig,
+};
 use super::dto::{
-    AuthResponseDto, CreatePostDto, LoginDto, PostDto, PostsListDto,
-    RegisterDto, UpdatePostDto, UserDto,
+    ActivityDayDto, ActivityDto, AuthResponseDto, BulkTagDto, BulkTagResultDto,
+    ChangePasswordDto, CommentDto, CreateCommentDto, CreatePostDto, LoginDto,
+    MaintenanceDto, Page, PostDto, PostFields, PostNeighborsDto, PostsListDto,
+    RefreshTokenDto, RegisterDto, SessionDto, TagCountDto, UpdatePostDto,
+    UserDto, VerifyEmailDto, session_label,
+};
+use super::health::HealthResponse;
+use super::maintenance::MaintenanceMode;
+use super::metrics::{Metrics, metrics_handler, track_requests};
+use super::middleware::{
+    AdminUser, AuthenticatedUser, ErrorResponse, OptionalAuthenticatedUser,
+    log_bodies,
 };
-use super::middleware::{AuthenticatedUser, ErrorResponse};
-use crate::application::{AuthService, BlogService};
+use super::openapi::{self, CommentsPage, PostsList, PostsPage};
+use super::rate_limit::{RateLimiter, quota, rate_limit};
+use super::request_id::{REQUEST_ID_HEADER, request_id_layers};
+use crate::application::{AuthService, BlogService, CommentService};
 use crate::domain::{
-    CreatePostCommand, DomainError, LoginCommand, RegisterCommand,
-    UpdatePostCommand,
+    AuthorSummary, CreateCommentCommand, CreatePostCommand, DomainError,
+    LoginCommand, Post, PostCursor, PostStatus, RegisterCommand, TagAction,
+    TagFilter, TagMode, UpdatePostCommand,
 };
-use tower_governor::{GovernorLayer, governor::GovernorConfigBuilder};
+use crate::infrastructure::Database;
+use tower::ServiceBuilder;
+use tower_http::compression::{
+    CompressionLayer,
+    predicate::{NotForContentType, Predicate, SizeAbove},
+};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
+use validator::Validate;
 
 #[derive(Clone)]
 pub struct AppState {
     pub auth_service: Arc<AuthService>,
     pub blog_service: Arc<BlogService>,
+    pub comment_service: Arc<CommentService>,
     pub pagination_config: PaginationConfig,
+    pub registration_config: RegistrationConfig,
+    pub maintenance: MaintenanceMode,
+    /// Body limit of `POST /posts/raw`
+    pub raw_post_max_bytes: usize,
+    pub features: Features,
+    /// Pinged by the health check; `None` with the in-memory backend
+    pub database: Option<Database>,
 }
 
 // Convert DomainError to HTTP response
 impl IntoResponse for DomainError {
     fn into_response(self) -> axum::response::Response {
         let (status, message) = match &self {
-            Self::UserNotFound | Self::PostNotFound => {
+            Self::UserNotFound | Self::PostNotFound | Self::SessionNotFound => {
                 (StatusCode::NOT_FOUND, self.to_string())
             }
-            Self::UserAlreadyExists => (StatusCode::CONFLICT, self.to_string()),
-            Self::InvalidCredentials => {
+            Self::UserAlreadyExists | Self::Conflict => {
+                (StatusCode::CONFLICT, self.to_string())
+            }
+            Self::IdempotencyKeyReused => {
+                (StatusCode::UNPROCESSABLE_ENTITY, self.to_string())
+            }
+            Self::InvalidCredentials | Self::InvalidToken => {
                 (StatusCode::UNAUTHORIZED, self.to_string())
             }
-            Self::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
-            Self::ValidationError(_) => {
+            Self::Forbidden | Self::EmailNotVerified => {
+                (StatusCode::FORBIDDEN, self.to_string())
+            }
+            Self::PostQuotaExceeded(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, self.to_string())
+            }
+            Self::PayloadTooLarge(_) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, self.to_string())
+            }
+            Self::ValidationError(_) | Self::InvalidFields(_) => {
                 (StatusCode::BAD_REQUEST, self.to_string())
             }
+            Self::Maintenance => {
+                (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
+            }
+            Self::FeatureDisabled(_) => {
+                (StatusCode::NOT_IMPLEMENTED, self.to_string())
+            }
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error".to_string(),
             ),
         };
 
-        (status, Json(ErrorResponse { error: message })).into_response()
+        let code = self.code();
+        let fields = match self {
+            Self::InvalidFields(fields) => Some(fields),
+            _ => None,
+        };
+        let body = ErrorResponse {
+            error: message,
+            code,
+            fields,
+        };
+        (status, Json(body)).into_response()
     }
 }
 
 // ============ Auth Handlers ============
 
+/// Header that opts a registration or post creation in to idempotent
+/// retries
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "auth",
+    params(
+        ("Idempotency-Key" = Option<String>, Header,
+            description = "Lets a retry with the same details get a token for the account it created"),
+    ),
+    request_body = RegisterDto,
+    responses(
+        (status = 201, description = "Account created", body = AuthResponseDto),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 403, description = "Origin not trusted", body = ErrorResponse),
+        (status = 409, description = "Username or email taken", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
 pub async fn register(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(dto): Json<RegisterDto>,
 ) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+
+    let origin = headers.get(ORIGIN).and_then(|v| v.to_str().ok());
+    if !state.registration_config.allows_origin(origin) {
+        tracing::warn!(
+            "Registration rejected from untrusted origin {:?}",
+            origin
+        );
+        return Err(DomainError::Forbidden);
+    }
+
+    dto.validate()?;
+
     let command = RegisterCommand {
         username: dto.username,
         email: dto.email,
         password: dto.password,
+        idempotency_key: headers
+            .get(IDEMPOTENCY_KEY)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
     };
 
     let result = state.auth_service.register(command).await?;
 
     let response = AuthResponseDto {
         token: result.token,
+        refresh_token: result.refresh_token,
         user: UserDto::from(&result.user),
     };
 
     Ok((StatusCode::CREATED, Json(response)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginDto,
+    responses(
+        (status = 200, description = "Logged in", body = AuthResponseDto),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 401, description = "Wrong username or password", body = ErrorResponse),
+    )
+)]
 pub async fn login(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(dto): Json<LoginDto>,
 ) -> Result<impl IntoResponse, DomainError> {
+    dto.validate()?;
+
     let command = LoginCommand {
         username: dto.username,
         password: dto.password,
+        device: headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(session_label),
     };
 
     let result = state.auth_service.login(command).await?;
 
     let response = AuthResponseDto {
         token: result.token,
+        refresh_token: result.refresh_token,
+        user: UserDto::from(&result.user),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/verify-email",
+    tag = "auth",
+    request_body = VerifyEmailDto,
+    responses(
+        (status = 200, description = "Email verified", body = AuthResponseDto),
+        (status = 400, description = "Invalid, expired or already used token", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(dto): Json<VerifyEmailDto>,
+) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+    let result = state.auth_service.verify_email(&dto.token).await?;
+
+    let response = AuthResponseDto {
+        token: result.token,
+        refresh_token: result.refresh_token,
+        user: UserDto::from(&result.user),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Exchanges a refresh token for a new access token. Unlike the other auth
+/// endpoints it keeps working in maintenance mode, like login.
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "auth",
+    request_body = RefreshTokenDto,
+    responses(
+        (status = 200, description = "New access token", body = AuthResponseDto),
+        (status = 401, description = "Invalid or expired refresh token", body = ErrorResponse),
+    )
+)]
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    Json(dto): Json<RefreshTokenDto>,
+) -> Result<impl IntoResponse, DomainError> {
+    let result = state.auth_service.refresh(&dto.refresh_token).await?;
+
+    let response = AuthResponseDto {
+        token: result.token,
+        refresh_token: result.refresh_token,
+        user: UserDto::from(&result.user),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Revokes the session of the token making the request. Tokens issued
+/// before sessions existed have nothing to revoke and expire on their own.
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, DomainError> {
+    if let Some(jti) = user.session_id {
+        state.auth_service.revoke_session(user.user_id, jti).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Changes the password after checking the current one. All sessions end,
+/// so the response carries a new token for the caller to continue with.
+#[utoipa::path(
+    post,
+    path = "/auth/change-password",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    request_body = ChangePasswordDto,
+    responses(
+        (status = 200, description = "Password changed", body = AuthResponseDto),
+        (status = 400, description = "Invalid new password", body = ErrorResponse),
+        (status = 401, description = "Wrong current password", body = ErrorResponse),
+        (status = 403, description = "Impersonation token", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(dto): Json<ChangePasswordDto>,
+) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+    // Support sessions never learn or set the user's password
+    if user.impersonator_id.is_some() {
+        return Err(DomainError::Forbidden);
+    }
+    dto.validate()?;
+
+    let result = state
+        .auth_service
+        .change_password(user.user_id, &dto.current_password, &dto.new_password)
+        .await?;
+
+    let response = AuthResponseDto {
+        token: result.token,
+        refresh_token: result.refresh_token,
         user: UserDto::from(&result.user),
     };
 
     Ok((StatusCode::OK, Json(response)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/auth/sessions",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Active sessions", body = Vec<SessionDto>),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, DomainError> {
+    let sessions = state.auth_service.sessions(user.user_id).await?;
+
+    let response: Vec<SessionDto> = sessions
+        .into_iter()
+        .map(|session| SessionDto::new(session, user.session_id))
+        .collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions/{jti}",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    params(("jti" = Uuid, Path, description = "Session id")),
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 404, description = "No such session", body = ErrorResponse),
+    )
+)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(jti): Path<Uuid>,
+) -> Result<impl IntoResponse, DomainError> {
+    state.auth_service.revoke_session(user.user_id, jti).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 // ============ Post Handlers ============
 
+/// Longest idempotency key, matching the `idempotency_keys.key` column
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<&str>, DomainError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => {
+            Ok(Some(key))
+        }
+        _ => Err(DomainError::ValidationError(format!(
+            "Idempotency-Key must be 1 to {MAX_IDEMPOTENCY_KEY_LEN} \
+             printable ASCII characters"
+        ))),
+    }
+}
+
+/// 201 with the new post, or 200 with the original one when the
+/// `Idempotency-Key` was already used
+#[utoipa::path(
+    post,
+    path = "/posts",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(
+        ("Idempotency-Key" = Option<String>, Header,
+            description = "1 to 255 characters; a repeat returns the post created with it"),
+    ),
+    request_body = CreatePostDto,
+    responses(
+        (status = 201, description = "Post created", body = PostDto),
+        (status = 200, description = "Idempotency-Key already used", body = PostDto),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "Email not verified", body = ErrorResponse),
+        (status = 422, description = "Idempotency-Key used for another request or a deleted post", body = ErrorResponse),
+        (status = 429, description = "Post quota reached", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
 pub async fn create_post(
     State(state): State<AppState>,
     user: AuthenticatedUser,
+    headers: HeaderMap,
     Json(dto): Json<CreatePostDto>,
 ) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+    dto.validate()?;
+    if !dto.tags.is_empty() && !state.features.tags {
+        return Err(DomainError::FeatureDisabled("tags"));
+    }
+    let key = idempotency_key(&headers)?;
+
+    let command = CreatePostCommand {
+        title: dto.title,
+        content: dto.content,
+        tags: dto.tags,
+        status: post_status(dto.status.as_deref())?,
+    };
+
+    let (post, created) = if let Some(key) = key {
+        state
+            .blog_service
+            .create_post_idempotent(
+                user.user_id,
+                user.email_verified,
+                command,
+                key,
+            )
+            .await?
+    } else {
+        let post = state
+            .blog_service
+            .create_post(user.user_id, user.email_verified, command)
+            .await?;
+        (post, true)
+    };
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+
+    Ok((status, Json(PostDto::from(post))))
+}
+
+/// `status` of a new post; drafts unless asked otherwise
+fn post_status(status: Option<&str>) -> Result<PostStatus, DomainError> {
+    status.map_or_else(|| Ok(PostStatus::default()), str::parse)
+}
+
+/// Header carrying the title for `POST /posts/raw`
+const POST_TITLE_HEADER: &str = "x-post-title";
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RawPostQuery {
+    /// Used when the title is not ASCII-safe enough for a header
+    pub title: Option<String>,
+    /// `draft` (default) or `published`
+    pub status: Option<String>,
+}
+
+/// Creates a post from a `text/plain` body taken verbatim as the content.
+/// Skips the JSON round trip, so very large posts are read once into the
+/// final string instead of as bytes plus a decoded copy.
+///
+/// The content is still held in memory whole, up to `raw_post_max_bytes`:
+/// sanitizing and the single-row insert both need all of it, so the body
+/// is read in chunks but not written through to the database.
+#[utoipa::path(
+    post,
+    path = "/posts/raw",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(
+        RawPostQuery,
+        ("X-Post-Title" = Option<String>, Header,
+            description = "Title; takes precedence over `?title=`"),
+    ),
+    request_body(content = String, content_type = "text/plain"),
+    responses(
+        (status = 201, description = "Post created", body = PostDto),
+        (status = 400, description = "Missing title or invalid body", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 413, description = "Body over `RAW_POST_MAX_BYTES`", body = ErrorResponse),
+    )
+)]
+pub async fn create_raw_post(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Query(query): Query<RawPostQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+
+    let title = headers
+        .get(POST_TITLE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or(query.title)
+        .ok_or_else(|| {
+            DomainError::ValidationError(
+                "title is required (X-Post-Title header or ?title=)"
+                    .to_string(),
+            )
+        })?;
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+    let content =
+        read_text_body(body, content_length, state.raw_post_max_bytes).await?;
+
+    let dto = CreatePostDto {
+        title,
+        content,
+        tags: Vec::new(),
+        status: query.status,
+    };
+    dto.validate()?;
+
     let command = CreatePostCommand {
         title: dto.title,
         content: dto.content,
+        tags: dto.tags,
+        status: post_status(dto.status.as_deref())?,
     };
 
     let post = state
         .blog_service
-        .create_post(user.user_id, command)
+        .create_post(user.user_id, user.email_verified, command)
         .await?;
 
     Ok((StatusCode::CREATED, Json(PostDto::from(post))))
 }
 
+/// Reads a body chunk by chunk into one buffer that becomes the string
+/// without copying. A declared `Content-Length` over `limit` is refused
+/// before reading, and a body growing past it stops at that chunk.
+async fn read_text_body(
+    body: Body,
+    content_length: Option<usize>,
+    limit: usize,
+) -> Result<String, DomainError> {
+    if content_length.is_some_and(|len| len > limit) {
+        return Err(DomainError::PayloadTooLarge(limit));
+    }
+
+    let mut buf = Vec::with_capacity(content_length.unwrap_or(0));
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| {
+            DomainError::ValidationError(format!("Failed to read body: {e}"))
+        })?;
+        if buf.len() + chunk.len() > limit {
+            return Err(DomainError::PayloadTooLarge(limit));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(buf).map_err(|_| {
+        DomainError::ValidationError("body must be valid UTF-8".to_string())
+    })
+}
+
+/// IMF-fixdate format used by HTTP date headers (RFC 9110 5.6.7)
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetPostQuery {
+    /// `full` (default), `summary`, or comma-separated post keys
+    pub fields: Option<String>,
+    /// Add an `author` card; its email is only shown to admins
+    #[serde(default)]
+    pub include_author: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "Post id"),
+        GetPostQuery,
+        ("If-None-Match" = Option<String>, Header,
+            description = "Answer 304 if the post still has this `ETag`"),
+        ("If-Modified-Since" = Option<String>, Header,
+            description = "Answer 304 if the post has not changed since"),
+    ),
+    responses(
+        (status = 200, description = "The post", body = PostDto),
+        (status = 304, description = "Not modified"),
+        (status = 400, description = "Unknown field", body = ErrorResponse),
+        (status = 404, description = "No such post, or a draft of someone else", body = ErrorResponse),
+    )
+)]
 pub async fn get_post(
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Query(query): Query<GetPostQuery>,
+    viewer: OptionalAuthenticatedUser,
+    headers: HeaderMap,
+) -> Result<Response, DomainError> {
+    let fields = parse_fields(query.fields.as_deref())?;
+    let post = state
+        .blog_service
+        .get_post(id, query.include_author, viewer.user_id())
+        .await?;
+    let post = author_visible_to(post, &viewer);
+    let etag = post_etag(post.updated_at, &representation(&fields, &post));
+    let not_modified = is_not_modified(&headers, &etag, post.updated_at);
+    let validators =
+        [(LAST_MODIFIED, http_date(post.updated_at)), (ETAG, etag)];
+
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, validators).into_response());
+    }
+
+    let post = fields.render(post, state.pagination_config.excerpt_chars);
+    Ok((StatusCode::OK, validators, Json(post)).into_response())
+}
+
+/// `post` with the author's email hidden unless `viewer` is an admin
+fn author_visible_to(
+    mut post: Post,
+    viewer: &OptionalAuthenticatedUser,
+) -> Post {
+    if !viewer.is_admin() {
+        post.author = post.author.map(AuthorSummary::without_email);
+    }
+    post
+}
+
+fn parse_fields(fields: Option<&str>) -> Result<PostFields, DomainError> {
+    fields.map_or(Ok(PostFields::Full), str::parse)
+}
+
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format(HTTP_DATE_FORMAT).to_string()
+}
+
+/// Strong entity tag of one representation of a post version: its
+/// `updated_at` in microseconds, the precision Postgres keeps, then what
+/// the body is made of. `If-Match` only looks at the version part.
+fn post_etag(updated_at: DateTime<Utc>, representation: &str) -> String {
+    format!("\"{}-{representation}\"", updated_at.timestamp_micros())
+}
+
+/// Shape of a post body for its `ETag`: the field set and whether the
+/// author card, and its email, are included
+fn representation(fields: &PostFields, post: &Post) -> String {
+    let author = match &post.author {
+        None => "none",
+        Some(author) if author.email.is_some() => "author+email",
+        Some(_) => "author",
+    };
+    format!("{}-{author}", fields.key())
+}
+
+/// Whether the client already has this representation, by `If-None-Match`
+/// against `etag` or else `If-Modified-Since` against `updated_at`. HTTP
+/// dates have second precision, so sub-second changes are ignored there.
+fn is_not_modified(
+    headers: &HeaderMap,
+    etag: &str,
+    updated_at: DateTime<Utc>,
+) -> bool {
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110 13.2.2)
+    if let Some(tags) = headers.get(IF_NONE_MATCH) {
+        return tags.to_str().is_ok_and(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        });
+    }
+
+    headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            NaiveDateTime::parse_from_str(value, HTTP_DATE_FORMAT).ok()
+        })
+        .is_some_and(|since| {
+            updated_at.timestamp() <= since.and_utc().timestamp()
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/neighbors",
+    tag = "posts",
+    params(("id" = i64, Path, description = "Post id")),
+    responses(
+        (status = 200, description = "Posts created right before and after", body = PostNeighborsDto),
+        (status = 404, description = "No such post", body = ErrorResponse),
+    )
+)]
+pub async fn post_neighbors(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, DomainError> {
+    let neighbors = state.blog_service.post_neighbors(id).await?;
+    Ok((StatusCode::OK, Json(PostNeighborsDto::from(neighbors))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/random",
+    tag = "posts",
+    responses(
+        (status = 200, description = "A random post", body = PostDto),
+        (status = 404, description = "There are no posts", body = ErrorResponse),
+    )
+)]
+pub async fn random_post(
+    State(state): State<AppState>,
 ) -> Result<impl IntoResponse, DomainError> {
-    let post = state.blog_service.get_post(id).await?;
+    let post = state.blog_service.random_post().await?;
     Ok((StatusCode::OK, Json(PostDto::from(post))))
 }
 
+/// `If-Match` as the `updated_at` an edit is based on. Takes one strong
+/// `ETag` as sent with the post, of any representation since only its
+/// version part counts; `*` matches any version.
+fn if_match(headers: &HeaderMap) -> Result<Option<DateTime<Utc>>, DomainError> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .and_then(|tag| tag.split('-').next())
+        .and_then(|micros| micros.parse().ok())
+        .and_then(DateTime::from_timestamp_micros)
+        .map(Some)
+        .ok_or_else(|| {
+            DomainError::ValidationError(
+                "If-Match must be the ETag of the post".to_string(),
+            )
+        })
+}
+
+/// With `If-Match`, the update is refused with 409 when the post has
+/// changed since that `ETag`, so concurrent edits cannot silently
+/// overwrite each other
+#[utoipa::path(
+    put,
+    path = "/posts/{id}",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = i64, Path, description = "Post id"),
+        ("If-Match" = Option<String>, Header,
+            description = "The post's `ETag` the edit is based on"),
+    ),
+    request_body = UpdatePostDto,
+    responses(
+        (status = 200, description = "Post updated", body = PostDto),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "Not the author", body = ErrorResponse),
+        (status = 404, description = "No such post", body = ErrorResponse),
+        (status = 409, description = "Post changed since `If-Match`", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
 pub async fn update_post(
     State(state): State<AppState>,
     user: AuthenticatedUser,
     Path(id): Path<i64>,
+    headers: HeaderMap,
     Json(dto): Json<UpdatePostDto>,
 ) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+    dto.validate()?;
+    if dto.tags.is_some() && !state.features.tags {
+        return Err(DomainError::FeatureDisabled("tags"));
+    }
+
     let command = UpdatePostCommand {
         title: dto.title,
         content: dto.content,
+        tags: dto.tags,
+        expected_updated_at: if_match(&headers)?,
     };
 
     let post = state
         .blog_service
-        .update_post(id, user.user_id, command)
+        .update_post(id, user.user_id, user.role, command)
         .await?;
 
-    Ok((StatusCode::OK, Json(PostDto::from(post))))
+    let representation = representation(&PostFields::Full, &post);
+    let etag = [(ETAG, post_etag(post.updated_at, &representation))];
+    Ok((StatusCode::OK, etag, Json(PostDto::from(post))))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/posts/{id}",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(("id" = i64, Path, description = "Post id")),
+    responses(
+        (status = 204, description = "Post deleted"),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "Not the author, or impersonating", body = ErrorResponse),
+        (status = 404, description = "No such post", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
 pub async fn delete_post(
     State(state): State<AppState>,
     user: AuthenticatedUser,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, DomainError> {
-    state.blog_service.delete_post(id, user.user_id).await?;
+    state.maintenance.ensure_writable()?;
+    // Support sessions may look and fix, but not destroy
+    if user.impersonator_id.is_some() {
+        return Err(DomainError::Forbidden);
+    }
+    state
+        .blog_service
+        .delete_post(id, user.user_id, user.role)
+        .await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
-#[derive(Deserialize, Default)]
+/// The author restores their own post, also through a support session
+/// for them; an admin may restore anyone's
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/restore",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(("id" = i64, Path, description = "Post id")),
+    responses(
+        (status = 200, description = "Post restored", body = PostDto),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "Neither the author nor an admin", body = ErrorResponse),
+        (status = 404, description = "No such deleted post", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
+pub async fn restore_post(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+    let post = state
+        .blog_service
+        .restore_post(id, user.user_id, user.role)
+        .await?;
+    tracing::info!(
+        target: "audit",
+        admin_id = user.impersonator_id,
+        user_id = user.user_id,
+        post_id = id,
+        "Post restored"
+    );
+    Ok((StatusCode::OK, Json(PostDto::from(post))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/publish",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(("id" = i64, Path, description = "Post id")),
+    responses(
+        (status = 200, description = "Post published", body = PostDto),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 404, description = "No post of this author", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
+pub async fn publish_post(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, DomainError> {
+    set_post_status(&state, &user, id, PostStatus::Published).await
+}
+
+/// Turns a published post back into a draft, hiding it from everyone
+/// but its author
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/unpublish",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(("id" = i64, Path, description = "Post id")),
+    responses(
+        (status = 200, description = "Post is a draft again", body = PostDto),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 404, description = "No post of this author", body = ErrorResponse),
+        (status = 503, description = "Maintenance mode", body = ErrorResponse),
+    )
+)]
+pub async fn unpublish_post(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, DomainError> {
+    set_post_status(&state, &user, id, PostStatus::Draft).await
+}
+
+async fn set_post_status(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    id: i64,
+    status: PostStatus,
+) -> Result<impl IntoResponse + use<>, DomainError> {
+    state.maintenance.ensure_writable()?;
+    let post = state
+        .blog_service
+        .set_post_status(id, user.user_id, status)
+        .await?;
+    Ok((StatusCode::OK, Json(PostDto::from(post))))
+}
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ListPostsQuery {
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// `full` (default), `summary`, or comma-separated post keys
+    pub fields: Option<String>,
+    pub sort: Option<String>,
+    /// Comma-separated tags to filter by
+    pub tags: Option<String>,
+    /// A single tag to filter by, combined with `tags`
+    pub tag: Option<String>,
+    /// `all` (default) or `any` of `tags`
+    pub tag_mode: Option<String>,
+    /// Continue after this `next_cursor`; `offset` is ignored then
+    pub cursor: Option<String>,
+    /// Add an `author` card to each post; its email is only shown to
+    /// admins
+    #[serde(default)]
+    pub include_author: bool,
 }
 
+#[utoipa::path(
+    get,
+    path = "/posts",
+    tag = "posts",
+    params(ListPostsQuery),
+    responses(
+        (status = 200, description = "A page of posts", body = PostsList),
+        (status = 400, description = "Invalid paging, sort, fields or cursor", body = ErrorResponse),
+    )
+)]
 pub async fn list_posts(
     State(state): State<AppState>,
     Query(query): Query<ListPostsQuery>,
+    viewer: OptionalAuthenticatedUser,
 ) -> Result<impl IntoResponse, DomainError> {
     let config = &state.pagination_config;
-    let limit = query
-        .limit
-        .unwrap_or(config.default_limit)
-        .clamp(1, config.max_limit);
-    let offset = query.offset.unwrap_or(0).max(0);
+    let (limit, offset) = config.page(query.limit, query.offset)?;
+    let sort = query
+        .sort
+        .as_deref()
+        .map_or(Ok(config.default_sort), str::parse)?;
+    let fields = parse_fields(query.fields.as_deref())?;
+    let tag_mode = query
+        .tag_mode
+        .as_deref()
+        .map(str::parse::<TagMode>)
+        .transpose()?
+        .unwrap_or_default();
+    let tags = query
+        .tags
+        .iter()
+        .flat_map(|tags| tags.split(','))
+        .chain(query.tag.as_deref())
+        .map(str::to_string)
+        .collect();
+    let filter = TagFilter::new(tags, tag_mode);
+    if filter.is_some() && !state.features.tags {
+        return Err(DomainError::FeatureDisabled("tags"));
+    }
+
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(str::parse::<PostCursor>)
+        .transpose()?;
+
+    let page = if let Some(cursor) = &cursor {
+        let (items, has_more) = state
+            .blog_service
+            .list_posts_after(
+                cursor,
+                limit,
+                sort,
+                filter.as_ref(),
+                query.include_author,
+                viewer.user_id(),
+            )
+            .await?;
+        let total = state
+            .blog_service
+            .count_posts(filter.as_ref(), viewer.user_id())
+            .await?;
+        Page {
+            items,
+            total,
+            limit,
+            offset,
+            has_more,
+        }
+    } else {
+        let (posts, total) = state
+            .blog_service
+            .list_posts(
+                limit,
+                offset,
+                sort,
+                filter.as_ref(),
+                query.include_author,
+                viewer.user_id(),
+            )
+            .await?;
+        Page::new(posts, total, limit, offset)
+    };
 
-    let (posts, total) = state.blog_service.list_posts(limit, offset).await?;
+    let next_cursor = page
+        .items
+        .last()
+        .filter(|_| page.has_more)
+        .map(|post| PostCursor::new(post, sort).to_string());
+    let page = page.map(|post| {
+        fields.render(author_visible_to(post, &viewer), config.excerpt_chars)
+    });
 
     let response = PostsListDto {
-        posts: posts.into_iter().map(PostDto::from).collect(),
-        total,
-        limit,
-        offset,
+        page,
+        sort: sort.as_str(),
+        next_cursor,
     };
 
     Ok((StatusCode::OK, Json(response)))
 }
 
-// ============ Health Check ============
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchPostsQuery {
+    /// Words that must all occur in the title or content
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
 
-#[derive(Serialize)]
-pub struct HealthResponse {
-    pub status: &'static str,
-    pub timestamp: String,
+/// Full-text search, best matches first
+#[utoipa::path(
+    get,
+    path = "/posts/search",
+    tag = "posts",
+    params(SearchPostsQuery),
+    responses(
+        (status = 200, description = "A page of matches", body = PostsPage),
+        (status = 400, description = "Invalid query or paging", body = ErrorResponse),
+    )
+)]
+pub async fn search_posts(
+    State(state): State<AppState>,
+    Query(query): Query<SearchPostsQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let config = &state.pagination_config;
+    let (limit, offset) = config.page(query.limit, query.offset)?;
+
+    let (posts, total) = state
+        .blog_service
+        .search_posts(query.q.as_deref().unwrap_or_default(), limit, offset)
+        .await?;
+    let page = Page::new(posts, total, limit, offset).map(PostDto::from);
+
+    Ok((StatusCode::OK, Json(page)))
 }
 
-pub async fn health_check() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok",
-        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-    })
+/// Server-sent events feed of newly created posts
+#[utoipa::path(
+    get,
+    path = "/posts/stream",
+    tag = "posts",
+    responses(
+        (status = 200, description = "`post_created` events, each carrying the new post",
+            content_type = "text/event-stream", body = PostDto),
+    )
+)]
+pub async fn stream_posts(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let receiver = state.blog_service.subscribe();
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(post) => {
+                    let event = Event::default()
+                        .event("post_created")
+                        .json_data(PostDto::from(post));
+                    return Some((event, receiver));
+                }
+                // Broadcast already dropped the oldest events; keep going
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(
+                        "Post feed subscriber lagged, skipped {} events",
+                        skipped
+                    );
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
 }
 
-// ============ Router ============
+// ============ User Handlers ============
 
-pub fn router(state: AppState, config: ServerConfig) -> Router {
-    let governor_conf = Arc::new(
-        GovernorConfigBuilder::default()
-            .per_second(config.rate_limit_per_second)
-            .burst_size(config.rate_limit_burst)
-            .finish()
-            .expect("Failed to build rate limit config"),
-    );
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
 
-    let auth_routes = Router::new()
-        .route("/register", post(register))
-        .route("/login", post(login));
+/// Streams the caller's posts as newline-delimited JSON
+#[utoipa::path(
+    get,
+    path = "/users/me/export",
+    tag = "users",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The caller's posts, one per line",
+            content_type = "application/x-ndjson", body = PostDto),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+pub async fn export_my_posts(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> impl IntoResponse {
+    let body = state.blog_service.export_posts(user.user_id).map(|batch| {
+        let batch = batch.inspect_err(|e| {
+            tracing::error!("Post export failed mid-stream: {}", e);
+        })?;
+        posts_to_ndjson(batch)
+    });
 
-    let posts_routes = Router::new()
-        .route("/", get(list_posts))
-        .route("/", post(create_post))
-        .route("/{id}", get(get_post))
-        .route("/{id}", put(update_post))
-        .route("/{id}", delete(delete_post));
+    (
+        [
+            (CONTENT_TYPE, NDJSON_CONTENT_TYPE),
+            (CONTENT_DISPOSITION, "attachment; filename=\"posts.ndjson\""),
+        ],
+        Body::from_stream(body),
+    )
+}
 
-    let api_v1 = Router::new()
-        .route("/health", get(health_check))
-        .nest("/auth", auth_routes)
-        .nest("/posts", posts_routes)
-        .with_state(state);
+fn posts_to_ndjson(posts: Vec<Post>) -> Result<Vec<u8>, BoxError> {
+    let mut buf = Vec::new();
+    for post in posts {
+        serde_json::to_writer(&mut buf, &PostDto::from(post))?;
+        buf.push(b'\n');
+    }
+    Ok(buf)
+}
 
-    Router::new()
-        .nest("/api/v1", api_v1)
-        .layer(GovernorLayer::new(governor_conf))
-        .layer(TraceLayer::new_for_http())
+/// Adds or removes a tag on several of the caller's posts in one
+/// transaction. Ids of other users' posts are skipped and counted.
+#[utoipa::path(
+    post,
+    path = "/users/me/posts/tags",
+    tag = "users",
+    security(("bearer_auth" = [])),
+    request_body = BulkTagDto,
+    responses(
+        (status = 200, description = "Posts changed, unchanged and skipped", body = BulkTagResultDto),
+        (status = 400, description = "Invalid fields or action", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+pub async fn bulk_tag_my_posts(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(dto): Json<BulkTagDto>,
+) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+    dto.validate()?;
+    let action = dto.action.parse::<TagAction>()?;
+
+    let result = state
+        .blog_service
+        .bulk_tag_posts(user.user_id, dto.post_ids, dto.tag, action)
+        .await?;
+
+    Ok((StatusCode::OK, Json(BulkTagResultDto::from(result))))
+}
+
+/// Tags in use, by name, with how many posts carry each
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "tags",
+    responses(
+        (status = 200, description = "Tags in use", body = Vec<TagCountDto>),
+    )
+)]
+pub async fn list_tags(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, DomainError> {
+    let tags: Vec<TagCountDto> = state
+        .blog_service
+        .tag_counts()
+        .await?
+        .into_iter()
+        .map(|(name, post_count)| TagCountDto { name, post_count })
+        .collect();
+
+    Ok((StatusCode::OK, Json(tags)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/comments",
+    tag = "posts",
+    security(("bearer_auth" = [])),
+    params(("id" = i64, Path, description = "Post id")),
+    request_body = CreateCommentDto,
+    responses(
+        (status = 201, description = "Comment added", body = CommentDto),
+        (status = 400, description = "Invalid fields", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 404, description = "No such post", body = ErrorResponse),
+    )
+)]
+pub async fn create_comment(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(post_id): Path<i64>,
+    Json(dto): Json<CreateCommentDto>,
+) -> Result<impl IntoResponse, DomainError> {
+    state.maintenance.ensure_writable()?;
+    dto.validate()?;
+
+    let command = CreateCommentCommand {
+        content: dto.content,
+    };
+    let comment = state
+        .comment_service
+        .add_comment(post_id, user.user_id, command)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(CommentDto::from(comment))))
+}
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListCommentsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// A post's comments, oldest first
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/comments",
+    tag = "posts",
+    params(("id" = i64, Path, description = "Post id"), ListCommentsQuery),
+    responses(
+        (status = 200, description = "A page of comments", body = CommentsPage),
+        (status = 400, description = "Invalid paging", body = ErrorResponse),
+        (status = 404, description = "No such post", body = ErrorResponse),
+    )
+)]
+pub async fn list_comments(
+    State(state): State<AppState>,
+    Path(post_id): Path<i64>,
+    Query(query): Query<ListCommentsQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let config = &state.pagination_config;
+    let (limit, offset) = config.page(query.limit, query.offset)?;
+
+    let (comments, total) = state
+        .comment_service
+        .list_comments(post_id, limit, offset)
+        .await?;
+    let page = Page::new(comments, total, limit, offset).map(CommentDto::from);
+
+    Ok((StatusCode::OK, Json(page)))
+}
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserPostsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// `full` (default), `summary`, or comma-separated post keys
+    pub fields: Option<String>,
+    pub sort: Option<String>,
+}
+
+/// A user's posts; an unknown user gets an empty page, not a 404
+#[utoipa::path(
+    get,
+    path = "/users/{id}/posts",
+    tag = "users",
+    params(("id" = i64, Path, description = "User id"), UserPostsQuery),
+    responses(
+        (status = 200, description = "A page of the user's posts", body = PostsList),
+        (status = 400, description = "Invalid paging, sort or fields", body = ErrorResponse),
+    )
+)]
+pub async fn list_user_posts(
+    State(state): State<AppState>,
+    Path(user_id): Path<i64>,
+    Query(query): Query<UserPostsQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let config = &state.pagination_config;
+    let (limit, offset) = config.page(query.limit, query.offset)?;
+    let sort = query
+        .sort
+        .as_deref()
+        .map_or(Ok(config.default_sort), str::parse)?;
+    let fields = parse_fields(query.fields.as_deref())?;
+
+    let (posts, total) = state
+        .blog_service
+        .list_author_posts(user_id, limit, offset, sort)
+        .await?;
+    let page = Page::new(posts, total, limit, offset)
+        .map(|post| fields.render(post, config.excerpt_chars));
+
+    let response = PostsListDto {
+        page,
+        sort: sort.as_str(),
+        next_cursor: None,
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// The caller's own posts, drafts included
+#[utoipa::path(
+    get,
+    path = "/posts/mine",
+    tag = "users",
+    security(("bearer_auth" = [])),
+    params(UserPostsQuery),
+    responses(
+        (status = 200, description = "A page of the caller's posts", body = PostsList),
+        (status = 400, description = "Invalid paging, sort or fields", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+pub async fn list_my_posts(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Query(query): Query<UserPostsQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let config = &state.pagination_config;
+    let (limit, offset) = config.page(query.limit, query.offset)?;
+    let sort = query
+        .sort
+        .as_deref()
+        .map_or(Ok(config.default_sort), str::parse)?;
+    let fields = parse_fields(query.fields.as_deref())?;
+
+    let (posts, total) = state
+        .blog_service
+        .list_my_posts(user.user_id, limit, offset, sort)
+        .await?;
+    let page = Page::new(posts, total, limit, offset)
+        .map(|post| fields.render(post, config.excerpt_chars));
+
+    let response = PostsListDto {
+        page,
+        sort: sort.as_str(),
+        next_cursor: None,
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Longest window `/users/{id}/activity` accepts
+const MAX_ACTIVITY_DAYS: u32 = 365;
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ActivityQuery {
+    /// Window size in days, ending today (UTC); defaults to 30
+    pub days: Option<u32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/activity",
+    tag = "users",
+    params(("id" = i64, Path, description = "User id"), ActivityQuery),
+    responses(
+        (status = 200, description = "Posts per day", body = ActivityDto),
+        (status = 400, description = "Window out of range", body = ErrorResponse),
+    )
+)]
+pub async fn user_activity(
+    State(state): State<AppState>,
+    Path(user_id): Path<i64>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let days = query.days.unwrap_or(30);
+    if !(1..=MAX_ACTIVITY_DAYS).contains(&days) {
+        return Err(DomainError::ValidationError(format!(
+            "days must be between 1 and {MAX_ACTIVITY_DAYS}"
+        )));
+    }
+
+    let first_day =
+        Utc::now().date_naive() - chrono::Days::new(u64::from(days - 1));
+    let since = first_day.and_time(chrono::NaiveTime::MIN).and_utc();
+    let counts = state.blog_service.post_activity(user_id, since).await?;
+
+    let response = ActivityDto {
+        user_id,
+        days: fill_missing_days(first_day, days, &counts),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// One entry per day starting at `first_day`, with zero for days absent
+/// from the sorted `counts`
+fn fill_missing_days(
+    first_day: NaiveDate,
+    days: u32,
+    counts: &[(NaiveDate, i64)],
+) -> Vec<ActivityDayDto> {
+    let mut counts = counts.iter().peekable();
+
+    first_day
+        .iter_days()
+        .take(days as usize)
+        .map(|date| {
+            let count = counts
+                .next_if(|(day, _)| *day == date)
+                .map_or(0, |(_, count)| *count);
+            ActivityDayDto { date, count }
+        })
+        .collect()
+}
+
+// ============ Health Check ============
+
+/// Readiness probe: pings the database and answers 503 when that fails,
+/// so load balancers stop routing to this instance
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready", body = HealthResponse),
+        (status = 503, description = "Database unavailable", body = HealthResponse),
+    )
+)]
+pub async fn health_check(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<HealthResponse>) {
+    let health = HealthResponse::probe(state.database.as_ref()).await;
+    let status = if health.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(health))
+}
+
+/// Liveness probe: answers as long as the process serves requests. Never
+/// touches the database, so an outage there does not get it restarted.
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "health",
+    responses((status = 200, description = "Alive", body = HealthResponse))
+)]
+pub async fn liveness() -> Json<HealthResponse> {
+    Json(HealthResponse::new("ok", None))
+}
+
+// ============ Admin ============
+
+/// Header carrying `ADMIN_TOKEN` for the admin endpoints
+const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+fn require_admin(
+    maintenance: &MaintenanceMode,
+    headers: &HeaderMap,
+) -> Result<(), DomainError> {
+    let token = headers
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok());
+    if maintenance.is_admin(token) {
+        Ok(())
+    } else {
+        Err(DomainError::Forbidden)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/maintenance",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Current state", body = MaintenanceDto),
+        (status = 403, description = "Wrong or no admin token", body = ErrorResponse),
+    )
+)]
+pub async fn get_maintenance(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, DomainError> {
+    require_admin(&state.maintenance, &headers)?;
+
+    let enabled = state.maintenance.is_enabled();
+    Ok((StatusCode::OK, Json(MaintenanceDto { enabled })))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/maintenance",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = MaintenanceDto,
+    responses(
+        (status = 200, description = "New state", body = MaintenanceDto),
+        (status = 403, description = "Wrong or no admin token", body = ErrorResponse),
+    )
+)]
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(dto): Json<MaintenanceDto>,
+) -> Result<impl IntoResponse, DomainError> {
+    require_admin(&state.maintenance, &headers)?;
+
+    state.maintenance.set_enabled(dto.enabled);
+    tracing::warn!(
+        "Maintenance mode {}",
+        if dto.enabled { "enabled" } else { "disabled" }
+    );
+    Ok((StatusCode::OK, Json(dto)))
+}
+
+/// Token acting as another user, for support. Short-lived, cannot
+/// delete posts, and audit-logged on every use.
+#[utoipa::path(
+    post,
+    path = "/admin/impersonate/{user_id}",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    params(("user_id" = i64, Path, description = "User to act as")),
+    responses(
+        (status = 200, description = "Token acting as the user", body = AuthResponseDto),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 404, description = "No such user", body = ErrorResponse),
+    )
+)]
+pub async fn impersonate_user(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(user_id): Path<i64>,
+) -> Result<impl IntoResponse, DomainError> {
+    let result = state
+        .auth_service
+        .impersonate(admin.0.user_id, admin.0.role, user_id)
+        .await?;
+
+    let response = AuthResponseDto {
+        token: result.token,
+        refresh_token: result.refresh_token,
+        user: UserDto::from(&result.user),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// ============ Router ============
+
+fn cors_layer(origins: &[String], max_age_secs: u64) -> CorsLayer {
+    let origins: Vec<HeaderValue> =
+        origins.iter().filter_map(|o| o.parse().ok()).collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers(Any)
+        .expose_headers([REQUEST_ID_HEADER, ETAG])
+        .max_age(Duration::from_secs(max_age_secs))
+}
+
+pub fn router(
+    state: AppState,
+    config: &ServerConfig,
+    cors_config: &CorsConfig,
+    metrics: Option<Metrics>,
+) -> Router {
+    let limiter = Arc::new(RateLimiter::new(
+        quota(config.user_rate_limit_per_second, config.user_rate_limit_burst),
+        quota(config.rate_limit_per_second, config.rate_limit_burst),
+        state.auth_service.clone(),
+    ));
+
+    let api_docs = state.features.api_docs;
+    let base_path = config.api_base_path.as_str();
+    let mut app = Router::new().nest(base_path, api_routes(state, cors_config));
+
+    if api_docs {
+        app = app.merge(openapi::swagger_ui(base_path));
+    }
+
+    // A route layer, so it sees the matched route; added before
+    // `/metrics` so that scrapes are not counted themselves
+    if let Some(metrics) = metrics {
+        app = app
+            .route_layer(middleware::from_fn(track_requests))
+            .route("/metrics", get(metrics_handler).with_state(metrics));
+    }
+
+    app = app.layer(middleware::from_fn_with_state(limiter, rate_limit));
+
+    // Unlike the per-client rate limit this caps total in-flight work,
+    // rejecting excess requests right away instead of queueing them
+    if config.max_concurrent_requests > 0 {
+        app = app.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(overloaded))
+                .load_shed()
+                .concurrency_limit(config.max_concurrent_requests),
+        );
+    }
+
+    // Inside compression, so bodies are logged before they are encoded
+    if config.log_bodies {
+        tracing::warn!("LOG_BODIES is on; do not use it in production");
+        app = app.layer(middleware::from_fn(log_bodies));
+    }
+
+    if config.compression_enabled {
+        app = app.layer(compression_layer(config.compression_min_bytes));
+    }
+
+    app.layer(request_id_layers(TraceLayer::new_for_http()))
+}
+
+/// Everything under `API_BASE_PATH`, without the server-wide layers
+fn api_routes(state: AppState, cors_config: &CorsConfig) -> Router {
+    // Auth routes get their own CORS policy, so the layer is applied per
+    // nested router instead of once over the whole app
+    let auth_routes = Router::new()
+        .route("/register", post(register))
+        .route("/login", post(login))
+        .route("/verify-email", post(verify_email))
+        .route("/refresh", post(refresh_token))
+        .route("/logout", post(logout))
+        .route("/change-password", post(change_password))
+        .route("/sessions", get(list_sessions))
+        .route("/sessions/{jti}", delete(revoke_session))
+        .layer(cors_layer(
+            cors_config.auth_origins(),
+            cors_config.max_age_secs,
+        ));
+
+    let features = state.features;
+    let posts_routes = Router::new()
+        .route("/", get(list_posts))
+        .route("/", post(create_post))
+        // A raw `Body` is not subject to the default 2 MB extractor
+        // limit; the handler enforces `RAW_POST_MAX_BYTES` itself
+        .route("/raw", optional(features.raw_posts, post(create_raw_post)))
+        .route("/stream", optional(features.post_stream, get(stream_posts)))
+        .route("/random", get(random_post))
+        .route("/search", optional(features.search, get(search_posts)))
+        .route("/mine", get(list_my_posts))
+        .route("/{id}", get(get_post))
+        .route("/{id}", put(update_post))
+        .route("/{id}", delete(delete_post))
+        .route("/{id}/restore", post(restore_post))
+        .route("/{id}/publish", post(publish_post))
+        .route("/{id}/unpublish", post(unpublish_post))
+        .route(
+            "/{id}/comments",
+            optional(
+                features.comments,
+                get(list_comments).post(create_comment),
+            ),
+        )
+        .route("/{id}/neighbors", get(post_neighbors));
+
+    let users_routes = Router::new()
+        .route("/me/export", optional(features.export, get(export_my_posts)))
+        .route(
+            "/me/posts/tags",
+            optional(features.tags, post(bulk_tag_my_posts)),
+        )
+        .route("/{id}/posts", get(list_user_posts))
+        .route(
+            "/{id}/activity",
+            optional(features.activity, get(user_activity)),
+        );
+
+    let public_routes = Router::new()
+        .route("/health", get(health_check))
+        .route("/health/live", get(liveness))
+        .route("/tags", optional(features.tags, get(list_tags)))
+        .nest("/posts", posts_routes)
+        .nest("/users", users_routes)
+        .layer(cors_layer(
+            &cors_config.allowed_origins,
+            cors_config.max_age_secs,
+        ));
+
+    // Operator-only, so no CORS layer: browsers cannot call it cross-origin
+    let admin_routes = Router::new()
+        .route("/maintenance", get(get_maintenance).put(set_maintenance))
+        .route("/impersonate/{user_id}", post(impersonate_user));
+
+    Router::new()
+        .nest("/auth", auth_routes)
+        .nest("/admin", admin_routes)
+        .merge(public_routes)
+        .with_state(state)
+}
+
+/// `route` when the feature is on. Otherwise the path answers 404 like
+/// an unknown one, rather than falling through to a `/{id}` route.
+fn optional(
+    enabled: bool,
+    route: MethodRouter<AppState>,
+) -> MethodRouter<AppState> {
+    if enabled {
+        route
+    } else {
+        any(|| async { StatusCode::NOT_FOUND })
+    }
+}
+
+/// Gzip/brotli for responses of at least `min_bytes`. The SSE feed and
+/// NDJSON export are streamed, and an encoder would hold their chunks
+/// back until it flushes, so they always go out uncompressed.
+fn compression_layer(min_bytes: u16) -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(min_bytes)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::const_new(NDJSON_CONTENT_TYPE));
+
+    CompressionLayer::new().compress_when(predicate)
+}
+
+async fn overloaded(_: BoxError) -> impl IntoResponse {
+    let body = ErrorResponse {
+        error: "Server is overloaded, try again later".to_string(),
+        code: "overloaded",
+        fields: None,
+    };
+    (StatusCode::SERVICE_UNAVAILABLE, Json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::BlogConfig;
+    use crate::domain::{PostSort, Role};
+    use crate::fixtures::{blog_config, state_with_post, state_with_post_and};
+    use crate::infrastructure::DbPools;
+
+    fn headers(name: axum::http::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_get_post_not_modified_since_last_update() {
+        let (state, post) = state_with_post().await;
+        let since = http_date(post.updated_at);
+
+        let response = get_post(
+            State(state),
+            Path(post.id),
+            Query(GetPostQuery::default()),
+            OptionalAuthenticatedUser(None),
+            headers(IF_MODIFIED_SINCE, &since),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[LAST_MODIFIED], since.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_get_post_modified_after_since() {
+        let (state, post) = state_with_post().await;
+        let since = http_date(post.updated_at - chrono::Duration::hours(1));
+
+        let response = get_post(
+            State(state),
+            Path(post.id),
+            Query(GetPostQuery::default()),
+            OptionalAuthenticatedUser(None),
+            headers(IF_MODIFIED_SINCE, &since),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_etag_differs_per_representation() {
+        let (state, post) = state_with_post().await;
+        let get = |query: GetPostQuery, headers: HeaderMap| {
+            get_post(
+                State(state.clone()),
+                Path(post.id),
+                Query(query),
+                OptionalAuthenticatedUser(None),
+                headers,
+            )
+        };
+        let titles = || GetPostQuery {
+            fields: Some("id,title".to_string()),
+            ..GetPostQuery::default()
+        };
+
+        let full = get(GetPostQuery::default(), HeaderMap::new())
+            .await
+            .unwrap();
+        let etag = full.headers()[ETAG].to_str().unwrap().to_string();
+        let other = get(titles(), headers(IF_NONE_MATCH, &etag)).await.unwrap();
+        let same = get(GetPostQuery::default(), headers(IF_NONE_MATCH, &etag))
+            .await
+            .unwrap();
+
+        assert_eq!(other.status(), StatusCode::OK);
+        assert_ne!(other.headers()[ETAG], etag.as_str());
+        assert_eq!(same.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    fn register_dto() -> Json<RegisterDto> {
+        Json(RegisterDto {
+            username: "newuser".to_string(),
+            email: "newuser@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_register_rejects_untrusted_origin() {
+        let (state, _) = state_with_post().await;
+
+        let untrusted = register(
+            State(state.clone()),
+            headers(ORIGIN, "https://bots.example.com"),
+            register_dto(),
+        )
+        .await;
+        let missing =
+            register(State(state), HeaderMap::new(), register_dto()).await;
+
+        assert!(matches!(untrusted, Err(DomainError::Forbidden)));
+        assert!(matches!(missing, Err(DomainError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_register_accepts_trusted_origin() {
+        let (state, _) = state_with_post().await;
+
+        let result = register(
+            State(state),
+            headers(ORIGIN, "https://blog.example.com"),
+            register_dto(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_blocks_writes_but_not_reads() {
+        let (state, post) = state_with_post().await;
+        state.maintenance.set_enabled(true);
+
+        let write = register(
+            State(state.clone()),
+            headers(ORIGIN, "https://blog.example.com"),
+            register_dto(),
+        )
+        .await;
+        let read = get_post(
+            State(state),
+            Path(post.id),
+            Query(GetPostQuery::default()),
+            OptionalAuthenticatedUser(None),
+            HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            write.into_response().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(read.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_toggle_requires_admin_token() {
+        let (state, _) = state_with_post().await;
+        let enable = || Json(MaintenanceDto { enabled: true });
+
+        let denied = set_maintenance(
+            State(state.clone()),
+            headers(
+                axum::http::HeaderName::from_static(ADMIN_TOKEN_HEADER),
+                "wrong",
+            ),
+            enable(),
+        )
+        .await;
+        assert!(matches!(denied, Err(DomainError::Forbidden)));
+        assert!(!state.maintenance.is_enabled());
+
+        set_maintenance(
+            State(state.clone()),
+            headers(
+                axum::http::HeaderName::from_static(ADMIN_TOKEN_HEADER),
+                "admin-secret",
+            ),
+            enable(),
+        )
+        .await
+        .unwrap();
+        assert!(state.maintenance.is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_list_posts_echoes_default_sort() {
+        let (state, _) = state_with_post().await;
+
+        let response = list_posts(
+            State(state),
+            Query(ListPostsQuery::default()),
+            OptionalAuthenticatedUser(None),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["sort"], PostSort::default().as_str());
+    }
+
+    #[tokio::test]
+    async fn test_user_posts_of_unknown_user_are_empty() {
+        let (state, post) = state_with_post().await;
+
+        let list = |user_id| {
+            list_user_posts(
+                State(state.clone()),
+                Path(user_id),
+                Query(UserPostsQuery::default()),
+            )
+        };
+        let own = list(post.author_id).await.unwrap().into_response();
+        let unknown =
+            list(post.author_id + 1000).await.unwrap().into_response();
+        let json = |body| async {
+            let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let own = json(own.into_body()).await;
+        let unknown = json(unknown.into_body()).await;
+
+        assert_eq!(own["total"], 1);
+        assert_eq!(own["posts"][0]["id"], post.id);
+        assert_eq!(unknown["total"], 0);
+        assert_eq!(unknown["posts"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_list_posts_clamps_limit_and_rejects_negatives() {
+        let (state, _) = state_with_post().await;
+        let list = |limit, offset| {
+            list_posts(
+                State(state.clone()),
+                Query(ListPostsQuery {
+                    limit,
+                    offset,
+                    ..ListPostsQuery::default()
+                }),
+                OptionalAuthenticatedUser(None),
+            )
+        };
+
+        let response = list(Some(1000), None).await.unwrap().into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["limit"], state.pagination_config.max_limit);
+        for (limit, offset) in [(Some(-1), None), (None, Some(-5))] {
+            assert!(matches!(
+                list(limit, offset).await,
+                Err(DomainError::ValidationError(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_posts_rejects_unknown_sort() {
+        let (state, _) = state_with_post().await;
+        let query = ListPostsQuery {
+            sort: Some("title; DROP TABLE posts".to_string()),
+            ..ListPostsQuery::default()
+        };
+
+        let result = list_posts(
+            State(state),
+            Query(query),
+            OptionalAuthenticatedUser(None),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rejects_access_token_with_401() {
+        let (state, _) = state_with_post().await;
+        let registered = state
+            .auth_service
+            .register(RegisterCommand {
+                username: "reader".to_string(),
+                email: "reader@example.com".to_string(),
+                password: "password123".to_string(),
+                idempotency_key: None,
+            })
+            .await
+            .unwrap();
+        let refresh = |token: String| {
+            refresh_token(
+                State(state.clone()),
+                Json(RefreshTokenDto {
+                    refresh_token: token,
+                }),
+            )
+        };
+
+        let renewed = refresh(registered.refresh_token.unwrap())
+            .await
+            .unwrap()
+            .into_response();
+        let rejected = refresh(registered.token).await.into_response();
+
+        assert_eq!(renewed.status(), StatusCode::OK);
+        assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_search_posts_returns_a_page() {
+        let (state, post) = state_with_post().await;
+        let query = SearchPostsQuery {
+            q: Some("title".to_string()),
+            ..SearchPostsQuery::default()
+        };
+
+        let response = search_posts(State(state.clone()), Query(query))
+            .await
+            .unwrap()
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let missing =
+            search_posts(State(state), Query(SearchPostsQuery::default()))
+                .await;
+
+        assert_eq!(json["posts"][0]["id"], post.id);
+        assert_eq!(json["total"], 1);
+        assert!(matches!(missing, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_post_returns_requested_fields_only() {
+        let (state, post) = state_with_post().await;
+        let query = GetPostQuery {
+            fields: Some("id,title".to_string()),
+            ..GetPostQuery::default()
+        };
+
+        let response = get_post(
+            State(state),
+            Path(post.id),
+            Query(query),
+            OptionalAuthenticatedUser(None),
+            HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": post.id, "title": "Title" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_posts_rejects_unknown_field() {
+        let (state, _) = state_with_post().await;
+        let query = ListPostsQuery {
+            fields: Some("id,secret".to_string()),
+            ..ListPostsQuery::default()
+        };
+
+        let result = list_posts(
+            State(state),
+            Query(query),
+            OptionalAuthenticatedUser(None),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[test]
+    fn test_if_none_match_takes_precedence() {
+        let now = Utc::now();
+        let mut headers = headers(IF_MODIFIED_SINCE, &http_date(now));
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+
+        let etag = post_etag(now, "full-none");
+        assert!(!is_not_modified(&headers, &etag, now));
+        headers.insert(IF_NONE_MATCH, etag.parse().unwrap());
+        assert!(is_not_modified(&headers, &etag, now));
+    }
+
+    #[tokio::test]
+    async fn test_list_posts_rejects_unknown_tag_mode() {
+        let (state, _) = state_with_post().await;
+        let query = ListPostsQuery {
+            tags: Some("rust".to_string()),
+            tag_mode: Some("most".to_string()),
+            ..ListPostsQuery::default()
+        };
+
+        let result = list_posts(
+            State(state),
+            Query(query),
+            OptionalAuthenticatedUser(None),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_update_of_a_changed_post_conflicts() {
+        let (state, post) = state_with_post().await;
+        let edit = |title: &str| UpdatePostDto {
+            title: title.to_string(),
+            content: "Content".to_string(),
+            tags: None,
+        };
+        let based_on =
+            || headers(IF_MATCH, &post_etag(post.updated_at, "summary-none"));
+
+        let first = update_post(
+            State(state.clone()),
+            author(&post),
+            Path(post.id),
+            based_on(),
+            Json(edit("First")),
+        )
+        .await
+        .map(IntoResponse::into_response);
+        let second = update_post(
+            State(state.clone()),
+            author(&post),
+            Path(post.id),
+            based_on(),
+            Json(edit("Second")),
+        )
+        .await
+        .map(IntoResponse::into_response);
+        let http_date = update_post(
+            State(state),
+            author(&post),
+            Path(post.id),
+            headers(IF_MATCH, &http_date(post.updated_at)),
+            Json(edit("Third")),
+        )
+        .await
+        .map(IntoResponse::into_response);
+
+        let first = first.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_ne!(
+            first.headers()[ETAG],
+            post_etag(post.updated_at, "full-none")
+        );
+        let second = second.unwrap_err().into_response();
+        assert_eq!(second.status(), StatusCode::CONFLICT);
+        assert!(matches!(http_date, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_updated_tags_are_counted_and_filterable() {
+        let (state, post) = state_with_post().await;
+        let json = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let dto = UpdatePostDto {
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            tags: Some(vec!["Rust".to_string(), "async".to_string()]),
+        };
+
+        update_post(
+            State(state.clone()),
+            author(&post),
+            Path(post.id),
+            HeaderMap::new(),
+            Json(dto),
+        )
+        .await
+        .unwrap();
+        let tags = json(
+            list_tags(State(state.clone()))
+                .await
+                .unwrap()
+                .into_response(),
+        )
+        .await;
+        let query = ListPostsQuery {
+            tag: Some("RUST".to_string()),
+            ..ListPostsQuery::default()
+        };
+        let listed = json(
+            list_posts(
+                State(state),
+                Query(query),
+                OptionalAuthenticatedUser(None),
+            )
+            .await
+            .unwrap()
+            .into_response(),
+        )
+        .await;
+
+        assert_eq!(
+            tags,
+            serde_json::json!([
+                { "name": "async", "post_count": 1 },
+                { "name": "rust", "post_count": 1 },
+            ])
+        );
+        assert_eq!(
+            listed["posts"][0]["tags"],
+            serde_json::json!(["async", "rust"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compression_skips_small_and_streamed_responses() {
+        use axum::http::{Request, header::ACCEPT_ENCODING};
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/large", get(|| async { "x".repeat(2048) }))
+            .route("/small", get(|| async { "x" }))
+            .route(
+                "/stream",
+                get(|| async {
+                    ([(CONTENT_TYPE, NDJSON_CONTENT_TYPE)], "x".repeat(2048))
+                }),
+            )
+            .layer(compression_layer(1024));
+        let encoding = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::get(uri)
+                    .header(ACCEPT_ENCODING, "gzip")
+                    .body(Body::empty())
+                    .unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                response.headers().get("content-encoding").cloned()
+            }
+        };
+
+        assert_eq!(encoding("/large").await.unwrap(), "gzip");
+        assert!(encoding("/small").await.is_none());
+        assert!(encoding("/stream").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_of_missing_post_is_not_found() {
+        let (state, _) = state_with_post().await;
+
+        let result = post_neighbors(State(state), Path(i64::MAX)).await;
+
+        assert!(matches!(result, Err(DomainError::PostNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_impersonated_session_cannot_delete_posts() {
+        let (state, post) = state_with_post().await;
+        let user = AuthenticatedUser {
+            impersonator_id: Some(99),
+            ..author(&post)
+        };
+
+        let result =
+            delete_post(State(state.clone()), user, Path(post.id)).await;
+
+        assert!(matches!(result, Err(DomainError::Forbidden)));
+        assert!(
+            state
+                .blog_service
+                .get_post(post.id, false, None)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_impersonated_session_cannot_change_password() {
+        let (state, post) = state_with_post().await;
+        let user = AuthenticatedUser {
+            impersonator_id: Some(99),
+            ..author(&post)
+        };
+        let dto = ChangePasswordDto {
+            current_password: "password123".to_string(),
+            new_password: "new-password".to_string(),
+        };
+
+        let result = change_password(State(state), user, Json(dto)).await;
+
+        assert!(matches!(result, Err(DomainError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_restore_by_author_or_admin() {
+        let (state, post) = state_with_post().await;
+        let delete = || {
+            state
+                .blog_service
+                .delete_post(post.id, post.author_id, Role::User)
+        };
+        let other = |role| AuthenticatedUser {
+            user_id: post.author_id + 1,
+            role,
+            ..author(&post)
+        };
+        let restore =
+            |user| restore_post(State(state.clone()), user, Path(post.id));
+
+        delete().await.unwrap();
+        let by_other_user = restore(other(Role::User)).await;
+        let by_author = restore(author(&post)).await;
+        let not_deleted = restore(author(&post)).await;
+        delete().await.unwrap();
+        let by_admin = restore(other(Role::Admin)).await;
+
+        assert!(matches!(by_other_user, Err(DomainError::Forbidden)));
+        assert!(by_author.is_ok());
+        assert!(matches!(not_deleted, Err(DomainError::PostNotFound)));
+        assert!(by_admin.is_ok());
+        assert!(
+            state
+                .blog_service
+                .get_post(post.id, false, None)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_role_deletes_posts_of_others() {
+        let (state, post) = state_with_post().await;
+        let other = |role| AuthenticatedUser {
+            user_id: post.author_id + 1,
+            username: "other".to_string(),
+            role,
+            ..author(&post)
+        };
+
+        let by_user =
+            delete_post(State(state.clone()), other(Role::User), Path(post.id))
+                .await;
+        let by_admin = delete_post(
+            State(state.clone()),
+            other(Role::Admin),
+            Path(post.id),
+        )
+        .await;
+
+        assert!(matches!(by_user, Err(DomainError::Forbidden)));
+        assert!(by_admin.is_ok());
+        assert!(
+            state
+                .blog_service
+                .get_post(post.id, false, None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_author_email_is_only_shown_to_admins() {
+        let (state, post) = state_with_post().await;
+        let read = |viewer: Option<AuthenticatedUser>| {
+            let query = GetPostQuery {
+                include_author: true,
+                ..GetPostQuery::default()
+            };
+            let state = state.clone();
+            async move {
+                let response = get_post(
+                    State(state),
+                    Path(post.id),
+                    Query(query),
+                    OptionalAuthenticatedUser(viewer),
+                    HeaderMap::new(),
+                )
+                .await
+                .unwrap();
+                let body =
+                    axum::body::to_bytes(response.into_body(), usize::MAX)
+                        .await
+                        .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        let admin = AuthenticatedUser {
+            role: Role::Admin,
+            ..author(&post)
+        };
+
+        let anonymous = read(None).await;
+        let by_admin = read(Some(admin)).await;
+
+        assert_eq!(anonymous["author"]["username"], "author");
+        assert!(anonymous["author"].get("email").is_none());
+        assert_eq!(by_admin["author"]["email"], "author@example.com");
+    }
+
+    fn author(post: &Post) -> AuthenticatedUser {
+        AuthenticatedUser {
+            user_id: post.author_id,
+            username: "author".to_string(),
+            email_verified: true,
+            role: Role::User,
+            impersonator_id: None,
+            session_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_idempotency_key_returns_the_original_post() {
+        let (state, post) = state_with_post().await;
+        let create = |key: &'static str| {
+            let dto = CreatePostDto {
+                title: "Once".to_string(),
+                content: "Content".to_string(),
+                tags: Vec::new(),
+                status: None,
+            };
+            create_post(
+                State(state.clone()),
+                author(&post),
+                headers(
+                    axum::http::HeaderName::from_static(IDEMPOTENCY_KEY),
+                    key,
+                ),
+                Json(dto),
+            )
+        };
+        let id = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+                .clone()
+        };
+
+        let first = create("retry-1").await.unwrap().into_response();
+        let first_status = first.status();
+        let first_id = id(first).await;
+        let replay = create("retry-1").await.unwrap().into_response();
+        let replay_status = replay.status();
+        let replay_id = id(replay).await;
+        let other = create("retry-2").await.unwrap().into_response();
+        let empty = create("").await;
+
+        assert_eq!(first_status, StatusCode::CREATED);
+        assert_eq!(replay_status, StatusCode::OK);
+        assert_eq!(replay_id, first_id);
+        assert_eq!(other.status(), StatusCode::CREATED);
+        assert!(matches!(empty, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_create_post_over_quota_is_too_many_requests() {
+        let (state, post) = state_with_post_and(BlogConfig {
+            max_posts_per_user: 2,
+            ..blog_config()
+        })
+        .await;
+        let create = || {
+            let dto = CreatePostDto {
+                title: "Title".to_string(),
+                content: "Content".to_string(),
+                tags: Vec::new(),
+                status: None,
+            };
+            create_post(
+                State(state.clone()),
+                author(&post),
+                HeaderMap::new(),
+                Json(dto),
+            )
+        };
+
+        let first = create().await.map(IntoResponse::into_response);
+        let second = create().await.map(IntoResponse::into_response);
+
+        assert_eq!(first.unwrap().status(), StatusCode::CREATED);
+        let second = second.unwrap_err().into_response();
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = axum::body::to_bytes(second.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "post_quota_exceeded");
+    }
+
+    #[tokio::test]
+    async fn test_draft_is_hidden_from_anonymous_readers_until_published() {
+        let (state, post) = state_with_post().await;
+        let json = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let anonymous_list = || {
+            list_posts(
+                State(state.clone()),
+                Query(ListPostsQuery::default()),
+                OptionalAuthenticatedUser(None),
+            )
+        };
+        let get = |viewer: Option<AuthenticatedUser>, id| {
+            get_post(
+                State(state.clone()),
+                Path(id),
+                Query(GetPostQuery::default()),
+                OptionalAuthenticatedUser(viewer),
+                HeaderMap::new(),
+            )
+        };
+        let dto = CreatePostDto {
+            title: "Draft".to_string(),
+            content: "Content".to_string(),
+            tags: Vec::new(),
+            status: None,
+        };
+
+        let created = create_post(
+            State(state.clone()),
+            author(&post),
+            HeaderMap::new(),
+            Json(dto),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let draft = json(created).await;
+        let draft_id = draft["id"].as_i64().unwrap();
+        let listed =
+            json(anonymous_list().await.unwrap().into_response()).await;
+        let mine = list_my_posts(
+            State(state.clone()),
+            author(&post),
+            Query(UserPostsQuery::default()),
+        )
+        .await
+        .unwrap()
+        .into_response();
+
+        assert_eq!(draft["status"], "draft");
+        assert_eq!(listed["total"], 1);
+        assert_eq!(listed["posts"][0]["id"], post.id);
+        assert!(matches!(
+            get(None, draft_id).await,
+            Err(DomainError::PostNotFound)
+        ));
+        assert!(get(Some(author(&post)), draft_id).await.is_ok());
+        assert_eq!(json(mine).await["total"], 2);
+
+        let published =
+            publish_post(State(state.clone()), author(&post), Path(draft_id))
+                .await
+                .unwrap()
+                .into_response();
+        let listed =
+            json(anonymous_list().await.unwrap().into_response()).await;
+
+        assert_eq!(json(published).await["status"], "published");
+        assert_eq!(listed["total"], 2);
+        assert!(get(None, draft_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_list_shows_drafts_to_their_author_only() {
+        let (state, post) = state_with_post().await;
+        let reader = state
+            .auth_service
+            .register(RegisterCommand {
+                username: "reader".to_string(),
+                email: "reader@example.com".to_string(),
+                password: "password123".to_string(),
+                idempotency_key: None,
+            })
+            .await
+            .unwrap();
+        let reader = AuthenticatedUser {
+            user_id: reader.user.id,
+            ..author(&post)
+        };
+        for (user, title) in [(author(&post), "Mine"), (reader, "Theirs")] {
+            let dto = CreatePostDto {
+                title: title.to_string(),
+                content: "Content".to_string(),
+                tags: Vec::new(),
+                status: None,
+            };
+            create_post(
+                State(state.clone()),
+                user,
+                HeaderMap::new(),
+                Json(dto),
+            )
+            .await
+            .unwrap();
+        }
+        let list = |viewer: Option<AuthenticatedUser>| async {
+            let response = list_posts(
+                State(state.clone()),
+                Query(ListPostsQuery::default()),
+                OptionalAuthenticatedUser(viewer),
+            )
+            .await
+            .unwrap()
+            .into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let titles = |listed: &serde_json::Value| {
+            listed["posts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|post| post["title"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let anonymous = list(None).await;
+        let as_author = list(Some(author(&post))).await;
+
+        assert_eq!(titles(&anonymous), ["Title"]);
+        assert_eq!(anonymous["total"], 1);
+        assert_eq!(titles(&as_author), ["Mine", "Title"]);
+        assert_eq!(as_author["total"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_raw_post_takes_body_as_content() {
+        let (state, post) = state_with_post().await;
+        let content = "line one\n{\"not\": \"json\"}\n";
+
+        let response = create_raw_post(
+            State(state),
+            author(&post),
+            Query(RawPostQuery::default()),
+            headers(
+                axum::http::HeaderName::from_static(POST_TITLE_HEADER),
+                "Raw",
+            ),
+            Body::from(content),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["title"], "Raw");
+        assert_eq!(json["content"], content);
+    }
+
+    #[tokio::test]
+    async fn test_comments_are_listed_under_their_post() {
+        let (state, post) = state_with_post().await;
+        let dto = CreateCommentDto {
+            content: "Nice post".to_string(),
+        };
+
+        let created = create_comment(
+            State(state.clone()),
+            author(&post),
+            Path(post.id),
+            Json(dto),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let missing = list_comments(
+            State(state.clone()),
+            Path(post.id + 1000),
+            Query(ListCommentsQuery::default()),
+        )
+        .await;
+        let response = list_comments(
+            State(state),
+            Path(post.id),
+            Query(ListCommentsQuery::default()),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(created.status(), StatusCode::CREATED);
+        assert!(matches!(missing, Err(DomainError::PostNotFound)));
+        assert_eq!(json["total"], 1);
+        assert_eq!(json["comments"][0]["content"], "Nice post");
+        assert_eq!(json["comments"][0]["author_username"], "author");
+    }
+
+    #[tokio::test]
+    async fn test_raw_post_over_limit_is_rejected() {
+        let (state, post) = state_with_post().await;
+        let query = RawPostQuery {
+            title: Some("Raw".to_string()),
+            status: None,
+        };
+
+        let result = create_raw_post(
+            State(state),
+            author(&post),
+            Query(query),
+            HeaderMap::new(),
+            Body::from("x".repeat(65)),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DomainError::PayloadTooLarge(64))));
+    }
+
+    #[tokio::test]
+    async fn test_declared_length_over_limit_is_refused_unread() {
+        let body = Body::from_stream(futures_util::stream::once(async {
+            Err::<axum::body::Bytes, _>(std::io::Error::other(
+                "body must not be read",
+            ))
+        }));
+
+        let result = read_text_body(body, Some(65), 64).await;
+
+        assert!(matches!(result, Err(DomainError::PayloadTooLarge(64))));
+    }
+
+    #[test]
+    fn test_activity_day_without_posts_is_zero() {
+        let day = |d: &str| d.parse::<NaiveDate>().unwrap();
+        let counts = [(day("2025-03-01"), 2), (day("2025-03-03"), 1)];
+
+        let filled = fill_missing_days(day("2025-03-01"), 4, &counts);
+
+        let series: Vec<_> = filled
+            .iter()
+            .map(|entry| (entry.date, entry.count))
+            .collect();
+        assert_eq!(
+            series,
+            [
+                (day("2025-03-01"), 2),
+                (day("2025-03-02"), 0),
+                (day("2025-03-03"), 1),
+                (day("2025-03-04"), 0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_readiness_fails_when_the_database_is_down() {
+        let (mut state, _) = state_with_post().await;
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_secs(1))
+            .connect_lazy("postgres://blog@127.0.0.1:1/blog")
+            .unwrap();
+        state.database = Some(Database::from_pools(DbPools::single(pool)));
+
+        let (status, Json(body)) = health_check(State(state)).await;
+        let Json(live) = liveness().await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.status, "unavailable");
+        assert_eq!(live.status, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_user_activity_ends_today() {
+        let (state, post) = state_with_post().await;
+        let query = ActivityQuery { days: Some(7) };
+
+        let response =
+            user_activity(State(state), Path(post.author_id), Query(query))
+                .await
+                .unwrap()
+                .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let days = json["days"].as_array().unwrap();
+        assert_eq!(days.len(), 7);
+        assert_eq!(days[0]["count"], 0);
+        assert_eq!(days[6]["date"], Utc::now().date_naive().to_string());
+        assert_eq!(days[6]["count"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_user_activity_rejects_out_of_range_days() {
+        let (state, post) = state_with_post().await;
+        let query = ActivityQuery { days: Some(0) };
+
+        let result =
+            user_activity(State(state), Path(post.author_id), Query(query))
+                .await;
+
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_feature_routes_are_absent() {
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let (mut state, _) = state_with_post().await;
+        state.features = Features {
+            tags: false,
+            search: false,
+            comments: false,
+            post_stream: false,
+            ..Features::default()
+        };
+        let cors_config = CorsConfig {
+            allowed_origins: Vec::new(),
+            auth_allowed_origins: None,
+            max_age_secs: 0,
+        };
+        let app = api_routes(state, &cors_config);
+        let status = |request: Request<Body>| {
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let bulk_tag = Request::post("/users/me/posts/tags")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status(bulk_tag).await, StatusCode::NOT_FOUND);
+        assert_eq!(status(get("/posts/stream")).await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            status(get("/posts/search?q=rust")).await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(get("/posts/1/comments")).await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(get("/posts?tags=rust")).await,
+            StatusCode::NOT_IMPLEMENTED
+        );
+        assert_eq!(status(get("/posts")).await, StatusCode::OK);
+        assert_eq!(status(get("/users/1/activity")).await, StatusCode::OK);
+    }
+    #[tokio::test]
+    async fn test_router_is_mounted_under_the_base_path() {
+        use axum::extract::ConnectInfo;
+        use axum::http::Request;
+        use std::net::SocketAddr;
+        use tower::ServiceExt;
+
+        use crate::presentation::config::ApiBasePath;
+
+        let (state, post) = state_with_post().await;
+        let config = ServerConfig {
+            api_base_path: "/custom/prefix".parse().unwrap(),
+            http_host: [127, 0, 0, 1].into(),
+            http_port: 0,
+            grpc_host: [127, 0, 0, 1].into(),
+            grpc_port: 0,
+            grpc_tls: None,
+            grpc_request_timeout: None,
+            rate_limit_per_second: 1,
+            rate_limit_burst: 100,
+            user_rate_limit_per_second: 1,
+            user_rate_limit_burst: 100,
+            max_concurrent_requests: 0,
+            compression_enabled: false,
+            compression_min_bytes: 1024,
+            log_bodies: false,
+            raw_post_max_bytes: 64,
+            shutdown_timeout: Duration::ZERO,
+        };
+        let cors_config = CorsConfig {
+            allowed_origins: Vec::new(),
+            auth_allowed_origins: None,
+            max_age_secs: 0,
+        };
+        let app = router(state, &config, &cors_config, None);
+        let status =
+            |uri: String| {
+                let app = app.clone();
+                async move {
+                    let mut request =
+                        Request::get(uri).body(Body::empty()).unwrap();
+                    request.extensions_mut().insert(ConnectInfo(
+                        SocketAddr::from(([127, 0, 0, 1], 40000)),
+                    ));
+                    app.oneshot(request).await.unwrap().status()
+                }
+            };
+
+        assert_eq!(
+            status(format!("/custom/prefix/posts/{}", post.id)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(format!("/api/v1/posts/{}", post.id)).await,
+            StatusCode::NOT_FOUND
+        );
+        assert!("custom".parse::<ApiBasePath>().is_err());
+        assert!("/custom/".parse::<ApiBasePath>().is_err());
+    }
 }
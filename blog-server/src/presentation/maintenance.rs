@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::config::MaintenanceConfig;
+use crate::domain::DomainError;
+
+/// Read-only switch shared by the HTTP and gRPC servers. While it is on,
+/// write endpoints fail with [`DomainError::Maintenance`]; reads keep
+/// working.
+#[derive(Clone, Default)]
+pub struct MaintenanceMode {
+    enabled: Arc<AtomicBool>,
+    /// Secret for the runtime toggle; `None` disables the admin endpoint
+    admin_token: Option<Arc<str>>,
+}
+
+impl MaintenanceMode {
+    pub fn new(config: &MaintenanceConfig) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(config.enabled)),
+            admin_token: config.admin_token.as_deref().map(Arc::from),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Called first by every write endpoint
+    pub fn ensure_writable(&self) -> Result<(), DomainError> {
+        if self.is_enabled() {
+            return Err(DomainError::Maintenance);
+        }
+        Ok(())
+    }
+
+    /// Whether `token` matches the configured admin token
+    pub fn is_admin(&self, token: Option<&str>) -> bool {
+        match (&self.admin_token, token) {
+            (Some(expected), Some(token)) => {
+                constant_time_eq(expected.as_bytes(), token.as_bytes())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compares without short-circuiting, so response timing does not reveal
+/// how much of the token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(enabled: bool, admin_token: Option<&str>) -> MaintenanceMode {
+        MaintenanceMode::new(&MaintenanceConfig {
+            enabled,
+            admin_token: admin_token.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn test_toggle_is_shared_between_clones() {
+        let mode = mode(false, None);
+        let other = mode.clone();
+        assert!(mode.ensure_writable().is_ok());
+
+        other.set_enabled(true);
+
+        assert!(matches!(
+            mode.ensure_writable(),
+            Err(DomainError::Maintenance)
+        ));
+    }
+
+    #[test]
+    fn test_admin_token() {
+        let mode = mode(false, Some("secret"));
+
+        assert!(mode.is_admin(Some("secret")));
+        assert!(!mode.is_admin(Some("secreT")));
+        assert!(!mode.is_admin(Some("secret2")));
+        assert!(!mode.is_admin(None));
+    }
+
+    #[test]
+    fn test_admin_endpoint_disabled_without_token() {
+        let mode = mode(true, None);
+
+        assert!(!mode.is_admin(Some("")));
+        assert!(!mode.is_admin(None));
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{
+    BuildError, Matcher, PrometheusBuilder, PrometheusHandle,
+};
+use sqlx::PgPool;
+
+use crate::infrastructure::DbPools;
+
+const REQUESTS_TOTAL: &str = "http_requests_total";
+const REQUEST_ERRORS_TOTAL: &str = "http_request_errors_total";
+const REQUEST_DURATION: &str = "http_request_duration_seconds";
+const DB_CONNECTIONS_ACTIVE: &str = "db_connections_active";
+const DB_CONNECTIONS_IDLE: &str = "db_connections_idle";
+
+/// Latency buckets in seconds, from cache hits to slow queries
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Prometheus exporter behind `GET /metrics`
+#[derive(Clone)]
+pub struct Metrics {
+    handle: PrometheusHandle,
+    /// Reported as connection gauges on every scrape; `None` with the
+    /// in-memory backend
+    pools: Option<DbPools>,
+}
+
+impl Metrics {
+    /// Installs the process-wide recorder, so call it once at startup
+    pub fn install(pools: Option<DbPools>) -> Result<Self, BuildError> {
+        let handle = PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Full(REQUEST_DURATION.to_string()),
+                LATENCY_BUCKETS,
+            )?
+            .install_recorder()?;
+
+        Ok(Self { handle, pools })
+    }
+
+    /// Metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        if let Some(pools) = &self.pools {
+            record_pool("primary", &pools.primary);
+            record_pool("replica", &pools.replica);
+        }
+        self.handle.run_upkeep();
+        self.handle.render()
+    }
+}
+
+fn record_pool(pool_name: &'static str, pool: &PgPool) {
+    let idle = pool.num_idle();
+    let active = usize::try_from(pool.size())
+        .unwrap_or(usize::MAX)
+        .saturating_sub(idle);
+
+    #[allow(clippy::cast_precision_loss)]
+    {
+        metrics::gauge!(DB_CONNECTIONS_ACTIVE, "pool" => pool_name)
+            .set(active as f64);
+        metrics::gauge!(DB_CONNECTIONS_IDLE, "pool" => pool_name)
+            .set(idle as f64);
+    }
+}
+
+pub async fn metrics_handler(State(metrics): State<Metrics>) -> Response {
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], metrics.render())
+        .into_response()
+}
+
+/// Counts requests and times them per route. Labels use the route
+/// template (`/api/v1/posts/{id}`), not the concrete path, to keep the
+/// number of series bounded; it has to be a route layer for that.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(String::new, |path| path.as_str().to_string());
+
+    let response = next.run(request).await;
+
+    let status = response.status();
+    let elapsed = started.elapsed().as_secs_f64();
+    let status_label = status.as_u16().to_string();
+    metrics::counter!(
+        REQUESTS_TOTAL,
+        "method" => method.clone(),
+        "path" => path.clone(),
+        "status" => status_label.clone()
+    )
+    .increment(1);
+    if status.is_client_error() || status.is_server_error() {
+        metrics::counter!(
+            REQUEST_ERRORS_TOTAL,
+            "method" => method.clone(),
+            "path" => path.clone(),
+            "status" => status_label
+        )
+        .increment(1);
+    }
+    metrics::histogram!(
+        REQUEST_DURATION,
+        "method" => method,
+        "path" => path
+    )
+    .record(elapsed);
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, http::Request, middleware, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_are_labeled_with_the_route_template() {
+        let metrics = Metrics::install(None).unwrap();
+        let app = Router::new()
+            .route("/items/{id}", get(|| async { "item" }))
+            .route_layer(middleware::from_fn(track_requests));
+
+        for item in [1, 2] {
+            let request = Request::get(format!("/items/{item}"))
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+        let rendered = metrics.render();
+
+        assert!(rendered.contains(
+            r#"http_requests_total{method="GET",path="/items/{id}",status="200"} 2"#
+        ));
+        assert!(rendered.contains("http_request_duration_seconds_bucket"));
+        assert!(!rendered.contains("/items/1"));
+    }
+}
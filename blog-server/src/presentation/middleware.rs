@@ -1,35 +1,122 @@
 use axum::{
     Json,
-    extract::FromRequestParts,
-    http::{StatusCode, header::AUTHORIZATION, request::Parts},
+    body::{Body, Bytes, HttpBody},
+    extract::{FromRequestParts, Request},
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, SET_COOKIE},
+        request::Parts,
+    },
+    middleware::Next,
     response::{IntoResponse, Response},
 };
 use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-use crate::infrastructure::JwtService;
+use crate::application::AuthService;
+use crate::domain::{DomainError, Role};
 
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
     pub user_id: i64,
     #[allow(dead_code)]
     pub username: String,
+    pub email_verified: bool,
+    /// Role from the token, so a promotion applies from the next login
+    pub role: Role,
+    /// Admin behind an impersonation token
+    pub impersonator_id: Option<i64>,
+    /// Session of the presented token, if it has one
+    pub session_id: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Stable machine-readable error code
+    pub code: &'static str,
+    /// Messages per invalid request field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<BTreeMap<String, Vec<String>>>,
 }
 
 pub struct AuthError(pub String);
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        (StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: self.0 }))
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: self.0,
+                code: "unauthorized",
+                fields: None,
+            }),
+        )
             .into_response()
     }
 }
 
+/// Like `AuthenticatedUser`, but anonymous requests are let through as
+/// `None`. A token that is present but invalid is still rejected.
+#[derive(Debug, Clone)]
+pub struct OptionalAuthenticatedUser(pub Option<AuthenticatedUser>);
+
+impl OptionalAuthenticatedUser {
+    pub fn user_id(&self) -> Option<i64> {
+        self.0.as_ref().map(|user| user.user_id)
+    }
+
+    /// Whether the viewer is logged in with the admin role
+    pub fn is_admin(&self) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|user| user.role.require_role(Role::Admin).is_ok())
+    }
+}
+
+/// Verifies the bearer token if an Authorization header is present
+async fn bearer_user(
+    parts: &Parts,
+) -> Result<Option<AuthenticatedUser>, AuthError> {
+    // Get Authorization header
+    let Some(auth_header) = parts.headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let auth_header = auth_header
+        .to_str()
+        .map_err(|_| AuthError("Invalid Authorization header".to_string()))?;
+
+    // Get auth service from extensions
+    let auth_service = parts
+        .extensions
+        .get::<Arc<AuthService>>()
+        .ok_or_else(|| AuthError("Auth service not configured".to_string()))?;
+
+    // Extract Bearer token
+    let token = auth_header.strip_prefix("Bearer ").ok_or_else(|| {
+        AuthError("Invalid Authorization header format".to_string())
+    })?;
+
+    // Verify token, including revocation by password change
+    let claims = auth_service
+        .authenticate(token)
+        .await
+        .map_err(|e| AuthError(format!("Invalid token: {e}")))?;
+
+    Ok(Some(AuthenticatedUser {
+        user_id: claims.user_id,
+        username: claims.username,
+        email_verified: claims.email_verified,
+        role: claims.role,
+        impersonator_id: claims.impersonator_id,
+        session_id: claims.jti,
+    }))
+}
+
 impl<S> FromRequestParts<S> for AuthenticatedUser
 where
     S: Send + Sync,
@@ -40,38 +127,386 @@ where
         parts: &mut Parts,
         _state: &S,
     ) -> Result<Self, Self::Rejection> {
-        // Get JWT service from extensions
-        let jwt_service = parts
-            .extensions
-            .get::<Arc<JwtService>>()
-            .ok_or_else(|| AuthError("JWT service not configured".to_string()))?
-            .clone();
-
-        // Get Authorization header
-        let auth_header = parts
-            .headers
-            .get(AUTHORIZATION)
-            .ok_or_else(|| {
-                AuthError("Missing Authorization header".to_string())
-            })?
-            .to_str()
-            .map_err(|_| {
-                AuthError("Invalid Authorization header".to_string())
-            })?;
-
-        // Extract Bearer token
-        let token = auth_header.strip_prefix("Bearer ").ok_or_else(|| {
-            AuthError("Invalid Authorization header format".to_string())
-        })?;
-
-        // Verify token
-        let claims = jwt_service
-            .verify_token(token)
-            .map_err(|e| AuthError(format!("Invalid token: {e}")))?;
-
-        Ok(Self {
-            user_id: claims.user_id,
-            username: claims.username,
+        bearer_user(parts).await?.ok_or_else(|| {
+            AuthError("Missing Authorization header".to_string())
         })
     }
 }
+
+/// An authenticated user with the admin role. Impersonation tokens never
+/// count, even when issued to an admin.
+#[derive(Debug, Clone)]
+pub struct AdminUser(pub AuthenticatedUser);
+
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthenticatedUser::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let is_admin = user.role.require_role(Role::Admin).is_ok();
+
+        if is_admin && user.impersonator_id.is_none() {
+            Ok(Self(user))
+        } else {
+            Err(DomainError::Forbidden.into_response())
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for OptionalAuthenticatedUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        bearer_user(parts).await.map(Self)
+    }
+}
+
+/// Largest body buffered for logging, same as axum's default request
+/// body limit. Bigger bodies pass through unlogged.
+const LOG_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Logged bodies are cut to this many characters
+const LOG_BODY_CHARS: usize = 2048;
+
+const REDACTED: &str = "[redacted]";
+
+/// Dev-only debugging aid: logs JSON request and response bodies at debug
+/// level, with credentials redacted. Bodies are buffered in full, so only
+/// JSON is touched; streamed responses (SSE, NDJSON) pass through as is.
+pub async fn log_bodies(request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let Ok((body, logged)) = buffer_json(&parts.headers, body).await else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let method = parts.method.clone();
+    let uri = parts.uri.clone();
+    tracing::debug!(
+        %method,
+        %uri,
+        headers = ?redacted_headers(&parts.headers),
+        body = logged.as_deref(),
+        "Request"
+    );
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let (parts, body) = response.into_parts();
+    let Ok((body, logged)) = buffer_json(&parts.headers, body).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    tracing::debug!(
+        %method,
+        %uri,
+        status = parts.status.as_u16(),
+        headers = ?redacted_headers(&parts.headers),
+        body = logged.as_deref(),
+        "Response"
+    );
+
+    Response::from_parts(parts, body)
+}
+
+/// Buffers a JSON body and renders it for the log. Other bodies are
+/// handed back untouched, without a rendering; so are JSON bodies over
+/// `LOG_BODY_LIMIT` or of unknown size, with just a note for the log.
+async fn buffer_json(
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<(Body, Option<String>), axum::Error> {
+    if !is_json(headers) {
+        return Ok((body, None));
+    }
+    let size = body.size_hint().exact();
+    if size.is_none_or(|size| size > LOG_BODY_LIMIT as u64) {
+        let note = size.map_or_else(
+            || "<body of unknown size, not logged>".to_string(),
+            |size| format!("<{size} bytes, not logged>"),
+        );
+        return Ok((body, Some(note)));
+    }
+
+    let bytes = axum::body::to_bytes(body, LOG_BODY_LIMIT).await?;
+    let logged = redacted_body(&bytes);
+    Ok((Body::from(bytes), Some(logged)))
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+fn redacted_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in [AUTHORIZATION, COOKIE, SET_COOKIE] {
+        if headers.contains_key(&name) {
+            headers.insert(name, HeaderValue::from_static(REDACTED));
+        }
+    }
+    headers
+}
+
+/// JSON body with secrets masked, truncated for the log. Unparseable
+/// bodies are not logged at all, since they could not be redacted.
+fn redacted_body(bytes: &Bytes) -> String {
+    let Ok(mut value) = serde_json::from_slice::<Value>(bytes) else {
+        return format!("<{} bytes of invalid JSON>", bytes.len());
+    };
+    redact_secrets(&mut value);
+
+    let text = value.to_string();
+    match text.char_indices().nth(LOG_BODY_CHARS) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text,
+    }
+}
+
+/// Masks passwords and tokens at any depth
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                let key = key.to_lowercase();
+                if key.contains("password") || key.contains("token") {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact_secrets(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::AuthConfig;
+    use crate::data::{InMemorySessionRepository, InMemoryUserRepository};
+    use crate::domain::{Password, PasswordParams, RegisterCommand, User};
+    use crate::infrastructure::{JwtConfig, JwtService};
+    use axum::http::Request;
+    use chrono::Utc;
+    use std::time::Duration;
+
+    fn jwt_service() -> JwtService {
+        JwtService::new(&JwtConfig {
+            secret: "test-secret-key-that-is-at-least-32-chars".to_string(),
+            previous_secrets: Vec::new(),
+            token_expiry_hours: 24,
+            email_verification_expiry_hours: 24,
+            refresh_token_expiry_days: 30,
+            leeway_secs: 60,
+            issuer: "blog-server".to_string(),
+            audience: "blog-api".to_string(),
+            validate_issuer_audience: true,
+        })
+    }
+
+    fn auth_service() -> Arc<AuthService> {
+        Arc::new(AuthService::new(
+            Arc::new(InMemoryUserRepository::new()),
+            Arc::new(InMemorySessionRepository::new()),
+            Arc::new(jwt_service()),
+            &AuthConfig {
+                password_version_cache_ttl: Duration::ZERO,
+                impersonation_ttl: Duration::from_mins(15),
+                password_params: PasswordParams::default(),
+                rehash_on_login: true,
+                session_cleanup_interval: Duration::ZERO,
+            },
+        ))
+    }
+
+    fn parts(
+        auth_service: Arc<AuthService>,
+        authorization: Option<&str>,
+    ) -> Parts {
+        let mut builder = Request::builder().extension(auth_service);
+        if let Some(value) = authorization {
+            builder = builder.header(AUTHORIZATION, value);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    #[tokio::test]
+    async fn test_optional_user_is_none_without_header() {
+        let mut parts = parts(auth_service(), None);
+        let viewer =
+            OptionalAuthenticatedUser::from_request_parts(&mut parts, &())
+                .await
+                .unwrap_or_else(|_| panic!("anonymous request rejected"));
+
+        assert_eq!(viewer.user_id(), None);
+    }
+
+    #[tokio::test]
+    async fn test_optional_user_is_set_with_valid_token() {
+        let auth_service = auth_service();
+        let registered = auth_service
+            .register(RegisterCommand {
+                username: "viewer".to_string(),
+                email: "viewer@example.com".to_string(),
+                password: "password123".to_string(),
+                idempotency_key: None,
+            })
+            .await
+            .unwrap();
+        let bearer = format!("Bearer {}", registered.token);
+        let mut parts = parts(auth_service, Some(&bearer));
+        let viewer =
+            OptionalAuthenticatedUser::from_request_parts(&mut parts, &())
+                .await
+                .unwrap_or_else(|_| panic!("valid token rejected"));
+
+        assert_eq!(viewer.user_id(), Some(registered.user.id));
+    }
+
+    async fn register(auth_service: &AuthService, username: &str) -> String {
+        auth_service
+            .register(RegisterCommand {
+                username: username.to_string(),
+                email: format!("{username}@example.com"),
+                password: "password123".to_string(),
+                idempotency_key: None,
+            })
+            .await
+            .unwrap()
+            .token
+    }
+
+    async fn admin_status(
+        auth_service: Arc<AuthService>,
+        token: &str,
+    ) -> Result<i64, StatusCode> {
+        let bearer = format!("Bearer {token}");
+        let mut parts = parts(auth_service, Some(&bearer));
+        AdminUser::from_request_parts(&mut parts, &())
+            .await
+            .map(|admin| admin.0.user_id)
+            .map_err(|response| response.status())
+    }
+
+    #[tokio::test]
+    async fn test_admin_extractor_refuses_non_admins() {
+        let auth_service = auth_service();
+        register(&auth_service, "admin").await;
+        let user = register(&auth_service, "user").await;
+        // Registration hands out the user role; promotion is done in the
+        // database and shows in the next token
+        let promoted = User::new(
+            1,
+            "admin".to_string(),
+            "admin@example.com".to_string(),
+            Password::from_hash(String::new()),
+            Utc::now(),
+        )
+        .with_role(Role::Admin);
+        let admin =
+            jwt_service().generate_token(&promoted, None).unwrap().token;
+        let impersonated = auth_service
+            .impersonate(1, Role::Admin, 1)
+            .await
+            .unwrap()
+            .token;
+
+        assert_eq!(admin_status(auth_service.clone(), &admin).await, Ok(1));
+        assert_eq!(
+            admin_status(auth_service.clone(), &user).await,
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            admin_status(auth_service, &impersonated).await,
+            Err(StatusCode::FORBIDDEN)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logged_out_token_is_rejected() {
+        let auth_service = auth_service();
+        let bearer =
+            format!("Bearer {}", register(&auth_service, "ivan").await);
+        let mut before = parts(auth_service.clone(), Some(&bearer));
+        let user = AuthenticatedUser::from_request_parts(&mut before, &())
+            .await
+            .unwrap_or_else(|_| panic!("valid token rejected"));
+
+        // What `POST /auth/logout` does with the token's session
+        auth_service
+            .revoke_session(user.user_id, user.session_id.unwrap())
+            .await
+            .unwrap();
+
+        let mut after = parts(auth_service, Some(&bearer));
+        let result =
+            AuthenticatedUser::from_request_parts(&mut after, &()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_optional_user_rejects_invalid_token() {
+        let mut parts = parts(auth_service(), Some("Bearer invalid-token"));
+        let result =
+            OptionalAuthenticatedUser::from_request_parts(&mut parts, &())
+                .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_redacted_body_masks_nested_secrets() {
+        let body = Bytes::from_static(
+            br#"{"username":"ivan","password":"hunter2","user":{"token":"jwt"}}"#,
+        );
+
+        let logged = redacted_body(&body);
+
+        assert!(logged.contains("ivan"));
+        assert!(!logged.contains("hunter2"));
+        assert!(!logged.contains("jwt"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_json_body_passes_through_unlogged() {
+        let headers = HeaderMap::from_iter([(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )]);
+        let small = Body::from(r#"{"title":"Title"}"#);
+        let large = Body::from(vec![b' '; LOG_BODY_LIMIT + 1]);
+
+        let (_, logged) = buffer_json(&headers, small).await.unwrap();
+        let (body, note) = buffer_json(&headers, large).await.unwrap();
+
+        assert_eq!(logged.as_deref(), Some(r#"{"title":"Title"}"#));
+        assert_eq!(
+            note,
+            Some(format!("<{} bytes, not logged>", LOG_BODY_LIMIT + 1))
+        );
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(bytes.len(), LOG_BODY_LIMIT + 1);
+    }
+
+    #[test]
+    fn test_redacted_body_skips_invalid_json() {
+        let body = Bytes::from_static(b"password=hunter2");
+
+        assert!(!redacted_body(&body).contains("hunter2"));
+    }
+}
@@ -3,9 +3,22 @@
 pub mod config;
 pub mod dto;
 pub mod grpc_service;
+pub mod health;
 pub mod http_handlers;
+pub mod maintenance;
+pub mod metrics;
 pub mod middleware;
+pub mod openapi;
+pub mod rate_limit;
+pub mod request_id;
+pub mod shutdown;
 
-pub use config::{CorsConfig, PaginationConfig, ServerConfig};
+pub use config::{
+    CorsConfig, Features, MaintenanceConfig, MetricsConfig, PaginationConfig,
+    RegistrationConfig, ServerConfig,
+};
 pub use grpc_service::{BlogGrpcService, proto};
 pub use http_handlers::{AppState, router};
+pub use maintenance::MaintenanceMode;
+pub use metrics::Metrics;
+pub use shutdown::{InFlight, shutdown_signal};
@@ -0,0 +1,203 @@
+use utoipa::openapi::Server;
+use utoipa::openapi::security::{
+    ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme,
+};
+use utoipa::{Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+use super::dto::{CommentDto, PostDto};
+use super::http_handlers;
+
+/// Where the generated spec is served
+pub const OPENAPI_JSON_PATH: &str = "/api-docs/openapi.json";
+
+/// Where Swagger UI is served
+pub const SWAGGER_UI_PATH: &str = "/swagger-ui";
+
+/// Description of the HTTP API, generated from the handler annotations.
+/// Paths are relative to the `/api/v1` server, which [`swagger_ui`]
+/// replaces with the configured base path.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Blog API", description = "Posts, comments and accounts"),
+    servers((url = "/api/v1")),
+    paths(
+        http_handlers::register,
+        http_handlers::login,
+        http_handlers::verify_email,
+        http_handlers::refresh_token,
+        http_handlers::logout,
+        http_handlers::change_password,
+        http_handlers::list_sessions,
+        http_handlers::revoke_session,
+        http_handlers::list_posts,
+        http_handlers::create_post,
+        http_handlers::create_raw_post,
+        http_handlers::stream_posts,
+        http_handlers::random_post,
+        http_handlers::search_posts,
+        http_handlers::get_post,
+        http_handlers::update_post,
+        http_handlers::delete_post,
+        http_handlers::restore_post,
+        http_handlers::publish_post,
+        http_handlers::unpublish_post,
+        http_handlers::list_comments,
+        http_handlers::create_comment,
+        http_handlers::post_neighbors,
+        http_handlers::export_my_posts,
+        http_handlers::bulk_tag_my_posts,
+        http_handlers::list_user_posts,
+        http_handlers::list_my_posts,
+        http_handlers::user_activity,
+        http_handlers::list_tags,
+        http_handlers::health_check,
+        http_handlers::liveness,
+        http_handlers::get_maintenance,
+        http_handlers::set_maintenance,
+        http_handlers::impersonate_user,
+    ),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "auth", description = "Accounts, tokens and sessions"),
+        (name = "posts", description = "Posts and their comments"),
+        (name = "users", description = "Posts by user and the caller's own"),
+        (name = "tags", description = "Tag statistics"),
+        (name = "health", description = "Probes for load balancers"),
+        (name = "admin", description = "Operator endpoints"),
+    )
+)]
+pub struct ApiDoc;
+
+/// `bearer_auth` for access tokens, `admin_token` for `ADMIN_TOKEN`
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components =
+            openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(
+                "X-Admin-Token",
+            ))),
+        );
+    }
+}
+
+// The schemas below only describe responses built from `Page`, which
+// serializes by hand under a per-item key; they are never constructed.
+
+/// Page of posts with the sort applied. With `fields`, posts carry only
+/// the requested keys.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct PostsList {
+    posts: Vec<PostDto>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    /// Whether a page after this one has posts
+    has_more: bool,
+    /// Sort that was applied, including the default
+    sort: String,
+    /// Pass as `cursor` to get the next page; absent on the last one
+    next_cursor: Option<String>,
+}
+
+/// Page of search results, best matches first
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct PostsPage {
+    posts: Vec<PostDto>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    /// Whether a page after this one has posts
+    has_more: bool,
+}
+
+/// Page of a post's comments, oldest first
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct CommentsPage {
+    comments: Vec<CommentDto>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    /// Whether a page after this one has comments
+    has_more: bool,
+}
+
+/// Swagger UI at [`SWAGGER_UI_PATH`] over the spec at [`OPENAPI_JSON_PATH`],
+/// with the API served under `base_path`
+pub fn swagger_ui(base_path: &str) -> SwaggerUi {
+    let mut spec = ApiDoc::openapi();
+    spec.servers = Some(vec![Server::new(base_path)]);
+    SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_JSON_PATH, spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_spec_covers_routes_and_security() {
+        let spec = ApiDoc::openapi();
+
+        for path in [
+            "/auth/register",
+            "/auth/login",
+            "/auth/sessions/{jti}",
+            "/posts",
+            "/posts/{id}",
+            "/posts/{id}/comments",
+            "/users/{id}/posts",
+            "/admin/maintenance",
+        ] {
+            assert!(spec.paths.paths.contains_key(path), "missing {path}");
+        }
+
+        let components = spec.components.expect("components");
+        assert!(components.security_schemes.contains_key("bearer_auth"));
+        assert!(components.schemas.contains_key("CreatePostDto"));
+        assert!(components.schemas.contains_key("PostsList"));
+
+        let create = spec.paths.paths["/posts"].post.as_ref().unwrap();
+        assert!(create.security.is_some());
+        assert!(create.responses.responses.contains_key("201"));
+    }
+
+    #[tokio::test]
+    async fn test_spec_is_served() {
+        let app = Router::new().merge(swagger_ui("/blog/api"));
+
+        let response = app
+            .oneshot(
+                Request::get(OPENAPI_JSON_PATH).body(Body::empty()).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(spec["servers"][0]["url"], "/blog/api");
+    }
+}
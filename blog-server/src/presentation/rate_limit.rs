@@ -0,0 +1,200 @@
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Json;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::{AUTHORIZATION, RETRY_AFTER};
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota};
+
+use super::middleware::ErrorResponse;
+use crate::application::AuthService;
+
+/// Request rate limits keyed by user for requests with a valid access
+/// token, so users behind one NAT do not share a bucket, and by client
+/// IP for the rest
+pub struct RateLimiter {
+    users: DefaultKeyedRateLimiter<i64>,
+    ips: DefaultKeyedRateLimiter<IpAddr>,
+    auth_service: Arc<AuthService>,
+}
+
+impl RateLimiter {
+    pub fn new(
+        users: Quota,
+        ips: Quota,
+        auth_service: Arc<AuthService>,
+    ) -> Self {
+        Self {
+            users: DefaultKeyedRateLimiter::keyed(users),
+            ips: DefaultKeyedRateLimiter::keyed(ips),
+            auth_service,
+        }
+    }
+
+    /// How long the caller has to wait, or `None` if the request may pass
+    fn check(&self, user_id: Option<i64>, ip: IpAddr) -> Option<Duration> {
+        user_id
+            .map_or_else(
+                || self.ips.check_key(&ip),
+                |user_id| self.users.check_key(&user_id),
+            )
+            .err()
+            .map(|not_until| {
+                not_until.wait_time_from(DefaultClock::default().now())
+            })
+    }
+}
+
+/// One request replenished every `period_secs` seconds, up to `burst`
+pub fn quota(period_secs: u64, burst: u32) -> Quota {
+    Quota::with_period(Duration::from_secs(period_secs))
+        .expect("Rate limit period must not be zero")
+        .allow_burst(
+            NonZeroU32::new(burst).expect("Rate limit burst must not be zero"),
+        )
+}
+
+/// Rejects requests over their limit with 429 and `Retry-After`. An
+/// invalid token counts as anonymous here; the handler rejects it later.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(ConnectInfo(peer)) = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .copied()
+    else {
+        return error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Client address unknown".to_string(),
+            "internal_error",
+        );
+    };
+    let user_id = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| limiter.auth_service.token_user_id(token));
+
+    let Some(wait) = limiter.check(user_id, peer.ip()) else {
+        return next.run(request).await;
+    };
+
+    // Rounded up, so a client that waits exactly this long gets through
+    let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    let mut response = error(
+        StatusCode::TOO_MANY_REQUESTS,
+        format!("Too many requests, retry in {retry_after}s"),
+        "rate_limited",
+    );
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+fn error(status: StatusCode, error: String, code: &'static str) -> Response {
+    let body = ErrorResponse {
+        error,
+        code,
+        fields: None,
+    };
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::body::Body;
+    use axum::middleware;
+    use axum::routing::get;
+    use chrono::Utc;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::application::AuthConfig;
+    use crate::data::{InMemorySessionRepository, InMemoryUserRepository};
+    use crate::domain::{Password, PasswordParams, User};
+    use crate::infrastructure::{JwtConfig, JwtService};
+
+    #[tokio::test]
+    async fn test_users_behind_one_ip_get_their_own_buckets() {
+        let jwt_service = Arc::new(JwtService::new(&JwtConfig {
+            secret: "test-secret-key-that-is-at-least-32-chars".to_string(),
+            previous_secrets: Vec::new(),
+            token_expiry_hours: 1,
+            email_verification_expiry_hours: 24,
+            refresh_token_expiry_days: 30,
+            leeway_secs: 60,
+            issuer: "blog-server".to_string(),
+            audience: "blog-api".to_string(),
+            validate_issuer_audience: true,
+        }));
+        let token = |id| {
+            let user = User::new(
+                id,
+                format!("user{id}"),
+                format!("user{id}@example.com"),
+                Password::from_hash(String::new()),
+                Utc::now(),
+            );
+            jwt_service.generate_token(&user, None).unwrap().token
+        };
+        let (alice, bob) = (token(1), token(2));
+        let auth_service = Arc::new(AuthService::new(
+            Arc::new(InMemoryUserRepository::new()),
+            Arc::new(InMemorySessionRepository::new()),
+            jwt_service.clone(),
+            &AuthConfig {
+                password_version_cache_ttl: Duration::ZERO,
+                impersonation_ttl: Duration::from_mins(15),
+                password_params: PasswordParams::default(),
+                rehash_on_login: true,
+                session_cleanup_interval: Duration::ZERO,
+            },
+        ));
+        let limiter = Arc::new(RateLimiter::new(
+            quota(3600, 1),
+            quota(3600, 1),
+            auth_service,
+        ));
+        let app = Router::new()
+            .route("/", get(|| async {}))
+            .layer(middleware::from_fn_with_state(limiter, rate_limit));
+        let status = |token: Option<&str>| {
+            let mut request = Request::get("/")
+                .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 0))));
+            if let Some(token) = token {
+                request =
+                    request.header(AUTHORIZATION, format!("Bearer {token}"));
+            }
+            let app = app.clone();
+            async move {
+                app.oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(status(Some(&alice)).await, StatusCode::OK);
+        assert_eq!(status(Some(&bob)).await, StatusCode::OK);
+        assert_eq!(status(None).await, StatusCode::OK);
+        assert_eq!(status(Some(&alice)).await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(status(Some(&bob)).await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(status(None).await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            status(Some("garbage")).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use axum::http::{HeaderName, Request};
+use tower::ServiceBuilder;
+use tower::layer::util::{Identity, Stack};
+use tower_http::classify::{GrpcCode, GrpcErrorsAsFailures, SharedClassifier};
+use tower_http::request_id::{
+    MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer,
+};
+use tower_http::trace::{MakeSpan, TraceLayer};
+use tracing::Span;
+
+/// Header (gRPC: metadata key) identifying a request in the logs. Kept
+/// when the client sends one, so a caller can correlate its own calls.
+pub const REQUEST_ID_HEADER: HeaderName =
+    HeaderName::from_static("x-request-id");
+
+/// What [`request_id_layers`] returns
+pub type RequestIdLayers<C> = ServiceBuilder<
+    Stack<
+        PropagateRequestIdLayer,
+        Stack<
+            TraceLayer<C, RequestSpan>,
+            Stack<SetRequestIdLayer<MakeRequestUuid>, Identity>,
+        >,
+    >,
+>;
+
+/// Gives every request an id, traces it in a span carrying the id and
+/// echoes the id in the response. `trace` picks how responses are
+/// classified, HTTP or gRPC.
+pub fn request_id_layers<C>(trace: TraceLayer<C>) -> RequestIdLayers<C> {
+    ServiceBuilder::new()
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+        .layer(trace.make_span_with(RequestSpan))
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+}
+
+/// Classifies gRPC responses for tracing. Codes that blame the caller
+/// count as successes, like 4xx over HTTP, so that only server faults
+/// are logged as errors.
+pub fn grpc_classifier() -> SharedClassifier<GrpcErrorsAsFailures> {
+    SharedClassifier::new(
+        GrpcErrorsAsFailures::new()
+            .with_success(GrpcCode::InvalidArgument)
+            .with_success(GrpcCode::NotFound)
+            .with_success(GrpcCode::AlreadyExists)
+            .with_success(GrpcCode::PermissionDenied)
+            .with_success(GrpcCode::Unauthenticated)
+            .with_success(GrpcCode::FailedPrecondition),
+    )
+}
+
+/// Span of one request; every line logged while handling it carries the
+/// request id
+#[derive(Debug, Clone, Copy)]
+pub struct RequestSpan;
+
+impl<B> MakeSpan<B> for RequestSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let request_id = request
+            .headers()
+            .get(&REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .unwrap_or_default();
+
+        tracing::info_span!(
+            "request",
+            method = %request.method(),
+            uri = %request.uri(),
+            request_id,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_id_is_kept_or_generated_and_echoed() {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(request_id_layers(TraceLayer::new_for_http()));
+        let request_id = |request: Request<Body>| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                response.headers()[&REQUEST_ID_HEADER]
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            }
+        };
+
+        let sent = request_id(
+            Request::get("/")
+                .header(&REQUEST_ID_HEADER, "from-client")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let generated =
+            request_id(Request::get("/").body(Body::empty()).unwrap()).await;
+
+        assert_eq!(sent, "from-client");
+        assert!(uuid::Uuid::parse_str(&generated).is_ok());
+    }
+}
@@ -0,0 +1,165 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM (what Kubernetes sends before
+/// killing a pod)
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+/// Resolves once `shutdown` flips to true; servers stop accepting new
+/// connections then and drain the open ones
+pub async fn stopped(mut shutdown: watch::Receiver<bool>) {
+    // A dropped sender means main is gone, which is a shutdown as well
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+/// Requests currently being handled, shared by the HTTP and gRPC servers
+/// so shutdown can report how many it is still draining. As a layer it
+/// counts a request until its response is ready; streamed bodies such as
+/// the SSE feed are not counted past that.
+#[derive(Clone, Default)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn enter(&self) -> InFlightGuard {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(self.0.clone())
+    }
+}
+
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S> Layer<S> for InFlight {
+    type Service = InFlightService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InFlightService {
+            inner,
+            in_flight: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InFlightService<S> {
+    inner: S,
+    in_flight: InFlight,
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+impl<S, R> Service<R> for InFlightService<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<S::Response, S::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let guard = self.in_flight.enter();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = response.await;
+            drop(guard);
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_counts_requests_until_their_response() {
+        let in_flight = InFlight::default();
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let mut released = Some(released);
+        let service = in_flight.layer(service_fn(move |(): ()| {
+            let released = released.take();
+            async move {
+                if let Some(released) = released {
+                    let _ = released.await;
+                }
+                Ok::<_, Infallible>(())
+            }
+        }));
+
+        let pending = tokio::spawn(service.oneshot(()));
+        while in_flight.count() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(in_flight.count(), 1);
+
+        release.send(()).unwrap();
+        pending.await.unwrap().unwrap();
+        assert_eq!(in_flight.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stopped_resolves_on_shutdown() {
+        let (shutdown, receiver) = watch::channel(false);
+        let stopping = tokio::spawn(stopped(receiver));
+
+        shutdown.send_replace(true);
+
+        stopping.await.unwrap();
+    }
+}
@@ -1,9 +1,15 @@
-use gloo_net::http::Request;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use gloo_net::http::{Request, Response};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 const TOKEN_KEY: &str = "blog_token";
 const USER_KEY: &str = "blog_user";
+const REFRESH_TOKEN_KEY: &str = "blog_refresh_token";
+
+/// Status the server answers with for a missing, expired or revoked token
+const UNAUTHORIZED: u16 = 401;
 
 // ============ Data Types ============
 
@@ -18,6 +24,8 @@ pub struct User {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthResponse {
     pub token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
     pub user: User,
 }
 
@@ -30,6 +38,11 @@ pub struct Post {
     pub author_username: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `draft` or `published`
+    #[serde(default)]
+    pub status: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +51,7 @@ pub struct PostsList {
     pub total: i64,
     pub limit: i64,
     pub offset: i64,
+    pub has_more: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -53,10 +67,23 @@ struct LoginRequest<'a> {
     password: &'a str,
 }
 
+/// Body of `POST /posts`; also the object shape `create_post_from_json`
+/// accepts, so unknown fields are rejected rather than silently dropped
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreatePostRequest {
+    title: String,
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// `draft` or `published`; the server saves a draft when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
-struct CreatePostRequest<'a> {
-    title: &'a str,
-    content: &'a str,
+struct RefreshRequest<'a> {
+    refresh_token: &'a str,
 }
 
 #[derive(Debug, Serialize)]
@@ -68,6 +95,32 @@ struct UpdatePostRequest<'a> {
 #[derive(Debug, Deserialize)]
 struct ApiError {
     error: String,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+/// Turns an error response into a JS `Error` whose `code` property holds
+/// the server's machine-readable error code (when present)
+async fn api_error(response: Response, fallback: &str) -> JsValue {
+    let error: ApiError = response.json().await.unwrap_or_else(|_| ApiError {
+        error: fallback.to_string(),
+        code: None,
+    });
+
+    js_error(&error.error, error.code.as_deref())
+}
+
+/// JS `Error` with an optional machine-readable `code` property
+fn js_error(message: &str, code: Option<&str>) -> JsValue {
+    let js_error = js_sys::Error::new(message);
+    if let Some(code) = code {
+        let _ = js_sys::Reflect::set(
+            &js_error,
+            &JsValue::from_str("code"),
+            &JsValue::from_str(code),
+        );
+    }
+    js_error.into()
 }
 
 // ============ Storage Helpers ============
@@ -94,20 +147,84 @@ fn remove_from_storage(key: &str) -> Result<(), JsValue> {
         .map_err(|_| JsValue::from_str("Failed to remove from localStorage"))
 }
 
+/// Stores the tokens and user of a successful register, login or refresh.
+/// `renewed_with` is the refresh token a refresh exchanged; see
+/// [`refresh_token_after`] for which refresh token is kept.
+fn save_auth(
+    auth: &AuthResponse,
+    renewed_with: Option<&str>,
+) -> Result<(), JsValue> {
+    save_to_storage(TOKEN_KEY, &auth.token)?;
+    match refresh_token_after(auth, renewed_with) {
+        Some(refresh_token) => {
+            save_to_storage(REFRESH_TOKEN_KEY, refresh_token)?;
+        }
+        None => remove_from_storage(REFRESH_TOKEN_KEY)?,
+    }
+    save_to_storage(
+        USER_KEY,
+        &serde_json::to_string(&auth.user)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?,
+    )
+}
+
+/// Refresh token to store after `auth`. The server does not rotate refresh
+/// tokens, so a refresh response carries none and the exchanged one stays
+/// valid. A register or login response without one drops any stored token,
+/// which belongs to an earlier session.
+fn refresh_token_after<'a>(
+    auth: &'a AuthResponse,
+    renewed_with: Option<&'a str>,
+) -> Option<&'a str> {
+    auth.refresh_token.as_deref().or(renewed_with)
+}
+
+fn clear_auth() -> Result<(), JsValue> {
+    remove_from_storage(TOKEN_KEY)?;
+    remove_from_storage(REFRESH_TOKEN_KEY)?;
+    remove_from_storage(USER_KEY)
+}
+
+// ============ Token Helpers ============
+
+/// The only claim the frontend needs; the signature is left to the server
+#[derive(Debug, Deserialize)]
+struct TokenExpiry {
+    exp: f64,
+}
+
+/// Expiry of `token` in milliseconds since the epoch, the unit JS `Date`
+/// uses. `None` if the token is not a JWT with an `exp` claim.
+fn token_expiry_millis(token: &str) -> Option<f64> {
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    let claims: TokenExpiry = serde_json::from_slice(&payload).ok()?;
+    Some(claims.exp * 1000.0)
+}
+
 // ============ BlogApp ============
 
 #[wasm_bindgen]
 pub struct BlogApp {
     base_url: String,
+    /// See [`BlogApp::new`]
+    renew_expired_session: bool,
 }
 
 #[wasm_bindgen]
 impl BlogApp {
+    /// With `renew_expired_session` (off when omitted), an authenticated
+    /// call the server answers with 401 renews the token with the stored
+    /// refresh token and is retried once. When there is nothing to renew
+    /// with, or the retry is refused too, the stored session is cleared
+    /// and the call fails with a JS `Error` whose `code` is
+    /// `"session_expired"`, so the page can send the user to log in.
     #[wasm_bindgen(constructor)]
-    pub fn new(base_url: &str) -> Self {
+    pub fn new(base_url: &str, renew_expired_session: Option<bool>) -> Self {
         console_error_panic_hook::set_once();
         Self {
             base_url: base_url.trim_end_matches('/').to_string(),
+            renew_expired_session: renew_expired_session.unwrap_or(false),
         }
     }
 
@@ -119,9 +236,31 @@ impl BlogApp {
         get_from_storage(TOKEN_KEY)
     }
 
+    /// Expiry of the stored token. A malformed token is cleared along with
+    /// the stored user, since no request would accept it anyway.
+    fn stored_token_expiry() -> Option<f64> {
+        let token = Self::get_token()?;
+        let expiry = token_expiry_millis(&token);
+        if expiry.is_none() {
+            let _ = clear_auth();
+        }
+        expiry
+    }
+
+    /// Whether a token is stored and has not expired yet.
     #[wasm_bindgen]
     pub fn is_authenticated(&self) -> bool {
-        Self::get_token().is_some()
+        Self::stored_token_expiry()
+            .is_some_and(|expiry| expiry > js_sys::Date::now())
+    }
+
+    /// When the stored token expires, in milliseconds since the epoch (pass
+    /// it to `new Date(...)`), or `undefined` without a valid token. Lets
+    /// the frontend ask the user to log in again before requests start
+    /// failing.
+    #[wasm_bindgen]
+    pub fn token_expires_at(&self) -> Option<f64> {
+        Self::stored_token_expiry()
     }
 
     /// Get the current user from local storage.
@@ -149,9 +288,7 @@ impl BlogApp {
     /// Returns `JsValue` error if local storage is unavailable.
     #[wasm_bindgen]
     pub fn logout(&self) -> Result<(), JsValue> {
-        remove_from_storage(TOKEN_KEY)?;
-        remove_from_storage(USER_KEY)?;
-        Ok(())
+        clear_auth()
     }
 
     /// Register a new user.
@@ -182,11 +319,7 @@ impl BlogApp {
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         if !response.ok() {
-            let error: ApiError =
-                response.json().await.unwrap_or_else(|_| ApiError {
-                    error: "Registration failed".to_string(),
-                });
-            return Err(JsValue::from_str(&error.error));
+            return Err(api_error(response, "Registration failed").await);
         }
 
         let auth: AuthResponse = response
@@ -194,12 +327,7 @@ impl BlogApp {
             .await
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-        save_to_storage(TOKEN_KEY, &auth.token)?;
-        save_to_storage(
-            USER_KEY,
-            &serde_json::to_string(&auth.user)
-                .map_err(|e| JsValue::from_str(&e.to_string()))?,
-        )?;
+        save_auth(&auth, None)?;
 
         serde_wasm_bindgen::to_value(&auth)
             .map_err(|e| JsValue::from_str(&e.to_string()))
@@ -228,11 +356,7 @@ impl BlogApp {
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         if !response.ok() {
-            let error: ApiError =
-                response.json().await.unwrap_or_else(|_| ApiError {
-                    error: "Login failed".to_string(),
-                });
-            return Err(JsValue::from_str(&error.error));
+            return Err(api_error(response, "Login failed").await);
         }
 
         let auth: AuthResponse = response
@@ -240,12 +364,7 @@ impl BlogApp {
             .await
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-        save_to_storage(TOKEN_KEY, &auth.token)?;
-        save_to_storage(
-            USER_KEY,
-            &serde_json::to_string(&auth.user)
-                .map_err(|e| JsValue::from_str(&e.to_string()))?,
-        )?;
+        save_auth(&auth, None)?;
 
         serde_wasm_bindgen::to_value(&auth)
             .map_err(|e| JsValue::from_str(&e.to_string()))
@@ -271,11 +390,7 @@ impl BlogApp {
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         if !response.ok() {
-            let error: ApiError =
-                response.json().await.unwrap_or_else(|_| ApiError {
-                    error: "Failed to load posts".to_string(),
-                });
-            return Err(JsValue::from_str(&error.error));
+            return Err(api_error(response, "Failed to load posts").await);
         }
 
         let posts: PostsList = response
@@ -300,11 +415,7 @@ impl BlogApp {
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         if !response.ok() {
-            let error: ApiError =
-                response.json().await.unwrap_or_else(|_| ApiError {
-                    error: "Post not found".to_string(),
-                });
-            return Err(JsValue::from_str(&error.error));
+            return Err(api_error(response, "Post not found").await);
         }
 
         let post: Post = response
@@ -316,7 +427,7 @@ impl BlogApp {
             .map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
-    /// Create a new post.
+    /// Create a new post, published right away.
     ///
     /// # Errors
     ///
@@ -327,36 +438,32 @@ impl BlogApp {
         title: &str,
         content: &str,
     ) -> Result<JsValue, JsValue> {
-        let token = Self::get_token()
-            .ok_or_else(|| JsValue::from_str("Not authenticated"))?;
-
-        let body = serde_json::to_string(&CreatePostRequest { title, content })
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-        let response = Request::post(&self.url("/posts"))
-            .header("Content-Type", "application/json")
-            .header("Authorization", &format!("Bearer {token}"))
-            .body(body)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?
-            .send()
-            .await
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-        if !response.ok() {
-            let error: ApiError =
-                response.json().await.unwrap_or_else(|_| ApiError {
-                    error: "Failed to create post".to_string(),
-                });
-            return Err(JsValue::from_str(&error.error));
-        }
+        self.send_new_post(&CreatePostRequest {
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: Vec::new(),
+            status: Some("published".to_string()),
+        })
+        .await
+    }
 
-        let post: Post = response
-            .json()
-            .await
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    /// Create a new post from an object such as
+    /// `{ title: "...", content: "...", tags: ["rust"], status: "draft" }`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a JS `Error` with `code: "invalid_post"` if the object is
+    /// missing required fields or has unexpected ones; otherwise the same
+    /// errors as `create_post`.
+    #[wasm_bindgen]
+    pub async fn create_post_from_json(
+        &self,
+        value: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let request: CreatePostRequest = serde_wasm_bindgen::from_value(value)
+            .map_err(|e| js_error(&e.to_string(), Some("invalid_post")))?;
 
-        serde_wasm_bindgen::to_value(&post)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+        self.send_new_post(&request).await
     }
 
     /// Update an existing post.
@@ -371,27 +478,19 @@ impl BlogApp {
         title: &str,
         content: &str,
     ) -> Result<JsValue, JsValue> {
-        let token = Self::get_token()
-            .ok_or_else(|| JsValue::from_str("Not authenticated"))?;
-
         let body = serde_json::to_string(&UpdatePostRequest { title, content })
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-        let response = Request::put(&self.url(&format!("/posts/{id}")))
-            .header("Content-Type", "application/json")
-            .header("Authorization", &format!("Bearer {token}"))
-            .body(body)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?
-            .send()
-            .await
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let response = self
+            .send_authorized(|| {
+                Request::put(&self.url(&format!("/posts/{id}")))
+                    .header("Content-Type", "application/json")
+                    .body(&body)
+            })
+            .await?;
 
         if !response.ok() {
-            let error: ApiError =
-                response.json().await.unwrap_or_else(|_| ApiError {
-                    error: "Failed to update post".to_string(),
-                });
-            return Err(JsValue::from_str(&error.error));
+            return Err(api_error(response, "Failed to update post").await);
         }
 
         let post: Post = response
@@ -410,27 +509,130 @@ impl BlogApp {
     /// Returns `JsValue` error if not authenticated, the request fails, or the server returns an error.
     #[wasm_bindgen]
     pub async fn delete_post(&self, id: i64) -> Result<(), JsValue> {
+        let response = self
+            .send_authorized(|| {
+                Request::delete(&self.url(&format!("/posts/{id}"))).build()
+            })
+            .await?;
+
+        if !response.ok() {
+            return Err(api_error(response, "Failed to delete post").await);
+        }
+
+        Ok(())
+    }
+}
+
+impl BlogApp {
+    async fn send_new_post(
+        &self,
+        request: &CreatePostRequest,
+    ) -> Result<JsValue, JsValue> {
+        let body = serde_json::to_string(request)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let response = self
+            .send_authorized(|| {
+                Request::post(&self.url("/posts"))
+                    .header("Content-Type", "application/json")
+                    .body(&body)
+            })
+            .await?;
+
+        if !response.ok() {
+            return Err(api_error(response, "Failed to create post").await);
+        }
+
+        let post: Post = response
+            .json()
+            .await
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        serde_wasm_bindgen::to_value(&post)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Sends the request `build` makes with the stored token. A 401 is
+    /// handled as described on [`BlogApp::new`] when that is enabled; a
+    /// second 401 is returned as is instead of renewing again, so a server
+    /// that keeps refusing cannot cause a loop.
+    async fn send_authorized(
+        &self,
+        build: impl Fn() -> Result<Request, gloo_net::Error>,
+    ) -> Result<Response, JsValue> {
         let token = Self::get_token()
             .ok_or_else(|| JsValue::from_str("Not authenticated"))?;
 
-        let response = Request::delete(&self.url(&format!("/posts/{id}")))
-            .header("Authorization", &format!("Bearer {token}"))
+        let response = send_with_token(&build, &token).await?;
+        if response.status() != UNAUTHORIZED || !self.renew_expired_session {
+            return Ok(response);
+        }
+
+        let Some(token) = self.renew_session().await? else {
+            return Err(session_expired());
+        };
+        let response = send_with_token(&build, &token).await?;
+        if response.status() == UNAUTHORIZED {
+            return Err(session_expired());
+        }
+        Ok(response)
+    }
+
+    /// Exchanges the stored refresh token for a new session, storing it.
+    /// `None` when there is no refresh token or the server refuses it.
+    async fn renew_session(&self) -> Result<Option<String>, JsValue> {
+        let Some(refresh_token) = get_from_storage(REFRESH_TOKEN_KEY) else {
+            return Ok(None);
+        };
+
+        let body = serde_json::to_string(&RefreshRequest {
+            refresh_token: &refresh_token,
+        })
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let response = Request::post(&self.url("/auth/refresh"))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?
             .send()
             .await
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         if !response.ok() {
-            let error: ApiError =
-                response.json().await.unwrap_or_else(|_| ApiError {
-                    error: "Failed to delete post".to_string(),
-                });
-            return Err(JsValue::from_str(&error.error));
+            return Ok(None);
         }
 
-        Ok(())
+        let auth: AuthResponse = response
+            .json()
+            .await
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        save_auth(&auth, Some(&refresh_token))?;
+
+        Ok(Some(auth.token))
     }
 }
 
+/// Sends the request `build` makes, authorized with `token`
+async fn send_with_token(
+    build: impl Fn() -> Result<Request, gloo_net::Error>,
+    token: &str,
+) -> Result<Response, JsValue> {
+    let request = build().map_err(|e| JsValue::from_str(&e.to_string()))?;
+    request
+        .headers()
+        .set("Authorization", &format!("Bearer {token}"));
+    request
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Clears the stored session and reports it as expired
+fn session_expired() -> JsValue {
+    let _ = clear_auth();
+    js_error("Session expired, please log in again", Some("session_expired"))
+}
+
 // ============ Console Logging ============
 
 #[wasm_bindgen]
@@ -443,3 +645,41 @@ extern "C" {
 macro_rules! console_log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(token: &str, refresh_token: Option<&str>) -> AuthResponse {
+        AuthResponse {
+            token: token.to_string(),
+            refresh_token: refresh_token.map(str::to_string),
+            user: User {
+                id: 1,
+                username: "alice".to_string(),
+                email: "alice@example.com".to_string(),
+                created_at: "2025-01-01T00:00:00Z".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_refresh_token_survives_repeated_renewals() {
+        let login = auth("access-1", Some("refresh"));
+        let mut stored = refresh_token_after(&login, None).map(str::to_string);
+
+        for access in ["access-2", "access-3"] {
+            let renewed_with = stored.clone();
+            let refreshed = auth(access, None);
+            stored = refresh_token_after(&refreshed, renewed_with.as_deref())
+                .map(str::to_string);
+        }
+
+        assert_eq!(stored.as_deref(), Some("refresh"));
+    }
+
+    #[test]
+    fn test_login_without_refresh_token_drops_the_stored_one() {
+        assert_eq!(refresh_token_after(&auth("access", None), None), None);
+    }
+}